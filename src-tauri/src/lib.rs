@@ -1,11 +1,13 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
-use std::collections::HashSet;
-use tauri::{AppHandle, Manager};
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
 use chrono::Local;
-use pulldown_cmark::{Parser, Event};
+use pulldown_cmark::{Parser, Event, Options, TextMergeStream};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct Config {
@@ -13,6 +15,71 @@ struct Config {
     last_project_path: Option<String>,
     #[serde(rename = "fontFamily")]
     font_family: Option<String>,
+    #[serde(default = "default_theme")]
+    theme: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    window: Option<WindowState>,
+    #[serde(rename = "autosaveIntervalSeconds", skip_serializing_if = "Option::is_none")]
+    autosave_interval_seconds: Option<u32>,
+    // Most recently opened project folders, newest first
+    #[serde(rename = "recentProjects", default)]
+    recent_projects: Vec<String>,
+    // Author filled into newly created projects
+    #[serde(rename = "defaultAuthor", skip_serializing_if = "Option::is_none")]
+    default_author: Option<String>,
+    // Format for chapter and project JSON; see JsonIndent::parse
+    #[serde(rename = "jsonIndent", skip_serializing_if = "Option::is_none")]
+    json_indent: Option<String>,
+}
+
+const RECENT_PROJECTS_LIMIT: usize = 10;
+
+const DEFAULT_AUTOSAVE_INTERVAL_SECONDS: u32 = 30;
+const AUTOSAVE_INTERVAL_RANGE: std::ops::RangeInclusive<u32> = 5..=600;
+
+// Last window geometry, restored on launch. x/y are omitted when unknown or off-screen.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct WindowState {
+    width: u32,
+    height: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    y: Option<i32>,
+    #[serde(default)]
+    maximized: bool,
+}
+
+const MIN_WINDOW_WIDTH: u32 = 480;
+const MIN_WINDOW_HEIGHT: u32 = 360;
+// Positions beyond this are treated as stale (e.g. from a disconnected monitor)
+const MAX_WINDOW_COORD: i32 = 16384;
+
+impl WindowState {
+    fn sanitized(mut self) -> Self {
+        self.width = self.width.max(MIN_WINDOW_WIDTH);
+        self.height = self.height.max(MIN_WINDOW_HEIGHT);
+        let on_screen = |pos: Option<i32>, size: u32| {
+            pos.filter(|&p| p + size as i32 > 0 && p < MAX_WINDOW_COORD)
+        };
+        let (x, y) = (on_screen(self.x, self.width), on_screen(self.y, self.height));
+        // A window half-placed is worse than letting the OS choose
+        if x.is_none() || y.is_none() {
+            self.x = None;
+            self.y = None;
+        } else {
+            self.x = x;
+            self.y = y;
+        }
+        self
+    }
+}
+
+// Themes the UI knows how to render; "system" follows the OS light/dark setting
+const THEMES: &[&str] = &["light", "dark", "sepia", "system"];
+
+fn default_theme() -> String {
+    "system".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -29,6 +96,15 @@ struct Project {
     styles: Option<serde_json::Value>,
     #[serde(rename = "pageSettings", skip_serializing_if = "Option::is_none")]
     page_settings: Option<serde_json::Value>,
+    // Bumped by every save_project; lets a window detect that another one saved first
+    #[serde(default)]
+    rev: u64,
+    // "ltr" or "rtl" reading direction for EPUB export; ltr when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction: Option<String>,
+    // BCP 47 language tag (e.g. "fr") for EPUB export; "en" when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -43,6 +119,13 @@ struct LoadProjectResponse {
     project: Project,
     chapters: Vec<Chapter>,
     path: String,
+    // Chapter id -> last edit time (RFC 3339)
+    #[serde(rename = "chapterModified")]
+    chapter_modified: HashMap<String, String>,
+    // Chapter to open first: project.json's lastOpenedChapter if it still exists, else the
+    // first chapter; None when the project has no chapters
+    #[serde(rename = "lastOpenedChapter")]
+    last_opened_chapter: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -51,70 +134,417 @@ struct CreateProjectResponse {
     path: String,
 }
 
-// Get the config directory for Scout
-fn get_config_dir(handle: &AppHandle) -> Result<PathBuf, String> {
-    let mut config_dir = handle
-        .path()
-        .app_config_dir()
-        .map_err(|e| format!("Failed to get app config dir: {}", e))?;
-    config_dir.push("Scout");
-    Ok(config_dir)
+// App-level directories, resolved once from the AppHandle. Commands are thin wrappers
+// over `*_in` functions taking these, so settings can be exercised against any folder.
+struct AppDirs {
+    // Scout's config folder (config.json, global dictionary)
+    config: PathBuf,
+    // Directory that relative lastProjectPath values in the config are resolved against
+    data: PathBuf,
+}
+
+impl AppDirs {
+    fn of(handle: &AppHandle) -> Result<Self, String> {
+        let config = handle
+            .path()
+            .app_config_dir()
+            .map_err(|e| format!("Failed to get app config dir: {}", e))?
+            .join("Scout");
+        let data = handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+        Ok(AppDirs { config, data })
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.config.join("config.json")
+    }
+}
+
+// Express `path` relative to `base` when it lives inside base or base's parent folder
+// (e.g. "exports" or "../exports"), so the stored value survives moving the whole tree.
+// Anything further away is kept absolute.
+fn to_portable_path(path: &Path, base: &Path) -> String {
+    if let Ok(inside) = path.strip_prefix(base) {
+        let rel = inside.to_string_lossy().replace('\\', "/");
+        return if rel.is_empty() { ".".to_string() } else { rel };
+    }
+    if let Some(sibling) = base.parent().filter(|p| p.parent().is_some()).and_then(|p| path.strip_prefix(p).ok()) {
+        let rel = sibling.to_string_lossy().replace('\\', "/");
+        return if rel.is_empty() { "..".to_string() } else { format!("../{}", rel) };
+    }
+    path.to_string_lossy().to_string()
 }
 
-// Get the config file path
-fn get_config_path(handle: &AppHandle) -> Result<PathBuf, String> {
-    let mut path = get_config_dir(handle)?;
-    path.push("config.json");
-    Ok(path)
+// Resolve a stored path: absolute values are used as-is, relative ones are joined to `base`
+fn resolve_portable_path(stored: &str, base: &Path) -> PathBuf {
+    let stored_path = PathBuf::from(stored);
+    if stored_path.is_absolute() {
+        return stored_path;
+    }
+    let mut resolved = base.to_path_buf();
+    for part in stored.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => { resolved.pop(); }
+            _ => resolved.push(part),
+        }
+    }
+    resolved
 }
 
 // Read config from app config directory
 #[tauri::command]
 fn read_config(handle: AppHandle) -> Result<Config, String> {
-    let config_path = get_config_path(&handle)?;
+    read_config_in(&AppDirs::of(&handle)?)
+}
+
+fn read_config_in(dirs: &AppDirs) -> Result<Config, String> {
+    let config_path = dirs.config_path();
 
     if !config_path.exists() {
         // Return default config if file doesn't exist
         return Ok(Config {
             last_project_path: None,
             font_family: None,
+            theme: default_theme(),
+            window: None,
+            autosave_interval_seconds: None,
+            recent_projects: Vec::new(),
+            default_author: None,
+            json_indent: None,
         });
     }
 
     let content = fs::read_to_string(&config_path)
         .map_err(|e| format!("Failed to read config: {}", e))?;
 
+    let mut config: Config = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse config: {}", e))?;
+
+    // Hand the frontend an absolute path even when a relative one is stored
+    if let Some(stored) = config.last_project_path.as_deref() {
+        if !Path::new(stored).is_absolute() {
+            config.last_project_path = Some(resolve_portable_path(stored, &dirs.data).to_string_lossy().to_string());
+        }
+    }
+
+    Ok(config)
+}
+
+// Write config to app config directory.
+// With `portable`, lastProjectPath is stored relative to the app data dir when possible.
+#[tauri::command]
+fn write_config(handle: AppHandle, last_project_path: String, portable: Option<bool>) -> Result<(), String> {
+    write_config_in(&AppDirs::of(&handle)?, last_project_path, portable)
+}
+
+fn write_config_in(dirs: &AppDirs, last_project_path: String, portable: Option<bool>) -> Result<(), String> {
+    // Merge into the existing file so other settings (font, theme, ...) survive
+    let mut config = read_config_value(dirs)?;
+
+    let mut recent: Vec<String> = config.get("recentProjects")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    recent.retain(|p| p != &last_project_path);
+    recent.insert(0, last_project_path.clone());
+    recent.truncate(RECENT_PROJECTS_LIMIT);
+    config["recentProjects"] = serde_json::json!(recent);
+
+    let last_project_path = if portable.unwrap_or(false) {
+        to_portable_path(Path::new(&last_project_path), &dirs.data)
+    } else {
+        last_project_path
+    };
+    config["lastProjectPath"] = serde_json::json!(last_project_path);
+    write_config_value(dirs, &config)
+}
+
+// True only when project.json is definitely gone. Errors such as permission denied
+// (e.g. a network drive that's offline) count as present so the entry isn't lost.
+fn project_is_missing(path: &str) -> bool {
+    matches!(
+        fs::metadata(Path::new(path).join("project.json")),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound
+    )
+}
+
+// Drop recent projects whose project.json no longer exists; returns the remaining list
+#[tauri::command]
+fn prune_recent_projects(handle: AppHandle) -> Result<Vec<String>, String> {
+    prune_recent_projects_in(&AppDirs::of(&handle)?)
+}
+
+fn prune_recent_projects_in(dirs: &AppDirs) -> Result<Vec<String>, String> {
+    let mut config = read_config_value(dirs)?;
+    let recent: Vec<String> = config.get("recentProjects")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+
+    let live: Vec<String> = recent.iter()
+        .filter(|p| !project_is_missing(p))
+        .cloned()
+        .collect();
+    if live.len() != recent.len() {
+        config["recentProjects"] = serde_json::json!(live);
+        write_config_value(dirs, &config)?;
+    }
+    Ok(live)
+}
+
+// Read config.json as raw JSON ({} if missing) so single settings can be updated in place
+fn read_config_value(dirs: &AppDirs) -> Result<serde_json::Value, String> {
+    let config_path = dirs.config_path();
+    if !config_path.exists() {
+        return Ok(serde_json::json!({}));
+    }
+    let content = fs::read_to_string(&config_path)
+        .map_err(|e| format!("Failed to read config: {}", e))?;
     serde_json::from_str(&content)
         .map_err(|e| format!("Failed to parse config: {}", e))
 }
 
-// Write config to app config directory
+fn write_config_value(dirs: &AppDirs, config: &serde_json::Value) -> Result<(), String> {
+    fs::create_dir_all(&dirs.config)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    let json = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    fs::write(dirs.config_path(), json)
+        .map_err(|e| format!("Failed to write config: {}", e))
+}
+
+// Get the app theme (light, dark, sepia or system); "system" when never set
 #[tauri::command]
-fn write_config(handle: AppHandle, last_project_path: String) -> Result<(), String> {
-    let config_dir = get_config_dir(&handle)?;
+fn get_theme(handle: AppHandle) -> Result<String, String> {
+    get_theme_in(&AppDirs::of(&handle)?)
+}
 
-    // Create config directory if it doesn't exist
-    fs::create_dir_all(&config_dir)
-        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+fn get_theme_in(dirs: &AppDirs) -> Result<String, String> {
+    let config = read_config_value(dirs)?;
+    Ok(config.get("theme")
+        .and_then(|v| v.as_str())
+        .filter(|t| THEMES.contains(t))
+        .map(|t| t.to_string())
+        .unwrap_or_else(default_theme))
+}
 
-    let config = Config {
-        last_project_path: Some(last_project_path),
-        font_family: None,
+// Save window geometry; sizes are clamped to a usable minimum
+#[tauri::command]
+fn save_window_state(handle: AppHandle, state: WindowState) -> Result<(), String> {
+    save_window_state_in(&AppDirs::of(&handle)?, state)
+}
+
+fn save_window_state_in(dirs: &AppDirs, state: WindowState) -> Result<(), String> {
+    let mut config = read_config_value(dirs)?;
+    config["window"] = serde_json::to_value(state.sanitized())
+        .map_err(|e| format!("Failed to serialize window state: {}", e))?;
+    write_config_value(dirs, &config)
+}
+
+// Saved window geometry, or None if never saved. Off-screen positions are dropped.
+#[tauri::command]
+fn read_window_state(handle: AppHandle) -> Result<Option<WindowState>, String> {
+    read_window_state_in(&AppDirs::of(&handle)?)
+}
+
+fn read_window_state_in(dirs: &AppDirs) -> Result<Option<WindowState>, String> {
+    let config = read_config_value(dirs)?;
+    Ok(config.get("window")
+        .and_then(|w| serde_json::from_value::<WindowState>(w.clone()).ok())
+        .map(WindowState::sanitized))
+}
+
+// Autosave interval in seconds (30 when unset or out of range)
+#[tauri::command]
+fn get_autosave_interval(handle: AppHandle) -> Result<u32, String> {
+    get_autosave_interval_in(&AppDirs::of(&handle)?)
+}
+
+fn get_autosave_interval_in(dirs: &AppDirs) -> Result<u32, String> {
+    let config = read_config_value(dirs)?;
+    Ok(config.get("autosaveIntervalSeconds")
+        .and_then(|v| v.as_u64())
+        .and_then(|v| u32::try_from(v).ok())
+        .filter(|v| AUTOSAVE_INTERVAL_RANGE.contains(v))
+        .unwrap_or(DEFAULT_AUTOSAVE_INTERVAL_SECONDS))
+}
+
+#[tauri::command]
+fn set_autosave_interval(handle: AppHandle, seconds: u32) -> Result<(), String> {
+    set_autosave_interval_in(&AppDirs::of(&handle)?, seconds)
+}
+
+fn set_autosave_interval_in(dirs: &AppDirs, seconds: u32) -> Result<(), String> {
+    if !AUTOSAVE_INTERVAL_RANGE.contains(&seconds) {
+        return Err(format!(
+            "Autosave interval must be between {} and {} seconds",
+            AUTOSAVE_INTERVAL_RANGE.start(), AUTOSAVE_INTERVAL_RANGE.end()
+        ));
+    }
+    let mut config = read_config_value(dirs)?;
+    config["autosaveIntervalSeconds"] = serde_json::json!(seconds);
+    write_config_value(dirs, &config)
+}
+
+#[tauri::command]
+fn set_theme(handle: AppHandle, theme: String) -> Result<(), String> {
+    set_theme_in(&AppDirs::of(&handle)?, theme)
+}
+
+fn set_theme_in(dirs: &AppDirs, theme: String) -> Result<(), String> {
+    if !THEMES.contains(&theme.as_str()) {
+        return Err(format!("Unknown theme '{}'; expected one of {}", theme, THEMES.join(", ")));
+    }
+    let mut config = read_config_value(dirs)?;
+    config["theme"] = serde_json::json!(theme);
+    write_config_value(dirs, &config)
+}
+
+// Author name new projects start with; None when never set
+#[tauri::command]
+fn get_default_author(handle: AppHandle) -> Result<Option<String>, String> {
+    get_default_author_in(&AppDirs::of(&handle)?)
+}
+
+fn get_default_author_in(dirs: &AppDirs) -> Result<Option<String>, String> {
+    let config = read_config_value(dirs)?;
+    Ok(config.get("defaultAuthor")
+        .and_then(|v| v.as_str())
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+        .map(|a| a.to_string()))
+}
+
+// A blank name clears the default
+#[tauri::command]
+fn set_default_author(handle: AppHandle, author: String) -> Result<(), String> {
+    set_default_author_in(&AppDirs::of(&handle)?, author)
+}
+
+fn set_default_author_in(dirs: &AppDirs, author: String) -> Result<(), String> {
+    let mut config = read_config_value(dirs)?;
+    let author = author.trim();
+    match config.as_object_mut() {
+        Some(obj) if author.is_empty() => { obj.remove("defaultAuthor"); }
+        _ => config["defaultAuthor"] = serde_json::json!(author),
+    }
+    write_config_value(dirs, &config)
+}
+
+// How chapter and project JSON is laid out on disk
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JsonIndent {
+    // One line, no whitespace: smallest files
+    Compact,
+    // Pretty-printed with this many spaces per level (1-8)
+    Spaces(u8),
+    Tabs,
+}
+
+impl JsonIndent {
+    // "compact", "tabs" or a number of spaces, e.g. "2"
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.trim() {
+            "compact" => Ok(JsonIndent::Compact),
+            "tabs" => Ok(JsonIndent::Tabs),
+            n => n.parse::<u8>().ok()
+                .filter(|n| (1..=8).contains(n))
+                .map(JsonIndent::Spaces)
+                .ok_or_else(|| format!("Unknown JSON format '{}'; expected \"compact\", \"tabs\" or 1-8 spaces", value)),
+        }
+    }
+
+    fn as_setting(self) -> String {
+        match self {
+            JsonIndent::Compact => "compact".to_string(),
+            JsonIndent::Spaces(n) => n.to_string(),
+            JsonIndent::Tabs => "tabs".to_string(),
+        }
+    }
+}
+
+// The configured JSON format; None (the default) leaves each file written as it always was.
+// Loaded from config.json on startup and kept in step by the set_json_indent command; only
+// those two touch it, so code under test passes a layout explicitly (see layout_json).
+static SAVED_JSON_INDENT: Mutex<Option<JsonIndent>> = Mutex::new(None);
+
+fn saved_json_indent() -> Option<JsonIndent> {
+    *SAVED_JSON_INDENT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn format_json(value: &impl Serialize, indent: JsonIndent) -> Result<String, serde_json::Error> {
+    let indent_str = match indent {
+        JsonIndent::Compact => return serde_json::to_string(value),
+        JsonIndent::Spaces(n) => " ".repeat(n as usize),
+        JsonIndent::Tabs => "\t".to_string(),
     };
+    let mut out = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent_str.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+    value.serialize(&mut serializer)?;
+    Ok(String::from_utf8(out).expect("serde_json writes UTF-8"))
+}
 
-    let config_path = get_config_path(&handle)?;
-    let json = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+// Serialize a chapter or project file in the configured format, or `default` when none is set
+fn to_saved_json(value: &impl Serialize, default: JsonIndent) -> Result<String, serde_json::Error> {
+    layout_json(value, saved_json_indent(), default)
+}
 
-    fs::write(&config_path, json)
-        .map_err(|e| format!("Failed to write config: {}", e))?;
+fn layout_json(value: &impl Serialize, configured: Option<JsonIndent>, default: JsonIndent) -> Result<String, serde_json::Error> {
+    format_json(value, configured.unwrap_or(default))
+}
+
+fn load_json_indent(dirs: &AppDirs) {
+    let indent = read_config_value(dirs).ok()
+        .and_then(|config| config.get("jsonIndent").and_then(|v| v.as_str()).map(JsonIndent::parse))
+        .and_then(Result::ok);
+    *SAVED_JSON_INDENT.lock().unwrap_or_else(|e| e.into_inner()) = indent;
+}
+
+// Configured JSON format ("compact", "tabs" or a number of spaces); None when unset
+#[tauri::command]
+fn get_json_indent(handle: AppHandle) -> Result<Option<String>, String> {
+    get_json_indent_in(&AppDirs::of(&handle)?)
+}
 
+fn get_json_indent_in(dirs: &AppDirs) -> Result<Option<String>, String> {
+    let config = read_config_value(dirs)?;
+    Ok(config.get("jsonIndent")
+        .and_then(|v| v.as_str())
+        .and_then(|v| JsonIndent::parse(v).ok())
+        .map(JsonIndent::as_setting))
+}
+
+// Applies to files as they are next saved; existing files are not rewritten.
+// A blank value restores the default layout.
+#[tauri::command]
+fn set_json_indent(handle: AppHandle, indent: String) -> Result<(), String> {
+    let parsed = set_json_indent_in(&AppDirs::of(&handle)?, indent)?;
+    *SAVED_JSON_INDENT.lock().unwrap_or_else(|e| e.into_inner()) = parsed;
     Ok(())
 }
 
-// Create a new project at the specified path
+// Store the setting in config.json; returns the parsed format for the caller to apply
+fn set_json_indent_in(dirs: &AppDirs, indent: String) -> Result<Option<JsonIndent>, String> {
+    let parsed = if indent.trim().is_empty() { None } else { Some(JsonIndent::parse(&indent)?) };
+    let mut config = read_config_value(dirs)?;
+    match (config.as_object_mut(), parsed) {
+        (Some(obj), None) => { obj.remove("jsonIndent"); }
+        (_, Some(parsed)) => config["jsonIndent"] = serde_json::json!(parsed.as_setting()),
+        (None, None) => {}
+    }
+    write_config_value(dirs, &config)?;
+    Ok(parsed)
+}
+
+// Create a new project at the specified path; the author comes from the configured default
 #[tauri::command]
-fn create_project(path: String, title: String) -> Result<CreateProjectResponse, String> {
+fn create_project(handle: AppHandle, path: String, title: String) -> Result<CreateProjectResponse, String> {
+    create_project_in(&AppDirs::of(&handle)?, path, title)
+}
+
+fn create_project_in(dirs: &AppDirs, path: String, title: String) -> Result<CreateProjectResponse, String> {
     let project_path = PathBuf::from(&path);
 
     // Ensure path exists
@@ -131,20 +561,20 @@ fn create_project(path: String, title: String) -> Result<CreateProjectResponse,
     // Create project.json
     let project = Project {
         title: title.clone(),
-        author: String::new(),
+        // An unreadable config shouldn't stop the project from being created
+        author: get_default_author_in(dirs).ok().flatten().unwrap_or_default(),
         chapter_order: vec![],
         font_family: None,
         export_dir: None,
         styles: None,
         page_settings: None,
+        rev: 0,
+        direction: None,
+        language: None,
     };
 
     let project_file = project_path.join("project.json");
-    let json = serde_json::to_string_pretty(&project)
-        .map_err(|e| format!("Failed to serialize project: {}", e))?;
-
-    fs::write(&project_file, json)
-        .map_err(|e| format!("Failed to write project.json: {}", e))?;
+    write_project_value(&project_file, &project)?;
 
     Ok(CreateProjectResponse {
         project,
@@ -152,6 +582,58 @@ fn create_project(path: String, title: String) -> Result<CreateProjectResponse,
     })
 }
 
+/// A project.json field with the wrong shape, e.g. `chapterOrder[2]` "is not an integer".
+#[derive(Debug, Serialize, Deserialize)]
+struct ProjectFieldError {
+    field: String,
+    problem: String,
+}
+
+impl std::fmt::Display for ProjectFieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {}", self.field, self.problem)
+    }
+}
+
+// Check the fields the Project struct needs, so a hand-edited project.json gets an error
+// naming the bad field rather than serde's "invalid type: string, expected u32"
+fn project_field_errors(project: &serde_json::Value) -> Vec<ProjectFieldError> {
+    let mut errors = Vec::new();
+    let mut error = |field: String, problem: &str| errors.push(ProjectFieldError { field, problem: problem.to_string() });
+
+    let Some(fields) = project.as_object() else {
+        error("project".to_string(), "is not a JSON object");
+        return errors;
+    };
+    for key in ["title", "author"] {
+        match fields.get(key) {
+            None => error(key.to_string(), "is missing"),
+            Some(v) if !v.is_string() => error(key.to_string(), "is not a string"),
+            _ => {}
+        }
+    }
+    match fields.get("chapterOrder") {
+        None => error("chapterOrder".to_string(), "is missing"),
+        Some(serde_json::Value::Array(ids)) => {
+            for (i, id) in ids.iter().enumerate() {
+                if id.as_u64().is_none_or(|n| u32::try_from(n).is_err()) {
+                    error(format!("chapterOrder[{}]", i), "is not an integer");
+                }
+            }
+        }
+        Some(_) => error("chapterOrder".to_string(), "is not an array"),
+    }
+    for key in ["fontFamily", "exportDir", "direction", "language"] {
+        if fields.get(key).is_some_and(|v| !v.is_string() && !v.is_null()) {
+            error(key.to_string(), "is not a string");
+        }
+    }
+    if fields.get("rev").is_some_and(|v| !v.is_u64()) {
+        error("rev".to_string(), "is not a non-negative integer");
+    }
+    errors
+}
+
 // Load a project from the specified path
 #[tauri::command]
 fn load_project(path: String) -> Result<LoadProjectResponse, String> {
@@ -170,6 +652,12 @@ fn load_project(path: String) -> Result<LoadProjectResponse, String> {
     let project_data: serde_json::Value = serde_json::from_str(&content)
         .map_err(|e| format!("Failed to parse project.json: {}", e))?;
 
+    let field_errors = project_field_errors(&project_data);
+    if !field_errors.is_empty() {
+        let problems: Vec<String> = field_errors.iter().map(|e| e.to_string()).collect();
+        return Err(format!("Invalid project.json: {}", problems.join("; ")));
+    }
+
     let project = serde_json::from_value::<Project>(project_data.clone())
         .map_err(|e| format!("Failed to parse project: {}", e))?;
 
@@ -227,10 +715,20 @@ fn load_project(path: String) -> Result<LoadProjectResponse, String> {
         .collect();
     chapters.sort_by_key(|ch| order_map.get(&ch.id).copied().unwrap_or(usize::MAX));
 
+    let chapter_modified = chapter_modified_map(&project_path, &project_data);
+
+    let last_opened_chapter = project_data.get("lastOpenedChapter")
+        .and_then(|v| v.as_u64())
+        .and_then(|id| chapters.iter().find(|ch| ch.id as u64 == id))
+        .or_else(|| chapters.first())
+        .map(|ch| ch.id);
+
     Ok(LoadProjectResponse {
         project,
         chapters,
         path: path.clone(),
+        chapter_modified,
+        last_opened_chapter,
     })
 }
 
@@ -240,6 +738,16 @@ fn save_chapter(
     project_path: String,
     chapter_id: u32,
     json_content: String,
+) -> Result<(), String> {
+    save_chapter_as(project_path, chapter_id, json_content, saved_json_indent())
+}
+
+// save_chapter with the JSON format given explicitly; None writes the content as sent
+fn save_chapter_as(
+    project_path: String,
+    chapter_id: u32,
+    json_content: String,
+    indent: Option<JsonIndent>,
 ) -> Result<(), String> {
     let path = PathBuf::from(project_path);
     let chapters_dir = path.join("chapters");
@@ -251,20 +759,44 @@ fn save_chapter(
     let chapter_file = chapters_dir.join(format!("{}.json", chapter_id));
 
     // Validate JSON before writing
-    serde_json::from_str::<serde_json::Value>(&json_content)
+    let doc = serde_json::from_str::<serde_json::Value>(&json_content)
         .map_err(|e| format!("Invalid JSON content: {}", e))?;
+    // Written as sent unless a JSON format is configured
+    let json_content = match indent {
+        Some(indent) => format_json(&doc, indent).map_err(|e| format!("Failed to serialize chapter: {}", e))?,
+        None => json_content,
+    };
 
-    fs::write(&chapter_file, json_content)
+    // Unchanged content (e.g. the save-all before an export) keeps its modified time
+    if fs::read_to_string(&chapter_file).is_ok_and(|existing| existing == json_content) {
+        return Ok(());
+    }
+
+    write_file_atomic(&chapter_file, &json_content)
         .map_err(|e| format!("Failed to save chapter: {}", e))?;
 
+    // Stamp the edit time so the sidebar can show "edited 2 hours ago"
+    let project_file = path.join("project.json");
+    if project_file.exists() {
+        let mut project = read_project_value(&project_file)?;
+        project["chapterModified"][chapter_id.to_string()] = serde_json::json!(Local::now().to_rfc3339());
+        write_project_value(&project_file, &project)?;
+    }
+
     Ok(())
 }
 
 // Save project metadata (title, author, chapter order).
 // Merges into existing project.json to preserve fields the frontend doesn't know about
 // (e.g. chapterTitles, exportDir set by other commands).
+// With `expected_rev`, the save is refused with a "Conflict" error when another window has
+// saved since this one loaded; the frontend then reloads. Returns the new revision.
 #[tauri::command]
-fn save_project(project_path: String, project_data: serde_json::Value) -> Result<(), String> {
+fn save_project(
+    project_path: String,
+    project_data: serde_json::Value,
+    expected_rev: Option<u64>,
+) -> Result<u64, String> {
     let path = PathBuf::from(project_path);
     let project_file = path.join("project.json");
 
@@ -277,6 +809,16 @@ fn save_project(project_path: String, project_data: serde_json::Value) -> Result
         serde_json::json!({})
     };
 
+    let disk_rev = merged.get("rev").and_then(|v| v.as_u64()).unwrap_or(0);
+    if let Some(expected) = expected_rev {
+        if expected != disk_rev {
+            return Err(format!(
+                "Conflict: project was saved elsewhere (revision {}, expected {}); reload the project",
+                disk_rev, expected
+            ));
+        }
+    }
+
     // Overlay new fields from frontend
     if let (Some(merged_obj), Some(new_obj)) = (merged.as_object_mut(), project_data.as_object()) {
         for (key, value) in new_obj {
@@ -286,19 +828,155 @@ fn save_project(project_path: String, project_data: serde_json::Value) -> Result
         merged = project_data;
     }
 
-    let json = serde_json::to_string_pretty(&merged)
-        .map_err(|e| format!("Failed to serialize project: {}", e))?;
+    // The revision is owned here; a stale value sent by the frontend never wins
+    let new_rev = disk_rev + 1;
+    if let Some(merged_obj) = merged.as_object_mut() {
+        merged_obj.insert("rev".to_string(), serde_json::json!(new_rev));
+    }
 
-    fs::write(&project_file, json)
-        .map_err(|e| format!("Failed to write project.json: {}", e))?;
+    write_project_value(&project_file, &merged)?;
 
-    Ok(())
+    Ok(new_rev)
+}
+
+// Convert TipTap inline content (text nodes with bold/italic marks) to RTF, escaping the text
+fn json_to_rtf_inline(items: &[serde_json::Value]) -> String {
+    let mut rtf = String::new();
+    for item in items {
+        if let Some(marks) = item.get("marks").and_then(|m| m.as_array()) {
+            let mut is_bold = false;
+            let mut is_italic = false;
+            for mark in marks {
+                if let Some(mark_type) = mark.get("type").and_then(|t| t.as_str()) {
+                    if mark_type == "bold" {
+                        is_bold = true;
+                    }
+                    if mark_type == "italic" {
+                        is_italic = true;
+                    }
+                }
+            }
+            if is_bold {
+                rtf.push_str("\\b ");
+            }
+            if is_italic {
+                rtf.push_str("\\i ");
+            }
+        }
+        if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
+            rtf.push_str(&escape_rtf(text));
+        }
+        rtf.push_str("\\b0\\i0 ");
+    }
+    rtf
+}
+
+/// Shift a heading level by `offset`, keeping it a valid h1–h6.
+fn offset_heading_level(level: u64, offset: i8) -> u64 {
+    (level as i64 + offset as i64).clamp(1, 6) as u64
+}
+
+/// How RTF export sizes headings.
+#[derive(Debug, Default)]
+struct RtfHeadingStyle {
+    // Heading level -> font size in pt; unlisted levels keep the defaults
+    sizes: HashMap<u8, f64>,
+    // Added to every heading level before sizing (clamped to 1–6)
+    offset: i8,
+}
+
+/// Line spacing for RTF body paragraphs; manuscript guidelines often ask for double.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum RtfLineSpacing {
+    #[default]
+    Single,
+    OneAndHalf,
+    Double,
+}
+
+impl RtfLineSpacing {
+    fn parse(spacing: Option<&str>) -> Result<Self, String> {
+        match spacing {
+            None | Some("single") => Ok(RtfLineSpacing::Single),
+            Some("1.5") => Ok(RtfLineSpacing::OneAndHalf),
+            Some("double") => Ok(RtfLineSpacing::Double),
+            Some(other) => Err(format!("Unsupported line spacing: {} (expected single, 1.5 or double)", other)),
+        }
+    }
+
+    // Control words placed after \pard; with \slmult1, \sl counts 240ths of a single line
+    fn control_words(self) -> &'static str {
+        match self {
+            RtfLineSpacing::Single => "",
+            RtfLineSpacing::OneAndHalf => "\\sl360\\slmult1 ",
+            RtfLineSpacing::Double => "\\sl480\\slmult1 ",
+        }
+    }
+}
+
+/// Paragraph formatting applied to RTF body text.
+#[derive(Debug, Default, Clone, Copy)]
+struct RtfBodyFormat {
+    line_spacing: RtfLineSpacing,
+    // First-line indent of plain paragraphs, in twips (720 = 0.5in); 0 for none
+    first_line_indent: u32,
+}
+
+// Heading size in half-points (RTF \fs units). A custom size in pt overrides the built-in default for its level.
+fn rtf_heading_half_points(level: Option<u64>, headings: &RtfHeadingStyle) -> u32 {
+    let level = level.map(|l| offset_heading_level(l, headings.offset));
+    let custom = level
+        .and_then(|l| u8::try_from(l).ok())
+        .and_then(|l| headings.sizes.get(&l))
+        .filter(|pt| pt.is_finite() && **pt > 0.0);
+    if let Some(pt) = custom {
+        return (pt * 2.0).round() as u32;
+    }
+    match level {
+        Some(2) => 32, // 16pt
+        Some(3) => 28, // 14pt
+        Some(4) => 24, // 12pt
+        Some(_) => 20, // 10pt
+        None => 28,
+    }
+}
+
+// Escape RTF control characters; anything outside ASCII becomes a \uN? escape
+fn escape_rtf(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\line "),
+            c if c.is_ascii() => out.push(c),
+            c => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    out.push_str(&format!("\\u{}?", *unit as i16));
+                }
+            }
+        }
+    }
+    out
+}
+
+// An italic, indented epigraph block with its attribution right-aligned, then a blank line
+fn rtf_epigraph(epigraph: &Epigraph) -> String {
+    let mut rtf = format!("{{\\pard \\li1440\\ri1440 \\i {}\\i0\\par}}\n", escape_rtf(&epigraph.text));
+    if let Some(attribution) = epigraph.attribution.as_deref() {
+        rtf.push_str(&format!("{{\\pard \\qr\\li1440\\ri1440 \\u8212? {}\\par}}\n", escape_rtf(attribution)));
+    }
+    rtf.push_str("{\\pard \\par}\n");
+    rtf
 }
 
-// Convert TipTap JSON content to RTF
 // Convert TipTap JSON content to RTF body (without document header/footer)
-fn json_to_rtf_content(content: &Option<serde_json::Value>) -> String {
+fn json_to_rtf_content(content: &Option<serde_json::Value>, headings: &RtfHeadingStyle, body: &RtfBodyFormat) -> String {
     let mut rtf = String::new();
+    let spacing = body.line_spacing.control_words();
 
     if let Some(doc) = content {
         if let Some(nodes) = doc.get("content").and_then(|c| c.as_array()) {
@@ -307,72 +985,41 @@ fn json_to_rtf_content(content: &Option<serde_json::Value>) -> String {
                     match node_type {
                         "paragraph" => {
                             rtf.push_str("{\\pard ");
+                            rtf.push_str(spacing);
+                            if body.first_line_indent > 0 {
+                                rtf.push_str(&format!("\\fi{} ", body.first_line_indent));
+                            }
                             if let Some(node_content) = node.get("content").and_then(|c| c.as_array()) {
-                                for item in node_content {
-                                    if let Some(marks) = item.get("marks").and_then(|m| m.as_array()) {
-                                        let mut is_bold = false;
-                                        let mut is_italic = false;
-                                        for mark in marks {
-                                            if let Some(mark_type) = mark.get("type").and_then(|t| t.as_str()) {
-                                                if mark_type == "bold" {
-                                                    is_bold = true;
-                                                }
-                                                if mark_type == "italic" {
-                                                    is_italic = true;
-                                                }
-                                            }
-                                        }
-                                        if is_bold {
-                                            rtf.push_str("\\b ");
-                                        }
-                                        if is_italic {
-                                            rtf.push_str("\\i ");
-                                        }
-                                    }
-                                    if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
-                                        rtf.push_str(text);
-                                    }
-                                    rtf.push_str("\\b0\\i0 ");
-                                }
+                                rtf.push_str(&json_to_rtf_inline(node_content));
                             }
                             rtf.push_str("\\par}\n");
                         }
                         "heading" => {
-                            if let Some(level) = node.get("attrs").and_then(|a| a.get("level")).and_then(|l| l.as_u64()) {
-                                let font_size = match level {
-                                    2 => 32,  // 16pt
-                                    3 => 28,  // 14pt
-                                    4 => 24,  // 12pt
-                                    _ => 20,  // 10pt
-                                };
-                                rtf.push_str(&format!("{{\\pard \\fs{} \\b ", font_size));
-                            } else {
-                                rtf.push_str("{\\pard \\fs28 \\b ");
-                            }
+                            let level = node.get("attrs").and_then(|a| a.get("level")).and_then(|l| l.as_u64());
+                            let font_size = rtf_heading_half_points(level, headings);
+                            rtf.push_str(&format!("{{\\pard {}\\fs{} \\b ", spacing, font_size));
 
                             if let Some(node_content) = node.get("content").and_then(|c| c.as_array()) {
                                 for item in node_content {
                                     if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
-                                        rtf.push_str(text);
+                                        rtf.push_str(&escape_rtf(text));
                                     }
                                 }
                             }
                             rtf.push_str("\\b0\\par}\n");
                         }
                         "blockquote" => {
-                            rtf.push_str("{\\pard \\li720 ");
+                            // One indented paragraph per child paragraph, keeping bold/italic
                             if let Some(node_content) = node.get("content").and_then(|c| c.as_array()) {
                                 for item in node_content {
+                                    rtf.push_str("{\\pard \\li720 ");
+                                    rtf.push_str(spacing);
                                     if let Some(item_content) = item.get("content").and_then(|c| c.as_array()) {
-                                        for content_item in item_content {
-                                            if let Some(text) = content_item.get("text").and_then(|t| t.as_str()) {
-                                                rtf.push_str(text);
-                                            }
-                                        }
+                                        rtf.push_str(&json_to_rtf_inline(item_content));
                                     }
+                                    rtf.push_str("\\par}\n");
                                 }
                             }
-                            rtf.push_str("\\par}\n");
                         }
                         _ => {}
                     }
@@ -384,7 +1031,8 @@ fn json_to_rtf_content(content: &Option<serde_json::Value>) -> String {
     rtf
 }
 
-// Get default export directory (parent of project folder)
+// Get default export directory (parent of project folder).
+// A saved exportDir may be relative to the project root; it's only used if it still exists.
 #[tauri::command]
 fn get_default_export_dir(project_path: String) -> Result<String, String> {
     let path = PathBuf::from(&project_path);
@@ -397,8 +1045,9 @@ fn get_default_export_dir(project_path: String) -> Result<String, String> {
 
         if let Ok(project) = serde_json::from_str::<serde_json::Value>(&content) {
             if let Some(export_dir) = project.get("exportDir").and_then(|d| d.as_str()) {
-                if !export_dir.is_empty() {
-                    return Ok(export_dir.to_string());
+                let resolved = resolve_portable_path(export_dir, &path);
+                if !export_dir.is_empty() && resolved.is_dir() {
+                    return Ok(resolved.to_string_lossy().to_string());
                 }
             }
         }
@@ -414,9 +1063,10 @@ fn get_default_export_dir(project_path: String) -> Result<String, String> {
     Err("Could not determine export directory".to_string())
 }
 
-// Update the project's saved export directory
+// Update the project's saved export directory.
+// With `portable`, it's stored relative to the project root when possible.
 #[tauri::command]
-fn update_export_dir(project_path: String, new_export_dir: String) -> Result<(), String> {
+fn update_export_dir(project_path: String, new_export_dir: String, portable: Option<bool>) -> Result<(), String> {
     let path = PathBuf::from(&project_path);
     let project_file = path.join("project.json");
 
@@ -430,41 +1080,70 @@ fn update_export_dir(project_path: String, new_export_dir: String) -> Result<(),
     let mut project: serde_json::Value = serde_json::from_str(&content)
         .map_err(|e| format!("Failed to parse project.json: {}", e))?;
 
+    let new_export_dir = if portable.unwrap_or(false) {
+        to_portable_path(Path::new(&new_export_dir), &path)
+    } else {
+        new_export_dir
+    };
     project["exportDir"] = serde_json::json!(new_export_dir);
 
-    let json = serde_json::to_string_pretty(&project)
-        .map_err(|e| format!("Failed to serialize project: {}", e))?;
-
-    fs::write(&project_file, json)
-        .map_err(|e| format!("Failed to write project.json: {}", e))?;
+    write_project_value(&project_file, &project)?;
 
     Ok(())
 }
 
 // Convert plain text to TipTap JSON
-fn text_to_tiptap_json(text: &str) -> serde_json::Value {
-	let paragraphs: Vec<&str> = text.split("\n\n").collect();
-	let mut content = Vec::new();
-
-	for para in paragraphs {
-		if !para.trim().is_empty() {
-			let para_content = vec![serde_json::json!({
-				"type": "text",
-				"text": para.trim()
-			})];
-			content.push(serde_json::json!({
-				"type": "paragraph",
-				"content": para_content
-			}));
+// Paragraphs are separated by one or more blank (or whitespace-only) lines, which never become
+// empty paragraphs unless `keep_blank_lines` asks for one where two or more blank lines were.
+// With `preserve_line_breaks`, single newlines inside a paragraph become hardBreak nodes
+// instead of being kept as plain text.
+// With `detect_lists`, blocks starting with two "1." / "1)" or "-" / "*" / "•" lines become lists.
+fn text_to_tiptap_json(text: &str, options: &ImportOptions) -> serde_json::Value {
+	let preserve_line_breaks = options.preserve_line_breaks;
+	let single_line = options.paragraph_mode == ParagraphMode::SingleLine;
+	let text = text.replace("\r\n", "\n");
+	let mut content: Vec<serde_json::Value> = Vec::new();
+
+	for (blank_lines, mut para) in text_blocks(&text) {
+		if single_line && blank_lines > 0 && !content.is_empty() {
+			content.push(serde_json::json!({ "type": "horizontalRule" }));
+		} else if options.keep_blank_lines && blank_lines > 1 && !content.is_empty() {
+			content.push(serde_json::json!({ "type": "paragraph", "content": [] }));
 		}
-	}
-
-	if content.is_empty() {
-		content.push(serde_json::json!({
-			"type": "paragraph",
-			"content": []
-		}));
-	}
+		if options.detect_lists {
+			if let Some((lists, rest)) = text_lists(para, preserve_line_breaks, content.last()) {
+				for list in lists {
+					append_text_list(&mut content, list);
+				}
+				// Unindented lines after the list are an ordinary paragraph
+				match rest {
+					Some(rest) => para = rest,
+					None => continue,
+				}
+			}
+		}
+		let lines: Vec<&str> = para.lines().collect();
+		if single_line {
+			for line in lines {
+				content.push(serde_json::json!({
+					"type": "paragraph",
+					"content": [{ "type": "text", "text": line.trim() }]
+				}));
+			}
+			continue;
+		}
+		content.push(serde_json::json!({
+			"type": "paragraph",
+			"content": text_line_nodes(&lines, preserve_line_breaks)
+		}));
+	}
+
+	if content.is_empty() {
+		content.push(serde_json::json!({
+			"type": "paragraph",
+			"content": []
+		}));
+	}
 
 	serde_json::json!({
 		"type": "doc",
@@ -472,22 +1151,305 @@ fn text_to_tiptap_json(text: &str) -> serde_json::Value {
 	})
 }
 
-// Convert markdown to TipTap JSON with full formatting support
-fn markdown_to_tiptap_json(markdown: &str) -> serde_json::Value {
-	let parser = Parser::new(markdown);
+// Paragraphs of plain text (runs of non-blank lines, trimmed), each with the number of blank
+// or whitespace-only lines before it
+fn text_blocks(text: &str) -> Vec<(usize, &str)> {
+	let mut blocks = Vec::new();
+	let mut blank_lines = 0;
+	let mut block_start: Option<usize> = None;
+	let mut offset = 0;
+	for line in text.split_inclusive('\n') {
+		if line.trim().is_empty() {
+			if let Some(start) = block_start.take() {
+				blocks.push((blank_lines, text[start..offset].trim()));
+				blank_lines = 0;
+			}
+			blank_lines += 1;
+		} else if block_start.is_none() {
+			block_start = Some(offset);
+		}
+		offset += line.len();
+	}
+	if let Some(start) = block_start {
+		blocks.push((blank_lines, text[start..].trim()));
+	}
+	blocks
+}
+
+// Inline content for lines of one paragraph, joined by hardBreaks or kept as a single text node
+fn text_line_nodes(lines: &[&str], preserve_line_breaks: bool) -> Vec<serde_json::Value> {
+	if !preserve_line_breaks {
+		return vec![serde_json::json!({ "type": "text", "text": lines.join("\n") })];
+	}
+	let mut nodes = Vec::new();
+	for (i, line) in lines.iter().enumerate() {
+		if i > 0 {
+			nodes.push(serde_json::json!({ "type": "hardBreak" }));
+		}
+		if !line.is_empty() {
+			nodes.push(serde_json::json!({ "type": "text", "text": line }));
+		}
+	}
+	nodes
+}
+
+// A plain-text list marker: Some(n) for "n." / "n)", None for a bullet; plus the item text
+fn text_list_marker(line: &str) -> Option<(Option<u64>, &str)> {
+	let line = line.trim_start();
+	if is_text_thematic_break(line) {
+		return None;
+	}
+	for bullet in ['-', '*', '+', '•'] {
+		if let Some(rest) = line.strip_prefix(bullet) {
+			if rest.starts_with([' ', '\t']) {
+				return Some((None, rest.trim()));
+			}
+		}
+	}
+	// Four or more digits reads as a year ("1990. A year."), not an item number
+	let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+	if digits == 0 || digits > 3 {
+		return None;
+	}
+	let rest = line[digits..].strip_prefix(['.', ')'])?;
+	if !rest.starts_with([' ', '\t']) {
+		return None;
+	}
+	Some((line[..digits].parse().ok(), rest.trim()))
+}
+
+// A line of three or more `*`, `-` or `_` (optionally spaced, e.g. "* * *"): a scene break
+fn is_text_thematic_break(line: &str) -> bool {
+	let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+	marks.len() >= 3 && ['*', '-', '_'].iter().any(|&mark| marks.iter().all(|&c| c == mark))
+}
+
+// A list parsed from plain text: (start number for ordered lists, item lines)
+type TextList<'a> = (Option<u64>, Vec<Vec<&'a str>>);
+
+// Lists in a block that opens with two consecutive list-marker lines, or with one that carries
+// on `previous` (items separated by blank lines). Indented lines without a marker continue the
+// previous item; switching between numbered and bulleted starts a new list. The first unindented
+// line without a marker ends the lists and is returned with the rest of the block.
+fn text_lists<'a>(
+	block: &'a str,
+	preserve_line_breaks: bool,
+	previous: Option<&serde_json::Value>,
+) -> Option<(Vec<serde_json::Value>, Option<&'a str>)> {
+	let mut lines = block.split_inclusive('\n');
+	let (first_number, first_text) = text_list_marker(lines.next()?.trim_end())?;
+	let second_is_item = lines.clone().next().is_some_and(|line| text_list_marker(line.trim_end()).is_some());
+	if !second_is_item && !previous.is_some_and(|previous| text_list_continues(previous, first_number)) {
+		return None;
+	}
+
+	let mut lists: Vec<TextList> = vec![(first_number, vec![vec![first_text]])];
+	let mut rest = None;
+	let mut offset = block.len() - lines.clone().map(str::len).sum::<usize>();
+	for raw_line in lines {
+		let line = raw_line.trim_end();
+		let Some((current, items)) = lists.last_mut() else { break };
+		match text_list_marker(line) {
+			Some((number, text)) if number.is_some() == current.is_some() => items.push(vec![text]),
+			Some((number, text)) => lists.push((number, vec![vec![text]])),
+			None if line.starts_with([' ', '\t']) => {
+				if let Some(item) = items.last_mut() {
+					item.push(line.trim());
+				}
+			}
+			None => {
+				rest = Some(block[offset..].trim());
+				break;
+			}
+		}
+		offset += raw_line.len();
+	}
+
+	let lists = lists.into_iter().map(|(start, items)| {
+		let items: Vec<serde_json::Value> = items.iter().map(|lines| serde_json::json!({
+			"type": "listItem",
+			"content": [{
+				"type": "paragraph",
+				"content": text_line_nodes(lines, preserve_line_breaks)
+			}]
+		})).collect();
+		match start {
+			Some(start) if start != 1 => serde_json::json!({
+				"type": "orderedList",
+				"attrs": { "start": start },
+				"content": items
+			}),
+			Some(_) => serde_json::json!({ "type": "orderedList", "content": items }),
+			None => serde_json::json!({ "type": "bulletList", "content": items }),
+		}
+	}).collect();
+	Some((lists, rest))
+}
+
+// Whether a list whose first item is `number` (None for a bullet) continues `previous`:
+// the same kind of list and, for numbered lists, the next number in sequence
+fn text_list_continues(previous: &serde_json::Value, number: Option<u64>) -> bool {
+	match (previous["type"].as_str(), number) {
+		(Some("orderedList"), Some(number)) => {
+			let previous_start = previous["attrs"]["start"].as_u64().unwrap_or(1);
+			let previous_len = previous["content"].as_array().map_or(0, |a| a.len()) as u64;
+			number == previous_start + previous_len
+		}
+		(Some("bulletList"), None) => true,
+		_ => false,
+	}
+}
+
+// Add a list to the document, continuing the previous block's list when it is the same kind
+// (items separated by blank lines)
+fn append_text_list(content: &mut Vec<serde_json::Value>, list: serde_json::Value) {
+	if let Some(previous) = content.last_mut() {
+		let number = (list["type"] == "orderedList").then(|| list["attrs"]["start"].as_u64().unwrap_or(1));
+		if text_list_continues(previous, number) {
+			if let (Some(items), Some(new_items)) = (previous["content"].as_array_mut(), list["content"].as_array()) {
+				items.extend(new_items.iter().cloned());
+				return;
+			}
+		}
+	}
+	content.push(list);
+}
+
+// Split text into (segment, Some(href)) for bare http(s):// or www. URLs and (segment, None) otherwise.
+// Trailing sentence punctuation is left outside the link.
+fn split_bare_urls(text: &str) -> Vec<(String, Option<String>)> {
+	let mut parts = Vec::new();
+	let mut plain_start = 0;
+	let mut i = 0;
+
+	while i < text.len() {
+		let rest = &text[i..];
+		let at_boundary = i == 0 || text[..i].ends_with(|c: char| c.is_whitespace() || c == '(');
+		let is_url = rest.starts_with("https://") || rest.starts_with("http://") || rest.starts_with("www.");
+
+		if at_boundary && is_url {
+			let mut end = rest.find(|c: char| c.is_whitespace() || c == '<').unwrap_or(rest.len());
+			let mut url = &rest[..end];
+			loop {
+				let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"']);
+				let trimmed = if trimmed.ends_with(')') && !trimmed.contains('(') {
+					&trimmed[..trimmed.len() - 1]
+				} else {
+					trimmed
+				};
+				if trimmed.len() == url.len() {
+					break;
+				}
+				url = trimmed;
+			}
+			end = url.len();
+
+			// Require something after the scheme/prefix
+			let min_len = if url.starts_with("www.") { 5 } else { url.find("//").map_or(0, |p| p + 3) };
+			if url.len() >= min_len && min_len > 0 {
+				if plain_start < i {
+					parts.push((text[plain_start..i].to_string(), None));
+				}
+				let href = if url.starts_with("www.") { format!("http://{}", url) } else { url.to_string() };
+				parts.push((url.to_string(), Some(href)));
+				i += end;
+				plain_start = i;
+				continue;
+			}
+		}
+
+		i += rest.chars().next().map_or(1, |c| c.len_utf8());
+	}
+
+	if plain_start < text.len() {
+		parts.push((text[plain_start..].to_string(), None));
+	}
+	parts
+}
+
+// Bold and italic marks for text inside ** and * spans
+fn markdown_emphasis_marks(in_strong: bool, in_em: bool) -> Vec<serde_json::Value> {
+	let mut marks = Vec::new();
+	if in_strong {
+		marks.push(serde_json::json!({ "type": "bold" }));
+	}
+	if in_em {
+		marks.push(serde_json::json!({ "type": "italic" }));
+	}
+	marks
+}
+
+// Append a text node, merging it into the previous one when both carry the same marks
+fn push_inline_text(para: &mut Vec<serde_json::Value>, node: serde_json::Value) {
+	if let Some(last) = para.last_mut() {
+		if last["type"] == "text" && node["type"] == "text" && last.get("marks") == node.get("marks") {
+			let merged = format!("{}{}", last["text"].as_str().unwrap_or(""), node["text"].as_str().unwrap_or(""));
+			last["text"] = serde_json::json!(merged);
+			return;
+		}
+	}
+	para.push(node);
+}
+
+// Block containers open while reading Markdown
+enum MarkdownContainer {
+	// Ordered lists carry their start number
+	List(Option<u64>),
+	Item,
+	BlockQuote,
+	FootnoteDefinition(String),
+}
+
+// Append a finished block to the innermost open container, or the document if none is open
+fn push_markdown_block(
+	containers: &mut [(MarkdownContainer, Vec<serde_json::Value>)],
+	content: &mut Vec<serde_json::Value>,
+	node: serde_json::Value,
+) {
+	match containers.last_mut() {
+		Some((_, children)) => children.push(node),
+		None => content.push(node),
+	}
+}
+
+// End a tight list item's text (which arrives without a Paragraph event) before a nested
+// block opens, so the text stays in the item instead of running into that block
+fn flush_tight_item_text(
+	containers: &mut [(MarkdownContainer, Vec<serde_json::Value>)],
+	current_paragraph: &mut Option<Vec<serde_json::Value>>,
+) {
+	if let Some((MarkdownContainer::Item, item)) = containers.last_mut() {
+		if let Some(para) = current_paragraph.take() {
+			if !para.is_empty() {
+				item.push(serde_json::json!({
+					"type": "paragraph",
+					"content": para
+				}));
+			}
+		}
+	}
+}
+
+// Convert markdown to TipTap JSON with full formatting support.
+// Smart punctuation (curly quotes, en/em dashes) is only applied when requested.
+fn markdown_to_tiptap_json(markdown: &str, smart_punctuation: bool) -> serde_json::Value {
+	let mut options = Options::ENABLE_FOOTNOTES;
+	if smart_punctuation {
+		options.insert(Options::ENABLE_SMART_PUNCTUATION);
+	}
+	// Merge adjacent Text events so URLs split at '_' etc. are scanned whole
+	let parser = TextMergeStream::new(Parser::new_ext(markdown, options));
 	let mut content = Vec::new();
 	let mut current_paragraph: Option<Vec<serde_json::Value>> = None;
 	let mut in_strong = false;
 	let mut in_em = false;
 	let mut _in_code = false;
-	let mut _in_link = false;
-	let mut _link_url = String::new();
+	let mut link_url: Option<String> = None;
 	let mut heading_level = 0;
 	let mut heading_content: Vec<serde_json::Value> = Vec::new();
-	let mut list_stack: Vec<(String, Vec<serde_json::Value>)> = Vec::new(); // (type, items)
-	let mut list_item_content: Option<Vec<serde_json::Value>> = None;
-	let mut blockquote_content: Vec<serde_json::Value> = Vec::new();
-	let mut in_blockquote = false;
+	// Open lists, list items, blockquotes and footnote definitions, innermost last; finished
+	// blocks go into the innermost one (see push_markdown_block)
+	let mut containers: Vec<(MarkdownContainer, Vec<serde_json::Value>)> = Vec::new();
 	let mut code_block_lang = String::new();
 	let mut code_block_content = String::new();
 	let mut in_code_block = false;
@@ -520,17 +1482,17 @@ fn markdown_to_tiptap_json(markdown: &str) -> serde_json::Value {
 							_ => String::new(),
 						};
 					}
-					pulldown_cmark::Tag::Link { .. } => {
-						// Links: extract text as is for now
+					pulldown_cmark::Tag::Link { dest_url, .. } => {
+						// Explicit links and <autolinks>: text inside gets a link mark
+						link_url = Some(dest_url.to_string());
 					}
 					pulldown_cmark::Tag::List(ordered) => {
-						list_stack.push((
-							if ordered.is_some() { "ordered".to_string() } else { "bullet".to_string() },
-							Vec::new(),
-						));
+						// A nested list ends the parent item's tight-list text
+						flush_tight_item_text(&mut containers, &mut current_paragraph);
+						containers.push((MarkdownContainer::List(ordered), Vec::new()));
 					}
 					pulldown_cmark::Tag::Item => {
-						list_item_content = Some(Vec::new());
+						containers.push((MarkdownContainer::Item, Vec::new()));
 						// For tight lists, pulldown_cmark emits Text directly inside Item
 						// without wrapping it in a Paragraph. Pre-init current_paragraph
 						// so those text nodes have somewhere to land.
@@ -539,8 +1501,11 @@ fn markdown_to_tiptap_json(markdown: &str) -> serde_json::Value {
 						}
 					}
 					pulldown_cmark::Tag::BlockQuote(_) => {
-						in_blockquote = true;
-						blockquote_content.clear();
+						flush_tight_item_text(&mut containers, &mut current_paragraph);
+						containers.push((MarkdownContainer::BlockQuote, Vec::new()));
+					}
+					pulldown_cmark::Tag::FootnoteDefinition(label) => {
+						containers.push((MarkdownContainer::FootnoteDefinition(label.to_string()), Vec::new()));
 					}
 					_ => {}
 				}
@@ -549,7 +1514,7 @@ fn markdown_to_tiptap_json(markdown: &str) -> serde_json::Value {
 				match tag {
 					pulldown_cmark::TagEnd::Heading(_) => {
 						if heading_level > 0 {
-							content.push(serde_json::json!({
+							push_markdown_block(&mut containers, &mut content, serde_json::json!({
 								"type": "heading",
 								"attrs": { "level": heading_level },
 								"content": heading_content.clone()
@@ -559,22 +1524,10 @@ fn markdown_to_tiptap_json(markdown: &str) -> serde_json::Value {
 						}
 					}
 					pulldown_cmark::TagEnd::Paragraph => {
+						// Empty paragraphs are only dropped at the top level
 						if let Some(para) = current_paragraph.take() {
-							if in_blockquote {
-								blockquote_content.push(serde_json::json!({
-									"type": "paragraph",
-									"content": para
-								}));
-							} else if !list_stack.is_empty() {
-								// Part of list item, don't add yet
-								if let Some(item) = list_item_content.as_mut() {
-									item.push(serde_json::json!({
-										"type": "paragraph",
-										"content": para
-									}));
-								}
-							} else if !para.is_empty() {
-								content.push(serde_json::json!({
+							if !para.is_empty() || !containers.is_empty() {
+								push_markdown_block(&mut containers, &mut content, serde_json::json!({
 									"type": "paragraph",
 									"content": para
 								}));
@@ -589,7 +1542,7 @@ fn markdown_to_tiptap_json(markdown: &str) -> serde_json::Value {
 					}
 					pulldown_cmark::TagEnd::CodeBlock => {
 						in_code_block = false;
-						content.push(serde_json::json!({
+						push_markdown_block(&mut containers, &mut content, serde_json::json!({
 							"type": "codeBlock",
 							"attrs": { "language": if code_block_lang.is_empty() { serde_json::Value::Null } else { serde_json::Value::String(code_block_lang.clone()) } },
 							"content": [{
@@ -601,25 +1554,41 @@ fn markdown_to_tiptap_json(markdown: &str) -> serde_json::Value {
 						code_block_lang.clear();
 					}
 					pulldown_cmark::TagEnd::Link => {
-						// Link end
+						link_url = None;
 					}
 					pulldown_cmark::TagEnd::List(_) => {
-						if let Some((list_type, items)) = list_stack.pop() {
+						if let Some((MarkdownContainer::List(ordered), items)) = containers.pop() {
 							if !items.is_empty() {
-								let node_type = if list_type == "ordered" { "orderedList" } else { "bulletList" };
-								content.push(serde_json::json!({
-									"type": node_type,
-									"content": items
-								}));
+								let node = match ordered {
+									// Markdown can't express alpha/roman numbering, but it does carry the start number
+									Some(start) if start != 1 => serde_json::json!({
+										"type": "orderedList",
+										"attrs": { "start": start },
+										"content": items
+									}),
+									Some(_) => serde_json::json!({
+										"type": "orderedList",
+										"content": items
+									}),
+									None => serde_json::json!({
+										"type": "bulletList",
+										"content": items
+									}),
+								};
+								// Nested lists belong to the enclosing list item or blockquote
+								push_markdown_block(&mut containers, &mut content, node);
 							}
 						}
+						if matches!(containers.last(), Some((MarkdownContainer::Item, _))) && current_paragraph.is_none() {
+							current_paragraph = Some(Vec::new());
+						}
 					}
 					pulldown_cmark::TagEnd::Item => {
 						// Flush any open paragraph (tight-list text lands here without
 						// a wrapping Paragraph event)
 						if let Some(para) = current_paragraph.take() {
 							if !para.is_empty() {
-								if let Some(item) = list_item_content.as_mut() {
+								if let Some((MarkdownContainer::Item, item)) = containers.last_mut() {
 									item.push(serde_json::json!({
 										"type": "paragraph",
 										"content": para
@@ -627,33 +1596,37 @@ fn markdown_to_tiptap_json(markdown: &str) -> serde_json::Value {
 								}
 							}
 						}
-						if let Some(item_content) = list_item_content.take() {
-							if let Some((_, items)) = list_stack.last_mut() {
-								if item_content.is_empty() {
-									items.push(serde_json::json!({
-										"type": "listItem",
-										"content": [{
-											"type": "paragraph",
-											"content": []
-										}]
-									}));
-								} else {
-									items.push(serde_json::json!({
-										"type": "listItem",
-										"content": item_content
-									}));
-								}
-							}
+						if let Some((MarkdownContainer::Item, item_content)) = containers.pop() {
+							let item_content = if item_content.is_empty() {
+								vec![serde_json::json!({ "type": "paragraph", "content": [] })]
+							} else {
+								item_content
+							};
+							push_markdown_block(&mut containers, &mut content, serde_json::json!({
+								"type": "listItem",
+								"content": item_content
+							}));
 						}
 					}
 					pulldown_cmark::TagEnd::BlockQuote => {
-						in_blockquote = false;
-						if !blockquote_content.is_empty() {
-							content.push(serde_json::json!({
-								"type": "blockquote",
-								"content": blockquote_content.clone()
+						// Nested quotes become a child of the enclosing blockquote or list item
+						if let Some((MarkdownContainer::BlockQuote, blockquote_content)) = containers.pop() {
+							if !blockquote_content.is_empty() {
+								push_markdown_block(&mut containers, &mut content, serde_json::json!({
+									"type": "blockquote",
+									"content": blockquote_content
+								}));
+							}
+						}
+					}
+					pulldown_cmark::TagEnd::FootnoteDefinition => {
+						// Definitions stay where they were written; exporters gather them per chapter
+						if let Some((MarkdownContainer::FootnoteDefinition(label), blocks)) = containers.pop() {
+							push_markdown_block(&mut containers, &mut content, serde_json::json!({
+								"type": "footnoteDefinition",
+								"attrs": { "label": label },
+								"content": blocks
 							}));
-							blockquote_content.clear();
 						}
 					}
 					_ => {}
@@ -661,37 +1634,42 @@ fn markdown_to_tiptap_json(markdown: &str) -> serde_json::Value {
 			}
 			// Inline events
 			Event::Text(text) => {
-				let mut marks = Vec::new();
-				if in_strong {
-					marks.push(serde_json::json!({ "type": "bold" }));
-				}
-				if in_em {
-					marks.push(serde_json::json!({ "type": "italic" }));
-				}
+				let marks = markdown_emphasis_marks(in_strong, in_em);
 
-				let text_node = if marks.is_empty() {
-					serde_json::json!({
-						"type": "text",
-						"text": text.to_string()
-					})
-				} else {
-					serde_json::json!({
-						"type": "text",
-						"text": text.to_string(),
-						"marks": marks
-					})
+				// Inside a link the whole run is linked; elsewhere bare URLs become links
+				let segments = match &link_url {
+					Some(url) => vec![(text.to_string(), Some(url.clone()))],
+					None => split_bare_urls(&text),
 				};
+				let text_nodes: Vec<serde_json::Value> = segments.into_iter()
+					.map(|(segment, href)| {
+						let mut segment_marks = marks.clone();
+						if let Some(href) = href {
+							segment_marks.push(serde_json::json!({ "type": "link", "attrs": { "href": href } }));
+						}
+						if segment_marks.is_empty() {
+							serde_json::json!({
+								"type": "text",
+								"text": segment
+							})
+						} else {
+							serde_json::json!({
+								"type": "text",
+								"text": segment,
+								"marks": segment_marks
+							})
+						}
+					})
+					.collect();
 
 				if in_code_block {
 					code_block_content.push_str(&text);
 				} else if heading_level > 0 {
-					heading_content.push(text_node);
-				} else if in_blockquote {
-					if let Some(para) = current_paragraph.as_mut() {
-						para.push(text_node);
-					}
+					heading_content.extend(text_nodes);
 				} else if let Some(para) = current_paragraph.as_mut() {
-					para.push(text_node);
+					for node in text_nodes {
+						push_inline_text(para, node);
+					}
 				}
 			}
 			Event::Code(text) => {
@@ -705,41 +1683,35 @@ fn markdown_to_tiptap_json(markdown: &str) -> serde_json::Value {
 					code_block_content.push_str(&text);
 				} else if heading_level > 0 {
 					heading_content.push(text_node);
-				} else if in_blockquote {
-					if let Some(para) = current_paragraph.as_mut() {
-						para.push(text_node);
-					}
 				} else if let Some(para) = current_paragraph.as_mut() {
 					para.push(text_node);
 				}
 			}
-			Event::SoftBreak => {
-				// Soft break becomes a space
-				if let Some(para) = current_paragraph.as_mut() {
-					if !para.is_empty() {
-						if let Some(last) = para.last_mut() {
-							if let Some(text_val) = last.get_mut("text") {
-								if let serde_json::Value::String(s) = text_val {
-									s.push(' ');
-								}
-							}
-						}
-					}
+			Event::FootnoteReference(label) => {
+				let node = serde_json::json!({
+					"type": "footnoteReference",
+					"attrs": { "label": label.to_string() }
+				});
+				if heading_level > 0 {
+					heading_content.push(node);
+				} else if let Some(para) = current_paragraph.as_mut() {
+					para.push(node);
 				}
 			}
-			Event::HardBreak => {
-				// Hard break - could end paragraph or just add newline
-				// For now treat as space like soft break
-				if let Some(para) = current_paragraph.as_mut() {
-					if !para.is_empty() {
-						if let Some(last) = para.last_mut() {
-							if let Some(text_val) = last.get_mut("text") {
-								if let serde_json::Value::String(s) = text_val {
-									s.push(' ');
-								}
-							}
-						}
+			// Soft and hard breaks become a space in the surrounding run, so a link or
+			// emphasis that wraps onto the next line stays one text node
+			Event::SoftBreak | Event::HardBreak => {
+				if let Some(para) = current_paragraph.as_mut().filter(|p| !p.is_empty()) {
+					let mut marks = markdown_emphasis_marks(in_strong, in_em);
+					if let Some(url) = &link_url {
+						marks.push(serde_json::json!({ "type": "link", "attrs": { "href": url } }));
 					}
+					let node = if marks.is_empty() {
+						serde_json::json!({ "type": "text", "text": " " })
+					} else {
+						serde_json::json!({ "type": "text", "text": " ", "marks": marks })
+					};
+					push_inline_text(para, node);
 				}
 			}
 			_ => {}
@@ -759,16 +1731,16 @@ fn markdown_to_tiptap_json(markdown: &str) -> serde_json::Value {
 	})
 }
 
-// Split content by delimiter into sections with titles
+// Split content into (title, body) sections at lines starting with the delimiter.
+// The title is None when it wasn't extracted from the delimiter line.
 fn split_by_delimiter(
 	content: &str,
 	delimiter: &str,
 	extract_titles: bool,
-) -> Vec<(String, String)> {
+) -> Vec<(Option<String>, String)> {
 	let lines: Vec<&str> = content.lines().collect();
 	let mut sections = Vec::new();
-	let mut chapter_num = 1;
-	let mut current_title: Option<String> = None;
+	let mut current_title: Option<Option<String>> = None;
 	let mut current_content = Vec::new();
 
 	for line in lines {
@@ -778,7 +1750,6 @@ fn split_by_delimiter(
 				let content_str = current_content.join("\n").trim().to_string();
 				if !content_str.is_empty() || !extract_titles {
 					sections.push((title, content_str));
-					chapter_num += 1;
 				}
 			}
 
@@ -786,12 +1757,12 @@ fn split_by_delimiter(
 			let title = if extract_titles {
 				let after_delim = line.strip_prefix(delimiter).unwrap_or("").trim();
 				if !after_delim.is_empty() {
-					after_delim.to_string()
+					Some(after_delim.to_string())
 				} else {
-					format!("Chapter {}", chapter_num)
+					None
 				}
 			} else {
-				format!("Chapter {}", chapter_num)
+				None
 			};
 
 			current_title = Some(title);
@@ -813,12 +1784,163 @@ fn split_by_delimiter(
 
 	// If no sections were created, return original content as one chapter
 	if sections.is_empty() {
-		sections.push((format!("Chapter 1"), content.to_string()));
+		sections.push((None, content.to_string()));
 	}
 
 	sections
 }
 
+// Split content into (title, body) sections at markdown thematic breaks (`***`, `---`,
+// `* * *`, ...), as parsed by pulldown_cmark so setext underlines and list items aren't
+// mistaken for breaks. With `extract_titles`, a heading opening a section becomes its title.
+fn split_on_thematic_breaks(content: &str, extract_titles: bool) -> Vec<(Option<String>, String)> {
+	let mut bounds = vec![0];
+	for (event, range) in Parser::new(content).into_offset_iter() {
+		if matches!(event, Event::Rule) {
+			bounds.push(range.start);
+			bounds.push(range.end);
+		}
+	}
+	bounds.push(content.len());
+
+	bounds.chunks(2)
+		.map(|b| content[b[0]..b[1]].trim())
+		.filter(|section| !section.is_empty())
+		.map(|section| if extract_titles { take_leading_heading(section) } else { (None, section.to_string()) })
+		.collect()
+}
+
+// Split content into (title, body) sections at form feeds (\x0C), the page breaks some
+// manuscript tools put between chapters. With `extract_titles`, the first non-empty line of a
+// section becomes its title (leading markdown `#`s dropped).
+fn split_on_form_feeds(content: &str, extract_titles: bool) -> Vec<(Option<String>, String)> {
+	content.split('\x0C')
+		.map(|section| section.trim())
+		.filter(|section| !section.is_empty())
+		.map(|section| {
+			if !extract_titles {
+				return (None, section.to_string());
+			}
+			let (first, rest) = section.split_once('\n').unwrap_or((section, ""));
+			let title = first.trim().trim_start_matches('#').trim();
+			if title.is_empty() {
+				(None, section.to_string())
+			} else {
+				(Some(title.to_string()), rest.trim().to_string())
+			}
+		})
+		.collect()
+}
+
+// Split a section's opening heading off as its title, returning (title, rest)
+fn take_leading_heading(section: &str) -> (Option<String>, String) {
+	let mut title = String::new();
+	let mut heading_end = None;
+	for (event, range) in Parser::new(section).into_offset_iter() {
+		match event {
+			Event::Start(pulldown_cmark::Tag::Heading { .. }) if range.start == 0 => heading_end = Some(range.end),
+			Event::Text(text) | Event::Code(text) if heading_end.is_some() => title.push_str(&text),
+			Event::End(pulldown_cmark::TagEnd::Heading(_)) => break,
+			_ if heading_end.is_none() => break,
+			_ => {}
+		}
+	}
+	match heading_end {
+		Some(end) if !title.trim().is_empty() => (Some(title.trim().to_string()), section[end..].trim().to_string()),
+		_ => (None, section.to_string()),
+	}
+}
+
+// Expand a chapter-title template. Placeholders: {n} (position in this import),
+// {id} (chapter id), {padN} (n zero-padded to N digits, e.g. {pad3} -> 007),
+// {word} (n spelled out, e.g. "Twenty-One") and {roman} (n as a Roman numeral, e.g. XXI).
+// Errors on unknown placeholders or when the template has no placeholder at all.
+fn render_title_template(template: &str, n: u32, id: u32) -> Result<String, String> {
+	let mut out = String::new();
+	let mut found = false;
+	let mut rest = template;
+
+	while let Some(start) = rest.find('{') {
+		out.push_str(&rest[..start]);
+		let after = &rest[start + 1..];
+		let end = after.find('}')
+			.ok_or_else(|| format!("Unclosed placeholder in title template: {}", template))?;
+		let name = &after[..end];
+		match name {
+			"n" => out.push_str(&n.to_string()),
+			"id" => out.push_str(&id.to_string()),
+			"word" => out.push_str(&number_to_words(n)),
+			"roman" => out.push_str(&to_roman(n)),
+			_ => {
+				let width = name.strip_prefix("pad")
+					.and_then(|w| w.parse::<usize>().ok())
+					.filter(|w| (1..=9).contains(w))
+					.ok_or_else(|| format!("Unknown placeholder {{{}}} in title template", name))?;
+				out.push_str(&format!("{:0width$}", n, width = width));
+			}
+		}
+		found = true;
+		rest = &after[end + 1..];
+	}
+	out.push_str(rest);
+
+	if !found {
+		return Err("Title template must contain {n}, {id}, {padN}, {word} or {roman}".to_string());
+	}
+	Ok(out)
+}
+
+// n in title-case words ("One", "Twenty-One", "One Hundred Five"); digits from 10,000 up
+fn number_to_words(n: u32) -> String {
+	const ONES: [&str; 20] = [
+		"Zero", "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine", "Ten",
+		"Eleven", "Twelve", "Thirteen", "Fourteen", "Fifteen", "Sixteen", "Seventeen", "Eighteen", "Nineteen",
+	];
+	const TENS: [&str; 10] = ["", "", "Twenty", "Thirty", "Forty", "Fifty", "Sixty", "Seventy", "Eighty", "Ninety"];
+
+	fn below_hundred(n: u32) -> String {
+		match n {
+			0..=19 => ONES[n as usize].to_string(),
+			_ if n.is_multiple_of(10) => TENS[(n / 10) as usize].to_string(),
+			_ => format!("{}-{}", TENS[(n / 10) as usize], ONES[(n % 10) as usize]),
+		}
+	}
+
+	if n >= 10_000 {
+		return n.to_string();
+	}
+	let mut parts = Vec::new();
+	if n >= 1000 {
+		parts.push(format!("{} Thousand", ONES[(n / 1000) as usize]));
+	}
+	if n % 1000 >= 100 {
+		parts.push(format!("{} Hundred", ONES[(n % 1000 / 100) as usize]));
+	}
+	if !n.is_multiple_of(100) || n == 0 {
+		parts.push(below_hundred(n % 100));
+	}
+	parts.join(" ")
+}
+
+// n as an uppercase Roman numeral; digits when 0 or above 3999
+fn to_roman(mut n: u32) -> String {
+	if n == 0 || n > 3999 {
+		return n.to_string();
+	}
+	const NUMERALS: [(u32, &str); 13] = [
+		(1000, "M"), (900, "CM"), (500, "D"), (400, "CD"), (100, "C"), (90, "XC"),
+		(50, "L"), (40, "XL"), (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I"),
+	];
+	let mut out = String::new();
+	for (value, numeral) in NUMERALS {
+		while n >= value {
+			out.push_str(numeral);
+			n -= value;
+		}
+	}
+	out
+}
+
 // Return a title that isn't already in used_titles, appending (1), (2), … as needed.
 // Comparison is case-insensitive; the set stores lowercased titles.
 fn make_unique_title(title: &str, used_titles: &HashSet<String>) -> String {
@@ -835,114 +1957,411 @@ fn make_unique_title(title: &str, used_titles: &HashSet<String>) -> String {
 	}
 }
 
-// Import chapters from files (text and markdown)
+// Cancellation flags for long-running jobs, keyed by a frontend-chosen job id
+#[derive(Default)]
+struct JobRegistry {
+	jobs: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl JobRegistry {
+	fn register(&self, job_id: &str) -> Arc<AtomicBool> {
+		let flag = Arc::new(AtomicBool::new(false));
+		if let Ok(mut jobs) = self.jobs.lock() {
+			jobs.insert(job_id.to_string(), flag.clone());
+		}
+		flag
+	}
+
+	fn finish(&self, job_id: &str) {
+		if let Ok(mut jobs) = self.jobs.lock() {
+			jobs.remove(job_id);
+		}
+	}
+
+	// Flag a running job to stop; false if no such job is running
+	fn cancel(&self, job_id: &str) -> bool {
+		match self.jobs.lock() {
+			Ok(jobs) => match jobs.get(job_id) {
+				Some(flag) => {
+					flag.store(true, Ordering::SeqCst);
+					true
+				}
+				None => false,
+			},
+			Err(_) => false,
+		}
+	}
+}
+
+// Request cancellation of a running job; returns false if no such job is running
 #[tauri::command]
+fn cancel_job(jobs: tauri::State<'_, JobRegistry>, job_id: String) -> bool {
+	jobs.cancel(&job_id)
+}
+
+// Import chapters from files (text and markdown); see ImportOptions for the settings.
+// When `job_id` is given the import can be stopped with cancel_job; it stops between files
+// and still records the chapters written so far in project.json. Runs off the main thread
+// so cancel_job (and the UI) isn't blocked behind it.
+#[tauri::command(async)]
 fn import_chapters(
+	jobs: tauri::State<'_, JobRegistry>,
 	project_path: String,
 	file_paths: Vec<String>,
-	use_filename_as_title: bool,
-	chapter_delimiter: Option<String>,
-	extract_title_from_delimiter: bool,
+	options: ImportOptions,
+	job_id: Option<String>,
 ) -> Result<Vec<Chapter>, String> {
-	let project_path_buf = PathBuf::from(&project_path);
-	let chapters_dir = project_path_buf.join("chapters");
-	let project_file = project_path_buf.join("project.json");
-
-	// Ensure chapters directory exists
-	fs::create_dir_all(&chapters_dir)
-		.map_err(|e| format!("Failed to create chapters directory: {}", e))?;
+	let options = options.checked()?;
+	let cancel = job_id.as_deref().map(|id| jobs.register(id));
+	let result = import_chapter_files(&project_path, file_paths, &options, cancel.as_deref());
+	if let Some(id) = job_id.as_deref() {
+		jobs.finish(id);
+	}
+	result
+}
 
-	// Read current project to get max chapter ID
-	let mut project_data: serde_json::Value = if project_file.exists() {
-		let content = fs::read_to_string(&project_file)
-			.map_err(|e| format!("Failed to read project.json: {}", e))?;
-		serde_json::from_str(&content)
-			.map_err(|e| format!("Failed to parse project.json: {}", e))?
-	} else {
-		serde_json::json!({
-			"title": "Project",
-			"author": "",
-			"chapterOrder": []
-		})
+// Import pasted text (e.g. a whole manuscript from the clipboard) without a temp file.
+// `format` is "text" or "markdown"; the options behave as in import_chapters, apart from
+// useFilenameAsTitle, which has no file to name chapters after.
+#[tauri::command]
+fn import_text(
+	project_path: String,
+	text: String,
+	format: String,
+	options: ImportOptions,
+) -> Result<Vec<Chapter>, String> {
+	let markdown = match format.as_str() {
+		"markdown" | "md" => true,
+		"text" | "txt" => false,
+		other => return Err(format!("Unsupported import format: {}", other)),
 	};
+	let options = ImportOptions { use_filename_as_title: false, ..options.checked()? };
+	let source = ImportSource { title: None, content: text, markdown };
+	import_sources(&project_path, std::iter::once(Ok(source)), &options, None)
+}
 
-	// Get current max ID
-	let current_ids: Vec<u32> = if let Some(ids) = project_data.get("chapterOrder").and_then(|v| v.as_array()) {
-		ids.iter().filter_map(|id| id.as_u64().map(|i| i as u32)).collect()
-	} else {
-		Vec::new()
-	};
-	let max_id = current_ids.iter().max().copied().unwrap_or(0);
+#[derive(Debug, Serialize, Deserialize)]
+struct ImportFileReport {
+	// "UTF-8", "UTF-8 (BOM)", "UTF-16LE", "UTF-16BE" or "unknown"
+	encoding: String,
+	#[serde(rename = "byteSize")]
+	byte_size: u64,
+	// Chapters import_chapters would create from this file with the same delimiter settings
+	#[serde(rename = "chapterCount")]
+	chapter_count: usize,
+	warnings: Vec<String>,
+}
 
-	// Seed the deduplication set with all titles already in the project
-	let mut used_titles: HashSet<String> = HashSet::new();
-	if let Some(titles_obj) = project_data.get("chapterTitles").and_then(|v| v.as_object()) {
-		for (_, v) in titles_obj {
-			if let Some(t) = v.as_str() {
-				used_titles.insert(t.to_lowercase());
-			}
-		}
+// Dry run of importing one file: reports what import_chapters would make of it without
+// writing anything. Unsupported or unreadable files are errors; problems that would give
+// a poor import (empty, binary, undecodable) are warnings.
+#[tauri::command]
+fn validate_import_file(
+	file_path: String,
+	chapter_delimiter: Option<String>,
+	extract_title_from_delimiter: Option<bool>,
+	split_on_thematic_break: Option<bool>,
+	split_on_form_feed: Option<bool>,
+) -> Result<ImportFileReport, String> {
+	let path = PathBuf::from(&file_path);
+	let extension = path
+		.extension()
+		.and_then(|s| s.to_str())
+		.unwrap_or("")
+		.to_lowercase();
+	if extension != "txt" && extension != "md" {
+		return Err(format!("Unsupported file type: .{} (expected .txt or .md)", extension));
 	}
 
-	let mut imported_chapters = Vec::new();
-	let mut next_id = max_id + 1;
+	let bytes = fs::read(&path)
+		.map_err(|e| format!("Failed to read file {}: {}", file_path, e))?;
+	let mut warnings = Vec::new();
 
-	// Process each file
-	for file_path in file_paths {
-		let file_path_buf = PathBuf::from(&file_path);
+	let (encoding, text) = detect_text_encoding(&bytes);
+	match encoding {
+		"UTF-16LE" | "UTF-16BE" => warnings.push("File is UTF-16; save it as UTF-8 to import it".to_string()),
+		"unknown" => warnings.push("File is not valid UTF-8 and will fail to import".to_string()),
+		_ => {}
+	}
 
-		// Check file extension
-		let extension = file_path_buf
-			.extension()
-			.and_then(|s| s.to_str())
-			.unwrap_or("")
-			.to_lowercase();
+	if bytes.contains(&0) && encoding != "UTF-16LE" && encoding != "UTF-16BE" {
+		warnings.push("File contains NUL bytes and may be binary".to_string());
+	}
 
-		if extension != "txt" && extension != "md" {
-			continue; // Skip unsupported file types
+	let chapter_count = match text {
+		Some(text) if text.trim().is_empty() => {
+			warnings.push("File is empty".to_string());
+			0
+		}
+		Some(text) if split_on_form_feed.unwrap_or(false) => {
+			split_on_form_feeds(text, extract_title_from_delimiter.unwrap_or(true)).len()
+		}
+		Some(text) if split_on_thematic_break.unwrap_or(false) => {
+			split_on_thematic_breaks(text, extract_title_from_delimiter.unwrap_or(true)).len()
 		}
+		Some(text) => match chapter_delimiter.as_deref().filter(|d| !d.is_empty()) {
+			Some(delimiter) => {
+				if !text.lines().any(|line| line.starts_with(delimiter)) {
+					warnings.push(format!("No lines start with the delimiter \"{}\"", delimiter));
+				}
+				split_by_delimiter(text, delimiter, extract_title_from_delimiter.unwrap_or(true)).len()
+			}
+			None => 1,
+		},
+		None => 0,
+	};
 
-		// Read file content
-		let file_content = fs::read_to_string(&file_path)
-			.map_err(|e| format!("Failed to read file {}: {}", file_path, e))?;
+	Ok(ImportFileReport {
+		encoding: encoding.to_string(),
+		byte_size: bytes.len() as u64,
+		chapter_count,
+		warnings,
+	})
+}
 
-		// Extract filename for title
-		let filename = file_path_buf
-			.file_stem()
+// Encoding of a text file from its bytes ("UTF-8", "UTF-8 (BOM)", "UTF-16LE", "UTF-16BE" or
+// "unknown") and, when it is UTF-8, the text with any BOM stripped
+fn detect_text_encoding(bytes: &[u8]) -> (&'static str, Option<&str>) {
+	if bytes.starts_with(&[0xFF, 0xFE]) {
+		return ("UTF-16LE", None);
+	}
+	if bytes.starts_with(&[0xFE, 0xFF]) {
+		return ("UTF-16BE", None);
+	}
+	let bom = bytes.starts_with(&[0xEF, 0xBB, 0xBF]);
+	match std::str::from_utf8(bytes) {
+		Ok(text) => (if bom { "UTF-8 (BOM)" } else { "UTF-8" }, Some(text.trim_start_matches('\u{feff}'))),
+		Err(_) => ("unknown", None),
+	}
+}
+
+// Settings for import_chapter_files, sent as the `options` object of import_chapters and
+// import_text. Every field may be left out.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct ImportOptions {
+	#[serde(rename = "useFilenameAsTitle")]
+	use_filename_as_title: bool,
+	#[serde(rename = "chapterDelimiter")]
+	chapter_delimiter: Option<String>,
+	// On by default: the delimiter line's text becomes the chapter title
+	#[serde(rename = "extractTitleFromDelimiter")]
+	extract_title_from_delimiter: bool,
+	// Curly quotes and dashes for markdown (off by default so prose isn't silently rewritten)
+	#[serde(rename = "smartPunctuation")]
+	smart_punctuation: bool,
+	// Fallback title for sections without a filename/delimiter title; see render_title_template
+	#[serde(rename = "titleTemplate")]
+	title_template: Option<String>,
+	// Turn single newlines in .txt files into hardBreaks (poetry, screenplays)
+	#[serde(rename = "preserveLineBreaks")]
+	preserve_line_breaks: bool,
+	// Start a new chapter at every markdown thematic break; takes precedence over chapter_delimiter
+	#[serde(rename = "splitOnThematicBreak")]
+	split_on_thematic_break: bool,
+	// Build list nodes from "1." / "-" lines in .txt files
+	#[serde(rename = "detectLists")]
+	detect_lists: bool,
+	// Keep one empty paragraph where .txt files have two or more blank lines in a row
+	#[serde(rename = "keepBlankLines")]
+	keep_blank_lines: bool,
+	// Start a new chapter at every form feed; takes precedence over the other split settings
+	#[serde(rename = "splitOnFormFeed")]
+	split_on_form_feed: bool,
+	// How .txt files mark paragraphs
+	#[serde(rename = "paragraphMode")]
+	paragraph_mode: ParagraphMode,
+}
+
+impl Default for ImportOptions {
+	fn default() -> Self {
+		ImportOptions {
+			use_filename_as_title: false,
+			chapter_delimiter: None,
+			extract_title_from_delimiter: true,
+			smart_punctuation: false,
+			title_template: None,
+			preserve_line_breaks: false,
+			split_on_thematic_break: false,
+			detect_lists: false,
+			keep_blank_lines: false,
+			split_on_form_feed: false,
+			paragraph_mode: ParagraphMode::default(),
+		}
+	}
+}
+
+impl ImportOptions {
+	// Blank delimiter and title template count as unset; a title template must render
+	fn checked(self) -> Result<Self, String> {
+		let title_template = self.title_template.filter(|t| !t.trim().is_empty());
+		if let Some(template) = title_template.as_deref() {
+			render_title_template(template, 1, 1)?;
+		}
+		Ok(ImportOptions {
+			chapter_delimiter: self.chapter_delimiter.filter(|d| !d.is_empty()),
+			title_template,
+			..self
+		})
+	}
+}
+
+/// How plain-text paragraphs are separated: "blank-line" (the default) or "single-line".
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+enum ParagraphMode {
+	// Blank lines between paragraphs; lines within one are joined (or hardBreaks)
+	#[default]
+	#[serde(rename = "blank-line")]
+	BlankLine,
+	// Every line is a paragraph and blank lines mark scene breaks
+	#[serde(rename = "single-line")]
+	SingleLine,
+}
+
+// One piece of text to import: a file's contents or text pasted by the user
+struct ImportSource {
+	// Title used when the source isn't split by a delimiter (the filename, when enabled)
+	title: Option<String>,
+	content: String,
+	markdown: bool,
+}
+
+fn import_chapter_files(
+	project_path: &str,
+	file_paths: Vec<String>,
+	options: &ImportOptions,
+	cancel: Option<&AtomicBool>,
+) -> Result<Vec<Chapter>, String> {
+	// Files are read lazily so a cancelled import doesn't read the remaining ones
+	let sources = file_paths.into_iter().filter_map(|file_path| {
+		let file_path_buf = PathBuf::from(&file_path);
+
+		// Check file extension
+		let extension = file_path_buf
+			.extension()
+			.and_then(|s| s.to_str())
+			.unwrap_or("")
+			.to_lowercase();
+
+		if extension != "txt" && extension != "md" {
+			return None; // Skip unsupported file types
+		}
+
+		// Read file content; a UTF-8 BOM would otherwise hide a delimiter on the first line
+		let content = match fs::read_to_string(&file_path) {
+			Ok(content) => content.strip_prefix('\u{feff}').map(str::to_string).unwrap_or(content),
+			Err(e) => return Some(Err(format!("Failed to read file {}: {}", file_path, e))),
+		};
+
+		// Extract filename for title
+		let filename = file_path_buf
+			.file_stem()
 			.and_then(|s| s.to_str())
 			.unwrap_or("Chapter")
 			.to_string();
 
+		Some(Ok(ImportSource {
+			title: options.use_filename_as_title.then_some(filename),
+			content,
+			markdown: extension == "md",
+		}))
+	});
+	import_sources(project_path, sources, options, cancel)
+}
+
+// Create chapters from each source (split by the delimiter, if any) and record them in project.json
+fn import_sources(
+	project_path: &str,
+	sources: impl Iterator<Item = Result<ImportSource, String>>,
+	options: &ImportOptions,
+	cancel: Option<&AtomicBool>,
+) -> Result<Vec<Chapter>, String> {
+	let project_path_buf = PathBuf::from(&project_path);
+	let chapters_dir = project_path_buf.join("chapters");
+	let project_file = project_path_buf.join("project.json");
+
+	// Ensure chapters directory exists
+	fs::create_dir_all(&chapters_dir)
+		.map_err(|e| format!("Failed to create chapters directory: {}", e))?;
+
+	// Read current project to get max chapter ID
+	let mut project_data: serde_json::Value = if project_file.exists() {
+		let content = fs::read_to_string(&project_file)
+			.map_err(|e| format!("Failed to read project.json: {}", e))?;
+		serde_json::from_str(&content)
+			.map_err(|e| format!("Failed to parse project.json: {}", e))?
+	} else {
+		serde_json::json!({
+			"title": "Project",
+			"author": "",
+			"chapterOrder": []
+		})
+	};
+
+	// Don't carry stale ids (no chapter file) forward into the new order
+	prune_missing_chapters(&project_path_buf, &mut project_data);
+
+	// New ids start above every id in use, trashed chapters included, so a restore never collides
+	let max_id = highest_chapter_id(&project_path_buf, &project_data);
+
+	// Seed the deduplication set with all titles already in the project
+	let mut used_titles: HashSet<String> = HashSet::new();
+	if let Some(titles_obj) = project_data.get("chapterTitles").and_then(|v| v.as_object()) {
+		for (_, v) in titles_obj {
+			if let Some(t) = v.as_str() {
+				used_titles.insert(t.to_lowercase());
+			}
+		}
+	}
+
+	let mut imported_chapters = Vec::new();
+	let mut next_id = max_id + 1;
+
+	// Process each source
+	for source in sources {
+		if cancel.is_some_and(|flag| flag.load(Ordering::SeqCst)) {
+			break;
+		}
+		let source = source?;
+
 		// If delimiter is provided, try to split the content
-		let sections = if let Some(delimiter) = chapter_delimiter.as_ref() {
-			split_by_delimiter(&file_content, delimiter, extract_title_from_delimiter)
+		let delimited = options.chapter_delimiter.is_some()
+			|| options.split_on_thematic_break
+			|| options.split_on_form_feed;
+		let sections = if options.split_on_form_feed {
+			split_on_form_feeds(&source.content, options.extract_title_from_delimiter)
+		} else if options.split_on_thematic_break {
+			split_on_thematic_breaks(&source.content, options.extract_title_from_delimiter)
+		} else if let Some(delimiter) = options.chapter_delimiter.as_ref() {
+			split_by_delimiter(&source.content, delimiter, options.extract_title_from_delimiter)
 		} else {
-			// No delimiter: treat entire file as one section
-			let title = if use_filename_as_title {
-				filename
-			} else {
-				format!("Chapter {}", next_id)
-			};
-			vec![(title, file_content.clone())]
+			// No delimiter: treat the entire source as one section
+			vec![(source.title, source.content)]
 		};
 
 		// Create a chapter for each section
-		for (raw_title, section_content) in sections {
+		for (index, (raw_title, section_content)) in sections.into_iter().enumerate() {
+			let raw_title = match raw_title {
+				Some(title) => title,
+				None => match options.title_template.as_deref() {
+					Some(template) => render_title_template(template, imported_chapters.len() as u32 + 1, next_id)?,
+					None if delimited => format!("Chapter {}", index + 1),
+					None => format!("Chapter {}", next_id),
+				},
+			};
 			let section_title = make_unique_title(&raw_title, &used_titles);
 			used_titles.insert(section_title.to_lowercase());
-			let tiptap_json = if extension == "md" {
-				markdown_to_tiptap_json(&section_content)
+			let tiptap_json = if source.markdown {
+				markdown_to_tiptap_json(&section_content, options.smart_punctuation)
 			} else {
-				text_to_tiptap_json(&section_content)
+				text_to_tiptap_json(&section_content, options)
 			};
 
 			// Save chapter file
 			let chapter_file = chapters_dir.join(format!("{}.json", next_id));
-			let json_str = serde_json::to_string_pretty(&tiptap_json)
+			let json_str = to_saved_json(&tiptap_json, JsonIndent::Spaces(2))
 				.map_err(|e| format!("Failed to serialize chapter: {}", e))?;
-
-			fs::write(&chapter_file, json_str)
-				.map_err(|e| format!("Failed to write chapter file: {}", e))?;
+			write_file_atomic(&chapter_file, &json_str)?;
 
 			// Add to imported chapters list
 			imported_chapters.push(Chapter {
@@ -974,18 +2393,24 @@ fn import_chapters(
 	}
 
 	// Save updated project.json
-	let json = serde_json::to_string_pretty(&project_data)
-		.map_err(|e| format!("Failed to serialize project: {}", e))?;
-
-	fs::write(&project_file, json)
-		.map_err(|e| format!("Failed to write project.json: {}", e))?;
+	write_project_value(&project_file, &project_data)?;
 
 	Ok(imported_chapters)
 }
 
-// Update a chapter's title
+// Trim a chapter title and collapse internal runs of whitespace; None if nothing is left
+fn normalize_chapter_title(title: &str) -> Option<String> {
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+// Update a chapter's title. The title is normalized first (see normalize_chapter_title) and an
+// empty one is rejected, since it would leave a blank entry in exported tables of contents.
+// Returns the title as stored.
 #[tauri::command]
-fn rename_chapter(project_path: String, chapter_id: u32, new_title: String) -> Result<(), String> {
+fn rename_chapter(project_path: String, chapter_id: u32, new_title: String) -> Result<String, String> {
+    let new_title = normalize_chapter_title(&new_title)
+        .ok_or_else(|| "Chapter title cannot be empty".to_string())?;
     let path = PathBuf::from(&project_path);
     let project_file = path.join("project.json");
 
@@ -1007,23 +2432,121 @@ fn rename_chapter(project_path: String, chapter_id: u32, new_title: String) -> R
     // Update the chapter title
     project["chapterTitles"][chapter_id.to_string()] = serde_json::json!(new_title);
 
-    let json = serde_json::to_string_pretty(&project)
-        .map_err(|e| format!("Failed to serialize project: {}", e))?;
+    write_project_value(&project_file, &project)?;
 
-    fs::write(&project_file, json)
-        .map_err(|e| format!("Failed to write project.json: {}", e))?;
+    Ok(new_title)
+}
 
-    Ok(())
+// Rename many chapters at once from an id -> title map. Every id must be in chapterOrder and
+// every title non-empty, or nothing is changed. Titles that collide with another chapter's
+// final title get a " (n)" suffix. Returns the number of chapters renamed.
+#[tauri::command]
+fn bulk_rename_chapters(project_path: String, mapping: HashMap<u32, String>) -> Result<usize, String> {
+    let project_file = PathBuf::from(&project_path).join("project.json");
+    let mut project = read_project_value(&project_file)?;
+
+    let order = chapter_order_of(&project);
+    let mut ids: Vec<u32> = mapping.keys().copied().collect();
+    ids.sort_unstable();
+    for id in &ids {
+        if !order.contains(id) {
+            return Err(format!("Chapter {} not found", id));
+        }
+        if normalize_chapter_title(&mapping[id]).is_none() {
+            return Err(format!("Title for chapter {} is empty", id));
+        }
+    }
+
+    // Titles of the chapters that keep their names
+    let mut used_titles: HashSet<String> = project.get("chapterTitles")
+        .and_then(|v| v.as_object())
+        .map(|titles| titles.iter()
+            .filter(|(id, _)| id.parse::<u32>().map_or(true, |id| !mapping.contains_key(&id)))
+            .filter_map(|(_, t)| t.as_str().map(|t| t.to_lowercase()))
+            .collect())
+        .unwrap_or_default();
+
+    if !project.get("chapterTitles").is_some_and(|t| t.is_object()) {
+        project["chapterTitles"] = serde_json::json!({});
+    }
+    for id in &ids {
+        let title = normalize_chapter_title(&mapping[id]).unwrap_or_default();
+        let title = make_unique_title(&title, &used_titles);
+        used_titles.insert(title.to_lowercase());
+        project["chapterTitles"][id.to_string()] = serde_json::json!(title);
+    }
+
+    write_project_value(&project_file, &project)?;
+    Ok(ids.len())
+}
+
+/// A quotation shown before a chapter's body in exports, stored under `chapterEpigraphs` in
+/// project.json keyed by chapter id.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Epigraph {
+    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    attribution: Option<String>,
+}
+
+// A chapter's epigraph, if it has one with non-blank text
+fn chapter_epigraph(project: &serde_json::Value, chapter_id: u32) -> Option<Epigraph> {
+    project.get("chapterEpigraphs")
+        .and_then(|e| e.get(chapter_id.to_string()))
+        .and_then(|v| serde_json::from_value::<Epigraph>(v.clone()).ok())
+        .filter(|e| !e.text.trim().is_empty())
+}
+
+#[tauri::command]
+fn get_chapter_epigraph(project_path: String, chapter_id: u32) -> Result<Option<Epigraph>, String> {
+    let project = read_project_value(&PathBuf::from(&project_path).join("project.json"))?;
+    Ok(chapter_epigraph(&project, chapter_id))
+}
+
+// Set or clear (None, or blank text) a chapter's epigraph. A blank attribution is dropped.
+#[tauri::command]
+fn set_chapter_epigraph(project_path: String, chapter_id: u32, epigraph: Option<Epigraph>) -> Result<(), String> {
+    let project_file = PathBuf::from(&project_path).join("project.json");
+    let mut project = read_project_value(&project_file)?;
+    if !chapter_order_of(&project).contains(&chapter_id) {
+        return Err(format!("Chapter {} not found", chapter_id));
+    }
+
+    let key = chapter_id.to_string();
+    match epigraph.filter(|e| !e.text.trim().is_empty()) {
+        Some(epigraph) => {
+            let epigraph = Epigraph {
+                text: epigraph.text.trim().to_string(),
+                attribution: epigraph.attribution
+                    .map(|a| a.trim().to_string())
+                    .filter(|a| !a.is_empty()),
+            };
+            if !project.get("chapterEpigraphs").is_some_and(|e| e.is_object()) {
+                project["chapterEpigraphs"] = serde_json::json!({});
+            }
+            project["chapterEpigraphs"][key] = serde_json::to_value(epigraph)
+                .map_err(|e| format!("Failed to serialize epigraph: {}", e))?;
+        }
+        None => {
+            if let Some(map) = project.get_mut("chapterEpigraphs").and_then(|v| v.as_object_mut()) {
+                map.remove(&key);
+            }
+        }
+    }
+    write_project_value(&project_file, &project)
 }
 
 // Update app-level font preference
 #[tauri::command]
 fn update_font(handle: AppHandle, font_family: String) -> Result<(), String> {
-    let config_dir = get_config_dir(&handle)?;
-    fs::create_dir_all(&config_dir)
+    update_font_in(&AppDirs::of(&handle)?, font_family)
+}
+
+fn update_font_in(dirs: &AppDirs, font_family: String) -> Result<(), String> {
+    fs::create_dir_all(&dirs.config)
         .map_err(|e| format!("Failed to create config directory: {}", e))?;
 
-    let config_path = get_config_path(&handle)?;
+    let config_path = dirs.config_path();
 
     let content = fs::read_to_string(&config_path)
         .unwrap_or_else(|_| "{}".to_string());
@@ -1060,21 +2583,14 @@ fn update_project_font(project_path: String, font_family: String) -> Result<(),
 
     project["fontFamily"] = serde_json::json!(font_family);
 
-    let json = serde_json::to_string_pretty(&project)
-        .map_err(|e| format!("Failed to serialize project: {}", e))?;
-
-    fs::write(&project_file, json)
-        .map_err(|e| format!("Failed to write project.json: {}", e))?;
+    write_project_value(&project_file, &project)?;
 
     Ok(())
 }
 
 // Get global dictionary path
-fn get_global_dict_path(handle: &AppHandle) -> Result<PathBuf, String> {
-	let config_dir = get_config_dir(handle)?;
-	let mut path = config_dir;
-	path.push("custom_dictionary.json");
-	Ok(path)
+fn get_global_dict_path(dirs: &AppDirs) -> PathBuf {
+	dirs.config.join("custom_dictionary.json")
 }
 
 // Get project dictionary path
@@ -1127,6 +2643,32 @@ fn save_dictionary(dict_path: &PathBuf, words: Vec<String>) -> Result<(), String
 	Ok(())
 }
 
+// Dictionary file for a scope ("global" or "project")
+fn get_scope_dict_path(dirs: &AppDirs, scope: &str, project_path: Option<&str>) -> Result<PathBuf, String> {
+	match scope {
+		"global" => Ok(get_global_dict_path(dirs)),
+		"project" => match project_path {
+			Some(proj_path) => Ok(get_project_dict_path(proj_path)),
+			None => Err("Project path required for project-scope dictionary".to_string()),
+		},
+		_ => Err("Invalid scope: use 'global' or 'project'".to_string()),
+	}
+}
+
+// Add each new word not already present (case-insensitive check), keeping the list sorted.
+// Returns how many were added.
+fn merge_dictionary_words(words: &mut Vec<String>, new_words: impl IntoIterator<Item = String>) -> usize {
+	let mut seen: HashSet<String> = words.iter().map(|w| w.to_lowercase()).collect();
+	let before = words.len();
+	for word in new_words {
+		if seen.insert(word.to_lowercase()) {
+			words.push(word);
+		}
+	}
+	words.sort(); // Keep sorted for readability
+	words.len() - before
+}
+
 // Add word to dictionary (global or project-specific)
 #[tauri::command]
 fn add_to_dictionary(
@@ -1135,27 +2677,20 @@ fn add_to_dictionary(
 	scope: String,
 	project_path: Option<String>,
 ) -> Result<(), String> {
-	let dict_path = if scope == "global" {
-		get_global_dict_path(&handle)?
-	} else if scope == "project" {
-		if let Some(proj_path) = project_path {
-			get_project_dict_path(&proj_path)
-		} else {
-			return Err("Project path required for project-scope dictionary".to_string());
-		}
-	} else {
-		return Err("Invalid scope: use 'global' or 'project'".to_string());
-	};
+	add_to_dictionary_in(&AppDirs::of(&handle)?, word, scope, project_path)
+}
+
+fn add_to_dictionary_in(
+	dirs: &AppDirs,
+	word: String,
+	scope: String,
+	project_path: Option<String>,
+) -> Result<(), String> {
+	let dict_path = get_scope_dict_path(dirs, &scope, project_path.as_deref())?;
 
 	// Load existing words
 	let mut words = load_dictionary(&dict_path)?;
-
-	// Add word if not already present (case-insensitive check)
-	let word_lower = word.to_lowercase();
-	if !words.iter().any(|w| w.to_lowercase() == word_lower) {
-		words.push(word);
-		words.sort(); // Keep sorted for readability
-	}
+	merge_dictionary_words(&mut words, std::iter::once(word));
 
 	// Save updated dictionary
 	save_dictionary(&dict_path, words)?;
@@ -1163,6 +2698,68 @@ fn add_to_dictionary(
 	Ok(())
 }
 
+// Write a project's dictionary (the global one when no project is given) to `dest_path` in the
+// same {"words": [...]} format, e.g. to reuse it in another book of a series. Returns the word count.
+#[tauri::command]
+fn export_dictionary(
+	handle: AppHandle,
+	project_path: Option<String>,
+	dest_path: String,
+) -> Result<usize, String> {
+	export_dictionary_in(&AppDirs::of(&handle)?, project_path, dest_path)
+}
+
+fn export_dictionary_in(
+	dirs: &AppDirs,
+	project_path: Option<String>,
+	dest_path: String,
+) -> Result<usize, String> {
+	let dict_path = match project_path {
+		Some(proj_path) => get_project_dict_path(&proj_path),
+		None => get_global_dict_path(dirs),
+	};
+	let words = load_dictionary(&dict_path)?;
+	let count = words.len();
+	save_dictionary(&PathBuf::from(dest_path), words)?;
+	Ok(count)
+}
+
+// Merge the words of an exported dictionary file into a scope's dictionary, skipping words
+// already there (case-insensitive). Returns how many words were added.
+#[tauri::command]
+fn import_dictionary(
+	handle: AppHandle,
+	src_path: String,
+	scope: String,
+	project_path: Option<String>,
+) -> Result<usize, String> {
+	import_dictionary_in(&AppDirs::of(&handle)?, src_path, scope, project_path)
+}
+
+fn import_dictionary_in(
+	dirs: &AppDirs,
+	src_path: String,
+	scope: String,
+	project_path: Option<String>,
+) -> Result<usize, String> {
+	let src = PathBuf::from(&src_path);
+	if !src.is_file() {
+		return Err(format!("Dictionary file not found: {}", src_path));
+	}
+	let dict_path = get_scope_dict_path(dirs, &scope, project_path.as_deref())?;
+	let imported = load_dictionary(&src)?
+		.into_iter()
+		.map(|w| w.trim().to_string())
+		.filter(|w| !w.is_empty());
+
+	let mut words = load_dictionary(&dict_path)?;
+	let added = merge_dictionary_words(&mut words, imported);
+	if added > 0 {
+		save_dictionary(&dict_path, words)?;
+	}
+	Ok(added)
+}
+
 // Get all dictionary words (global + project)
 #[tauri::command]
 fn get_dictionary_words(
@@ -1172,7 +2769,7 @@ fn get_dictionary_words(
 	let mut all_words = Vec::new();
 
 	// Load global dictionary
-	if let Ok(global_dict_path) = get_global_dict_path(&handle) {
+	if let Ok(global_dict_path) = AppDirs::of(&handle).map(|dirs| get_global_dict_path(&dirs)) {
 		if let Ok(words) = load_dictionary(&global_dict_path) {
 			all_words.extend(words);
 		}
@@ -1193,9 +2790,106 @@ fn get_dictionary_words(
 	Ok(all_words)
 }
 
-// Delete a chapter: remove its file and all references in project.json
+// Edit distance between two words, counted in characters
+fn levenshtein(a: &str, b: &str) -> usize {
+	let b: Vec<char> = b.chars().collect();
+	let mut prev: Vec<usize> = (0..=b.len()).collect();
+	let mut curr = vec![0; b.len() + 1];
+	for (i, ca) in a.chars().enumerate() {
+		curr[0] = i + 1;
+		for (j, cb) in b.iter().enumerate() {
+			let cost = if ca == *cb { 0 } else { 1 };
+			curr[j + 1] = (prev[j + 1] + 1).min(curr[j] + 1).min(prev[j] + cost);
+		}
+		std::mem::swap(&mut prev, &mut curr);
+	}
+	prev[b.len()]
+}
+
+// Candidates within edit distance 2 of `word`, closest first. Ties prefer a matching first letter,
+// then the earlier candidate. Suggestions are capitalized to match the input.
+fn suggest_words<'a>(word: &str, candidates: impl IntoIterator<Item = &'a str>, max: usize) -> Vec<String> {
+	const MAX_DISTANCE: usize = 2;
+	let lower = word.to_lowercase();
+	let len = lower.chars().count();
+	let first = lower.chars().next();
+
+	let mut seen = HashSet::new();
+	let mut ranked: Vec<(usize, bool, usize, String)> = Vec::new();
+	for (index, candidate) in candidates.into_iter().enumerate() {
+		let candidate_lower = candidate.to_lowercase();
+		if candidate == word || !seen.insert(candidate_lower.clone()) {
+			continue;
+		}
+		if candidate_lower.chars().count().abs_diff(len) > MAX_DISTANCE {
+			continue;
+		}
+		let distance = levenshtein(&lower, &candidate_lower);
+		if distance <= MAX_DISTANCE {
+			let other_first = candidate_lower.chars().next() != first;
+			ranked.push((distance, other_first, index, candidate.to_string()));
+		}
+	}
+	ranked.sort();
+
+	let capitalize = word.chars().next().is_some_and(|c| c.is_uppercase());
+	ranked.into_iter()
+		.take(max)
+		.map(|(_, _, _, s)| if capitalize { capitalize_first(&s) } else { s })
+		.collect()
+}
+
+fn capitalize_first(s: &str) -> String {
+	let mut chars = s.chars();
+	match chars.next() {
+		Some(c) => c.to_uppercase().chain(chars).collect(),
+		None => String::new(),
+	}
+}
+
+// Suggest corrections for a misspelled word from the custom dictionaries (names, invented
+// words). Common words are left to the webview's own spellchecker, which has a full dictionary.
+#[tauri::command]
+fn spelling_suggestions(
+	handle: AppHandle,
+	project_path: Option<String>,
+	word: String,
+	max: usize,
+) -> Result<Vec<String>, String> {
+	let custom = get_dictionary_words(handle, project_path)?;
+	Ok(suggest_words(word.trim(), custom.iter().map(|w| w.as_str()), max))
+}
+
+// Drop chapterOrder entries (and their chapterTitles and excludedChapters entries) that have
+// no chapters/<id>.json file. Returns the ids removed.
+fn prune_missing_chapters(project_path: &Path, project: &mut serde_json::Value) -> Vec<u32> {
+	let chapters_dir = project_path.join("chapters");
+	let missing: Vec<u32> = chapter_order_of(project)
+		.into_iter()
+		.filter(|id| !chapters_dir.join(format!("{}.json", id)).exists())
+		.collect();
+	if missing.is_empty() {
+		return missing;
+	}
+
+	if let Some(order) = project.get_mut("chapterOrder").and_then(|v| v.as_array_mut()) {
+		order.retain(|v| !v.as_u64().is_some_and(|id| missing.contains(&(id as u32))));
+	}
+	if let Some(titles) = project.get_mut("chapterTitles").and_then(|v| v.as_object_mut()) {
+		for id in &missing {
+			titles.remove(&id.to_string());
+		}
+	}
+	if let Some(excluded) = project.get_mut("excludedChapters").and_then(|v| v.as_array_mut()) {
+		excluded.retain(|v| !v.as_u64().is_some_and(|id| missing.contains(&(id as u32))));
+	}
+	missing
+}
+
+// Delete a chapter: remove its file and all references in project.json.
+// Also drops any stale chapterOrder ids whose files are missing; those ids are returned.
 #[tauri::command]
-fn delete_chapter(project_path: String, chapter_id: u32) -> Result<(), String> {
+fn delete_chapter(project_path: String, chapter_id: u32) -> Result<Vec<u32>, String> {
 	let path = PathBuf::from(&project_path);
 	let project_file = path.join("project.json");
 
@@ -1208,7 +2902,7 @@ fn delete_chapter(project_path: String, chapter_id: u32) -> Result<(), String> {
 
 	// Update project.json
 	if !project_file.exists() {
-		return Ok(());
+		return Ok(Vec::new());
 	}
 
 	let content = fs::read_to_string(&project_file)
@@ -1216,83 +2910,666 @@ fn delete_chapter(project_path: String, chapter_id: u32) -> Result<(), String> {
 	let mut project: serde_json::Value = serde_json::from_str(&content)
 		.map_err(|e| format!("Failed to parse project.json: {}", e))?;
 
-	// Remove from chapterOrder
-	if let Some(order) = project.get_mut("chapterOrder").and_then(|v| v.as_array_mut()) {
-		order.retain(|v| v.as_u64().map(|id| id as u32) != Some(chapter_id));
+	// Remove from chapterOrder and excludedChapters
+	for key in ["chapterOrder", "excludedChapters"] {
+		if let Some(list) = project.get_mut(key).and_then(|v| v.as_array_mut()) {
+			list.retain(|v| v.as_u64().map(|id| id as u32) != Some(chapter_id));
+		}
 	}
 
-	// Remove from chapterTitles
-	if let Some(titles) = project.get_mut("chapterTitles").and_then(|v| v.as_object_mut()) {
-		titles.remove(&chapter_id.to_string());
+	// Remove from chapterTitles, chapterModified, chapterEpigraphs and chapterTargets
+	for key in ["chapterTitles", "chapterModified", "chapterEpigraphs", "chapterTargets"] {
+		if let Some(map) = project.get_mut(key).and_then(|v| v.as_object_mut()) {
+			map.remove(&chapter_id.to_string());
+		}
 	}
 
-	let json = serde_json::to_string_pretty(&project)
-		.map_err(|e| format!("Failed to serialize project: {}", e))?;
+	let stale_ids = prune_missing_chapters(&path, &mut project);
 
-	fs::write(&project_file, json)
-		.map_err(|e| format!("Failed to write project.json: {}", e))?;
+	write_project_value(&project_file, &project)?;
 
-	Ok(())
+	Ok(stale_ids)
+}
+
+fn trash_dir(project_path: &Path) -> PathBuf {
+	project_path.join("chapters").join(".trash")
+}
+
+// Highest chapter id in chapterOrder, the trash entries in project.json and chapters/.trash/
+// (0 when there are none)
+fn highest_chapter_id(project_path: &Path, project: &serde_json::Value) -> u32 {
+	let trashed_entries = project.get("trash")
+		.and_then(|v| v.as_object())
+		.into_iter()
+		.flat_map(|trash| trash.keys().filter_map(|id| id.parse::<u32>().ok()));
+	let trashed_files = fs::read_dir(trash_dir(project_path))
+		.into_iter()
+		.flatten()
+		.filter_map(|entry| entry.ok())
+		.filter_map(|entry| {
+			let name = entry.file_name().to_string_lossy().to_string();
+			name.strip_suffix(".json").and_then(|id| id.parse::<u32>().ok())
+		});
+	chapter_order_of(project).into_iter()
+		.chain(trashed_entries)
+		.chain(trashed_files)
+		.max()
+		.unwrap_or(0)
 }
 
-// Export project chapters to RTF file
+// Soft-delete: move chapters/<id>.json into chapters/.trash/ and remember its position and
+// title under `trash` in project.json so restore_chapter can put it back
 #[tauri::command]
-fn export_project(
-    project_path: String,
-    export_dir: String,
-    chapter_ids: Vec<u32>,
-) -> Result<String, String> {
-    let project_path_buf = PathBuf::from(&project_path);
-    let chapters_dir = project_path_buf.join("chapters");
+fn trash_chapter(project_path: String, chapter_id: u32) -> Result<(), String> {
+	let path = PathBuf::from(&project_path);
+	let project_file = path.join("project.json");
+	let mut project = read_project_value(&project_file)?;
 
-    // Load project metadata for title
-    let project_file = project_path_buf.join("project.json");
-    let project_content = fs::read_to_string(&project_file)
-        .map_err(|e| format!("Failed to read project.json: {}", e))?;
+	let key = chapter_id.to_string();
+	let index = chapter_order_of(&project)
+		.iter()
+		.position(|&id| id == chapter_id)
+		.ok_or_else(|| format!("Chapter {} not found", chapter_id))?;
 
-    let project: Project = serde_json::from_str(&project_content)
-        .map_err(|e| format!("Failed to parse project.json: {}", e))?;
+	let chapter_file = path.join("chapters").join(format!("{}.json", chapter_id));
+	if chapter_file.exists() {
+		let trash = trash_dir(&path);
+		fs::create_dir_all(&trash)
+			.map_err(|e| format!("Failed to create trash directory: {}", e))?;
+		fs::rename(&chapter_file, trash.join(format!("{}.json", chapter_id)))
+			.map_err(|e| format!("Failed to move chapter to trash: {}", e))?;
+	}
 
-    // Determine which chapters to export
-    let ids_to_export: Vec<u32> = if chapter_ids.is_empty() {
-        project.chapter_order.clone()
-    } else {
-        chapter_ids
-    };
+	let title = project.get("chapterTitles")
+		.and_then(|t| t.get(&key))
+		.cloned()
+		.unwrap_or(serde_json::Value::Null);
+	if let Some(order) = project.get_mut("chapterOrder").and_then(|v| v.as_array_mut()) {
+		order.remove(index);
+	}
+	if let Some(titles) = project.get_mut("chapterTitles").and_then(|v| v.as_object_mut()) {
+		titles.remove(&key);
+	}
+	if !project.get("trash").is_some_and(|t| t.is_object()) {
+		project["trash"] = serde_json::json!({});
+	}
+	project["trash"][key] = serde_json::json!({
+		"index": index,
+		"title": title,
+		"trashedAt": Local::now().to_rfc3339(),
+	});
 
-    // Build a single RTF document with all chapters
-    let mut rtf_content = String::from("{\\rtf1\\ansi\\ansicpg1252\\cocoartf2\n");
-    rtf_content.push_str("{\\colortbl;\\red255\\green255\\blue255;}\n");
-    rtf_content.push_str("{\\*\\expandedcolortbl;;}\n");
-    rtf_content.push_str("\\margl1440\\margr1440\\margtsxn0\\margbsxn0\\vieww11900\\viewh8605\\viewkind0\n");
-    rtf_content.push_str("\\pard\\tx720\\tx1440\\tx2160\\pardirnatural\\partightenfactor200\n\n");
+	write_project_value(&project_file, &project)
+}
 
-    // Load and add chapter content
-    for (i, chapter_id) in ids_to_export.iter().enumerate() {
-        let chapter_file = chapters_dir.join(format!("{}.json", chapter_id));
+// Put a trashed chapter back at its recorded chapterOrder position (or the end, if the
+// order has since become shorter) and return it
+#[tauri::command]
+fn restore_chapter(project_path: String, chapter_id: u32) -> Result<Chapter, String> {
+	let path = PathBuf::from(&project_path);
+	let project_file = path.join("project.json");
+	let mut project = read_project_value(&project_file)?;
 
-        if chapter_file.exists() {
-            // Add chapter title as a heading
-            let chapter_title = format!("Chapter {}", chapter_id);
-            rtf_content.push_str("{\\pard \\fs28 \\b ");
-            rtf_content.push_str(&chapter_title);
-            rtf_content.push_str("\\b0\\par}\n");
+	let key = chapter_id.to_string();
+	let entry = project.get("trash")
+		.and_then(|t| t.get(&key))
+		.cloned()
+		.ok_or_else(|| format!("Chapter {} is not in the trash", chapter_id))?;
+
+	let chapter_file = path.join("chapters").join(format!("{}.json", chapter_id));
+	if chapter_order_of(&project).contains(&chapter_id) || chapter_file.exists() {
+		return Err(format!("Chapter id {} is already in use; it can't be restored", chapter_id));
+	}
+	let trashed_file = trash_dir(&path).join(format!("{}.json", chapter_id));
+	if trashed_file.exists() {
+		fs::rename(&trashed_file, &chapter_file)
+			.map_err(|e| format!("Failed to restore chapter file: {}", e))?;
+	}
+
+	if !project.get("chapterOrder").is_some_and(|o| o.is_array()) {
+		project["chapterOrder"] = serde_json::json!([]);
+	}
+	if let Some(order) = project.get_mut("chapterOrder").and_then(|v| v.as_array_mut()) {
+		let index = entry.get("index").and_then(|v| v.as_u64()).unwrap_or(u64::MAX) as usize;
+		order.insert(index.min(order.len()), serde_json::json!(chapter_id));
+	}
+	let title = entry.get("title").and_then(|v| v.as_str()).map(|t| t.to_string());
+	if let Some(title) = title.as_ref() {
+		if !project.get("chapterTitles").is_some_and(|t| t.is_object()) {
+			project["chapterTitles"] = serde_json::json!({});
+		}
+		project["chapterTitles"][&key] = serde_json::json!(title);
+	}
+	if let Some(trash) = project.get_mut("trash").and_then(|v| v.as_object_mut()) {
+		trash.remove(&key);
+	}
+	write_project_value(&project_file, &project)?;
+
+	Ok(Chapter {
+		id: chapter_id,
+		title: title.unwrap_or_else(|| format!("Chapter {}", chapter_id)),
+		content: read_chapter_content(&path, chapter_id),
+	})
+}
+
+// Permanently delete every trashed chapter; returns how many were removed
+#[tauri::command]
+fn empty_trash(project_path: String) -> Result<usize, String> {
+	let path = PathBuf::from(&project_path);
+	let project_file = path.join("project.json");
+	let mut project = read_project_value(&project_file)?;
+
+	let ids: Vec<String> = project.get("trash")
+		.and_then(|t| t.as_object())
+		.map(|t| t.keys().cloned().collect())
+		.unwrap_or_default();
+
+	let trash = trash_dir(&path);
+	if trash.exists() {
+		fs::remove_dir_all(&trash)
+			.map_err(|e| format!("Failed to empty trash: {}", e))?;
+	}
+
+	// Drop per-chapter data that only the trashed chapters still referenced
+	// (an id may have been reused by a newer chapter since it was trashed)
+	let order = chapter_order_of(&project);
+	let ids_to_forget: Vec<String> = ids.iter()
+		.filter(|id| id.parse::<u32>().map_or(true, |id| !order.contains(&id)))
+		.cloned()
+		.collect();
+	for key in ["chapterModified", "chapterEpigraphs", "chapterTargets"] {
+		if let Some(map) = project.get_mut(key).and_then(|v| v.as_object_mut()) {
+			for id in &ids_to_forget {
+				map.remove(id);
+			}
+		}
+	}
+	if let Some(excluded) = project.get_mut("excludedChapters").and_then(|v| v.as_array_mut()) {
+		excluded.retain(|v| !ids_to_forget.contains(&v.to_string()));
+	}
+	if let Some(obj) = project.as_object_mut() {
+		obj.remove("trash");
+	}
+	write_project_value(&project_file, &project)?;
+	Ok(ids.len())
+}
+
+// Rewrite a chapter id list through `ids`, dropping ids that belong to no chapter
+fn remap_id_list(list: &mut serde_json::Value, ids: &HashMap<u32, u32>) {
+	if let Some(items) = list.as_array_mut() {
+		*items = items.iter()
+			.filter_map(|v| v.as_u64().and_then(|id| ids.get(&(id as u32))))
+			.map(|&id| serde_json::json!(id))
+			.collect();
+	}
+}
+
+// Reassign chapter ids to 1..N in chapterOrder sequence (trashed chapters follow, in id order),
+// renaming chapter files and remapping every per-chapter entry in project.json. Files are first
+// moved aside and then into place; if any step fails, the moves already made are undone and
+// project.json is left untouched. Returns old id -> new id for the chapters that changed.
+#[tauri::command]
+fn renumber_chapters(project_path: String) -> Result<HashMap<u32, u32>, String> {
+	let path = PathBuf::from(&project_path);
+	let project_file = path.join("project.json");
+	let mut project = read_project_value(&project_file)?;
+
+	let mut order = chapter_order_of(&project);
+	let mut seen = HashSet::new();
+	order.retain(|id| seen.insert(*id));
+	let mut trashed: Vec<u32> = project.get("trash")
+		.and_then(|t| t.as_object())
+		.map(|t| t.keys().filter_map(|k| k.parse().ok()).filter(|id| !seen.contains(id)).collect())
+		.unwrap_or_default();
+	trashed.sort_unstable();
+
+	let chapters_dir = path.join("chapters");
+	let trash = trash_dir(&path);
+	let ids: HashMap<u32, u32> = order.iter().chain(trashed.iter())
+		.enumerate()
+		.map(|(i, &old)| (old, i as u32 + 1))
+		.collect();
+	let dir_of = |old: u32| if order.contains(&old) { &chapters_dir } else { &trash };
+
+	let mut changes: Vec<(u32, u32)> = ids.iter()
+		.filter(|(old, new)| old != new)
+		.map(|(&old, &new)| (old, new))
+		.collect();
+	if changes.is_empty() {
+		return Ok(HashMap::new());
+	}
+	changes.sort_unstable();
+
+	// A file already sitting at a target name that no chapter is moving out of would be overwritten
+	for &(old, new) in &changes {
+		let dir = dir_of(old);
+		let target = dir.join(format!("{}.json", new));
+		let vacated = ids.contains_key(&new) && dir_of(new) == dir;
+		if target.exists() && !vacated {
+			return Err(format!("{} belongs to no chapter; move it aside before renumbering", target.display()));
+		}
+	}
+
+	project["chapterOrder"] = serde_json::json!((1..=order.len() as u32).collect::<Vec<u32>>());
+	for key in ["chapterTitles", "chapterModified", "chapterEpigraphs", "chapterNotes", "chapterTargets", "trash"] {
+		if let Some(map) = project.get_mut(key).and_then(|v| v.as_object_mut()) {
+			*map = std::mem::take(map).into_iter()
+				.filter_map(|(k, v)| k.parse::<u32>().ok().and_then(|id| ids.get(&id)).map(|id| (id.to_string(), v)))
+				.collect();
+		}
+	}
+	if let Some(excluded) = project.get_mut("excludedChapters") {
+		remap_id_list(excluded, &ids);
+	}
+	if let Some(parts) = project.get_mut("parts").and_then(|v| v.as_array_mut()) {
+		for part in parts {
+			if let Some(list) = part.get_mut("chapterIds") {
+				remap_id_list(list, &ids);
+			}
+		}
+	}
+	if let Some(presets) = project.get_mut("exportPresets").and_then(|v| v.as_object_mut()) {
+		for preset in presets.values_mut() {
+			if let Some(list) = preset.get_mut("chapterIds") {
+				remap_id_list(list, &ids);
+			}
+		}
+	}
+	let last_opened = project.get("lastOpenedChapter")
+		.and_then(|v| v.as_u64())
+		.and_then(|id| ids.get(&(id as u32)).copied());
+	if let Some(obj) = project.as_object_mut() {
+		match last_opened {
+			Some(id) => { obj.insert("lastOpenedChapter".into(), serde_json::json!(id)); }
+			None => { obj.remove("lastOpenedChapter"); }
+		}
+	}
+
+	let json = to_saved_json(&project, JsonIndent::Spaces(2))
+		.map_err(|e| format!("Failed to serialize project: {}", e))?;
+
+	// (from, to) for every rename done so far, undone in reverse on failure
+	let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+	let rollback = |moved: &[(PathBuf, PathBuf)]| {
+		for (from, to) in moved.iter().rev() {
+			let _ = fs::rename(to, from);
+		}
+	};
+	let staged = |old: u32| dir_of(old).join(format!("{}.json.renumber", old));
+	let steps = changes.iter()
+		.map(|&(old, _)| (dir_of(old).join(format!("{}.json", old)), staged(old)))
+		.chain(changes.iter().map(|&(old, new)| (staged(old), dir_of(old).join(format!("{}.json", new)))));
+	for (from, to) in steps {
+		if !from.exists() {
+			continue;
+		}
+		if let Err(e) = fs::rename(&from, &to) {
+			rollback(&moved);
+			return Err(format!("Failed to rename {}: {}", from.display(), e));
+		}
+		moved.push((from, to));
+	}
+
+	if let Err(e) = write_file_atomic(&project_file, &json) {
+		rollback(&moved);
+		return Err(e);
+	}
+	Ok(changes.into_iter().collect())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProjectIssues {
+	// Ids listed in chapterOrder with no chapters/<id>.json file
+	#[serde(rename = "missingChapterIds")]
+	missing_chapter_ids: Vec<u32>,
+	// Chapter files on disk that chapterOrder doesn't list
+	#[serde(rename = "unlistedChapterIds")]
+	unlisted_chapter_ids: Vec<u32>,
+	// Fields with the wrong type; load_project refuses the project until these are fixed
+	#[serde(rename = "fieldErrors")]
+	field_errors: Vec<ProjectFieldError>,
+}
+
+// Ids of the chapters/<id>.json files on disk, ascending
+fn chapter_file_ids(chapters_dir: &Path) -> Vec<u32> {
+	let mut ids: Vec<u32> = fs::read_dir(chapters_dir)
+		.map(|entries| entries
+			.filter_map(|e| e.ok())
+			.map(|e| e.path())
+			.filter(|p| p.extension().and_then(|s| s.to_str()) == Some("json"))
+			.filter_map(|p| p.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse::<u32>().ok()))
+			.collect())
+		.unwrap_or_default();
+	ids.sort_unstable();
+	ids
+}
+
+// Replace chapterOrder with every chapter file on disk in ascending id order, for recovering
+// a lost or corrupted order. Titles and other fields are left alone. Returns the new order.
+#[tauri::command]
+fn rebuild_chapter_order(project_path: String) -> Result<Vec<u32>, String> {
+	let path = PathBuf::from(&project_path);
+	let project_file = path.join("project.json");
+	let mut project = read_project_value(&project_file)?;
+
+	let order = chapter_file_ids(&path.join("chapters"));
+	project["chapterOrder"] = serde_json::json!(order);
+	write_project_value(&project_file, &project)?;
+	Ok(order)
+}
+
+// Check that chapterOrder and the chapters/ directory agree, and that project.json's fields
+// have the expected types
+#[tauri::command]
+fn verify_project(project_path: String) -> Result<ProjectIssues, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+	let order = chapter_order_of(&project);
+	let chapters_dir = path.join("chapters");
+
+	let missing_chapter_ids = order.iter()
+		.copied()
+		.filter(|id| !chapters_dir.join(format!("{}.json", id)).exists())
+		.collect();
+
+	let unlisted_chapter_ids = chapter_file_ids(&chapters_dir)
+		.into_iter()
+		.filter(|id| !order.contains(id))
+		.collect();
+
+	let field_errors = project_field_errors(&project);
+	Ok(ProjectIssues { missing_chapter_ids, unlisted_chapter_ids, field_errors })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChapterFileIssue {
+	#[serde(rename = "chapterId")]
+	chapter_id: u32,
+	// As detected from the bytes: "UTF-8", "UTF-8 (BOM)", "UTF-16LE", "UTF-16BE" or "unknown"
+	encoding: String,
+	// What would make loading or saving the chapter fail, e.g. the JSON error with its position
+	problems: Vec<String>,
+}
+
+// Read every chapters/<id>.json file and report the ones with encoding or JSON problems (e.g.
+// hand-edited and saved with a BOM or as UTF-16). Nothing is modified. Files without problems
+// are left out; the rest are in ascending id order.
+#[tauri::command]
+fn check_chapter_files(project_path: String) -> Result<Vec<ChapterFileIssue>, String> {
+	let chapters_dir = PathBuf::from(&project_path).join("chapters");
+	let mut issues = Vec::new();
+
+	for chapter_id in chapter_file_ids(&chapters_dir) {
+		let bytes = match fs::read(chapters_dir.join(format!("{}.json", chapter_id))) {
+			Ok(bytes) => bytes,
+			Err(e) => {
+				issues.push(ChapterFileIssue {
+					chapter_id,
+					encoding: "unknown".to_string(),
+					problems: vec![format!("Failed to read file: {}", e)],
+				});
+				continue;
+			}
+		};
+
+		let mut problems = Vec::new();
+		let (encoding, text) = detect_text_encoding(&bytes);
+		match encoding {
+			"UTF-8 (BOM)" => problems.push("Starts with a UTF-8 byte order mark, which the JSON parser rejects".to_string()),
+			"UTF-16LE" | "UTF-16BE" => problems.push(format!("Saved as {}; chapter files must be UTF-8", encoding)),
+			"unknown" => {
+				let offset = std::str::from_utf8(&bytes).err().map_or(0, |e| e.valid_up_to());
+				problems.push(format!("Not valid UTF-8 (first bad byte at offset {})", offset));
+			}
+			_ => {}
+		}
+		// Without the BOM, is the rest of the file sound?
+		if let Some(text) = text {
+			if text.trim().is_empty() {
+				problems.push("File is empty".to_string());
+			} else if let Err(e) = serde_json::from_str::<serde_json::Value>(text) {
+				problems.push(format!("Invalid JSON: {}", e));
+			}
+		}
+
+		if !problems.is_empty() {
+			issues.push(ChapterFileIssue { chapter_id, encoding: encoding.to_string(), problems });
+		}
+	}
+
+	Ok(issues)
+}
+
+// Progress payload for the `export://progress` event, sent once per chapter
+// and a final time with stage "complete" and the output path.
+#[derive(Debug, Serialize, Clone)]
+struct ExportProgress {
+    done: usize,
+    total: usize,
+    stage: String,
+    #[serde(rename = "outputPath", skip_serializing_if = "Option::is_none")]
+    output_path: Option<String>,
+}
+
+const EXPORT_PROGRESS_EVENT: &str = "export://progress";
+
+fn emit_export_progress(handle: &AppHandle, progress: ExportProgress) {
+    // Progress is best-effort; a missing listener must not fail the export
+    let _ = handle.emit(EXPORT_PROGRESS_EVENT, progress);
+}
+
+fn chapter_progress(done: usize, total: usize) -> ExportProgress {
+    ExportProgress { done, total, stage: "chapters".to_string(), output_path: None }
+}
+
+fn complete_progress(total: usize, output_path: &str) -> ExportProgress {
+    ExportProgress { done: total, total, stage: "complete".to_string(), output_path: Some(output_path.to_string()) }
+}
+
+// Replace anything but letters, digits, '-' and '_' so a title is safe in a filename
+fn sanitize_filename_part(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+// Windows device names that can't be used as a file stem, with or without an extension
+const RESERVED_FILENAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Leaves headroom under the 255-byte limit of common filesystems
+const MAX_FILENAME_BYTES: usize = 200;
+
+// Make a complete export filename (stem plus extension) valid on Windows, macOS and Linux:
+// replace characters Windows forbids, drop trailing dots and spaces, avoid reserved device
+// names and truncate the stem so the whole name fits in MAX_FILENAME_BYTES.
+fn sanitize_filename(filename: &str) -> String {
+    let cleaned: String = filename.chars()
+        .map(|c| if c.is_control() || "<>:\"/\\|?*".contains(c) { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches(['.', ' ']);
+
+    let (stem, ext) = match cleaned.rfind('.') {
+        Some(dot) if dot > 0 => (&cleaned[..dot], &cleaned[dot..]),
+        _ => (cleaned, ""),
+    };
+    let mut stem = stem.trim_end_matches(['.', ' ']).to_string();
+    if stem.is_empty() {
+        stem = "export".to_string();
+    }
+
+    // "CON.epub" and "con.tar.gz" are both reserved: only the part before the first dot counts
+    let device = stem.split('.').next().unwrap_or("");
+    if RESERVED_FILENAMES.iter().any(|r| r.eq_ignore_ascii_case(device)) {
+        stem.insert(device.len(), '_');
+    }
+
+    let max_stem = MAX_FILENAME_BYTES.saturating_sub(ext.len());
+    if stem.len() > max_stem {
+        let mut end = max_stem;
+        while !stem.is_char_boundary(end) {
+            end -= 1;
+        }
+        stem.truncate(end);
+        stem = stem.trim_end_matches(['.', ' ']).to_string();
+    }
+    format!("{}{}", stem, ext)
+}
+
+// Build an export filename from a user template with {title}, {author}, {date}, {count}
+// and {ext} placeholders. Title and author are sanitized; ".{ext}" is appended when the
+// template doesn't place it.
+fn render_export_filename(
+    template: &str,
+    project: &Project,
+    date: &str,
+    count: usize,
+    ext: &str,
+) -> Result<String, String> {
+    if template.contains('/') || template.contains('\\') {
+        return Err("Filename template must not contain path separators".to_string());
+    }
+
+    let mut filename = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filename.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in filename template: {}", template))?;
+        match &after[..end] {
+            "title" => filename.push_str(&sanitize_filename_part(&project.title)),
+            "author" => filename.push_str(&sanitize_filename_part(&project.author)),
+            "date" => filename.push_str(date),
+            "count" => filename.push_str(&count.to_string()),
+            "ext" => filename.push_str(ext),
+            other => return Err(format!("Unknown placeholder {{{}}} in filename template", other)),
+        }
+        rest = &after[end + 1..];
+    }
+    filename.push_str(rest);
+
+    let filename = filename.trim().to_string();
+    if filename.is_empty() {
+        return Err("Filename template produced an empty filename".to_string());
+    }
+    let filename = if template.contains("{ext}") { filename } else { format!("{}.{}", filename, ext) };
+    // Dots are fine ("{title}... draft") unless they make the whole name "." or ".."
+    if filename == "." || filename == ".." {
+        return Err(format!("Filename template produced an invalid filename: {}", filename));
+    }
+    Ok(filename)
+}
+
+// Export project chapters to RTF file.
+// With `renumber`, headings count 1..N over the exported chapters instead of showing chapter ids.
+// `heading_sizes` maps heading level to font size in pt; unlisted levels keep the defaults.
+// `heading_offset` shifts every heading level (e.g. 1 turns h2 into h3) before sizing.
+// `line_spacing` is "single" (the default), "1.5" or "double".
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn export_project(
+    handle: AppHandle,
+    project_path: String,
+    export_dir: String,
+    chapter_ids: Vec<u32>,
+    renumber: Option<bool>,
+    filename_template: Option<String>,
+    modified_since: Option<String>,
+    heading_sizes: Option<HashMap<u8, f64>>,
+    heading_offset: Option<i8>,
+    line_spacing: Option<String>,
+) -> Result<String, String> {
+    let line_spacing = RtfLineSpacing::parse(line_spacing.as_deref())?;
+    let chapter_ids = chapters_modified_since(&project_path, chapter_ids, modified_since.as_deref())?;
+    let template = filename_template.as_deref().filter(|t| !t.trim().is_empty());
+    let headings = RtfHeadingStyle {
+        sizes: heading_sizes.unwrap_or_default(),
+        offset: heading_offset.unwrap_or(0),
+    };
+    write_rtf_export(&project_path, &export_dir, chapter_ids, renumber.unwrap_or(false), template, &headings, line_spacing, &mut |progress| {
+        emit_export_progress(&handle, progress)
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_rtf_export(
+    project_path: &str,
+    export_dir: &str,
+    chapter_ids: Vec<u32>,
+    renumber: bool,
+    filename_template: Option<&str>,
+    headings: &RtfHeadingStyle,
+    line_spacing: RtfLineSpacing,
+    on_progress: &mut dyn FnMut(ExportProgress),
+) -> Result<String, String> {
+    let project_path_buf = PathBuf::from(&project_path);
+    let chapters_dir = project_path_buf.join("chapters");
+
+    // Load project metadata for title
+    let project_file = project_path_buf.join("project.json");
+    let project_content = fs::read_to_string(&project_file)
+        .map_err(|e| format!("Failed to read project.json: {}", e))?;
+
+    let project_value: serde_json::Value = serde_json::from_str(&project_content)
+        .map_err(|e| format!("Failed to parse project.json: {}", e))?;
+    let project: Project = serde_json::from_value(project_value.clone())
+        .map_err(|e| format!("Failed to parse project.json: {}", e))?;
+
+    // Determine which chapters to export; a full-book export skips excluded chapters
+    let full_book = chapter_ids.is_empty();
+    let ids_to_export: Vec<u32> = if full_book {
+        full_book_chapter_ids(&project_value)
+    } else {
+        chapter_ids
+    };
+
+    // Build a single RTF document with all chapters
+    let mut rtf_content = String::from("{\\rtf1\\ansi\\ansicpg1252\\cocoartf2\n");
+    rtf_content.push_str("{\\colortbl;\\red255\\green255\\blue255;}\n");
+    rtf_content.push_str("{\\*\\expandedcolortbl;;}\n");
+    rtf_content.push_str("\\margl1440\\margr1440\\margtsxn0\\margbsxn0\\vieww11900\\viewh8605\\viewkind0\n");
+    rtf_content.push_str("\\pard\\tx720\\tx1440\\tx2160\\pardirnatural\\partightenfactor200\n\n");
+
+    // Load and add chapter content
+    let mut exported_count = 0;
+    for (i, chapter_id) in ids_to_export.iter().enumerate() {
+        let chapter_file = chapters_dir.join(format!("{}.json", chapter_id));
+
+        if chapter_file.exists() {
+            exported_count += 1;
+
+            // Add chapter title as a heading
+            let chapter_number = if renumber { exported_count } else { *chapter_id };
+            let chapter_title = format!("Chapter {}", chapter_number);
+            rtf_content.push_str("{\\pard \\fs28 \\b ");
+            rtf_content.push_str(&chapter_title);
+            rtf_content.push_str("\\b0\\par}\n");
 
             // Add spacing (two blank lines)
             rtf_content.push_str("{\\pard \\par}\n");
             rtf_content.push_str("{\\pard \\par}\n");
 
+            if let Some(epigraph) = chapter_epigraph(&project_value, *chapter_id) {
+                rtf_content.push_str(&rtf_epigraph(&epigraph));
+            }
+
             let chapter_json = fs::read_to_string(&chapter_file)
                 .map_err(|e| format!("Failed to read chapter {}: {}", chapter_id, e))?;
 
             let chapter_content: Option<serde_json::Value> = serde_json::from_str(&chapter_json).ok();
-            rtf_content.push_str(&json_to_rtf_content(&chapter_content));
+            rtf_content.push_str(&json_to_rtf_content(&chapter_content, headings, &RtfBodyFormat { line_spacing, ..Default::default() }));
 
             // Add page break between chapters (not after the last one)
             if i < ids_to_export.len() - 1 {
                 rtf_content.push_str("\\page\n");
             }
         }
+
+        on_progress(chapter_progress(i + 1, ids_to_export.len()));
     }
 
     // Close the RTF document
@@ -1300,7 +3577,9 @@ fn export_project(
 
     // Generate filename
     let date = Local::now().format("%Y-%m-%d").to_string();
-    let filename = if ids_to_export.len() == project.chapter_order.len() {
+    let filename = sanitize_filename(&if let Some(template) = filename_template {
+        render_export_filename(template, &project, &date, exported_count as usize, "rtf")?
+    } else if full_book {
         format!("{}_{}.rtf", project.title.replace(" ", "_"), date)
     } else {
         let id_range = ids_to_export.iter()
@@ -1308,7 +3587,7 @@ fn export_project(
             .collect::<Vec<_>>()
             .join("-");
         format!("{}_{}_Chapters_{}.rtf", project.title.replace(" ", "_"), date, id_range)
-    };
+    });
 
     // Write RTF file
     let export_path = PathBuf::from(&export_dir).join(&filename);
@@ -1316,188 +3595,5004 @@ fn export_project(
         .map_err(|e| format!("Failed to write RTF file: {}", e))?;
 
     // Return the full path to the exported file
-    export_path.to_str()
+    let output_path = export_path.to_str()
         .map(|s| s.to_string())
-        .ok_or_else(|| "Failed to convert path to string".to_string())
+        .ok_or_else(|| "Failed to convert path to string".to_string())?;
+    on_progress(complete_progress(ids_to_export.len(), &output_path));
+    Ok(output_path)
 }
 
-// ============================================================
-// Asset handling
-// ============================================================
-
-fn base64_encode(data: &[u8]) -> String {
-    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
-    for chunk in data.chunks(3) {
-        let n = chunk.len();
-        let b = [
-            chunk[0],
-            if n > 1 { chunk[1] } else { 0 },
-            if n > 2 { chunk[2] } else { 0 },
-        ];
-        out.push(CHARS[((b[0] >> 2) & 0x3f) as usize] as char);
-        out.push(CHARS[(((b[0] & 0x03) << 4) | ((b[1] >> 4) & 0x0f)) as usize] as char);
-        out.push(if n >= 2 { CHARS[(((b[1] & 0x0f) << 2) | ((b[2] >> 6) & 0x03)) as usize] as char } else { '=' });
-        out.push(if n >= 3 { CHARS[(b[2] & 0x3f) as usize] as char } else { '=' });
+// "1234567" -> "1,234,567"
+fn group_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
     }
     out
 }
 
-fn image_mime_for_ext(ext: &str) -> &'static str {
-    let lower = ext.to_lowercase();
-    match lower.as_str() {
-        "jpg" | "jpeg" => "image/jpeg",
-        "png"  => "image/png",
-        "gif"  => "image/gif",
-        "webp" => "image/webp",
-        "svg"  => "image/svg+xml",
-        _      => "image/jpeg",
-    }
+// Title-page word count: rounded to the nearest hundred as submission guidelines ask,
+// exact below a hundred words
+fn manuscript_word_count(words: usize) -> String {
+    let rounded = if words < 100 { words } else { (words + 50) / 100 * 100 };
+    format!("about {} words", group_thousands(rounded))
 }
 
-/// Copy an image file into the project's assets/ dir and return a data URL.
+// Export chapters in standard manuscript format: 12pt Courier New, double-spaced, 1-inch
+// margins and a 0.5-inch first-line indent. A title page carries the contact block
+// (project.json `contactInfo`, or the author name) with the word count opposite, then the
+// title and byline; later pages have a "Surname / TITLE / page" header. Each chapter starts
+// on a new page a third of the way down.
 #[tauri::command]
-fn copy_asset_and_encode(
+fn export_manuscript(
+    handle: AppHandle,
     project_path: String,
-    src_path: String,
-) -> Result<serde_json::Value, String> {
-    let project_path_buf = PathBuf::from(&project_path);
-    let assets_dir = project_path_buf.join("assets");
-    fs::create_dir_all(&assets_dir)
-        .map_err(|e| format!("Failed to create assets directory: {}", e))?;
+    export_dir: String,
+    chapter_ids: Vec<u32>,
+) -> Result<String, String> {
+    write_manuscript_export(&project_path, &export_dir, chapter_ids, &mut |progress| {
+        emit_export_progress(&handle, progress)
+    })
+}
 
-    let src = PathBuf::from(&src_path);
-    let raw_name = src.file_name()
-        .ok_or_else(|| "Invalid source path".to_string())?
-        .to_string_lossy()
-        .to_string();
+fn write_manuscript_export(
+    project_path: &str,
+    export_dir: &str,
+    chapter_ids: Vec<u32>,
+    on_progress: &mut dyn FnMut(ExportProgress),
+) -> Result<String, String> {
+    let project_path_buf = PathBuf::from(project_path);
+    let project_value = read_project_value(&project_path_buf.join("project.json"))?;
+    let project: Project = serde_json::from_value(project_value.clone())
+        .map_err(|e| format!("Failed to parse project.json: {}", e))?;
 
-    // Sanitize filename
-    let safe_name: String = raw_name.chars()
-        .map(|c| if c.is_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+    // Keep project order; a full-book export skips excluded chapters
+    let ids_to_export: Vec<u32> = if chapter_ids.is_empty() {
+        full_book_chapter_ids(&project_value)
+    } else {
+        project.chapter_order.iter()
+            .filter(|id| chapter_ids.contains(id))
+            .copied()
+            .collect()
+    };
+    let chapters: Vec<(u32, Option<serde_json::Value>)> = ids_to_export.iter()
+        .map(|&id| (id, read_chapter_content(&project_path_buf, id)))
         .collect();
+    let words: usize = chapters.iter()
+        .filter_map(|(_, content)| content.as_ref())
+        .map(|doc| count_words(&tiptap_to_plain_text(doc)))
+        .sum();
+
+    let author = project.author.trim();
+    let title = project.title.trim();
+    let surname = author.split_whitespace().last();
+    let header = match surname {
+        Some(surname) => format!("{} / {}", escape_rtf(surname), escape_rtf(&title.to_uppercase())),
+        None => escape_rtf(&title.to_uppercase()),
+    };
 
-    // Find a non-conflicting destination path
-    let dest_path = {
-        let candidate = assets_dir.join(&safe_name);
-        if !candidate.exists() {
-            candidate
+    // 12240x15840 is US Letter; \titlepg gives the title page its own (empty) header
+    let mut rtf = String::from("{\\rtf1\\ansi\\ansicpg1252\\deff0\n");
+    rtf.push_str("{\\fonttbl{\\f0\\fmodern\\fcharset0 Courier New;}}\n");
+    rtf.push_str("\\paperw12240\\paperh15840\\margl1440\\margr1440\\margt1440\\margb1440\\titlepg\n");
+    rtf.push_str("\\f0\\fs24\n");
+    rtf.push_str(&format!("{{\\header\\pard\\qr\\f0\\fs24 {} / \\chpgn\\par}}\n", header));
+    rtf.push_str("{\\headerf\\pard\\par}\n");
+
+    // Title page: contact block top left, word count top right on the first line
+    let contact = project_value.get("contactInfo")
+        .and_then(|v| v.as_str())
+        .map(|c| c.lines().map(|l| l.trim()).filter(|l| !l.is_empty()).collect::<Vec<_>>())
+        .filter(|lines| !lines.is_empty())
+        .unwrap_or_else(|| vec![author]);
+    for (i, line) in contact.iter().enumerate() {
+        if i == 0 {
+            rtf.push_str(&format!(
+                "{{\\pard\\tqr\\tx9360 {}\\tab {}\\par}}\n",
+                escape_rtf(line), manuscript_word_count(words)
+            ));
         } else {
-            let ext = PathBuf::from(&safe_name)
-                .extension()
-                .map(|e| format!(".{}", e.to_string_lossy()))
-                .unwrap_or_default();
+            rtf.push_str(&format!("{{\\pard {}\\par}}\n", escape_rtf(line)));
+        }
+    }
+    // Title and byline about halfway down the page
+    for _ in 0..(12usize.saturating_sub(contact.len())) {
+        rtf.push_str("{\\pard\\par}\n");
+    }
+    rtf.push_str(&format!("{{\\pard\\qc\\sl480\\slmult1 {}\\par}}\n", escape_rtf(title)));
+    if !author.is_empty() {
+        rtf.push_str(&format!("{{\\pard\\qc\\sl480\\slmult1 by {}\\par}}\n", escape_rtf(author)));
+    }
+
+    let body = RtfBodyFormat { line_spacing: RtfLineSpacing::Double, first_line_indent: 720 };
+    for (i, (id, content)) in chapters.iter().enumerate() {
+        rtf.push_str("\\page\n");
+        for _ in 0..6 {
+            rtf.push_str("{\\pard\\par}\n");
+        }
+        let chapter_title = project_value.get("chapterTitles")
+            .and_then(|t| t.get(id.to_string()))
+            .and_then(|v| v.as_str())
+            .filter(|t| !t.trim().is_empty())
+            .map(|t| t.to_string())
+            .unwrap_or_else(|| format!("Chapter {}", number_to_words(i as u32 + 1)));
+        rtf.push_str(&format!("{{\\pard\\qc\\sl480\\slmult1 {}\\par}}\n", escape_rtf(&chapter_title)));
+        rtf.push_str("{\\pard\\sl480\\slmult1\\par}\n");
+        if let Some(epigraph) = chapter_epigraph(&project_value, *id) {
+            rtf.push_str(&rtf_epigraph(&epigraph));
+        }
+        rtf.push_str(&json_to_rtf_content(content, &RtfHeadingStyle::default(), &body));
+        on_progress(chapter_progress(i + 1, chapters.len()));
+    }
+    rtf.push_str("{\\pard\\qc\\sl480\\slmult1 END\\par}\n");
+    rtf.push('}');
+
+    let date = Local::now().format("%Y-%m-%d").to_string();
+    let filename = sanitize_filename(&format!("{}_manuscript_{}.rtf", sanitize_filename_part(&project.title), date));
+    let export_path = PathBuf::from(export_dir).join(&filename);
+    fs::write(&export_path, rtf)
+        .map_err(|e| format!("Failed to write RTF file: {}", e))?;
+
+    let output_path = export_path.to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| "Failed to convert path to string".to_string())?;
+    on_progress(complete_progress(chapters.len(), &output_path));
+    Ok(output_path)
+}
+
+// ============================================================
+// Statistics
+// ============================================================
+
+// Read and parse project.json as a raw JSON value
+fn read_project_value(project_file: &Path) -> Result<serde_json::Value, String> {
+	if !project_file.exists() {
+		return Err("project.json not found".to_string());
+	}
+	let content = fs::read_to_string(project_file)
+		.map_err(|e| format!("Failed to read project.json: {}", e))?;
+	serde_json::from_str(&content)
+		.map_err(|e| format!("Failed to parse project.json: {}", e))
+}
+
+// Serialize and write a raw JSON value back to project.json
+fn write_project_value(project_file: &Path, project: &impl Serialize) -> Result<(), String> {
+	let json = to_saved_json(project, JsonIndent::Spaces(2))
+		.map_err(|e| format!("Failed to serialize project: {}", e))?;
+	write_file_atomic(project_file, &json)
+		.map_err(|e| format!("Failed to write project.json: {}", e))
+}
+
+// Read chapters/<id>.json; None if the file is missing or not valid JSON
+fn read_chapter_content(project_path: &Path, chapter_id: u32) -> Option<serde_json::Value> {
+	let chapter_file = project_path.join("chapters").join(format!("{}.json", chapter_id));
+	fs::read_to_string(chapter_file)
+		.ok()
+		.and_then(|s| serde_json::from_str(&s).ok())
+}
+
+// chapterOrder ids from a raw project.json value
+fn chapter_order_of(project: &serde_json::Value) -> Vec<u32> {
+	project.get("chapterOrder")
+		.and_then(|v| v.as_array())
+		.map(|ids| ids.iter().filter_map(|id| id.as_u64().map(|i| i as u32)).collect())
+		.unwrap_or_default()
+}
+
+// Chapters marked "exclude from export" (scratch, outlines)
+fn excluded_chapter_ids(project: &serde_json::Value) -> Vec<u32> {
+	project.get("excludedChapters")
+		.and_then(|v| v.as_array())
+		.map(|ids| ids.iter().filter_map(|id| id.as_u64().map(|i| i as u32)).collect())
+		.unwrap_or_default()
+}
+
+// chapterOrder minus excluded chapters: what a full-book export contains
+fn full_book_chapter_ids(project: &serde_json::Value) -> Vec<u32> {
+	let excluded = excluded_chapter_ids(project);
+	chapter_order_of(project)
+		.into_iter()
+		.filter(|id| !excluded.contains(id))
+		.collect()
+}
+
+// Mark a chapter to be left out of full-book exports (it can still be exported by selecting it)
+#[tauri::command]
+fn set_chapter_excluded(project_path: String, chapter_id: u32, excluded: bool) -> Result<Vec<u32>, String> {
+	let project_file = PathBuf::from(&project_path).join("project.json");
+	let mut project = read_project_value(&project_file)?;
+
+	let mut ids = excluded_chapter_ids(&project);
+	ids.retain(|&id| id != chapter_id);
+	if excluded {
+		ids.push(chapter_id);
+	}
+	ids.sort_unstable();
+	project["excludedChapters"] = serde_json::json!(ids);
+	write_project_value(&project_file, &project)?;
+	Ok(ids)
+}
+
+// Remember the chapter the author has open so load_project can return to it
+#[tauri::command]
+fn set_last_chapter(project_path: String, chapter_id: u32) -> Result<(), String> {
+	let project_file = PathBuf::from(&project_path).join("project.json");
+	let mut project = read_project_value(&project_file)?;
+	if !chapter_order_of(&project).contains(&chapter_id) {
+		return Err(format!("Chapter {} not found", chapter_id));
+	}
+	project["lastOpenedChapter"] = serde_json::json!(chapter_id);
+	write_project_value(&project_file, &project)
+}
+
+// Last edit time per chapter in chapterOrder: the chapterModified stamp written by save_chapter,
+// or the chapter file's mtime for chapters never saved through Scout (imported, copied in)
+fn chapter_modified_map(project_path: &Path, project: &serde_json::Value) -> HashMap<String, String> {
+	let stamped = project.get("chapterModified").and_then(|v| v.as_object());
+	chapter_order_of(project)
+		.into_iter()
+		.filter_map(|id| {
+			let key = id.to_string();
+			let modified = stamped
+				.and_then(|m| m.get(&key))
+				.and_then(|v| v.as_str())
+				.map(|s| s.to_string())
+				.or_else(|| {
+					fs::metadata(project_path.join("chapters").join(format!("{}.json", id)))
+						.and_then(|m| m.modified())
+						.ok()
+						.map(|t| chrono::DateTime::<Local>::from(t).to_rfc3339())
+				})?;
+			Some((key, modified))
+		})
+		.collect()
+}
+
+// Chapter id -> last edit time (RFC 3339); chapters with no file and no stamp are omitted
+#[tauri::command]
+fn get_chapter_modified(project_path: String) -> Result<HashMap<String, String>, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+	Ok(chapter_modified_map(&path, &project))
+}
+
+// Check that a value has the shape of a TipTap document: a "doc" root, every node an object
+// with a string `type`, `content` always an array of nodes and text nodes carrying string text
+fn validate_tiptap_doc(doc: &serde_json::Value) -> Result<(), String> {
+	if doc.get("type").and_then(|t| t.as_str()) != Some("doc") {
+		return Err("Document root must have type \"doc\"".to_string());
+	}
+	validate_tiptap_node(doc, "doc")
+}
+
+fn validate_tiptap_node(node: &serde_json::Value, path: &str) -> Result<(), String> {
+	let node_type = node.get("type")
+		.and_then(|t| t.as_str())
+		.ok_or_else(|| format!("Node at {} has no type", path))?;
+	if node_type == "text" && !node.get("text").is_some_and(|t| t.is_string()) {
+		return Err(format!("Text node at {} has no text", path));
+	}
+	if let Some(marks) = node.get("marks") {
+		let marks = marks.as_array().ok_or_else(|| format!("Marks at {} must be an array", path))?;
+		if marks.iter().any(|m| !m.get("type").is_some_and(|t| t.is_string())) {
+			return Err(format!("Mark at {} has no type", path));
+		}
+	}
+	match node.get("content") {
+		None => Ok(()),
+		Some(content) => {
+			let children = content.as_array()
+				.ok_or_else(|| format!("Content of {} must be an array", path))?;
+			for (i, child) in children.iter().enumerate() {
+				validate_tiptap_node(child, &format!("{}.content[{}]", path, i))?;
+			}
+			Ok(())
+		}
+	}
+}
+
+// A chapter's TipTap document, addressed by id only so callers don't depend on the
+// chapters/<id>.json layout
+#[tauri::command]
+fn get_chapter_json(project_path: String, chapter_id: u32) -> Result<serde_json::Value, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+	if !chapter_order_of(&project).contains(&chapter_id) {
+		return Err(format!("Chapter {} not found", chapter_id));
+	}
+	let chapter_file = path.join("chapters").join(format!("{}.json", chapter_id));
+	let content = fs::read_to_string(&chapter_file)
+		.map_err(|_| format!("Chapter {} not found", chapter_id))?;
+	serde_json::from_str(&content)
+		.map_err(|e| format!("Chapter {} is not valid JSON: {}", chapter_id, e))
+}
+
+// Replace a chapter's TipTap document after checking its structure; saved like save_chapter
+#[tauri::command]
+fn set_chapter_json(project_path: String, chapter_id: u32, doc: serde_json::Value) -> Result<(), String> {
+	let project = read_project_value(&PathBuf::from(&project_path).join("project.json"))?;
+	if !chapter_order_of(&project).contains(&chapter_id) {
+		return Err(format!("Chapter {} not found", chapter_id));
+	}
+	validate_tiptap_doc(&doc)?;
+	let json_content = serde_json::to_string(&doc)
+		.map_err(|e| format!("Failed to serialize chapter: {}", e))?;
+	save_chapter(project_path, chapter_id, json_content)
+}
+
+// Narrow an export selection to chapters edited after `since` (RFC 3339); an empty selection
+// means the full book. Errors when nothing qualifies, since an empty list would export everything.
+fn chapters_modified_since(project_path: &str, chapter_ids: Vec<u32>, since: Option<&str>) -> Result<Vec<u32>, String> {
+	let Some(since) = since.filter(|s| !s.trim().is_empty()) else {
+		return Ok(chapter_ids);
+	};
+	let cutoff = chrono::DateTime::parse_from_rfc3339(since.trim())
+		.map_err(|e| format!("Invalid timestamp '{}': {}", since, e))?;
+
+	let path = PathBuf::from(project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+	let modified = chapter_modified_map(&path, &project);
+	let candidates = if chapter_ids.is_empty() { full_book_chapter_ids(&project) } else { chapter_ids };
+
+	let ids: Vec<u32> = candidates
+		.into_iter()
+		.filter(|id| {
+			modified
+				.get(&id.to_string())
+				.and_then(|m| chrono::DateTime::parse_from_rfc3339(m).ok())
+				.is_some_and(|m| m > cutoff)
+		})
+		.collect();
+	if ids.is_empty() {
+		return Err(format!("No chapters modified since {}", since));
+	}
+	Ok(ids)
+}
+
+// Flatten a TipTap node to plain text, putting each block on its own line
+fn tiptap_to_plain_text(node: &serde_json::Value) -> String {
+	let mut out = String::new();
+	collect_plain_text(node, &mut out, false);
+	out
+}
+
+// Like tiptap_to_plain_text but leaves out code (see is_in_code_context), for prose analysis
+fn tiptap_to_prose_text(node: &serde_json::Value) -> String {
+	let mut out = String::new();
+	collect_plain_text(node, &mut out, true);
+	out
+}
+
+// True for a codeBlock node and for text marked as inline code. Commands that analyse or
+// rewrite prose leave these alone unless the caller opts in to including code.
+fn is_in_code_context(node: &serde_json::Value) -> bool {
+	match node.get("type").and_then(|t| t.as_str()) {
+		Some("codeBlock") => true,
+		Some("text") => node.get("marks")
+			.and_then(|m| m.as_array())
+			.is_some_and(|marks| marks.iter().any(|m| m.get("type").and_then(|t| t.as_str()) == Some("code"))),
+		_ => false,
+	}
+}
+
+fn collect_plain_text(node: &serde_json::Value, out: &mut String, skip_code: bool) {
+	if skip_code && is_in_code_context(node) {
+		return;
+	}
+	match node.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+		"text" => {
+			if let Some(text) = node.get("text").and_then(|t| t.as_str()) {
+				out.push_str(text);
+			}
+		}
+		"hardBreak" => out.push('\n'),
+		_ => {
+			if let Some(children) = node.get("content").and_then(|c| c.as_array()) {
+				for child in children {
+					collect_plain_text(child, out, skip_code);
+				}
+			}
+			if !out.is_empty() && !out.ends_with('\n') {
+				out.push('\n');
+			}
+		}
+	}
+}
+
+fn count_words(text: &str) -> usize {
+	text.split_whitespace().count()
+}
+
+// Word count of every chapter in chapterOrder, as (id, words) pairs in order
+fn chapter_word_counts(project_path: &Path) -> Result<Vec<(u32, usize)>, String> {
+	let project = read_project_value(&project_path.join("project.json"))?;
+	let order = chapter_order_of(&project);
+
+	Ok(order.into_iter()
+		.map(|id| {
+			let words = read_chapter_content(project_path, id)
+				.map(|doc| count_words(&tiptap_to_plain_text(&doc)))
+				.unwrap_or(0);
+			(id, words)
+		})
+		.collect())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WordGoalProgress {
+	goal: u64,
+	#[serde(rename = "currentWords")]
+	current_words: u64,
+	percent: f64,
+	remaining: u64,
+}
+
+// Store a total word goal for the project
+#[tauri::command]
+fn set_word_goal(project_path: String, total_goal: i64) -> Result<(), String> {
+	if total_goal <= 0 {
+		return Err("Word goal must be greater than zero".to_string());
+	}
+
+	let project_file = PathBuf::from(&project_path).join("project.json");
+	let mut project = read_project_value(&project_file)?;
+	project["wordGoal"] = serde_json::json!(total_goal);
+	write_project_value(&project_file, &project)
+}
+
+// Report progress toward the project's word goal
+#[tauri::command]
+fn word_goal_progress(project_path: String) -> Result<WordGoalProgress, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+
+	let goal = project.get("wordGoal")
+		.and_then(|v| v.as_u64())
+		.filter(|&g| g > 0)
+		.ok_or_else(|| "No word goal set for this project".to_string())?;
+
+	let current_words = project_total_words(&path)?;
+
+	let percent = (current_words as f64 / goal as f64 * 1000.0).round() / 10.0;
+
+	Ok(WordGoalProgress {
+		goal,
+		current_words,
+		percent,
+		remaining: goal.saturating_sub(current_words),
+	})
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChapterTargetProgress {
+	#[serde(rename = "chapterId")]
+	chapter_id: u32,
+	target: u64,
+	current: u64,
+	percent: f64,
+}
+
+// Set (or clear, with None) a chapter's target word count, stored under `chapterTargets`
+#[tauri::command]
+fn set_chapter_target(project_path: String, chapter_id: u32, target: Option<i64>) -> Result<(), String> {
+	let project_file = PathBuf::from(&project_path).join("project.json");
+	let mut project = read_project_value(&project_file)?;
+	if !chapter_order_of(&project).contains(&chapter_id) {
+		return Err(format!("Chapter {} not found", chapter_id));
+	}
+
+	let key = chapter_id.to_string();
+	match target {
+		Some(target) if target <= 0 => {
+			return Err("Chapter target must be greater than zero".to_string());
+		}
+		Some(target) => {
+			if !project.get("chapterTargets").is_some_and(|t| t.is_object()) {
+				project["chapterTargets"] = serde_json::json!({});
+			}
+			project["chapterTargets"][key] = serde_json::json!(target);
+		}
+		None => {
+			if let Some(map) = project.get_mut("chapterTargets").and_then(|v| v.as_object_mut()) {
+				map.remove(&key);
+			}
+		}
+	}
+	write_project_value(&project_file, &project)
+}
+
+// Progress of every chapter in chapterOrder that has a target, in order
+#[tauri::command]
+fn chapter_targets_progress(project_path: String) -> Result<Vec<ChapterTargetProgress>, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+	let Some(targets) = project.get("chapterTargets").and_then(|v| v.as_object()) else {
+		return Ok(Vec::new());
+	};
+
+	Ok(chapter_word_counts(&path)?
+		.into_iter()
+		.filter_map(|(id, words)| {
+			let target = targets.get(&id.to_string())
+				.and_then(|v| v.as_u64())
+				.filter(|&t| t > 0)?;
+			let current = words as u64;
+			Some(ChapterTargetProgress {
+				chapter_id: id,
+				target,
+				current,
+				percent: (current as f64 / target as f64 * 1000.0).round() / 10.0,
+			})
+		})
+		.collect())
+}
+
+// Number of daily entries kept in project.json's wordHistory
+const WORD_HISTORY_LIMIT: usize = 365;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WordsSinceBaseline {
+	baseline: u64,
+	#[serde(rename = "currentWords")]
+	current_words: u64,
+	delta: i64,
+	#[serde(rename = "markedAt")]
+	marked_at: String,
+}
+
+fn project_total_words(project_path: &Path) -> Result<u64, String> {
+	Ok(chapter_word_counts(project_path)?
+		.iter()
+		.map(|&(_, words)| words as u64)
+		.sum())
+}
+
+// Record the current total word count as the session baseline.
+// Also appends to wordHistory (one entry per day, latest mark wins) so the UI can chart output.
+#[tauri::command]
+fn mark_word_baseline(project_path: String) -> Result<u64, String> {
+	let path = PathBuf::from(&project_path);
+	let project_file = path.join("project.json");
+	let mut project = read_project_value(&project_file)?;
+
+	let words = project_total_words(&path)?;
+	let now = Local::now();
+	let today = now.format("%Y-%m-%d").to_string();
+
+	project["wordBaseline"] = serde_json::json!({
+		"words": words,
+		"markedAt": now.to_rfc3339(),
+	});
+
+	if !project.get("wordHistory").is_some_and(|v| v.is_array()) {
+		project["wordHistory"] = serde_json::json!([]);
+	}
+	if let Some(history) = project.get_mut("wordHistory").and_then(|v| v.as_array_mut()) {
+		history.retain(|entry| entry.get("date").and_then(|d| d.as_str()) != Some(today.as_str()));
+		history.push(serde_json::json!({ "date": today, "words": words }));
+		if history.len() > WORD_HISTORY_LIMIT {
+			let excess = history.len() - WORD_HISTORY_LIMIT;
+			history.drain(..excess);
+		}
+	}
+
+	write_project_value(&project_file, &project)?;
+	Ok(words)
+}
+
+// Words written (or cut, if negative) since the last mark_word_baseline
+#[tauri::command]
+fn words_since_baseline(project_path: String) -> Result<WordsSinceBaseline, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+
+	let baseline_obj = project.get("wordBaseline")
+		.ok_or_else(|| "No word baseline recorded for this project".to_string())?;
+	let baseline = baseline_obj.get("words").and_then(|v| v.as_u64()).unwrap_or(0);
+	let marked_at = baseline_obj.get("markedAt")
+		.and_then(|v| v.as_str())
+		.unwrap_or("")
+		.to_string();
+
+	let current_words = project_total_words(&path)?;
+
+	Ok(WordsSinceBaseline {
+		baseline,
+		current_words,
+		delta: current_words as i64 - baseline as i64,
+		marked_at,
+	})
+}
+
+const DEFAULT_WORDS_PER_PAGE: u32 = 250;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChapterPageEstimate {
+	id: u32,
+	words: u64,
+	pages: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PageEstimate {
+	#[serde(rename = "wordsPerPage")]
+	words_per_page: u32,
+	chapters: Vec<ChapterPageEstimate>,
+	#[serde(rename = "totalWords")]
+	total_words: u64,
+	#[serde(rename = "totalPages")]
+	total_pages: u64,
+}
+
+// Estimate printed page counts per chapter (in chapterOrder) and in total.
+// Each chapter rounds up separately since chapters start on a new page.
+#[tauri::command]
+fn page_estimate(project_path: String, words_per_page: Option<u32>) -> Result<PageEstimate, String> {
+	let words_per_page = words_per_page.unwrap_or(DEFAULT_WORDS_PER_PAGE);
+	if words_per_page == 0 {
+		return Err("Words per page must be greater than zero".to_string());
+	}
+
+	let chapters: Vec<ChapterPageEstimate> = chapter_word_counts(&PathBuf::from(&project_path))?
+		.into_iter()
+		.map(|(id, words)| ChapterPageEstimate {
+			id,
+			words: words as u64,
+			pages: (words as u64).div_ceil(words_per_page as u64),
+		})
+		.collect();
+
+	Ok(PageEstimate {
+		words_per_page,
+		total_words: chapters.iter().map(|c| c.words).sum(),
+		total_pages: chapters.iter().map(|c| c.pages).sum(),
+		chapters,
+	})
+}
+
+// Lowercased word tokens, split on anything that isn't a letter, digit or apostrophe
+fn normalized_tokens(text: &str) -> Vec<String> {
+	text.split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '\u{2019}'))
+		.map(|t| t.trim_matches(|c| c == '\'' || c == '\u{2019}').to_lowercase())
+		.filter(|t| !t.is_empty())
+		.collect()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DuplicateChapterPair {
+	#[serde(rename = "idA")]
+	id_a: u32,
+	#[serde(rename = "idB")]
+	id_b: u32,
+	similarity: f64,
+}
+
+// Report pairs of chapters whose token-set Jaccard similarity exceeds the threshold (0.0–1.0),
+// most similar first. Empty chapters are ignored.
+#[tauri::command]
+fn find_duplicate_chapters(project_path: String, threshold: f64) -> Result<Vec<DuplicateChapterPair>, String> {
+	if !(0.0..=1.0).contains(&threshold) {
+		return Err("Threshold must be between 0 and 1".to_string());
+	}
+
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+	let order = chapter_order_of(&project);
+
+	let token_sets: Vec<(u32, HashSet<String>)> = order.into_iter()
+		.filter_map(|id| {
+			let doc = read_chapter_content(&path, id)?;
+			let tokens: HashSet<String> = normalized_tokens(&tiptap_to_plain_text(&doc)).into_iter().collect();
+			if tokens.is_empty() { None } else { Some((id, tokens)) }
+		})
+		.collect();
+
+	let mut pairs = Vec::new();
+	for (i, (id_a, tokens_a)) in token_sets.iter().enumerate() {
+		for (id_b, tokens_b) in &token_sets[i + 1..] {
+			let intersection = tokens_a.intersection(tokens_b).count();
+			let union = tokens_a.len() + tokens_b.len() - intersection;
+			let similarity = intersection as f64 / union as f64;
+			if similarity > threshold {
+				pairs.push(DuplicateChapterPair { id_a: *id_a, id_b: *id_b, similarity });
+			}
+		}
+	}
+
+	pairs.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+	Ok(pairs)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DiffChange {
+	// "insert", "delete" or "replace"
+	kind: String,
+	// Character (not byte) ranges in textA and textB; the range is empty on the side without text
+	#[serde(rename = "startA")]
+	start_a: usize,
+	#[serde(rename = "endA")]
+	end_a: usize,
+	#[serde(rename = "startB")]
+	start_b: usize,
+	#[serde(rename = "endB")]
+	end_b: usize,
+	#[serde(rename = "oldText")]
+	old_text: String,
+	#[serde(rename = "newText")]
+	new_text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChapterDiff {
+	#[serde(rename = "textA")]
+	text_a: String,
+	#[serde(rename = "textB")]
+	text_b: String,
+	changes: Vec<DiffChange>,
+}
+
+// Above this many LCS table cells a differing stretch is reported as one replacement
+const DIFF_CELL_LIMIT: usize = 4_000_000;
+
+// Runs where `a` and `b` differ, as (range in a, range in b), from a longest common subsequence.
+// An adjacent deletion and insertion come back as one run covering both.
+fn diff_runs<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(std::ops::Range<usize>, std::ops::Range<usize>)> {
+	let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+	let suffix = a[prefix..].iter().rev()
+		.zip(b[prefix..].iter().rev())
+		.take_while(|(x, y)| x == y)
+		.count();
+	let a_mid = &a[prefix..a.len() - suffix];
+	let b_mid = &b[prefix..b.len() - suffix];
+	let (n, m) = (a_mid.len(), b_mid.len());
+	if n == 0 && m == 0 {
+		return Vec::new();
+	}
+	if n == 0 || m == 0 || n * m > DIFF_CELL_LIMIT {
+		return vec![(prefix..prefix + n, prefix..prefix + m)];
+	}
+
+	// lcs[i * (m + 1) + j] = length of the LCS of a_mid[i..] and b_mid[j..]
+	let width = m + 1;
+	let mut lcs = vec![0u32; (n + 1) * width];
+	for i in (0..n).rev() {
+		for j in (0..m).rev() {
+			lcs[i * width + j] = if a_mid[i] == b_mid[j] {
+				lcs[(i + 1) * width + j + 1] + 1
+			} else {
+				lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+			};
+		}
+	}
+
+	let mut runs = Vec::new();
+	let mut open: Option<(usize, usize)> = None;
+	let (mut i, mut j) = (0, 0);
+	while i < n || j < m {
+		if i < n && j < m && a_mid[i] == b_mid[j] {
+			if let Some((si, sj)) = open.take() {
+				runs.push((prefix + si..prefix + i, prefix + sj..prefix + j));
+			}
+			i += 1;
+			j += 1;
+			continue;
+		}
+		open.get_or_insert((i, j));
+		if j < m && (i == n || lcs[i * width + j + 1] >= lcs[(i + 1) * width + j]) {
+			j += 1;
+		} else {
+			i += 1;
+		}
+	}
+	if let Some((si, sj)) = open {
+		runs.push((prefix + si..prefix + n, prefix + sj..prefix + m));
+	}
+	runs
+}
+
+// Words, whitespace runs and single punctuation characters, which concatenate back to `text`
+fn diff_tokens(text: &str) -> Vec<&str> {
+	let class = |c: char| if c.is_alphanumeric() || c == '\'' || c == '\u{2019}' { 0 } else if c.is_whitespace() { 1 } else { 2 };
+	let mut tokens = Vec::new();
+	let mut start = 0;
+	let mut previous: Option<u8> = None;
+	for (i, c) in text.char_indices() {
+		let kind = class(c);
+		if let Some(p) = previous {
+			if p != kind || kind == 2 {
+				tokens.push(&text[start..i]);
+				start = i;
+			}
+		}
+		previous = Some(kind);
+	}
+	if start < text.len() {
+		tokens.push(&text[start..]);
+	}
+	tokens
+}
+
+// Character offset at which each token starts, plus the total length at the end
+fn token_char_offsets(tokens: &[&str]) -> Vec<usize> {
+	let mut offsets = Vec::with_capacity(tokens.len() + 1);
+	let mut total = 0;
+	offsets.push(0);
+	for token in tokens {
+		total += token.chars().count();
+		offsets.push(total);
+	}
+	offsets
+}
+
+// Line-level diff of two texts, refined to words inside each changed stretch of lines
+fn diff_texts(text_a: &str, text_b: &str) -> Vec<DiffChange> {
+	let lines_a: Vec<&str> = text_a.split_inclusive('\n').collect();
+	let lines_b: Vec<&str> = text_b.split_inclusive('\n').collect();
+	let line_offsets_a = token_char_offsets(&lines_a);
+	let line_offsets_b = token_char_offsets(&lines_b);
+
+	let mut changes = Vec::new();
+	for (lines_range_a, lines_range_b) in diff_runs(&lines_a, &lines_b) {
+		let words_a = diff_tokens_of(&lines_a[lines_range_a.clone()]);
+		let words_b = diff_tokens_of(&lines_b[lines_range_b.clone()]);
+		let base_a = line_offsets_a[lines_range_a.start];
+		let base_b = line_offsets_b[lines_range_b.start];
+		let offsets_a = token_char_offsets(&words_a);
+		let offsets_b = token_char_offsets(&words_b);
+		for (ra, rb) in diff_runs(&words_a, &words_b) {
+			let kind = match (ra.is_empty(), rb.is_empty()) {
+				(true, _) => "insert",
+				(_, true) => "delete",
+				_ => "replace",
+			};
+			changes.push(DiffChange {
+				kind: kind.to_string(),
+				start_a: base_a + offsets_a[ra.start],
+				end_a: base_a + offsets_a[ra.end],
+				start_b: base_b + offsets_b[rb.start],
+				end_b: base_b + offsets_b[rb.end],
+				old_text: words_a[ra].concat(),
+				new_text: words_b[rb].concat(),
+			});
+		}
+	}
+	changes
+}
+
+fn diff_tokens_of<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+	lines.iter().flat_map(|line| diff_tokens(line)).collect()
+}
+
+// Compare two chapters as plain text, e.g. a chapter and its revised copy, for a compare view
+#[tauri::command]
+fn diff_chapters(project_path: String, id_a: u32, id_b: u32) -> Result<ChapterDiff, String> {
+	let path = PathBuf::from(&project_path);
+	let text_of = |id: u32| read_chapter_content(&path, id)
+		.map(|doc| tiptap_to_plain_text(&doc))
+		.ok_or_else(|| format!("Chapter {} not found", id));
+	let text_a = text_of(id_a)?;
+	let text_b = text_of(id_b)?;
+	let changes = diff_texts(&text_a, &text_b);
+	Ok(ChapterDiff { text_a, text_b, changes })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MarkerMatch {
+	#[serde(rename = "chapterId")]
+	chapter_id: u32,
+	marker: String,
+	// The marker's line, cut to MARKER_CONTEXT_CHARS either side with "…"
+	context: String,
+	// Character (not byte) offset in the chapter's plain text
+	offset: usize,
+}
+
+// Markers find_markers looks for when no patterns are given
+const DEFAULT_MARKERS: &[&str] = &["[TODO]", "TODO", "TK", "???", "XXX"];
+
+const MARKER_CONTEXT_CHARS: usize = 40;
+
+// Byte ranges of `patterns` in `text`, earliest first, longest first where matches overlap.
+// A pattern that starts or ends with a letter or digit must not touch another letter or digit
+// there, so "TK" doesn't match inside "TKO".
+fn marker_ranges(text: &str, patterns: &[&str]) -> Vec<(usize, usize)> {
+	let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric());
+	let mut ranges: Vec<(usize, usize)> = Vec::new();
+	for pattern in patterns {
+		let first_word = is_word(pattern.chars().next());
+		let last_word = is_word(pattern.chars().next_back());
+		for (start, found) in text.match_indices(pattern) {
+			let end = start + found.len();
+			if (first_word && is_word(text[..start].chars().next_back()))
+				|| (last_word && is_word(text[end..].chars().next()))
+			{
+				continue;
+			}
+			ranges.push((start, end));
+		}
+	}
+	ranges.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+	let mut kept: Vec<(usize, usize)> = Vec::new();
+	for range in ranges {
+		if kept.last().is_none_or(|last| range.0 >= last.1) {
+			kept.push(range);
+		}
+	}
+	kept
+}
+
+// The line around text[start..end], at most MARKER_CONTEXT_CHARS characters either side
+fn marker_context(text: &str, start: usize, end: usize) -> String {
+	let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+	let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i);
+
+	let before: Vec<char> = text[line_start..start].chars().collect();
+	let after: Vec<char> = text[end..line_end].chars().collect();
+	let mut context = String::new();
+	if before.len() > MARKER_CONTEXT_CHARS {
+		context.push('…');
+		context.extend(&before[before.len() - MARKER_CONTEXT_CHARS..]);
+	} else {
+		context.extend(&before);
+	}
+	context.push_str(&text[start..end]);
+	context.extend(after.iter().take(MARKER_CONTEXT_CHARS));
+	if after.len() > MARKER_CONTEXT_CHARS {
+		context.push('…');
+	}
+	context.trim().to_string()
+}
+
+// Find drafting markers such as TK, TODO or ??? in every chapter, in chapterOrder.
+// `patterns` replaces the default markers when given; matching is case-sensitive.
+#[tauri::command]
+fn find_markers(project_path: String, patterns: Option<Vec<String>>) -> Result<Vec<MarkerMatch>, String> {
+	let custom: Vec<String> = patterns.unwrap_or_default()
+		.into_iter()
+		.map(|p| p.trim().to_string())
+		.filter(|p| !p.is_empty())
+		.collect();
+	let patterns: Vec<&str> = if custom.is_empty() {
+		DEFAULT_MARKERS.to_vec()
+	} else {
+		custom.iter().map(|p| p.as_str()).collect()
+	};
+
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+
+	let mut matches = Vec::new();
+	for id in chapter_order_of(&project) {
+		let Some(doc) = read_chapter_content(&path, id) else {
+			continue;
+		};
+		let text = tiptap_to_plain_text(&doc);
+		for (start, end) in marker_ranges(&text, &patterns) {
+			matches.push(MarkerMatch {
+				chapter_id: id,
+				marker: text[start..end].to_string(),
+				context: marker_context(&text, start, end),
+				offset: text[..start].chars().count(),
+			});
+		}
+	}
+	Ok(matches)
+}
+
+// Abbreviations whose trailing period doesn't end a sentence (lowercased, without the period)
+const ABBREVIATIONS: &[&str] = &[
+	"mr", "mrs", "ms", "dr", "st", "jr", "sr", "prof", "rev", "gen", "col", "capt", "lt",
+	"sgt", "mt", "vs", "etc", "e.g", "i.e", "fig", "approx", "dept", "est", "inc", "ltd",
+];
+
+// Count sentences in plain text: a word ending in . ! or ? (before any closing quotes/brackets)
+// ends a sentence, except known abbreviations and single-letter initials. An ellipsis only ends
+// a sentence when the next word is capitalised. Each line's unterminated tail counts as one.
+fn count_sentences(text: &str) -> usize {
+	let mut sentences = 0;
+	for line in text.lines() {
+		let words: Vec<&str> = line.split_whitespace().collect();
+		let mut open = false;
+		for (i, word) in words.iter().enumerate() {
+			open = true;
+			let core = word.trim_end_matches(['"', '\'', ')', ']', '\u{201d}', '\u{2019}']);
+			if !core.ends_with(['.', '!', '?', '\u{2026}']) {
+				continue;
+			}
+			let stem = core.trim_end_matches(['.', '!', '?', '\u{2026}']);
+			let bare = stem.trim_start_matches(['"', '\'', '(', '[', '\u{201c}', '\u{2018}']).to_lowercase();
+			let next_capitalised = words.get(i + 1)
+				.and_then(|w| w.trim_start_matches(['"', '\'', '(', '[', '\u{201c}', '\u{2018}']).chars().next())
+				.is_none_or(|c| c.is_uppercase() || c.is_numeric());
+
+			let is_ellipsis = core.ends_with("..") || core.ends_with('\u{2026}');
+			let is_abbreviation = core.ends_with('.') && !is_ellipsis
+				&& (ABBREVIATIONS.contains(&bare.as_str())
+					|| (bare.chars().count() == 1 && bare.chars().all(char::is_alphabetic)));
+
+			if is_abbreviation || (is_ellipsis && !next_capitalised) {
+				continue;
+			}
+			sentences += 1;
+			open = false;
+		}
+		if open {
+			sentences += 1;
+		}
+	}
+	sentences
+}
+
+// Top-level paragraph nodes with any text in them
+fn count_paragraphs(doc: &serde_json::Value) -> usize {
+	doc.get("content")
+		.and_then(|c| c.as_array())
+		.map(|nodes| nodes.iter()
+			.filter(|n| n.get("type").and_then(|t| t.as_str()) == Some("paragraph"))
+			.filter(|n| !tiptap_to_plain_text(n).trim().is_empty())
+			.count())
+		.unwrap_or(0)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TextStats {
+	words: usize,
+	characters: usize,
+	sentences: usize,
+	paragraphs: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChapterStats {
+	id: u32,
+	#[serde(flatten)]
+	stats: TextStats,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProjectStats {
+	chapters: Vec<ChapterStats>,
+	total: TextStats,
+}
+
+fn text_stats(doc: &serde_json::Value) -> TextStats {
+	let text = tiptap_to_plain_text(doc);
+	TextStats {
+		words: count_words(&text),
+		characters: text.chars().filter(|&c| c != '\n').count(),
+		sentences: count_sentences(&tiptap_to_prose_text(doc)),
+		paragraphs: count_paragraphs(doc),
+	}
+}
+
+// Word, character, sentence and paragraph counts per chapter (in chapterOrder) and in total
+#[tauri::command]
+fn project_stats(project_path: String) -> Result<ProjectStats, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+
+	let chapters: Vec<ChapterStats> = chapter_order_of(&project)
+		.into_iter()
+		.map(|id| ChapterStats {
+			id,
+			stats: read_chapter_content(&path, id).map(|doc| text_stats(&doc)).unwrap_or_default(),
+		})
+		.collect();
+
+	let total = chapters.iter().fold(TextStats::default(), |acc, ch| TextStats {
+		words: acc.words + ch.stats.words,
+		characters: acc.characters + ch.stats.characters,
+		sentences: acc.sentences + ch.stats.sentences,
+		paragraphs: acc.paragraphs + ch.stats.paragraphs,
+	});
+
+	Ok(ProjectStats { chapters, total })
+}
+
+// Syllable estimate: count vowel groups, drop a silent trailing "e" (but not "-le") or "-ed"
+// (but not "-ted"/"-ded"), minimum one
+fn count_syllables(word: &str) -> usize {
+	let word: String = word.to_lowercase().chars().filter(|c| c.is_alphabetic()).collect();
+	if word.is_empty() {
+		return 0;
+	}
+	let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+	let mut groups = 0;
+	let mut prev_vowel = false;
+	for c in word.chars() {
+		let vowel = is_vowel(c);
+		if vowel && !prev_vowel {
+			groups += 1;
+		}
+		prev_vowel = vowel;
+	}
+	let silent_e = word.len() > 2 && word.ends_with('e') && !word.ends_with("le")
+		&& !word[..word.len() - 1].ends_with(is_vowel);
+	let silent_ed = word.len() > 3 && word.ends_with("ed") && !word.ends_with("ted") && !word.ends_with("ded")
+		&& !word[..word.len() - 2].ends_with(is_vowel);
+	if silent_e || silent_ed {
+		groups -= 1;
+	}
+	groups.max(1)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ReadabilityScore {
+	words: usize,
+	sentences: usize,
+	syllables: usize,
+	// Flesch Reading Ease (higher is easier); None for empty text
+	#[serde(rename = "readingEase")]
+	reading_ease: Option<f64>,
+	// Flesch-Kincaid Grade Level; None for empty text
+	#[serde(rename = "gradeLevel")]
+	grade_level: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChapterReadability {
+	id: u32,
+	#[serde(flatten)]
+	score: ReadabilityScore,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ReadabilityReport {
+	chapters: Vec<ChapterReadability>,
+	overall: ReadabilityScore,
+}
+
+fn readability_score(words: usize, sentences: usize, syllables: usize) -> ReadabilityScore {
+	let round1 = |x: f64| (x * 10.0).round() / 10.0;
+	let (reading_ease, grade_level) = if words == 0 || sentences == 0 {
+		(None, None)
+	} else {
+		let words_per_sentence = words as f64 / sentences as f64;
+		let syllables_per_word = syllables as f64 / words as f64;
+		(
+			Some(round1(206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word)),
+			Some(round1(0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59)),
+		)
+	};
+	ReadabilityScore { words, sentences, syllables, reading_ease, grade_level }
+}
+
+// Flesch Reading Ease and Flesch-Kincaid Grade Level per chapter and overall
+// for the given chapters (all chapters if empty)
+#[tauri::command]
+fn readability(project_path: String, chapter_ids: Vec<u32>) -> Result<ReadabilityReport, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+
+	let mut chapters = Vec::new();
+	let (mut total_words, mut total_sentences, mut total_syllables) = (0, 0, 0);
+
+	for id in chapter_order_of(&project) {
+		if !chapter_ids.is_empty() && !chapter_ids.contains(&id) {
+			continue;
+		}
+		let text = read_chapter_content(&path, id)
+			.map(|doc| tiptap_to_prose_text(&doc))
+			.unwrap_or_default();
+		let tokens = normalized_tokens(&text);
+		let words = tokens.len();
+		let sentences = count_sentences(&text);
+		let syllables: usize = tokens.iter().map(|t| count_syllables(t)).sum();
+
+		total_words += words;
+		total_sentences += sentences;
+		total_syllables += syllables;
+		chapters.push(ChapterReadability { id, score: readability_score(words, sentences, syllables) });
+	}
+
+	Ok(ReadabilityReport {
+		chapters,
+		overall: readability_score(total_words, total_sentences, total_syllables),
+	})
+}
+
+// Common English function words left out of the style report's frequency list
+const STOPWORDS: &[&str] = &[
+	"a", "about", "after", "all", "also", "am", "an", "and", "any", "are", "as", "at",
+	"be", "because", "been", "before", "being", "but", "by", "can", "could", "did", "do",
+	"does", "for", "from", "had", "has", "have", "he", "her", "here", "hers", "him", "his",
+	"how", "i", "if", "in", "into", "is", "it", "it's", "its", "just", "me", "my", "no",
+	"not", "of", "on", "one", "or", "our", "out", "over", "said", "she", "so", "some",
+	"than", "that", "the", "their", "them", "then", "there", "they", "this", "to", "up",
+	"us", "was", "we", "were", "what", "when", "where", "which", "who", "will", "with",
+	"would", "you", "your",
+];
+
+// Words ending in -ly that aren't adverbs
+const NON_ADVERB_LY: &[&str] = &[
+	"apply", "belly", "bully", "early", "family", "fly", "holy", "italy", "jelly", "july",
+	"lily", "only", "rally", "reply", "supply", "ugly",
+];
+
+fn is_ly_adverb(word: &str) -> bool {
+	word.len() > 4 && word.ends_with("ly") && !NON_ADVERB_LY.contains(&word)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WordFrequency {
+	word: String,
+	count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChapterStyleReport {
+	id: u32,
+	words: usize,
+	#[serde(rename = "topWords")]
+	top_words: Vec<WordFrequency>,
+	adverbs: usize,
+	// -ly adverbs per 100 words
+	#[serde(rename = "adverbDensity")]
+	adverb_density: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StyleReport {
+	chapters: Vec<ChapterStyleReport>,
+	#[serde(rename = "topWords")]
+	top_words: Vec<WordFrequency>,
+	words: usize,
+	adverbs: usize,
+	#[serde(rename = "adverbDensity")]
+	adverb_density: f64,
+}
+
+// Most frequent words first, ties broken alphabetically
+fn top_words(counts: &HashMap<String, usize>, top_n: usize) -> Vec<WordFrequency> {
+	let mut words: Vec<WordFrequency> = counts.iter()
+		.map(|(word, &count)| WordFrequency { word: word.clone(), count })
+		.collect();
+	words.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.word.cmp(&b.word)));
+	words.truncate(top_n);
+	words
+}
+
+fn per_hundred(count: usize, words: usize) -> f64 {
+	if words == 0 {
+		0.0
+	} else {
+		(count as f64 / words as f64 * 1000.0).round() / 10.0
+	}
+}
+
+// Frequent-word and -ly adverb report for the given chapters (all chapters if empty).
+// Stopwords are excluded from the frequency lists; code is skipped unless `include_code`.
+#[tauri::command]
+fn style_report(
+	project_path: String,
+	chapter_ids: Vec<u32>,
+	top_n: Option<usize>,
+	include_code: Option<bool>,
+) -> Result<StyleReport, String> {
+	let include_code = include_code.unwrap_or(false);
+	let top_n = top_n.unwrap_or(20);
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+	let ids: Vec<u32> = chapter_order_of(&project)
+		.into_iter()
+		.filter(|id| chapter_ids.is_empty() || chapter_ids.contains(id))
+		.collect();
+
+	let mut chapters = Vec::new();
+	let mut total_counts: HashMap<String, usize> = HashMap::new();
+	let mut total_words = 0;
+	let mut total_adverbs = 0;
+
+	for id in ids {
+		let text = read_chapter_content(&path, id)
+			.map(|doc| if include_code { tiptap_to_plain_text(&doc) } else { tiptap_to_prose_text(&doc) })
+			.unwrap_or_default();
+		let tokens = normalized_tokens(&text);
+
+		let mut counts: HashMap<String, usize> = HashMap::new();
+		let mut adverbs = 0;
+		for token in &tokens {
+			if is_ly_adverb(token) {
+				adverbs += 1;
+			}
+			if !STOPWORDS.contains(&token.as_str()) {
+				*counts.entry(token.clone()).or_insert(0) += 1;
+			}
+		}
+		for (word, count) in &counts {
+			*total_counts.entry(word.clone()).or_insert(0) += count;
+		}
+		total_words += tokens.len();
+		total_adverbs += adverbs;
+
+		chapters.push(ChapterStyleReport {
+			id,
+			words: tokens.len(),
+			top_words: top_words(&counts, top_n),
+			adverbs,
+			adverb_density: per_hundred(adverbs, tokens.len()),
+		});
+	}
+
+	Ok(StyleReport {
+		chapters,
+		top_words: top_words(&total_counts, top_n),
+		words: total_words,
+		adverbs: total_adverbs,
+		adverb_density: per_hundred(total_adverbs, total_words),
+	})
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TypographyCounts {
+	#[serde(rename = "straightDoubleQuotes")]
+	straight_double_quotes: usize,
+	#[serde(rename = "straightSingleQuotes")]
+	straight_single_quotes: usize,
+	#[serde(rename = "curlyDoubleQuotes")]
+	curly_double_quotes: usize,
+	#[serde(rename = "curlySingleQuotes")]
+	curly_single_quotes: usize,
+	hyphens: usize,
+	#[serde(rename = "enDashes")]
+	en_dashes: usize,
+	#[serde(rename = "emDashes")]
+	em_dashes: usize,
+	// Runs of two or more spaces following a period (and any closing quotes)
+	#[serde(rename = "doubleSpaces")]
+	double_spaces: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChapterTypography {
+	id: u32,
+	#[serde(flatten)]
+	counts: TypographyCounts,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TypographyReport {
+	chapters: Vec<ChapterTypography>,
+	total: TypographyCounts,
+}
+
+fn typography_counts(text: &str) -> TypographyCounts {
+	let mut counts = TypographyCounts::default();
+	let chars: Vec<char> = text.chars().collect();
+	for (i, &c) in chars.iter().enumerate() {
+		match c {
+			'"' => counts.straight_double_quotes += 1,
+			'\'' => counts.straight_single_quotes += 1,
+			'\u{201c}' | '\u{201d}' => counts.curly_double_quotes += 1,
+			'\u{2018}' | '\u{2019}' => counts.curly_single_quotes += 1,
+			'-' => counts.hyphens += 1,
+			'\u{2013}' => counts.en_dashes += 1,
+			'\u{2014}' => counts.em_dashes += 1,
+			'.' => {
+				// Allow closing quotes between the period and the spaces: `fine."  Then`
+				let after = chars[i + 1..].iter().skip_while(|c| matches!(c, '"' | '\'' | '\u{201d}' | '\u{2019}'));
+				if after.take(2).filter(|&&c| c == ' ').count() == 2 {
+					counts.double_spaces += 1;
+				}
+			}
+			_ => {}
+		}
+	}
+	counts
+}
+
+// Smart-quote, dash and double-space counts per chapter and in total for the given chapters
+// (all chapters if empty); codeBlock text is skipped
+#[tauri::command]
+fn typography_report(project_path: String, chapter_ids: Vec<u32>) -> Result<TypographyReport, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+
+	let chapters: Vec<ChapterTypography> = chapter_order_of(&project)
+		.into_iter()
+		.filter(|id| chapter_ids.is_empty() || chapter_ids.contains(id))
+		.map(|id| ChapterTypography {
+			id,
+			counts: read_chapter_content(&path, id)
+				.map(|doc| typography_counts(&tiptap_to_prose_text(&doc)))
+				.unwrap_or_default(),
+		})
+		.collect();
+
+	let mut total = TypographyCounts::default();
+	for ch in &chapters {
+		total.straight_double_quotes += ch.counts.straight_double_quotes;
+		total.straight_single_quotes += ch.counts.straight_single_quotes;
+		total.curly_double_quotes += ch.counts.curly_double_quotes;
+		total.curly_single_quotes += ch.counts.curly_single_quotes;
+		total.hyphens += ch.counts.hyphens;
+		total.en_dashes += ch.counts.en_dashes;
+		total.em_dashes += ch.counts.em_dashes;
+		total.double_spaces += ch.counts.double_spaces;
+	}
+
+	Ok(TypographyReport { chapters, total })
+}
+
+// A quote opens when it starts a block or follows whitespace, an opening bracket or a dash
+fn quote_opens(prev: Option<char>) -> bool {
+	prev.is_none_or(|p| p.is_whitespace() || matches!(p, '(' | '[' | '{' | '\u{2013}' | '\u{2014}' | '\u{201c}' | '\u{2018}'))
+}
+
+// Curl straight quotes and collapse runs of spaces in the text nodes under `node`, leaving
+// code alone unless `include_code`. `prev` is the last character seen in the current block.
+fn normalize_typography_node(node: &mut serde_json::Value, prev: &mut Option<char>, include_code: bool) {
+	if !include_code && is_in_code_context(node) {
+		// Inline code still counts as the character before the next quote; a code block ends the run
+		*prev = match node.get("text").and_then(|t| t.as_str()) {
+			Some(text) => text.chars().last().or(*prev),
+			None => None,
+		};
+		return;
+	}
+	match node.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+		"text" => {
+			let Some(text) = node.get("text").and_then(|t| t.as_str()) else {
+				return;
+			};
+			let mut out = String::with_capacity(text.len());
+			for c in text.chars() {
+				let mapped = match c {
+					' ' if *prev == Some(' ') => continue,
+					'"' if quote_opens(*prev) => '\u{201c}',
+					'"' => '\u{201d}',
+					'\'' if quote_opens(*prev) => '\u{2018}',
+					'\'' => '\u{2019}',
+					c => c,
+				};
+				out.push(mapped);
+				*prev = Some(mapped);
+			}
+			if out != text {
+				node["text"] = serde_json::json!(out);
+			}
+		}
+		"hardBreak" => *prev = None,
+		_ => {
+			if let Some(children) = node.get_mut("content").and_then(|c| c.as_array_mut()) {
+				for child in children.iter_mut() {
+					normalize_typography_node(child, prev, include_code);
+				}
+				// A text node that was only a collapsed space is left empty, which TipTap rejects
+				children.retain(|c| c.get("text").and_then(|t| t.as_str()) != Some(""));
+			}
+			*prev = None;
+		}
+	}
+}
+
+// Rewrite straight quotes to curly ones and collapse double spaces in the given chapters
+// (all chapters if empty), skipping code unless `include_code`. Returns how many chapters changed.
+#[tauri::command]
+fn normalize_typography(project_path: String, chapter_ids: Vec<u32>, include_code: Option<bool>) -> Result<usize, String> {
+	let include_code = include_code.unwrap_or(false);
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+
+	let mut changed = 0;
+	for id in chapter_order_of(&project) {
+		if !chapter_ids.is_empty() && !chapter_ids.contains(&id) {
+			continue;
+		}
+		let Some(mut doc) = read_chapter_content(&path, id) else {
+			continue;
+		};
+		let original = doc.clone();
+		normalize_typography_node(&mut doc, &mut None, include_code);
+		if doc != original {
+			let json_content = serde_json::to_string(&doc)
+				.map_err(|e| format!("Failed to serialize chapter {}: {}", id, e))?;
+			save_chapter(project_path.clone(), id, json_content)?;
+			changed += 1;
+		}
+	}
+	Ok(changed)
+}
+
+// Append `text` to a plain inline run, merging it into a trailing text node
+fn push_plain_text(out: &mut Vec<serde_json::Value>, text: &str) {
+	if text.is_empty() {
+		return;
+	}
+	match out.last_mut().filter(|last| last["type"] == "text") {
+		Some(last) => {
+			let merged = format!("{}{}", last["text"].as_str().unwrap_or(""), text);
+			last["text"] = serde_json::json!(merged);
+		}
+		None => out.push(serde_json::json!({ "type": "text", "text": text })),
+	}
+}
+
+// Text and hard breaks of an inline run with every mark dropped and adjacent text merged.
+// Footnote references become "[label]" text to match the notes flatten_block appends; other
+// inline nodes are left out
+fn plain_inline(items: &[serde_json::Value]) -> Vec<serde_json::Value> {
+	let mut out: Vec<serde_json::Value> = Vec::new();
+	for item in items {
+		match item.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+			"text" => push_plain_text(&mut out, item.get("text").and_then(|t| t.as_str()).unwrap_or("")),
+			"hardBreak" => out.push(serde_json::json!({ "type": "hardBreak" })),
+			"footnoteReference" => push_plain_text(&mut out, &format!("[{}]", footnote_label(item))),
+			_ => {}
+		}
+	}
+	out
+}
+
+fn plain_paragraph(content: Vec<serde_json::Value>) -> serde_json::Value {
+	if content.is_empty() {
+		serde_json::json!({ "type": "paragraph" })
+	} else {
+		serde_json::json!({ "type": "paragraph", "content": content })
+	}
+}
+
+// Append `node` to `out` as attribute-free, mark-free paragraphs. Paragraphs and headings
+// keep their text, code blocks give one paragraph per line, and containers (lists, list
+// items, blockquotes) are unwrapped into their blocks. Footnote bodies go to `notes` instead,
+// led by "[label]", for the caller to put after the text. Images are kept as they are; other
+// blocks without text, such as scene breaks, are dropped.
+fn flatten_block(node: &serde_json::Value, out: &mut Vec<serde_json::Value>, notes: &mut Vec<serde_json::Value>) {
+	let children = node.get("content").and_then(|c| c.as_array()).map(|c| c.as_slice()).unwrap_or(&[]);
+	match node.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+		"paragraph" => out.push(plain_paragraph(plain_inline(children))),
+		"heading" => {
+			let content = plain_inline(children);
+			if !content.is_empty() {
+				out.push(plain_paragraph(content));
+			}
+		}
+		"codeBlock" => {
+			let mut text = String::new();
+			for child in children {
+				text.push_str(child.get("text").and_then(|t| t.as_str()).unwrap_or(""));
+			}
+			for line in text.lines().filter(|l| !l.trim().is_empty()) {
+				out.push(plain_paragraph(vec![serde_json::json!({ "type": "text", "text": line })]));
+			}
+		}
+		"imageBleed" => out.push(node.clone()),
+		"footnoteDefinition" => {
+			let mut body: Vec<serde_json::Value> = Vec::new();
+			for child in children {
+				flatten_block(child, &mut body, notes);
+			}
+			let marker = format!("[{}]", footnote_label(node));
+			match body.first_mut().filter(|b| b["type"] == "paragraph") {
+				Some(first) => {
+					let mut content = vec![serde_json::json!({ "type": "text", "text": format!("{} ", marker) })];
+					for item in first.get("content").and_then(|c| c.as_array()).cloned().unwrap_or_default() {
+						match item.get("text").and_then(|t| t.as_str()) {
+							Some(text) => push_plain_text(&mut content, text),
+							None => content.push(item),
+						}
+					}
+					*first = plain_paragraph(content);
+				}
+				None => body.insert(0, plain_paragraph(vec![serde_json::json!({ "type": "text", "text": marker })])),
+			}
+			notes.extend(body);
+		}
+		_ => {
+			for child in children {
+				flatten_block(child, out, notes);
+			}
+		}
+	}
+}
+
+// Strip a chapter down to plain paragraphs: no marks, headings, lists or other block types,
+// for cleaning up pasted text. Images stay and footnotes move to the end of the chapter.
+// Returns whether anything changed.
+#[tauri::command]
+fn flatten_chapter(project_path: String, chapter_id: u32) -> Result<bool, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+	if !chapter_order_of(&project).contains(&chapter_id) {
+		return Err(format!("Chapter {} not found", chapter_id));
+	}
+	let Some(mut doc) = read_chapter_content(&path, chapter_id) else {
+		return Err(format!("Failed to read chapter {}", chapter_id));
+	};
+
+	let mut paragraphs: Vec<serde_json::Value> = Vec::new();
+	let mut notes: Vec<serde_json::Value> = Vec::new();
+	if let Some(blocks) = doc.get("content").and_then(|c| c.as_array()) {
+		for block in blocks {
+			flatten_block(block, &mut paragraphs, &mut notes);
+		}
+	}
+	paragraphs.extend(notes);
+	if paragraphs.is_empty() {
+		paragraphs.push(plain_paragraph(Vec::new()));
+	}
+	let flattened = serde_json::json!(paragraphs);
+	if doc.get("content") == Some(&flattened) {
+		return Ok(false);
+	}
+	doc["content"] = flattened;
+
+	let json_content = serde_json::to_string(&doc)
+		.map_err(|e| format!("Failed to serialize chapter {}: {}", chapter_id, e))?;
+	save_chapter(project_path, chapter_id, json_content)?;
+	Ok(true)
+}
+
+// Point textStyle marks whose fontFamily matches `from` (case-insensitive; empty matches an
+// unset family) at `to` (empty unsets it). Returns how many marks changed.
+fn retarget_font_node(node: &mut serde_json::Value, from: &str, to: &str) -> usize {
+	let mut changed = 0;
+	if let Some(marks) = node.get_mut("marks").and_then(|m| m.as_array_mut()) {
+		for mark in marks.iter_mut().filter(|m| m.get("type").and_then(|t| t.as_str()) == Some("textStyle")) {
+			let current = mark.get("attrs")
+				.and_then(|a| a.get("fontFamily"))
+				.and_then(|f| f.as_str())
+				.map(|f| f.trim())
+				.unwrap_or("");
+			if !current.eq_ignore_ascii_case(from) || current == to {
+				continue;
+			}
+			if !mark.get("attrs").is_some_and(|a| a.is_object()) {
+				mark["attrs"] = serde_json::json!({});
+			}
+			mark["attrs"]["fontFamily"] = if to.is_empty() { serde_json::Value::Null } else { serde_json::json!(to) };
+			changed += 1;
+		}
+	}
+	if let Some(children) = node.get_mut("content").and_then(|c| c.as_array_mut()) {
+		for child in children {
+			changed += retarget_font_node(child, from, to);
+		}
+	}
+	changed
+}
+
+// Write via a temporary file and rename, so a crash never leaves a half-written file
+fn write_file_atomic(path: &Path, contents: &str) -> Result<(), String> {
+	let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+	tmp_name.push(".tmp");
+	let tmp = path.with_file_name(tmp_name);
+	fs::write(&tmp, contents)
+		.map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+	fs::rename(&tmp, path)
+		.map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+// Change every textStyle fontFamily of `from_family` to `to_family` across all chapters, e.g.
+// after switching the house font. An empty `from_family` matches marks with no family set.
+// All chapters are rewritten in memory before any is written. Returns how many marks changed.
+#[tauri::command]
+fn retarget_font(project_path: String, from_family: String, to_family: String) -> Result<usize, String> {
+	let path = PathBuf::from(&project_path);
+	let project_file = path.join("project.json");
+	let mut project = read_project_value(&project_file)?;
+	let (from, to) = (from_family.trim(), to_family.trim());
+
+	let mut updates: Vec<(u32, String)> = Vec::new();
+	let mut changed = 0;
+	for id in chapter_order_of(&project) {
+		let Some(mut doc) = read_chapter_content(&path, id) else {
+			continue;
+		};
+		let count = retarget_font_node(&mut doc, from, to);
+		if count > 0 {
+			let json_content = to_saved_json(&doc, JsonIndent::Compact)
+				.map_err(|e| format!("Failed to serialize chapter {}: {}", id, e))?;
+			updates.push((id, json_content));
+			changed += count;
+		}
+	}
+	if updates.is_empty() {
+		return Ok(0);
+	}
+
+	let now = Local::now().to_rfc3339();
+	for (id, json_content) in &updates {
+		write_file_atomic(&path.join("chapters").join(format!("{}.json", id)), json_content)?;
+		project["chapterModified"][id.to_string()] = serde_json::json!(now);
+	}
+	write_project_value(&project_file, &project)?;
+	Ok(changed)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OutlineHeading {
+	level: u64,
+	text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChapterOutline {
+	id: u32,
+	title: String,
+	headings: Vec<OutlineHeading>,
+}
+
+// Heading nodes anywhere under `node` (e.g. inside a colorBleed), in document order
+fn collect_headings(node: &serde_json::Value, out: &mut Vec<OutlineHeading>) {
+	if node.get("type").and_then(|t| t.as_str()) == Some("heading") {
+		let level = node.get("attrs").and_then(|a| a.get("level")).and_then(|l| l.as_u64()).unwrap_or(2);
+		out.push(OutlineHeading { level, text: tiptap_to_plain_text(node).trim().to_string() });
+		return;
+	}
+	if let Some(children) = node.get("content").and_then(|c| c.as_array()) {
+		for child in children {
+			collect_headings(child, out);
+		}
+	}
+}
+
+// Chapter titles (in chapterOrder) with each chapter's headings, for the outline tree
+#[tauri::command]
+fn outline(project_path: String) -> Result<Vec<ChapterOutline>, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+
+	Ok(chapter_order_of(&project)
+		.into_iter()
+		.map(|id| {
+			let title = project.get("chapterTitles")
+				.and_then(|t| t.get(id.to_string()))
+				.and_then(|v| v.as_str())
+				.map(|t| t.to_string())
+				.unwrap_or_else(|| format!("Chapter {}", id));
+			let mut headings = Vec::new();
+			if let Some(doc) = read_chapter_content(&path, id) {
+				collect_headings(&doc, &mut headings);
+			}
+			ChapterOutline { id, title, headings }
+		})
+		.collect())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LinkEntry {
+	href: String,
+	// Link text and chapter of the first occurrence
+	text: String,
+	#[serde(rename = "chapterId")]
+	chapter_id: u32,
+	count: usize,
+	// Every chapter the link appears in, in chapterOrder
+	#[serde(rename = "chapterIds")]
+	chapter_ids: Vec<u32>,
+}
+
+fn link_href(node: &serde_json::Value) -> Option<&str> {
+	node.get("marks")?
+		.as_array()?
+		.iter()
+		.find(|m| m.get("type").and_then(|t| t.as_str()) == Some("link"))?
+		.get("attrs")?
+		.get("href")?
+		.as_str()
+}
+
+// (href, text) for each link under `node`. Adjacent text nodes with the same href (e.g. a
+// partly bold link) count as one link.
+fn collect_links(node: &serde_json::Value, out: &mut Vec<(String, String)>) {
+	let Some(children) = node.get("content").and_then(|c| c.as_array()) else {
+		return;
+	};
+	let mut current: Option<(String, String)> = None;
+	for child in children {
+		let text = child.get("text").and_then(|t| t.as_str());
+		match (text, link_href(child)) {
+			(Some(text), Some(href)) => match current.as_mut() {
+				Some((h, t)) if h == href => t.push_str(text),
+				_ => {
+					out.extend(current.take());
+					current = Some((href.to_string(), text.to_string()));
+				}
+			},
+			_ => {
+				out.extend(current.take());
+				collect_links(child, out);
+			}
+		}
+	}
+	out.extend(current);
+}
+
+// Every external link in the manuscript, one entry per href in order of first appearance
+#[tauri::command]
+fn extract_links(project_path: String) -> Result<Vec<LinkEntry>, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+
+	let mut entries: Vec<LinkEntry> = Vec::new();
+	for id in chapter_order_of(&project) {
+		let Some(doc) = read_chapter_content(&path, id) else {
+			continue;
+		};
+		let mut links = Vec::new();
+		collect_links(&doc, &mut links);
+		for (href, text) in links {
+			match entries.iter_mut().find(|e| e.href == href) {
+				Some(entry) => {
+					entry.count += 1;
+					if !entry.chapter_ids.contains(&id) {
+						entry.chapter_ids.push(id);
+					}
+				}
+				None => entries.push(LinkEntry {
+					href,
+					text: text.trim().to_string(),
+					chapter_id: id,
+					count: 1,
+					chapter_ids: vec![id],
+				}),
+			}
+		}
+	}
+	Ok(entries)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FontUsage {
+	family: String,
+	// Text runs set in the family, plus one each if it's the project or app default font
+	count: usize,
+	// Chapters with text set in the family, in chapterOrder
+	#[serde(rename = "chapterIds")]
+	chapter_ids: Vec<u32>,
+}
+
+// fontFamily of every textStyle-marked text node under `node`, once per text node
+fn collect_font_families(node: &serde_json::Value, out: &mut Vec<String>) {
+	let family = node.get("marks")
+		.and_then(|m| m.as_array())
+		.and_then(|marks| marks.iter().find(|m| m.get("type").and_then(|t| t.as_str()) == Some("textStyle")))
+		.and_then(|m| m.get("attrs"))
+		.and_then(|a| a.get("fontFamily"))
+		.and_then(|f| f.as_str())
+		.map(|f| f.trim())
+		.filter(|f| !f.is_empty());
+	if let Some(family) = family {
+		out.push(family.to_string());
+	}
+	if let Some(children) = node.get("content").and_then(|c| c.as_array()) {
+		for child in children {
+			collect_font_families(child, out);
+		}
+	}
+}
+
+// Every font family the project uses: textStyle marks in the chapters plus the project's and
+// the app's default fontFamily. Most used first.
+#[tauri::command]
+fn used_fonts(handle: AppHandle, project_path: String) -> Result<Vec<FontUsage>, String> {
+	used_fonts_in(&AppDirs::of(&handle)?, project_path)
+}
+
+fn used_fonts_in(dirs: &AppDirs, project_path: String) -> Result<Vec<FontUsage>, String> {
+	let path = PathBuf::from(&project_path);
+	let project = read_project_value(&path.join("project.json"))?;
+
+	let mut usages: Vec<FontUsage> = Vec::new();
+	let mut count = |family: &str, chapter_id: Option<u32>| {
+		let usage = match usages.iter().position(|u| u.family == family) {
+			Some(i) => &mut usages[i],
+			None => {
+				usages.push(FontUsage { family: family.to_string(), count: 0, chapter_ids: Vec::new() });
+				usages.last_mut().unwrap()
+			}
+		};
+		usage.count += 1;
+		if let Some(id) = chapter_id.filter(|id| !usage.chapter_ids.contains(id)) {
+			usage.chapter_ids.push(id);
+		}
+	};
+
+	for id in chapter_order_of(&project) {
+		let Some(doc) = read_chapter_content(&path, id) else {
+			continue;
+		};
+		let mut families = Vec::new();
+		collect_font_families(&doc, &mut families);
+		for family in families {
+			count(&family, Some(id));
+		}
+	}
+	let config = read_config_value(dirs)?;
+	for default in [project.get("fontFamily"), config.get("fontFamily")] {
+		if let Some(family) = default.and_then(|f| f.as_str()).map(|f| f.trim()).filter(|f| !f.is_empty()) {
+			count(family, None);
+		}
+	}
+
+	usages.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.family.cmp(&b.family)));
+	Ok(usages)
+}
+
+// ============================================================
+// Project management
+// ============================================================
+
+// Folders inside a project that hold history rather than content; not carried into copies
+const PROJECT_SKIP_DIRS: &[&str] = &[".backups", ".drafts"];
+
+// Recursively copy src into dest, skipping top-level entries named in `skip`
+fn copy_dir_recursive(src: &Path, dest: &Path, skip: &[&str]) -> Result<(), String> {
+	fs::create_dir_all(dest)
+		.map_err(|e| format!("Failed to create directory {}: {}", dest.display(), e))?;
+
+	let entries = fs::read_dir(src)
+		.map_err(|e| format!("Failed to read directory {}: {}", src.display(), e))?;
+	for entry in entries {
+		let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+		let name = entry.file_name();
+		if skip.iter().any(|s| name.to_str() == Some(*s)) {
+			continue;
+		}
+		let from = entry.path();
+		let to = dest.join(&name);
+		if from.is_dir() {
+			copy_dir_recursive(&from, &to, &[])?;
+		} else {
+			fs::copy(&from, &to)
+				.map_err(|e| format!("Failed to copy {}: {}", from.display(), e))?;
+		}
+	}
+	Ok(())
+}
+
+fn is_empty_dir(path: &Path) -> bool {
+	fs::read_dir(path).map(|mut entries| entries.next().is_none()).unwrap_or(false)
+}
+
+// Absolute form of a path that may not exist yet, with symlinks and ".." resolved: each
+// existing prefix is canonicalized and the rest is applied component by component
+fn resolve_path(path: &Path) -> PathBuf {
+	let mut resolved = std::env::current_dir().unwrap_or_default();
+	for component in path.components() {
+		match component {
+			std::path::Component::CurDir => {}
+			std::path::Component::ParentDir => {
+				resolved.pop();
+			}
+			std::path::Component::Normal(name) => {
+				resolved.push(name);
+				if let Ok(canonical) = resolved.canonicalize() {
+					resolved = canonical;
+				}
+			}
+			// Prefix and root replace the current directory for absolute paths
+			other => resolved.push(other),
+		}
+	}
+	resolved
+}
+
+// Copy a whole project (chapters, assets, dictionary, metadata) to a new folder under a new title
+#[tauri::command]
+fn clone_project(source_path: String, dest_path: String, new_title: String) -> Result<CreateProjectResponse, String> {
+	let source = PathBuf::from(&source_path);
+	let dest = PathBuf::from(&dest_path);
+
+	let new_title = new_title.trim();
+	if new_title.is_empty() {
+		return Err("Project title cannot be empty".to_string());
+	}
+	if !source.join("project.json").exists() {
+		return Err("project.json not found in the source directory".to_string());
+	}
+	if dest.exists() && !is_empty_dir(&dest) {
+		return Err("Destination folder already exists and is not empty".to_string());
+	}
+	if resolve_path(&dest).starts_with(resolve_path(&source)) {
+		return Err("Destination folder cannot be inside the source project".to_string());
+	}
+
+	copy_dir_recursive(&source, &dest, PROJECT_SKIP_DIRS)?;
+
+	let project_file = dest.join("project.json");
+	let mut project_value = read_project_value(&project_file)?;
+	project_value["title"] = serde_json::json!(new_title);
+	write_project_value(&project_file, &project_value)?;
+
+	let project = serde_json::from_value::<Project>(project_value)
+		.map_err(|e| format!("Failed to parse project: {}", e))?;
+
+	Ok(CreateProjectResponse {
+		project,
+		path: dest_path,
+	})
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProjectSummary {
+	path: String,
+	title: String,
+	author: String,
+	#[serde(rename = "chapterCount")]
+	chapter_count: usize,
+	// Last modification time of project.json (RFC 3339)
+	modified: Option<String>,
+}
+
+// Read just the metadata a library listing needs; chapters are not loaded
+fn summarize_project(dir: &Path) -> Option<ProjectSummary> {
+	let project_file = dir.join("project.json");
+	let project = read_project_value(&project_file).ok()?;
+	let modified = fs::metadata(&project_file)
+		.and_then(|m| m.modified())
+		.ok()
+		.map(|t| chrono::DateTime::<Local>::from(t).to_rfc3339());
+
+	Some(ProjectSummary {
+		path: dir.to_string_lossy().to_string(),
+		title: project.get("title").and_then(|v| v.as_str()).unwrap_or("Untitled").to_string(),
+		author: project.get("author").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+		chapter_count: chapter_order_of(&project).len(),
+		modified,
+	})
+}
+
+fn scan_projects_in(dir: &Path, depth: u32, max_depth: u32, found: &mut Vec<ProjectSummary>) {
+	if dir.join("project.json").is_file() {
+		// A project's own folders (chapters, assets, backups) never contain other projects
+		if let Some(summary) = summarize_project(dir) {
+			found.push(summary);
+		}
+		return;
+	}
+	if depth >= max_depth {
+		return;
+	}
+
+	let Ok(entries) = fs::read_dir(dir) else { return };
+	for entry in entries.filter_map(|e| e.ok()) {
+		// file_type() doesn't follow symlinks, so link cycles can't recurse forever
+		let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+		let hidden = entry.file_name().to_string_lossy().starts_with('.');
+		if is_dir && !hidden {
+			scan_projects_in(&entry.path(), depth + 1, max_depth, found);
+		}
+	}
+}
+
+// Find projects (folders containing project.json) under root_dir, up to max_depth levels down.
+// Hidden folders such as .backups are skipped.
+#[tauri::command]
+fn scan_projects(root_dir: String, max_depth: u32) -> Result<Vec<ProjectSummary>, String> {
+	let root = PathBuf::from(&root_dir);
+	if !root.is_dir() {
+		return Err(format!("Not a directory: {}", root_dir));
+	}
+
+	let mut found = Vec::new();
+	scan_projects_in(&root, 0, max_depth, &mut found);
+	found.sort_by(|a, b| a.path.cmp(&b.path));
+	Ok(found)
+}
+
+// Top-level project entries packed into a .scout bundle
+const BUNDLE_ENTRIES: &[&str] = &["project.json", "chapters", "assets", "custom_dictionary.json"];
+
+// Add a file or directory (recursively) to the zip under the given archive name
+fn zip_add_path<W: Write + std::io::Seek>(
+	zip: &mut zip::ZipWriter<W>,
+	path: &Path,
+	name: &str,
+	options: zip::write::SimpleFileOptions,
+) -> Result<(), String> {
+	if path.is_dir() {
+		zip.add_directory(format!("{}/", name), options).map_err(|e| e.to_string())?;
+		let entries = fs::read_dir(path)
+			.map_err(|e| format!("Failed to read directory {}: {}", path.display(), e))?;
+		for entry in entries {
+			let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+			let child_name = format!("{}/{}", name, entry.file_name().to_string_lossy());
+			zip_add_path(zip, &entry.path(), &child_name, options)?;
+		}
+	} else {
+		let bytes = fs::read(path)
+			.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+		zip.start_file(name, options).map_err(|e| e.to_string())?;
+		zip.write_all(&bytes).map_err(|e| e.to_string())?;
+	}
+	Ok(())
+}
+
+// Pack a project into a single .scout (zip) file for moving between machines
+#[tauri::command]
+fn export_bundle(project_path: String, dest_zip: String) -> Result<String, String> {
+	let project_path_buf = PathBuf::from(&project_path);
+	if !project_path_buf.join("project.json").exists() {
+		return Err("project.json not found".to_string());
+	}
+
+	let file = fs::File::create(&dest_zip)
+		.map_err(|e| format!("Failed to create bundle: {}", e))?;
+	let mut zip = zip::ZipWriter::new(file);
+	let options = zip::write::SimpleFileOptions::default()
+		.compression_method(zip::CompressionMethod::Deflated);
+
+	for entry in BUNDLE_ENTRIES {
+		let path = project_path_buf.join(entry);
+		if path.exists() {
+			zip_add_path(&mut zip, &path, entry, options)?;
+		}
+	}
+
+	zip.finish().map_err(|e| format!("Failed to finalize bundle: {}", e))?;
+	Ok(dest_zip)
+}
+
+// Unpack a .scout bundle into dest_dir (which must be empty or not exist) and load it
+#[tauri::command]
+fn import_bundle(archive_path: String, dest_dir: String) -> Result<LoadProjectResponse, String> {
+	let file = fs::File::open(&archive_path)
+		.map_err(|e| format!("Failed to open bundle: {}", e))?;
+	let mut archive = zip::ZipArchive::new(file)
+		.map_err(|e| format!("Failed to read bundle: {}", e))?;
+
+	if archive.index_for_name("project.json").is_none() {
+		return Err("Bundle does not contain a project.json".to_string());
+	}
+
+	let dest = PathBuf::from(&dest_dir);
+	if dest.exists() && !is_empty_dir(&dest) {
+		return Err("Destination folder already exists and is not empty".to_string());
+	}
+
+	// Check every entry before writing anything. enclosed_name rejects absolute paths
+	// and `..` components (zip-slip).
+	let mut relative_paths = Vec::with_capacity(archive.len());
+	for i in 0..archive.len() {
+		let entry = archive.by_index(i)
+			.map_err(|e| format!("Failed to read bundle entry: {}", e))?;
+		let relative = entry.enclosed_name()
+			.ok_or_else(|| format!("Bundle contains an unsafe path: {}", entry.name()))?;
+		relative_paths.push(relative);
+	}
+
+	for (i, relative) in relative_paths.into_iter().enumerate() {
+		let mut entry = archive.by_index(i)
+			.map_err(|e| format!("Failed to read bundle entry: {}", e))?;
+		let out_path = dest.join(relative);
+
+		if entry.is_dir() {
+			fs::create_dir_all(&out_path)
+				.map_err(|e| format!("Failed to create directory: {}", e))?;
+			continue;
+		}
+		if let Some(parent) = out_path.parent() {
+			fs::create_dir_all(parent)
+				.map_err(|e| format!("Failed to create directory: {}", e))?;
+		}
+		let mut out_file = fs::File::create(&out_path)
+			.map_err(|e| format!("Failed to create {}: {}", out_path.display(), e))?;
+		std::io::copy(&mut entry, &mut out_file)
+			.map_err(|e| format!("Failed to extract {}: {}", out_path.display(), e))?;
+	}
+
+	load_project(dest_dir)
+}
+
+// ============================================================
+// Asset handling
+// ============================================================
+
+fn base64_encode(data: &[u8]) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.len();
+        let b = [
+            chunk[0],
+            if n > 1 { chunk[1] } else { 0 },
+            if n > 2 { chunk[2] } else { 0 },
+        ];
+        out.push(CHARS[((b[0] >> 2) & 0x3f) as usize] as char);
+        out.push(CHARS[(((b[0] & 0x03) << 4) | ((b[1] >> 4) & 0x0f)) as usize] as char);
+        out.push(if n >= 2 { CHARS[(((b[1] & 0x0f) << 2) | ((b[2] >> 6) & 0x03)) as usize] as char } else { '=' });
+        out.push(if n >= 3 { CHARS[(b[2] & 0x3f) as usize] as char } else { '=' });
+    }
+    out
+}
+
+fn base64_decode(data: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a' + 26) as u32),
+            b'0'..=b'9' => Some((c - b'0' + 52) as u32),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+    let clean: Vec<u8> = data.bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    let trimmed = match clean.iter().position(|&b| b == b'=') {
+        Some(pad) if clean[pad..].iter().all(|&b| b == b'=') => &clean[..pad],
+        Some(_) => return None,
+        None => &clean[..],
+    };
+    let mut out = Vec::with_capacity(trimmed.len() * 3 / 4);
+    for chunk in trimmed.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut n = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            n |= value(c)? << (18 - 6 * i);
+        }
+        out.push((n >> 16) as u8);
+        if chunk.len() > 2 { out.push((n >> 8) as u8); }
+        if chunk.len() > 3 { out.push(n as u8); }
+    }
+    Some(out)
+}
+
+/// Split a base64 `data:` URL into its MIME type and decoded bytes.
+fn parse_data_url(src: &str) -> Option<(String, Vec<u8>)> {
+    let rest = src.strip_prefix("data:")?;
+    let (meta, payload) = rest.split_once(',')?;
+    let mime = meta.strip_suffix(";base64")?;
+    Some((mime.to_lowercase(), base64_decode(payload)?))
+}
+
+fn image_ext_for_mime(mime: &str) -> &'static str {
+    match mime {
+        "image/png"     => "png",
+        "image/gif"     => "gif",
+        "image/webp"    => "webp",
+        "image/svg+xml" => "svg",
+        _               => "jpg",
+    }
+}
+
+fn image_mime_for_ext(ext: &str) -> &'static str {
+    let lower = ext.to_lowercase();
+    match lower.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png"  => "image/png",
+        "gif"  => "image/gif",
+        "webp" => "image/webp",
+        "svg"  => "image/svg+xml",
+        _      => "image/jpeg",
+    }
+}
+
+/// Copy an image file into the project's assets/ dir and return a data URL.
+#[tauri::command]
+fn copy_asset_and_encode(
+    project_path: String,
+    src_path: String,
+) -> Result<serde_json::Value, String> {
+    let project_path_buf = PathBuf::from(&project_path);
+    let assets_dir = project_path_buf.join("assets");
+    fs::create_dir_all(&assets_dir)
+        .map_err(|e| format!("Failed to create assets directory: {}", e))?;
+
+    let src = PathBuf::from(&src_path);
+    let raw_name = src.file_name()
+        .ok_or_else(|| "Invalid source path".to_string())?
+        .to_string_lossy()
+        .to_string();
+
+    let bytes = fs::read(&src)
+        .map_err(|e| format!("Failed to read image: {}", e))?;
+
+    // Same image dropped again (possibly under another name): reuse the stored file
+    let hash = asset_content_hash(&bytes);
+    let project_file = project_path_buf.join("project.json");
+    let mut project_value = read_project_value(&project_file).ok();
+    if let Some(existing) = find_identical_asset(&assets_dir, project_value.as_ref(), &hash, &bytes) {
+        if let Some(project) = project_value.as_mut() {
+            record_asset_hash(project, &hash, &existing);
+            write_project_value(&project_file, project)?;
+        }
+        let ext = Path::new(&existing).extension().and_then(|e| e.to_str()).unwrap_or("");
+        let data_url = format!("data:{};base64,{}", image_mime_for_ext(ext), base64_encode(&bytes));
+        return Ok(serde_json::json!({
+            "name": existing,
+            "dataUrl": data_url,
+            "reused": true,
+        }));
+    }
+
+    // Sanitize filename
+    let safe_name: String = raw_name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+
+    // Find a non-conflicting destination path
+    let dest_path = {
+        let candidate = assets_dir.join(&safe_name);
+        if !candidate.exists() {
+            candidate
+        } else {
+            let ext = PathBuf::from(&safe_name)
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
             let stem_len = safe_name.len().saturating_sub(ext.len());
             let stem = &safe_name[..stem_len];
             let mut n = 1u32;
-            loop {
-                let c = assets_dir.join(format!("{}_{}{}", stem, n, ext));
-                if !c.exists() { break c; }
+            loop {
+                let c = assets_dir.join(format!("{}_{}{}", stem, n, ext));
+                if !c.exists() { break c; }
+                n += 1;
+            }
+        }
+    };
+
+    fs::write(&dest_path, &bytes)
+        .map_err(|e| format!("Failed to copy image: {}", e))?;
+
+    let final_name = dest_path.file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+
+    if let Some(project) = project_value.as_mut() {
+        record_asset_hash(project, &hash, &final_name);
+        write_project_value(&project_file, project)?;
+    }
+
+    let ext = dest_path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+    let mime = image_mime_for_ext(ext);
+    let data_url = format!("data:{};base64,{}", mime, base64_encode(&bytes));
+
+    Ok(serde_json::json!({
+        "name": final_name,
+        "dataUrl": data_url,
+        "reused": false,
+    }))
+}
+
+/// 64-bit FNV-1a hash of an asset's bytes, as hex.
+fn asset_content_hash(bytes: &[u8]) -> String {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for &b in bytes {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", h)
+}
+
+/// Name of a file in assets/ with exactly these bytes. Checks the project's `assetHashes` map
+/// first, then any same-sized file (for assets added before the map existed). Bytes are
+/// always compared, so a stale map entry or hash collision can't return the wrong image.
+fn find_identical_asset(
+    assets_dir: &Path,
+    project: Option<&serde_json::Value>,
+    hash: &str,
+    bytes: &[u8],
+) -> Option<String> {
+    let same_bytes = |name: &str| fs::read(assets_dir.join(name)).is_ok_and(|b| b == bytes);
+
+    if let Some(name) = project
+        .and_then(|p| p.get("assetHashes"))
+        .and_then(|m| m.get(hash))
+        .and_then(|v| v.as_str())
+    {
+        if same_bytes(name) {
+            return Some(name.to_string());
+        }
+    }
+
+    fs::read_dir(assets_dir).ok()?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.metadata().is_ok_and(|m| m.is_file() && m.len() == bytes.len() as u64))
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .find(|name| same_bytes(name))
+}
+
+fn record_asset_hash(project: &mut serde_json::Value, hash: &str, name: &str) {
+    if !project.get("assetHashes").is_some_and(|m| m.is_object()) {
+        project["assetHashes"] = serde_json::json!({});
+    }
+    project["assetHashes"][hash] = serde_json::json!(name);
+}
+
+// ============================================================
+// EPUB export
+// ============================================================
+
+/// Drop characters XML 1.0 doesn't allow (control chars other than tab/newline/CR, U+FFFE, U+FFFF),
+/// which would otherwise make the EPUB fail validation.
+fn sanitize_xml_text(s: &str) -> String {
+    s.chars()
+        .filter(|&c| matches!(c, '\t' | '\n' | '\r') || (c >= '\u{20}' && c != '\u{FFFE}' && c != '\u{FFFF}'))
+        .collect()
+}
+
+/// Escape text for XML content or attribute values (either quote style).
+fn escape_xml(s: &str) -> String {
+    sanitize_xml_text(s)
+     .replace('&', "&amp;")
+     .replace('<', "&lt;")
+     .replace('>', "&gt;")
+     .replace('"', "&quot;")
+     .replace('\'', "&apos;")
+}
+
+/// Pseudo-UUID v4 from FNV hash of seed + current timestamp.
+fn generate_epub_uuid(seed: &str) -> String {
+    let ts = Local::now().timestamp_millis() as u64;
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in seed.bytes() {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h ^= ts;
+    h = h.wrapping_mul(0x100000001b3);
+    let a = (h >> 32) as u32;
+    let b = ((h >> 16) & 0xffff) as u16;
+    let c = 0x4000u16 | ((h >> 4) & 0x0fff) as u16;
+    let d = 0x8000u16 | ((h >> 2) & 0x3fff) as u16;
+    let e = h.wrapping_mul(0x9e3779b97f4a7c15) & 0xffffffffffff;
+    format!("{:08x}-{:04x}-{:04x}-{:04x}-{:012x}", a, b, c, d, e)
+}
+
+// Canonical nesting order for inline marks, outermost first. TipTap doesn't guarantee mark
+// order, so sorting by this keeps the markup identical for the same formatting and keeps
+// span-level wrappers (drop cap, link, text style) outside the phrase-level ones.
+fn mark_nesting_rank(mark: &serde_json::Value) -> u8 {
+    match mark.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+        "dropcap"   => 0,
+        "link"      => 1,
+        "textStyle" => 2,
+        "bold"      => 3,
+        "italic"    => 4,
+        "strike"    => 5,
+        "code"      => 6,
+        _           => 7,
+    }
+}
+
+/// Footnote label from a footnoteReference/footnoteDefinition node.
+fn footnote_label(node: &serde_json::Value) -> &str {
+    node.get("attrs").and_then(|a| a.get("label")).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+/// Element id for a footnote's aside; labels are free text, ids must be XML names.
+/// ASCII letters, digits and '-' are kept and every other character becomes `_<hex>_`, so
+/// distinct labels ("一"/"二", "a b"/"ab") never share an id.
+fn footnote_id(label: &str) -> String {
+    let mut id = String::from("fn-");
+    for c in label.chars() {
+        if c.is_ascii_alphanumeric() || c == '-' {
+            id.push(c);
+        } else {
+            id.push_str(&format!("_{:x}_", c as u32));
+        }
+    }
+    id
+}
+
+/// Render TipTap inline content (text nodes + hardBreak) to XHTML.
+fn render_inline(items: &[serde_json::Value]) -> String {
+    let mut out = String::new();
+    for item in items {
+        match item.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+            "hardBreak" => out.push_str("<br/>"),
+            "footnoteReference" => {
+                let label = footnote_label(item);
+                out.push_str(&format!(
+                    "<sup><a epub:type=\"noteref\" href=\"#{}\">{}</a></sup>",
+                    footnote_id(label), escape_xml(label)
+                ));
+            }
+            "text" => {
+                let text = item.get("text").and_then(|v| v.as_str()).unwrap_or("");
+                let mut marks: Vec<&serde_json::Value> = item.get("marks")
+                    .and_then(|m| m.as_array())
+                    .map(|m| m.iter().collect())
+                    .unwrap_or_default();
+                marks.sort_by_key(|m| mark_nesting_rank(m));
+                // Open marks in canonical order
+                for mark in marks.iter() {
+                    match mark.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+                        "bold"   => out.push_str("<strong>"),
+                        "italic" => out.push_str("<em>"),
+                        "strike" => out.push_str("<s>"),
+                        "code"   => out.push_str("<code>"),
+                        "dropcap" => out.push_str("<span class=\"dropcap\">"),
+                        "link" => {
+                            let href = mark.get("attrs").and_then(|a| a.get("href"))
+                                .and_then(|v| v.as_str()).unwrap_or("");
+                            out.push_str(&format!("<a href=\"{}\">", escape_xml(href)));
+                        }
+                        "textStyle" => {
+                            let a = mark.get("attrs");
+                            let fs = a.and_then(|x| x.get("fontSize")).and_then(|v| v.as_f64());
+                            let ff = a.and_then(|x| x.get("fontFamily")).and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+                            if fs.is_some() || ff.is_some() {
+                                let mut style = String::new();
+                                if let Some(sz) = fs  { style.push_str(&format!("font-size:{}pt;", sz)); }
+                                if let Some(fm) = ff  { style.push_str(&format!("font-family:{};", escape_xml(fm))); }
+                                out.push_str(&format!("<span style=\"{}\">", style));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                out.push_str(&escape_xml(text));
+                // Close marks in reverse
+                for mark in marks.iter().rev() {
+                    match mark.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+                        "bold"   => out.push_str("</strong>"),
+                        "italic" => out.push_str("</em>"),
+                        "strike" => out.push_str("</s>"),
+                        "code"   => out.push_str("</code>"),
+                        "dropcap" => out.push_str("</span>"),
+                        "link"   => out.push_str("</a>"),
+                        "textStyle" => {
+                            let a = mark.get("attrs");
+                            let fs = a.and_then(|x| x.get("fontSize")).and_then(|v| v.as_f64());
+                            let ff = a.and_then(|x| x.get("fontFamily")).and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+                            if fs.is_some() || ff.is_some() { out.push_str("</span>"); }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// ` class="..."` from a block's `attrs.class` (space-separated names, each sanitized), or
+/// nothing when it has none, so project CSS can target single paragraphs (e.g. "noindent").
+fn block_class_attr(node: &serde_json::Value) -> String {
+    let classes: Vec<String> = node.get("attrs")
+        .and_then(|a| a.get("class"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .split_whitespace()
+        .map(sanitize_css_class)
+        .filter(|c| !c.is_empty())
+        .collect();
+    if classes.is_empty() {
+        return String::new();
+    }
+    format!(" class=\"{}\"", classes.join(" "))
+}
+
+/// Render TipTap block nodes to XHTML.
+fn render_blocks(nodes: &[serde_json::Value], layout: &EpubLayout) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        let t = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        let align = node.get("attrs")
+            .and_then(|a| a.get("textAlign"))
+            .and_then(|v| v.as_str())
+            .filter(|&a| a != "left");
+        let style = align.map(|a| format!(" style=\"text-align:{}\"", a)).unwrap_or_default();
+
+        match t {
+            "paragraph" => {
+                let inner = node.get("content").and_then(|c| c.as_array())
+                    .map(|items| render_inline(items)).unwrap_or_default();
+                let attrs = format!("{}{}", block_class_attr(node), style);
+                if inner.is_empty() {
+                    out.push_str(&format!("<p{}>&#160;</p>\n", attrs));
+                } else {
+                    out.push_str(&format!("<p{}>{}</p>\n", attrs, inner));
+                }
+            }
+            "heading" => {
+                let level = node.get("attrs").and_then(|a| a.get("level"))
+                    .and_then(|v| v.as_u64()).unwrap_or(2).clamp(2, 6);
+                let level = offset_heading_level(level, layout.heading_offset);
+                let inner = node.get("content").and_then(|c| c.as_array())
+                    .map(|items| render_inline(items)).unwrap_or_default();
+                out.push_str(&format!("<h{}{}>{}</h{}>\n", level, style, inner, level));
+            }
+            "blockquote" => {
+                out.push_str("<blockquote>\n");
+                if let Some(inner) = node.get("content").and_then(|c| c.as_array()) {
+                    out.push_str(&render_blocks(inner, layout));
+                }
+                // Attribution from attrs.cite; wrapped in a <p> since EPUB 2 blockquotes hold only blocks
+                let cite = node.get("attrs").and_then(|a| a.get("cite")).and_then(|c| c.as_str())
+                    .map(str::trim).filter(|c| !c.is_empty());
+                if let Some(cite) = cite {
+                    out.push_str(&format!("<p class=\"quote-attribution\"><cite>\u{2014} {}</cite></p>\n", escape_xml(cite)));
+                }
+                out.push_str("</blockquote>\n");
+            }
+            "bulletList" | "orderedList" => {
+                let tag = if t == "bulletList" { "ul" } else { "ol" };
+                let attrs = if t == "orderedList" { ordered_list_attrs(node) } else { String::new() };
+                out.push_str(&format!("<{}{}>\n", tag, attrs));
+                if let Some(items) = node.get("content").and_then(|c| c.as_array()) {
+                    for item in items {
+                        out.push_str("<li>");
+                        if let Some(item_content) = item.get("content").and_then(|c| c.as_array()) {
+                            for para in item_content {
+                                if let Some(inline) = para.get("content").and_then(|c| c.as_array()) {
+                                    out.push_str(&render_inline(inline));
+                                }
+                            }
+                        }
+                        out.push_str("</li>\n");
+                    }
+                }
+                out.push_str(&format!("</{}>\n", tag));
+            }
+            "horizontalRule" => match layout.scene_break.as_deref() {
+                Some(glyph) => out.push_str(&format!("<p class=\"scene-break\">{}</p>\n", escape_xml(glyph))),
+                None => out.push_str("<hr/>\n"),
+            },
+            "colorBleed" => {
+                let bg = node.get("attrs").and_then(|a| a.get("backgroundColor"))
+                    .and_then(|v| v.as_str()).unwrap_or("#000000");
+                let text = node.get("attrs").and_then(|a| a.get("textColor"))
+                    .and_then(|v| v.as_str()).unwrap_or("#ffffff");
+                out.push_str(&format!(
+                    "<div style=\"background-color:{};color:{};margin:0 -2em;padding:2em;\">\n",
+                    escape_xml(bg), escape_xml(text)
+                ));
+                if let Some(inner) = node.get("content").and_then(|c| c.as_array()) {
+                    out.push_str(&render_blocks(inner, layout));
+                }
+                out.push_str("</div>\n");
+            }
+            // Footnote bodies are gathered at the end of the chapter by chapter_to_xhtml
+            "footnoteDefinition" => {}
+            "imageBleed" => {
+                let name = node.get("attrs").and_then(|a| a.get("name"))
+                    .and_then(|v| v.as_str()).unwrap_or("");
+                let alt = node.get("attrs").and_then(|a| a.get("alt"))
+                    .and_then(|v| v.as_str()).unwrap_or("");
+                if !name.is_empty() {
+                    out.push_str(&format!(
+                        "<div class=\"image-bleed\"><img src=\"../images/{}\" alt=\"{}\"/></div>\n",
+                        escape_xml(name), escape_xml(alt)
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Give imageBleed nodes that only carry a data: URL `src` (no asset `name`) a generated name,
+/// queueing the decoded bytes in `inline_images` for the EPUB. Identical URLs share one file.
+fn embed_data_url_images(
+    node: &mut serde_json::Value,
+    taken: &mut HashSet<String>,
+    names_by_src: &mut HashMap<String, String>,
+    inline_images: &mut Vec<(String, Vec<u8>)>,
+) {
+    if node.get("type").and_then(|v| v.as_str()) == Some("imageBleed") {
+        let attrs = node.get("attrs");
+        let has_name = attrs.and_then(|a| a.get("name")).and_then(|v| v.as_str()).is_some_and(|n| !n.is_empty());
+        let src = attrs.and_then(|a| a.get("src")).and_then(|v| v.as_str()).unwrap_or("").to_string();
+        if !has_name && src.starts_with("data:") {
+            let name = match names_by_src.get(&src) {
+                Some(name) => Some(name.clone()),
+                None => parse_data_url(&src).map(|(mime, bytes)| {
+                    let ext = image_ext_for_mime(&mime);
+                    let mut n = taken.len() + 1;
+                    let mut name = format!("inline-{}.{}", n, ext);
+                    while taken.contains(&name) {
+                        n += 1;
+                        name = format!("inline-{}.{}", n, ext);
+                    }
+                    taken.insert(name.clone());
+                    names_by_src.insert(src.clone(), name.clone());
+                    inline_images.push((name.clone(), bytes));
+                    name
+                }),
+            };
+            if let Some(name) = name {
+                node["attrs"]["name"] = serde_json::json!(name);
+            }
+        }
+    }
+    if let Some(children) = node.get_mut("content").and_then(|c| c.as_array_mut()) {
+        for child in children {
+            embed_data_url_images(child, taken, names_by_src, inline_images);
+        }
+    }
+}
+
+/// Collect all imageBleed asset names from a chapter's TipTap JSON.
+fn collect_image_names(content: &Option<serde_json::Value>) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Some(doc) = content {
+        if let Some(nodes) = doc.get("content").and_then(|c| c.as_array()) {
+            for node in nodes {
+                collect_image_names_from_node(node, &mut names);
+            }
+        }
+    }
+    names
+}
+
+fn collect_image_names_from_node(node: &serde_json::Value, names: &mut Vec<String>) {
+    if node.get("type").and_then(|v| v.as_str()) == Some("imageBleed") {
+        if let Some(name) = node.get("attrs")
+            .and_then(|a| a.get("name"))
+            .and_then(|v| v.as_str())
+        {
+            let s = name.to_string();
+            if !s.is_empty() && !names.contains(&s) {
+                names.push(s);
+            }
+        }
+    }
+    if let Some(children) = node.get("content").and_then(|c| c.as_array()) {
+        for child in children {
+            collect_image_names_from_node(child, names);
+        }
+    }
+}
+
+/// imageBleed asset names referenced by `contents` with no file under the project's assets/.
+fn missing_image_assets<'a>(
+    project_path: &Path,
+    contents: impl IntoIterator<Item = &'a Option<serde_json::Value>>,
+) -> Vec<String> {
+    let assets_dir = project_path.join("assets");
+    let mut missing: Vec<String> = Vec::new();
+    for content in contents {
+        for name in collect_image_names(content) {
+            if !assets_dir.join(&name).is_file() && !missing.contains(&name) {
+                missing.push(name);
+            }
+        }
+    }
+    missing
+}
+
+/// Asset names the given chapters (all exported chapters if empty) reference but that are
+/// missing from assets/, so the UI can warn before an export drops the images.
+#[tauri::command]
+fn check_missing_assets(project_path: String, chapter_ids: Vec<u32>) -> Result<Vec<String>, String> {
+    let path = PathBuf::from(&project_path);
+    let project = read_project_value(&path.join("project.json"))?;
+    let ids: Vec<u32> = if chapter_ids.is_empty() {
+        full_book_chapter_ids(&project)
+    } else {
+        chapter_order_of(&project).into_iter().filter(|id| chapter_ids.contains(id)).collect()
+    };
+    let contents: Vec<Option<serde_json::Value>> = ids.iter()
+        .map(|&id| read_chapter_content(&path, id))
+        .collect();
+    Ok(missing_image_assets(&path, &contents))
+}
+
+// MIME type from an image's leading bytes, or None if it isn't a format we embed
+fn sniff_image_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some("image/png");
+    }
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some("image/jpeg");
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some("image/gif");
+    }
+    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    // SVG is text: look for the root element near the top, past any XML declaration or comments
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).to_lowercase();
+    if head.trim_start_matches('\u{feff}').trim_start().starts_with('<') && head.contains("<svg") {
+        return Some("image/svg+xml");
+    }
+    None
+}
+
+/// An asset whose extension claims a different image type than its contents.
+#[derive(Debug, Serialize, Deserialize)]
+struct AssetMismatch {
+    name: String,
+    // What the extension implies
+    #[serde(rename = "extensionMime")]
+    extension_mime: String,
+    // What the magic bytes say
+    #[serde(rename = "actualMime")]
+    actual_mime: String,
+    // Name with the correct extension; the file was renamed to it when repairing
+    #[serde(rename = "suggestedName")]
+    suggested_name: String,
+    repaired: bool,
+}
+
+// Point imageBleed nodes at renamed assets, fixing the MIME type of their embedded data URLs
+// along the way. Returns how many nodes changed.
+fn rename_image_refs_node(node: &mut serde_json::Value, renames: &HashMap<String, &AssetMismatch>) -> usize {
+    let mut count = 0;
+    if node.get("type").and_then(|v| v.as_str()) == Some("imageBleed") {
+        if let Some(attrs) = node.get_mut("attrs").and_then(|a| a.as_object_mut()) {
+            let name = attrs.get("name").and_then(|v| v.as_str()).unwrap_or("").to_string();
+            if let Some(m) = renames.get(&name) {
+                attrs.insert("name".to_string(), serde_json::json!(m.suggested_name));
+                let declared = format!("data:{};", m.extension_mime);
+                if let Some(src) = attrs.get("src").and_then(|v| v.as_str()).filter(|s| s.starts_with(&declared)) {
+                    let fixed = format!("data:{};{}", m.actual_mime, &src[declared.len()..]);
+                    attrs.insert("src".to_string(), serde_json::json!(fixed));
+                }
+                count += 1;
+            }
+        }
+    }
+    if let Some(children) = node.get_mut("content").and_then(|c| c.as_array_mut()) {
+        for child in children {
+            count += rename_image_refs_node(child, renames);
+        }
+    }
+    count
+}
+
+/// Compare every file in assets/ against its extension and report the ones whose contents are
+/// a different image type (e.g. a PNG saved as .jpg), which some EPUB readers refuse to show.
+/// With `repair`, each is renamed to the right extension and chapter references (including
+/// trashed chapters) and the cover image setting follow the new name.
+#[tauri::command]
+fn verify_assets(project_path: String, repair: Option<bool>) -> Result<Vec<AssetMismatch>, String> {
+    let path = PathBuf::from(&project_path);
+    let assets_dir = path.join("assets");
+    let Ok(entries) = fs::read_dir(&assets_dir) else {
+        return Ok(Vec::new());
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+
+    let mut mismatches: Vec<AssetMismatch> = Vec::new();
+    let mut taken: Vec<String> = names.clone();
+    for name in &names {
+        let Ok(bytes) = fs::read(assets_dir.join(name)) else {
+            continue;
+        };
+        let Some(actual) = sniff_image_mime(&bytes) else {
+            continue;
+        };
+        let file = Path::new(name);
+        let ext = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let declared = image_mime_for_ext(ext);
+        if declared == actual {
+            continue;
+        }
+        let stem = file.file_stem().and_then(|s| s.to_str()).unwrap_or(name);
+        let new_ext = image_ext_for_mime(actual);
+        let mut suggested = format!("{}.{}", stem, new_ext);
+        let mut n = 1;
+        while taken.contains(&suggested) {
+            suggested = format!("{}-{}.{}", stem, n, new_ext);
+            n += 1;
+        }
+        taken.push(suggested.clone());
+        mismatches.push(AssetMismatch {
+            name: name.clone(),
+            extension_mime: declared.to_string(),
+            actual_mime: actual.to_string(),
+            suggested_name: suggested,
+            repaired: false,
+        });
+    }
+    if !repair.unwrap_or(false) || mismatches.is_empty() {
+        return Ok(mismatches);
+    }
+
+    // Rewrite references in memory first so nothing is renamed if a chapter can't be serialized
+    let project_file = path.join("project.json");
+    let mut project = read_project_value(&project_file)?;
+    let renames: HashMap<String, &AssetMismatch> = mismatches.iter()
+        .map(|m| (m.name.clone(), m))
+        .collect();
+    let chapters_dir = path.join("chapters");
+    // (file, live chapter id, original text, rewritten text)
+    let mut updates: Vec<(PathBuf, Option<u32>, String, String)> = Vec::new();
+    for (dir, live) in [(chapters_dir.clone(), true), (trash_dir(&path), false)] {
+        for id in chapter_file_ids(&dir) {
+            let file = dir.join(format!("{}.json", id));
+            let Ok(original) = fs::read_to_string(&file) else {
+                continue;
+            };
+            let Ok(mut doc) = serde_json::from_str::<serde_json::Value>(&original) else {
+                continue;
+            };
+            if rename_image_refs_node(&mut doc, &renames) > 0 {
+                let json_content = to_saved_json(&doc, JsonIndent::Compact)
+                    .map_err(|e| format!("Failed to serialize chapter {}: {}", id, e))?;
+                updates.push((file, live.then_some(id), original, json_content));
+            }
+        }
+    }
+
+    let now = Local::now().to_rfc3339();
+    for (_, id, _, _) in &updates {
+        if let Some(id) = id {
+            project["chapterModified"][id.to_string()] = serde_json::json!(now);
+        }
+    }
+    if let Some(cover) = project.get("coverImage").and_then(|v| v.as_str()) {
+        if let Some(m) = renames.get(cover) {
+            project["coverImage"] = serde_json::json!(m.suggested_name);
+        }
+    }
+    // Deduplication hashes still point at the old names
+    if let Some(hashes) = project.get_mut("assetHashes").and_then(|h| h.as_object_mut()) {
+        for value in hashes.values_mut() {
+            if let Some(m) = value.as_str().and_then(|s| renames.get(s)) {
+                *value = serde_json::json!(m.suggested_name);
+            }
+        }
+    }
+
+    // Renames, then chapters, then project.json; if any step fails, the renames are undone and
+    // the chapters already written get their original text back, as in renumber_chapters
+    let mut renamed: Vec<&AssetMismatch> = Vec::new();
+    let mut written: Vec<(&PathBuf, &String)> = Vec::new();
+    let rollback = |renamed: &[&AssetMismatch], written: &[(&PathBuf, &String)]| {
+        for (file, original) in written.iter().rev() {
+            let _ = write_file_atomic(file, original);
+        }
+        for m in renamed.iter().rev() {
+            let _ = fs::rename(assets_dir.join(&m.suggested_name), assets_dir.join(&m.name));
+        }
+    };
+    for m in &mismatches {
+        if let Err(e) = fs::rename(assets_dir.join(&m.name), assets_dir.join(&m.suggested_name)) {
+            rollback(&renamed, &written);
+            return Err(format!("Failed to rename {}: {}", m.name, e));
+        }
+        renamed.push(m);
+    }
+    for (file, _, original, json_content) in &updates {
+        if let Err(e) = write_file_atomic(file, json_content) {
+            rollback(&renamed, &written);
+            return Err(e);
+        }
+        written.push((file, original));
+    }
+    if let Err(e) = write_project_value(&project_file, &project) {
+        rollback(&renamed, &written);
+        return Err(e);
+    }
+
+    Ok(mismatches.into_iter().map(|m| AssetMismatch { repaired: true, ..m }).collect())
+}
+
+// Generated covers use the common 2:3 ebook cover ratio
+const TEXT_COVER_WIDTH: u32 = 1600;
+const TEXT_COVER_HEIGHT: u32 = 2400;
+// Title lines wrap at about this many characters
+const TEXT_COVER_LINE_CHARS: usize = 16;
+// The title block stays between these heights, clear of the author line near the bottom
+const TEXT_COVER_TITLE_TOP: u32 = TEXT_COVER_HEIGHT / 12;
+const TEXT_COVER_TITLE_BOTTOM: u32 = TEXT_COVER_HEIGHT * 3 / 4;
+// Smallest title font; titles that still don't fit are cut short with an ellipsis
+const TEXT_COVER_MIN_FONT: i64 = 24;
+
+// "#rgb" or "#rrggbb", lowercased; anything else is refused so it can't break out of the SVG attribute
+fn parse_hex_color(color: &str) -> Option<String> {
+    let color = color.trim();
+    let hex = color.strip_prefix('#')?;
+    ((hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| color.to_lowercase())
+}
+
+// Greedy word wrap; a word longer than `width` gets a line of its own
+fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+/// SVG cover with the title (wrapped) and author centered on a solid background.
+fn build_text_cover_svg(title: &str, author: &str, background: &str, foreground: &str) -> String {
+    let mut lines = wrap_words(title, TEXT_COVER_LINE_CHARS);
+    // Long titles get a smaller font so every line fits in the title area
+    let title_area = (TEXT_COVER_TITLE_BOTTOM - TEXT_COVER_TITLE_TOP) as i64;
+    let max_lines = ((title_area - TEXT_COVER_MIN_FONT) / (TEXT_COVER_MIN_FONT * 6 / 5) + 1) as usize;
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        if let Some(last) = lines.last_mut() {
+            last.push('\u{2026}');
+        }
+    }
+    let line_count = lines.len().max(1) as i64;
+    let font_size = (if line_count > 3 { 110 } else { 140 })
+        .min(title_area * 5 / (6 * line_count))
+        .max(TEXT_COVER_MIN_FONT);
+    let line_height = font_size * 6 / 5;
+    let center_x = TEXT_COVER_WIDTH / 2;
+    // Title block sits a little above the middle, the author near the bottom
+    let block_height = (line_count - 1) * line_height;
+    let first_y = (TEXT_COVER_HEIGHT as i64 * 2 / 5 - block_height / 2)
+        .min(TEXT_COVER_TITLE_BOTTOM as i64 - block_height)
+        .max(TEXT_COVER_TITLE_TOP as i64 + font_size);
+    let tspans: String = lines.iter().enumerate().map(|(i, line)| format!(
+        "  <tspan x=\"{}\" y=\"{}\">{}</tspan>\n",
+        center_x, first_y + i as i64 * line_height, escape_xml(line)
+    )).collect();
+    let author_el = if author.trim().is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<text x=\"{}\" y=\"{}\" font-family=\"serif\" font-size=\"72\" text-anchor=\"middle\" fill=\"{}\">{}</text>\n",
+            center_x, TEXT_COVER_HEIGHT * 5 / 6, foreground, escape_xml(author.trim())
+        )
+    };
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n\
+           <rect width=\"100%\" height=\"100%\" fill=\"{bg}\"/>\n\
+           <text font-family=\"serif\" font-size=\"{size}\" font-weight=\"bold\" text-anchor=\"middle\" fill=\"{fg}\">\n\
+         {tspans}</text>\n\
+         {author_el}</svg>\n",
+        w = TEXT_COVER_WIDTH, h = TEXT_COVER_HEIGHT, bg = background, fg = foreground,
+        size = font_size, tspans = tspans, author_el = author_el
+    )
+}
+
+/// Write a plain text cover (title and author on a solid background) as SVG to `out_path`,
+/// for drafts that have no cover art yet. Colors are hex ("#1d2a44"); they default to white
+/// text on dark blue. With `set_as_cover`, a copy goes into assets/ under the same file name
+/// (numbered if another asset already has it) and becomes the project's coverImage. Returns the
+/// path written.
+#[tauri::command]
+fn generate_text_cover(
+    project_path: String,
+    out_path: String,
+    background: Option<String>,
+    text_color: Option<String>,
+    set_as_cover: Option<bool>,
+) -> Result<String, String> {
+    let path = PathBuf::from(&project_path);
+    let project_file = path.join("project.json");
+    let mut project = read_project_value(&project_file)?;
+    let color = |value: Option<String>, default: &str| match value.filter(|c| !c.trim().is_empty()) {
+        Some(c) => parse_hex_color(&c).ok_or_else(|| format!("Invalid color: {} (expected #rgb or #rrggbb)", c)),
+        None => Ok(default.to_string()),
+    };
+    let background = color(background, "#1d2a44")?;
+    let foreground = color(text_color, "#ffffff")?;
+
+    let title = project.get("title").and_then(|v| v.as_str()).unwrap_or("").trim().to_string();
+    if title.is_empty() {
+        return Err("The project has no title to put on a cover".to_string());
+    }
+    let author = project.get("author").and_then(|v| v.as_str()).unwrap_or("");
+    let svg = build_text_cover_svg(&title, author, &background, &foreground);
+
+    let out = PathBuf::from(&out_path);
+    if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    fs::write(&out, &svg)
+        .map_err(|e| format!("Failed to write cover: {}", e))?;
+
+    if set_as_cover.unwrap_or(false) {
+        let name = out.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| "Invalid output path".to_string())?;
+        let assets_dir = path.join("assets");
+        fs::create_dir_all(&assets_dir)
+            .map_err(|e| format!("Failed to create assets directory: {}", e))?;
+        // Writing straight into assets/ needs no copy; otherwise don't overwrite an existing asset
+        let mut asset = assets_dir.join(&name);
+        if fs::canonicalize(&asset).ok() != fs::canonicalize(&out).ok() {
+            let file = Path::new(&name);
+            let stem = file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let ext = file.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+            let mut n = 1u32;
+            while asset.exists() {
+                asset = assets_dir.join(format!("{}_{}{}", stem, n, ext));
                 n += 1;
             }
+            fs::write(&asset, &svg)
+                .map_err(|e| format!("Failed to write cover asset: {}", e))?;
+        }
+        let name = asset.file_name().unwrap_or_default().to_string_lossy().to_string();
+        project["coverImage"] = serde_json::json!(name);
+        write_project_value(&project_file, &project)?;
+    }
+    Ok(out.to_string_lossy().to_string())
+}
+
+/// Rough output size of an export, for showing before the user commits to it.
+#[derive(Debug, Serialize)]
+struct ExportSizeEstimate {
+    bytes: u64,
+    // e.g. "1.4 MB"
+    human: String,
+}
+
+// Markup each format wraps around the prose, as a multiple of the plain-text size
+const RTF_MARKUP_FACTOR: f64 = 1.15;
+const MARKDOWN_MARKUP_FACTOR: f64 = 1.05;
+const XHTML_MARKUP_FACTOR: f64 = 1.4;
+const DOCX_MARKUP_FACTOR: f64 = 2.5;
+// What deflate typically leaves of English prose and its markup inside EPUB/DOCX zips
+const ZIP_TEXT_RATIO: f64 = 0.35;
+// Fixed files independent of the chapters: OPF, nav, CSS and container for EPUB; styles,
+// numbering and relationships for DOCX
+const EPUB_PACKAGE_BYTES: u64 = 6 * 1024;
+const DOCX_PACKAGE_BYTES: u64 = 4 * 1024;
+
+// 1024-based size with one decimal above a kilobyte, e.g. "812 B", "14.2 KB"
+fn format_byte_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Estimate the size of exporting the given chapters (every exported chapter if empty) as
+/// `format`: the chapters' text scaled by that format's markup overhead, zip compression for
+/// EPUB and DOCX, plus the image assets EPUB embeds. RTF, DOCX and Markdown leave images out.
+#[tauri::command]
+fn estimate_export_size(project_path: String, chapter_ids: Vec<u32>, format: String) -> Result<ExportSizeEstimate, String> {
+    let path = PathBuf::from(&project_path);
+    let project = read_project_value(&path.join("project.json"))?;
+    let ids: Vec<u32> = if chapter_ids.is_empty() {
+        full_book_chapter_ids(&project)
+    } else {
+        chapter_order_of(&project).into_iter().filter(|id| chapter_ids.contains(id)).collect()
+    };
+    let contents: Vec<Option<serde_json::Value>> = ids.iter()
+        .map(|&id| read_chapter_content(&path, id))
+        .collect();
+    let text_bytes: u64 = contents.iter()
+        .flatten()
+        .map(|doc| tiptap_to_plain_text(doc).len() as u64)
+        .sum();
+
+    let scaled = |factor: f64| (text_bytes as f64 * factor).round() as u64;
+    let bytes = match format.as_str() {
+        "epub" => {
+            let mut names: Vec<String> = Vec::new();
+            for name in contents.iter().flat_map(collect_image_names) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            let asset_bytes: u64 = names.iter()
+                .filter_map(|name| fs::metadata(path.join("assets").join(name)).ok())
+                .map(|meta| meta.len())
+                .sum();
+            scaled(XHTML_MARKUP_FACTOR * ZIP_TEXT_RATIO) + asset_bytes + EPUB_PACKAGE_BYTES
+        }
+        "docx" => scaled(DOCX_MARKUP_FACTOR * ZIP_TEXT_RATIO) + DOCX_PACKAGE_BYTES,
+        "rtf" => scaled(RTF_MARKUP_FACTOR),
+        "markdown" | "md" => scaled(MARKDOWN_MARKUP_FACTOR),
+        other => return Err(format!("Unsupported export format: {}", other)),
+    };
+    Ok(ExportSizeEstimate { bytes, human: format_byte_size(bytes) })
+}
+
+/// Reduce a user-supplied class name to characters that are valid in a CSS identifier.
+fn sanitize_css_class(name: &str) -> String {
+    name.trim()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
+
+/// Chapter-level class from the TipTap doc's top-level `attrs.className` (e.g. "prologue").
+fn chapter_class_name(content: &Option<serde_json::Value>) -> Option<String> {
+    content.as_ref()
+        .and_then(|doc| doc.get("attrs"))
+        .and_then(|a| a.get("className"))
+        .and_then(|v| v.as_str())
+        .map(sanitize_css_class)
+        .filter(|s| !s.is_empty())
+}
+
+/// Per-project EPUB layout switches read from the project's `styles`.
+#[derive(Debug, Default)]
+struct EpubLayout {
+    drop_caps: bool,
+    // Glyph (e.g. "⁂" or "* * *") that replaces <hr/> for horizontalRule; None keeps the rule
+    scene_break: Option<String>,
+    // Right-to-left book (project.json `direction: "rtl"`), for Arabic, Hebrew, vertical Japanese
+    rtl: bool,
+    // BCP 47 tag from project.json `language`; see lang()
+    language: Option<String>,
+    // Added to every in-chapter heading level (clamped to 1–6), e.g. 1 renders h2 as h3
+    heading_offset: i8,
+    // Open each chapter with its title as <h1 class="chapter-title">
+    show_chapter_titles: bool,
+}
+
+impl EpubLayout {
+    fn from_styles(styles: &Option<serde_json::Value>) -> Self {
+        let flag = |key: &str| styles.as_ref()
+            .and_then(|s| s.get(key))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let scene_break = styles.as_ref()
+            .and_then(|s| s.get("sceneBreak"))
+            .and_then(|v| v.as_str())
+            .map(|g| g.trim())
+            .filter(|g| !g.is_empty() && *g != "rule")
+            .map(|g| g.to_string());
+        EpubLayout {
+            drop_caps: flag("dropCaps"),
+            scene_break,
+            show_chapter_titles: flag("showChapterTitles"),
+            ..Default::default()
+        }
+    }
+
+    /// Layout for a whole book: `styles` (possibly overridden for this export) plus the
+    /// project-level `direction`.
+    fn for_project(project: &serde_json::Value, styles: &Option<serde_json::Value>) -> Self {
+        let rtl = project.get("direction").and_then(|v| v.as_str()) == Some("rtl");
+        let language = project.get("language")
+            .and_then(|v| v.as_str())
+            .map(|l| l.trim().to_string());
+        EpubLayout { rtl, language, ..Self::from_styles(styles) }
+    }
+
+    /// The book's language tag, "en" when unset or not a plausible BCP 47 tag.
+    fn lang(&self) -> &str {
+        self.language.as_deref()
+            .filter(|l| !l.is_empty() && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or("en")
+    }
+
+    /// Attributes for each XHTML document's <html> element: language for screen readers
+    /// and hyphenation, plus the reading direction for right-to-left books.
+    fn html_attrs(&self) -> String {
+        let dir = if self.rtl { " dir=\"rtl\"" } else { "" };
+        format!(" lang=\"{lang}\" xml:lang=\"{lang}\"{dir}", lang = self.lang(), dir = dir)
+    }
+}
+
+/// `start` and numbering style for an orderedList. TipTap's `type` attr may be an HTML type
+/// ("a", "I") or a CSS name ("lower-alpha"); decimal and unknown values add nothing.
+fn ordered_list_attrs(node: &serde_json::Value) -> String {
+    let attrs = node.get("attrs");
+    let mut out = String::new();
+    if let Some(start) = attrs.and_then(|a| a.get("start")).and_then(|v| v.as_u64()).filter(|&s| s != 1) {
+        out.push_str(&format!(" start=\"{}\"", start));
+    }
+    let style = match attrs.and_then(|a| a.get("type")).and_then(|v| v.as_str()).unwrap_or("") {
+        "a" | "lower-alpha" | "lower-latin" => Some("lower-alpha"),
+        "A" | "upper-alpha" | "upper-latin" => Some("upper-alpha"),
+        "i" | "lower-roman" => Some("lower-roman"),
+        "I" | "upper-roman" => Some("upper-roman"),
+        _ => None,
+    };
+    if let Some(style) = style {
+        out.push_str(&format!(" style=\"list-style-type: {}\"", style));
+    }
+    out
+}
+
+/// Split the first letter of the chapter's opening paragraph into its own text node carrying
+/// a `dropcap` mark. Leading headings and empty paragraphs are skipped; nothing changes if the
+/// opening block isn't a paragraph or its first character isn't a letter.
+fn apply_drop_cap(nodes: &mut [serde_json::Value]) {
+    for node in nodes.iter_mut() {
+        let t = node.get("type").and_then(|v| v.as_str()).unwrap_or("").to_string();
+        let inline = node.get_mut("content").and_then(|c| c.as_array_mut());
+        match (t.as_str(), inline) {
+            ("heading", _) => continue,
+            ("paragraph", None) => continue,
+            ("paragraph", Some(items)) if items.is_empty() => continue,
+            ("paragraph", Some(items)) => {
+                let Some(first) = items.first_mut() else { return };
+                let Some(text) = first.get("text").and_then(|v| v.as_str()).map(|s| s.to_string()) else { return };
+                let Some(letter) = text.chars().next().filter(|c| c.is_alphabetic()) else { return };
+
+                let mut cap = first.clone();
+                cap["text"] = serde_json::json!(letter.to_string());
+                let mut marks = cap.get("marks").and_then(|m| m.as_array()).cloned().unwrap_or_default();
+                marks.insert(0, serde_json::json!({ "type": "dropcap" }));
+                cap["marks"] = serde_json::json!(marks);
+
+                let rest = &text[letter.len_utf8()..];
+                if rest.is_empty() {
+                    items[0] = cap;
+                } else {
+                    items[0]["text"] = serde_json::json!(rest);
+                    items.insert(0, cap);
+                }
+                return;
+            }
+            _ => return,
+        }
+    }
+}
+
+/// The visible chapter title at the top of a chapter, or nothing unless show_chapter_titles is set.
+fn chapter_title_heading(title: &str, layout: &EpubLayout) -> String {
+    if !layout.show_chapter_titles {
+        return String::new();
+    }
+    format!("<h1 class=\"chapter-title\">{}</h1>\n", escape_xml(title))
+}
+
+/// The chapter's epigraph as `<blockquote class="epigraph">`, or nothing when it has none.
+fn epigraph_html(epigraph: Option<&Epigraph>) -> String {
+    let Some(epigraph) = epigraph else {
+        return String::new();
+    };
+    let lines: Vec<String> = epigraph.text.lines().map(escape_xml).collect();
+    let mut html = format!("<blockquote class=\"epigraph\">\n<p>{}</p>\n", lines.join("<br/>"));
+    if let Some(attribution) = epigraph.attribution.as_deref() {
+        html.push_str(&format!("<p class=\"epigraph-attribution\">\u{2014} {}</p>\n", escape_xml(attribution)));
+    }
+    html.push_str("</blockquote>\n");
+    html
+}
+
+fn chapter_to_xhtml(title: &str, content: &Option<serde_json::Value>, epigraph: Option<&Epigraph>, layout: &EpubLayout) -> String {
+    let (body, has_notes) = chapter_body_xhtml(title, content, epigraph, layout);
+    // epub:type on noterefs and asides needs the ops namespace
+    let epub_ns = if has_notes { " xmlns:epub=\"http://www.idpf.org/2007/ops\"" } else { "" };
+    let body_class = chapter_class_name(content)
+        .map(|c| format!(" class=\"{}\"", c))
+        .unwrap_or_default();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\"{epub_ns}{html_attrs}>\n\
+         <head>\n<title>{title}</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"../style.css\"/>\n\
+         </head>\n<body{body_class}>\n{body}</body>\n</html>\n",
+        epub_ns = epub_ns, html_attrs = layout.html_attrs(), title = escape_xml(title), body_class = body_class, body = body
+    )
+}
+
+// Title heading, epigraph, blocks and footnotes of a chapter, and whether it has footnotes
+fn chapter_body_xhtml(title: &str, content: &Option<serde_json::Value>, epigraph: Option<&Epigraph>, layout: &EpubLayout) -> (String, bool) {
+    let mut nodes = content.as_ref()
+        .and_then(|doc| doc.get("content").and_then(|c| c.as_array()))
+        .cloned()
+        .unwrap_or_default();
+    if layout.drop_caps {
+        apply_drop_cap(&mut nodes);
+    }
+    let mut body = chapter_title_heading(title, layout);
+    body.push_str(&epigraph_html(epigraph));
+    body.push_str(&render_blocks(&nodes, layout));
+    let notes = render_footnotes(&nodes, layout);
+    body.push_str(&notes);
+    (body, !notes.is_empty())
+}
+
+/// Footnote ids and links in a chapter body, prefixed with the chapter's anchor so notes
+/// stay unique when several chapters share one document.
+fn prefix_footnote_ids(body: &str, anchor: &str) -> String {
+    body.replace("id=\"fn-", &format!("id=\"{}-fn-", anchor))
+        .replace("href=\"#fn-", &format!("href=\"#{}-fn-", anchor))
+}
+
+/// One chapter of a single-file EPUB: a `<section>` anchored at the chapter's manifest id.
+/// Images move up a directory and footnote ids are prefixed with the anchor so notes from
+/// different chapters don't collide in the shared document.
+fn single_file_chapter_section(anchor: &str, title: &str, content: &Option<serde_json::Value>, epigraph: Option<&Epigraph>, layout: &EpubLayout) -> String {
+    let (body, _) = chapter_body_xhtml(title, content, epigraph, layout);
+    let body = prefix_footnote_ids(&body.replace("src=\"../images/", "src=\"images/"), anchor);
+    let class = chapter_class_name(content)
+        .map(|c| format!(" {}", c))
+        .unwrap_or_default();
+    format!("<section class=\"chapter{}\" id=\"{}\">\n{}</section>\n", class, anchor, body)
+}
+
+// OEBPS/content.xhtml of a single-file EPUB, wrapping the chapter and part sections
+fn build_single_file_content(title: &str, sections: &str, layout: &EpubLayout) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\"{html_attrs}>\n\
+         <head>\n<title>{title}</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n\
+         </head>\n<body>\n{sections}</body>\n</html>\n",
+        html_attrs = layout.html_attrs(), title = escape_xml(title), sections = sections
+    )
+}
+
+/// The chapter's footnoteDefinition blocks as `<aside epub:type="footnote">` elements, in
+/// document order, for the end of the chapter. Empty when the chapter has no footnotes.
+fn render_footnotes(nodes: &[serde_json::Value], layout: &EpubLayout) -> String {
+    let asides: String = nodes.iter()
+        .filter(|n| n.get("type").and_then(|v| v.as_str()) == Some("footnoteDefinition"))
+        .map(|n| {
+            let inner = n.get("content").and_then(|c| c.as_array())
+                .map(|blocks| render_blocks(blocks, layout)).unwrap_or_default();
+            format!("<aside epub:type=\"footnote\" id=\"{}\">\n{}</aside>\n", footnote_id(footnote_label(n)), inner)
+        })
+        .collect();
+    if asides.is_empty() {
+        return asides;
+    }
+    format!("<section class=\"footnotes\">\n{}</section>\n", asides)
+}
+
+/// Book-level values written into content.opf.
+struct OpfMetadata<'a> {
+    title: &'a str,
+    author: &'a str,
+    uuid: &'a str,
+    modified: &'a str,
+    // Cover image file under images/, also listed in the manifest as the cover
+    cover: Option<&'a str>,
+}
+
+fn build_opf(
+    meta: &OpfMetadata,
+    toc: &[TocEntry],
+    images: &[String],
+    options: &EpubExportOptions,
+    layout: &EpubLayout,
+) -> String {
+    let OpfMetadata { title, author, uuid, modified, cover } = *meta;
+    let author_el = if !author.is_empty() {
+        format!("    <dc:creator>{}</dc:creator>\n", escape_xml(author))
+    } else { String::new() };
+    // <meta name="cover"> is the EPUB 2 convention; EPUB 3 readers also look for it
+    let cover_el = if cover.is_some() { "    <meta name=\"cover\" content=\"cover-image\"/>\n" } else { "" };
+    let metadata_el = format!("{}    <dc:language>{}</dc:language>\n{}", author_el, layout.lang(), cover_el);
+    // Part dividers and chapters, in reading order
+    let docs = flatten_toc(toc);
+    let mut manifest: String = docs.iter().map(|d| format!(
+        "    <item id=\"{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n", d.id, d.href
+    )).collect();
+    let mut image_manifest: String = images.iter().filter(|img| Some(img.as_str()) != cover).map(|img| {
+        let ext = std::path::Path::new(img.as_str())
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+        let mime = image_mime_for_ext(ext);
+        // Use a safe ID (replace non-alphanumeric with _)
+        let id: String = img.chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        format!("    <item id=\"img-{id}\" href=\"images/{img}\" media-type=\"{mime}\"/>\n",
+            id = id, img = escape_xml(img), mime = mime)
+    }).collect();
+    let mut spine: String = docs.iter().map(|d| format!(
+        "    <itemref idref=\"{}\"/>\n", d.id
+    )).collect();
+    // Every page lives in one document; the cover, contents and sample pages are sections of it
+    if options.single_file {
+        manifest = "    <item id=\"content\" href=\"content.xhtml\" media-type=\"application/xhtml+xml\"/>\n".to_string();
+        spine = "    <itemref idref=\"content\"/>\n".to_string();
+    }
+    // The visible contents page reads before the first chapter
+    if options.include_toc_page && !options.single_file {
+        manifest.insert_str(0, "    <item id=\"toc-page\" href=\"toc.xhtml\" media-type=\"application/xhtml+xml\"/>\n");
+        spine.insert_str(0, "    <itemref idref=\"toc-page\"/>\n");
+    }
+    // ...and the cover before everything
+    if let Some(cover) = cover {
+        let ext = Path::new(cover).extension().and_then(|e| e.to_str()).unwrap_or("");
+        let properties = if options.version == EpubVersion::Epub3 { " properties=\"cover-image\"" } else { "" };
+        image_manifest.push_str(&format!(
+            "    <item id=\"cover-image\" href=\"images/{}\" media-type=\"{}\"{}/>\n",
+            escape_xml(cover), image_mime_for_ext(ext), properties
+        ));
+        if !options.single_file {
+            manifest.insert_str(0, "    <item id=\"cover\" href=\"cover.xhtml\" media-type=\"application/xhtml+xml\"/>\n");
+            spine.insert_str(0, "    <itemref idref=\"cover\" linear=\"no\"/>\n");
+        }
+    }
+    if options.sample && !options.single_file {
+        manifest.push_str("    <item id=\"sample\" href=\"sample.xhtml\" media-type=\"application/xhtml+xml\"/>\n");
+        spine.push_str("    <itemref idref=\"sample\"/>\n");
+    }
+    if options.version == EpubVersion::Epub2 {
+        let mut guide = String::new();
+        let (cover_href, toc_href) = if options.single_file {
+            ("content.xhtml#cover", "content.xhtml#toc-page")
+        } else {
+            ("cover.xhtml", "toc.xhtml")
+        };
+        if cover.is_some() {
+            guide.push_str(&format!("    <reference type=\"cover\" title=\"Cover\" href=\"{}\"/>\n", cover_href));
+        }
+        if options.include_toc_page {
+            guide.push_str(&format!("    <reference type=\"toc\" title=\"Contents\" href=\"{}\"/>\n", toc_href));
+        }
+        if let Some(first) = docs.first() {
+            guide.push_str(&format!("    <reference type=\"text\" title=\"Start\" href=\"{}\"/>\n", first.href));
+        }
+        return build_opf2(title, &metadata_el, uuid, &manifest, &image_manifest, &spine, &guide);
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\">\n\
+           <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+             <dc:identifier id=\"book-id\">urn:uuid:{uuid}</dc:identifier>\n\
+             <dc:title>{title}</dc:title>\n\
+         {metadata_el}    <meta property=\"dcterms:modified\">{modified}</meta>\n\
+           </metadata>\n\
+           <manifest>\n\
+             <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
+             <item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\"/>\n\
+             <item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n\
+         {manifest}{image_manifest}  </manifest>\n\
+           <spine toc=\"ncx\"{progression}>\n\
+         {spine}  </spine>\n\
+         </package>",
+        uuid = uuid, title = escape_xml(title),
+        metadata_el = metadata_el, modified = modified,
+        manifest = manifest, image_manifest = image_manifest, spine = spine,
+        progression = if layout.rtl { " page-progression-direction=\"rtl\"" } else { "" }
+    )
+}
+
+// EPUB 2 package: no nav document or dcterms:modified; the NCX is the only table of
+// contents and a <guide> points readers at the contents page and first chapter.
+fn build_opf2(
+    title: &str,
+    // dc:creator and dc:language lines
+    metadata_el: &str,
+    uuid: &str,
+    manifest: &str,
+    image_manifest: &str,
+    spine: &str,
+    guide_refs: &str,
+) -> String {
+    let guide = if guide_refs.is_empty() {
+        String::new()
+    } else {
+        format!("  <guide>\n{}  </guide>\n", guide_refs)
+    };
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"2.0\" unique-identifier=\"book-id\">\n\
+           <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:opf=\"http://www.idpf.org/2007/opf\">\n\
+             <dc:identifier id=\"book-id\" opf:scheme=\"UUID\">urn:uuid:{uuid}</dc:identifier>\n\
+             <dc:title>{title}</dc:title>\n\
+         {metadata_el}  </metadata>\n\
+           <manifest>\n\
+             <item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\"/>\n\
+             <item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n\
+         {manifest}{image_manifest}  </manifest>\n\
+           <spine toc=\"ncx\">\n\
+         {spine}  </spine>\n\
+         {guide}</package>",
+        uuid = uuid, title = escape_xml(title),
+        metadata_el = metadata_el, manifest = manifest,
+        image_manifest = image_manifest, spine = spine, guide = guide
+    )
+}
+
+/// An entry in the book's table of contents: a chapter, or a part divider whose
+/// `children` are its chapters. Every entry is also a content document in the spine.
+struct TocEntry {
+    // Manifest id, e.g. "ch001" or "part01"
+    id: String,
+    title: String,
+    // Path relative to OEBPS/
+    href: String,
+    children: Vec<TocEntry>,
+}
+
+impl TocEntry {
+    /// The `index`-th exported chapter (0-based).
+    fn chapter(index: usize, title: &str) -> Self {
+        TocEntry {
+            id: format!("ch{:03}", index + 1),
+            title: title.to_string(),
+            href: format!("chapters/ch{:03}.xhtml", index + 1),
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Point every entry at its anchor inside a single-file EPUB's content.xhtml.
+fn anchor_toc_in_single_file(entries: &mut [TocEntry]) {
+    for entry in entries {
+        entry.href = format!("content.xhtml#{}", entry.id);
+        anchor_toc_in_single_file(&mut entry.children);
+    }
+}
+
+/// Entries in reading order: each part divider followed by its chapters.
+fn flatten_toc(entries: &[TocEntry]) -> Vec<&TocEntry> {
+    let mut out = Vec::new();
+    for entry in entries {
+        out.push(entry);
+        out.extend(flatten_toc(&entry.children));
+    }
+    out
+}
+
+/// Table of contents for the exported chapters. project.json `parts` (a list of
+/// `{ title, chapterIds }`) groups chapters under part dividers; each consecutive run of
+/// chapters from one part gets a divider, and chapters in no part stay at the top level.
+fn epub_toc(project: &serde_json::Value, chapter_ids: &[u32], chapter_titles: &[String]) -> Vec<TocEntry> {
+    let parts: Vec<(String, Vec<u32>)> = project.get("parts")
+        .and_then(|p| p.as_array())
+        .map(|parts| parts.iter().filter_map(|part| {
+            let title = part.get("title").and_then(|t| t.as_str())?.trim();
+            let ids = part.get("chapterIds").and_then(|c| c.as_array())?
+                .iter()
+                .filter_map(|v| v.as_u64().map(|n| n as u32))
+                .collect();
+            (!title.is_empty()).then(|| (title.to_string(), ids))
+        }).collect())
+        .unwrap_or_default();
+
+    let mut toc: Vec<TocEntry> = Vec::new();
+    let mut current_part: Option<usize> = None;
+    for (i, (id, title)) in chapter_ids.iter().zip(chapter_titles).enumerate() {
+        let part = parts.iter().position(|(_, ids)| ids.contains(id));
+        let chapter = TocEntry::chapter(i, title);
+        match part {
+            None => toc.push(chapter),
+            Some(p) if current_part == Some(p) => {
+                if let Some(divider) = toc.last_mut() {
+                    divider.children.push(chapter);
+                }
+            }
+            Some(p) => {
+                let n = toc.iter().filter(|e| e.id.starts_with("part")).count() + 1;
+                toc.push(TocEntry {
+                    id: format!("part{:02}", n),
+                    title: parts[p].0.clone(),
+                    href: format!("parts/part{:02}.xhtml", n),
+                    children: vec![chapter],
+                });
+            }
+        }
+        current_part = part;
+    }
+    toc
+}
+
+/// Nested `<li>` items for the nav document and contents page; hrefs are relative to OEBPS/.
+fn toc_list_items(entries: &[TocEntry], indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    entries.iter().map(|e| {
+        let link = format!("<a href=\"{}\">{}</a>", e.href, escape_xml(&e.title));
+        if e.children.is_empty() {
+            format!("{pad}<li>{link}</li>\n", pad = pad, link = link)
+        } else {
+            format!(
+                "{pad}<li>{link}\n{pad}  <ol>\n{items}{pad}  </ol>\n{pad}</li>\n",
+                pad = pad, link = link, items = toc_list_items(&e.children, indent + 4)
+            )
+        }
+    }).collect()
+}
+
+fn build_nav(title: &str, toc: &[TocEntry], layout: &EpubLayout) -> String {
+    let items = toc_list_items(toc, 6);
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\"{html_attrs}>\n\
+         <head><title>{title}</title></head>\n\
+         <body>\n  <nav epub:type=\"toc\">\n    <h1>{title}</h1>\n    <ol>\n\
+         {items}    </ol>\n  </nav>\n</body>\n</html>",
+        html_attrs = layout.html_attrs(), title = escape_xml(title), items = items
+    )
+}
+
+// Heading and linked chapter list of the contents page
+fn toc_page_body(toc: &[TocEntry]) -> String {
+    format!("<h2>Contents</h2>\n<ol class=\"toc\">\n{}</ol>\n", toc_list_items(toc, 2))
+}
+
+// Visible "Contents" page placed first in the spine, for readers that don't show the nav doc
+fn build_toc_page(toc: &[TocEntry], layout: &EpubLayout) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\"{html_attrs}>\n\
+         <head>\n<title>Contents</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n\
+         </head>\n<body class=\"toc-page\">\n{body}</body>\n</html>\n",
+        html_attrs = layout.html_attrs(), body = toc_page_body(toc)
+    )
+}
+
+// The cover image, linked from a document in OEBPS/; `image` is the file under images/
+fn cover_page_body(title: &str, image: &str) -> String {
+    format!(
+        "<div class=\"cover\"><img src=\"images/{image}\" alt=\"{title}\"/></div>\n",
+        title = escape_xml(title), image = escape_xml(image)
+    )
+}
+
+// Full-page cover image; `image` is the file under images/
+fn build_cover_page(title: &str, image: &str, layout: &EpubLayout) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\"{html_attrs}>\n\
+         <head>\n<title>{title}</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n\
+         </head>\n<body class=\"cover\">\n{body}</body>\n</html>\n",
+        html_attrs = layout.html_attrs(), title = escape_xml(title), body = cover_page_body(title, image)
+    )
+}
+
+// Heading and note of the sample page, naming the book it was taken from
+fn sample_page_body(title: &str, author: &str, chapters: usize) -> String {
+    let byline = if author.is_empty() { String::new() } else { format!(" by {}", escape_xml(author)) };
+    let extent = if chapters == 1 { "the first chapter".to_string() } else { format!("the first {} chapters", chapters) };
+    format!(
+        "<h1 class=\"sample-title\">Sample</h1>\n\
+         <p class=\"sample-note\">You have been reading {extent} of <em>{title}</em>{byline}.</p>\n",
+        extent = extent, title = escape_xml(title), byline = byline
+    )
+}
+
+// Closing page of a sample EPUB
+fn build_sample_page(title: &str, author: &str, chapters: usize, layout: &EpubLayout) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\"{html_attrs}>\n\
+         <head>\n<title>Sample</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n\
+         </head>\n<body class=\"sample\">\n{body}</body>\n</html>\n",
+        html_attrs = layout.html_attrs(), body = sample_page_body(title, author, chapters)
+    )
+}
+
+// Divider page that opens a part, e.g. "Part One"
+fn build_part_page(title: &str, layout: &EpubLayout) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\"{html_attrs}>\n\
+         <head>\n<title>{title}</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"../style.css\"/>\n\
+         </head>\n<body class=\"part\">\n<h1 class=\"part-title\">{title}</h1>\n</body>\n</html>\n",
+        html_attrs = layout.html_attrs(), title = escape_xml(title)
+    )
+}
+
+fn ncx_nav_points(entries: &[TocEntry], play_order: &mut usize, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    entries.iter().map(|e| {
+        *play_order += 1;
+        let head = format!(
+            "{pad}<navPoint id=\"{id}\" playOrder=\"{ord}\">\n\
+             {pad}  <navLabel><text>{title}</text></navLabel>\n\
+             {pad}  <content src=\"{href}\"/>\n",
+            pad = pad, id = e.id, ord = play_order, title = escape_xml(&e.title), href = e.href
+        );
+        let children = ncx_nav_points(&e.children, play_order, indent + 2);
+        format!("{}{}{}</navPoint>\n", head, children, pad)
+    }).collect()
+}
+
+fn build_ncx(title: &str, uuid: &str, toc: &[TocEntry]) -> String {
+    let nav_points = ncx_nav_points(toc, &mut 0, 4);
+    let depth = if toc.iter().any(|e| !e.children.is_empty()) { 2 } else { 1 };
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <ncx xmlns=\"http://www.daisy.org/z3986/2005/ncx/\" version=\"2005-1\">\n\
+           <head>\n\
+             <meta name=\"dtb:uid\" content=\"urn:uuid:{uuid}\"/>\n\
+             <meta name=\"dtb:depth\" content=\"{depth}\"/>\n\
+             <meta name=\"dtb:totalPageCount\" content=\"0\"/>\n\
+             <meta name=\"dtb:maxPageNumber\" content=\"0\"/>\n\
+           </head>\n\
+           <docTitle><text>{title}</text></docTitle>\n\
+           <navMap>\n{nav_points}  </navMap>\n\
+         </ncx>",
+        uuid = uuid, depth = depth, title = escape_xml(title), nav_points = nav_points
+    )
+}
+
+const EPUB_CSS: &str = "\
+body { font-family: serif; font-size: 1em; line-height: 1.6; margin: 0; padding: 0; }\n\
+p { margin: 0 0 1em; orphans: 2; widows: 2; }\n\
+h2 { font-size: 1.5em; font-weight: bold; margin: 1.5em 0 0.5em; page-break-after: avoid; }\n\
+h3 { font-size: 1.3em; font-weight: bold; margin: 1.5em 0 0.5em; page-break-after: avoid; }\n\
+h4 { font-size: 1.1em; font-weight: bold; margin: 1.5em 0 0.5em; page-break-after: avoid; }\n\
+h5 { font-size: 1em;   font-weight: bold; margin: 1.5em 0 0.5em; page-break-after: avoid; }\n\
+h6 { font-size: 0.9em; font-weight: bold; margin: 1.5em 0 0.5em; page-break-after: avoid; }\n\
+blockquote { margin: 1em 2em; font-style: italic; }\n\
+p.quote-attribution { text-align: right; text-indent: 0; margin-top: 0.5em; }\n\
+p.quote-attribution cite { font-style: normal; }\n\
+ul, ol { margin: 0 0 1em; padding-left: 2em; }\n\
+li { margin: 0.25em 0; }\n\
+hr { border: none; border-top: 1px solid #ccc; margin: 2em 0; }\n\
+p.scene-break { text-align: center; text-indent: 0; margin: 1.5em 0; }\n\
+ol.toc { list-style: none; padding-left: 0; }\n\
+h1.part-title { text-align: center; margin-top: 30%; }\n\
+div.cover { text-align: center; margin: 0; padding: 0; }\n\
+div.cover img { max-width: 100%; max-height: 100%; }\n\
+h1.sample-title, p.sample-note { text-align: center; text-indent: 0; }\n\
+h1.sample-title { margin-top: 30%; }\n\
+h1.chapter-title { font-size: 2em; font-weight: bold; text-align: center; margin: 2em 0 1.5em; page-break-after: avoid; }\n\
+blockquote.epigraph { margin: 1em 3em 2em; }\n\
+blockquote.epigraph p { text-indent: 0; }\n\
+p.epigraph-attribution { text-align: right; font-style: normal; }\n\
+section.footnotes { margin-top: 2em; border-top: 1px solid #ccc; font-size: 0.9em; }\n\
+strong { font-weight: bold; }\n\
+em { font-style: italic; }\n\
+s { text-decoration: line-through; }\n\
+code { font-family: monospace; font-size: 0.9em; }";
+
+/// Convert a project StyleDefinition (fontSize, fontFamily, lineHeight, bold, italic) to CSS declarations.
+fn style_definition_to_css(def: &serde_json::Value) -> String {
+    let mut css = String::new();
+    if let Some(sz) = def.get("fontSize").and_then(|v| v.as_f64()) {
+        css.push_str(&format!("font-size: {}pt; ", sz));
+    }
+    if let Some(ff) = def.get("fontFamily").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+        css.push_str(&format!("font-family: {}; ", ff.replace(['{', '}', ';'], "")));
+    }
+    if let Some(lh) = def.get("lineHeight").and_then(|v| v.as_f64()) {
+        css.push_str(&format!("line-height: {}; ", lh));
+    }
+    if let Some(b) = def.get("bold").and_then(|v| v.as_bool()) {
+        css.push_str(if b { "font-weight: bold; " } else { "font-weight: normal; " });
+    }
+    if let Some(i) = def.get("italic").and_then(|v| v.as_bool()) {
+        css.push_str(if i { "font-style: italic; " } else { "font-style: normal; " });
+    }
+    css.trim_end().to_string()
+}
+
+/// Fiction-style paragraphs: first-line indent, no spacing, and no indent after a heading or break.
+const EPUB_INDENT_CSS: &str = "\n\
+p { text-indent: 1.5em; margin: 0; }\n\
+body > p:first-child, h1 + p, h2 + p, h3 + p, h4 + p, h5 + p, h6 + p, hr + p, p.scene-break + p, p.noindent { text-indent: 0; }";
+
+/// Drop cap on the first letter of a chapter, about three lines tall.
+const EPUB_DROPCAP_CSS: &str = "\n\
+span.dropcap { float: left; font-size: 3.2em; line-height: 0.85; margin: 0.05em 0.08em 0 0; font-weight: bold; }";
+
+/// Starts each chapter and part of a single-file EPUB on a new page.
+const EPUB_SINGLE_FILE_CSS: &str = "\n\
+section.chapter, section.part, section.toc-page, section.sample { page-break-before: always; }\n\
+body > section:first-child { page-break-before: auto; }";
+
+/// Mirrors the left-leaning defaults for right-to-left books.
+const EPUB_RTL_CSS: &str = "\n\
+body { direction: rtl; }\n\
+ul, ol { padding-left: 0; padding-right: 2em; }\n\
+ol.toc { padding-right: 0; }\n\
+span.dropcap { float: right; margin: 0.05em 0 0 0.08em; }";
+
+/// EPUB_CSS plus a `body.<class>` rule for each entry in the project's `styles.chapterClasses`.
+/// `styles.indentParagraphs` swaps the spaced paragraph style for first-line indents.
+fn build_epub_css(styles: &Option<serde_json::Value>) -> String {
+    let mut css = String::from(EPUB_CSS);
+    if styles.as_ref()
+        .and_then(|s| s.get("indentParagraphs"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        css.push_str(EPUB_INDENT_CSS);
+    }
+    if EpubLayout::from_styles(styles).drop_caps {
+        css.push_str(EPUB_DROPCAP_CSS);
+    }
+    css.push_str(&chapter_class_css(styles, "body"));
+    css
+}
+
+// A `<element>.<class>` rule for each entry in `styles.chapterClasses`
+fn chapter_class_css(styles: &Option<serde_json::Value>, element: &str) -> String {
+    let mut css = String::new();
+    if let Some(classes) = styles.as_ref()
+        .and_then(|s| s.get("chapterClasses"))
+        .and_then(|c| c.as_object())
+    {
+        for (name, def) in classes {
+            let class = sanitize_css_class(name);
+            let decls = style_definition_to_css(def);
+            if !class.is_empty() && !decls.is_empty() {
+                css.push_str(&format!("\n{}.{} {{ {} }}", element, class, decls));
+            }
+        }
+    }
+    css
+}
+
+// User stylesheets larger than this are refused rather than bloating every EPUB
+const MAX_CUSTOM_CSS_BYTES: u64 = 256 * 1024;
+
+/// Read a user-supplied stylesheet. A missing file is skipped (None); an oversized one is an error.
+fn read_custom_css(path: Option<&str>) -> Result<Option<String>, String> {
+    let Some(path) = path.map(str::trim).filter(|p| !p.is_empty()) else { return Ok(None) };
+    let Ok(meta) = fs::metadata(path) else { return Ok(None) };
+    if meta.len() > MAX_CUSTOM_CSS_BYTES {
+        return Err(format!("Custom CSS file is too large (limit {} KB)", MAX_CUSTOM_CSS_BYTES / 1024));
+    }
+    let css = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read custom CSS: {}", e))?;
+    Ok(Some(css.replace('\0', "")))
+}
+
+/// EPUB package version to write. EPUB 2 is for legacy readers and distributors that
+/// reject EPUB 3 packages.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum EpubVersion {
+    Epub2,
+    #[default]
+    Epub3,
+}
+
+impl EpubVersion {
+    fn parse(version: Option<u8>) -> Result<Self, String> {
+        match version {
+            None | Some(3) => Ok(EpubVersion::Epub3),
+            Some(2) => Ok(EpubVersion::Epub2),
+            Some(other) => Err(format!("Unsupported EPUB version: {} (expected 2 or 3)", other)),
+        }
+    }
+}
+
+/// Optional settings for write_epub_export.
+#[derive(Debug, Default)]
+struct EpubExportOptions<'a> {
+    // Number untitled chapters 1..N over the exported set instead of by id
+    renumber: bool,
+    filename_template: Option<&'a str>,
+    // Style keys (e.g. from an export preset) that replace the project's for this export only
+    style_overrides: Option<&'a serde_json::Value>,
+    // Raw CSS appended after the generated stylesheet so it can override it
+    custom_css: Option<String>,
+    version: EpubVersion,
+    // Add a visible toc.xhtml contents page as the first spine item
+    include_toc_page: bool,
+    // Shift in-chapter heading levels by this much; see EpubLayout::heading_offset
+    heading_offset: i8,
+    // Fail instead of leaving out images whose asset files are missing
+    strict_assets: bool,
+    // Title template (see render_title_template) prepended to every displayed chapter title,
+    // numbered over the exported chapters, e.g. "Chapter {n}: "
+    title_prefix: Option<&'a str>,
+    // Open with a cover page for the project's coverImage asset, when it has one
+    cover: bool,
+    // Close with a page marking the book as a sample of its first chapters
+    sample: bool,
+    // Put every chapter and part divider, and the cover, contents and sample pages, in one
+    // OEBPS/content.xhtml, for readers that cope badly with many small files; the nav and NCX
+    // link to anchors inside it
+    single_file: bool,
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn export_epub(
+    handle: AppHandle,
+    project_path: String,
+    export_dir: String,
+    chapter_ids: Vec<u32>,
+    renumber: Option<bool>,
+    filename_template: Option<String>,
+    custom_css_path: Option<String>,
+    epub_version: Option<u8>,
+    modified_since: Option<String>,
+    include_toc_page: Option<bool>,
+    heading_offset: Option<i8>,
+    strict: Option<bool>,
+    title_prefix: Option<String>,
+    single_file_content: Option<bool>,
+) -> Result<String, String> {
+    let chapter_ids = chapters_modified_since(&project_path, chapter_ids, modified_since.as_deref())?;
+    let options = EpubExportOptions {
+        renumber: renumber.unwrap_or(false),
+        filename_template: filename_template.as_deref().filter(|t| !t.trim().is_empty()),
+        custom_css: read_custom_css(custom_css_path.as_deref())?,
+        version: EpubVersion::parse(epub_version)?,
+        include_toc_page: include_toc_page.unwrap_or(false),
+        heading_offset: heading_offset.unwrap_or(0),
+        strict_assets: strict.unwrap_or(false),
+        title_prefix: title_prefix.as_deref().filter(|p| !p.trim().is_empty()),
+        single_file: single_file_content.unwrap_or(false),
+        ..Default::default()
+    };
+    write_epub_export(&project_path, &export_dir, chapter_ids, &options, &mut |progress| {
+        emit_export_progress(&handle, progress)
+    })
+}
+
+// Chapters a sample EPUB takes from the start of the book when none are picked
+const SAMPLE_CHAPTERS: usize = 3;
+
+// Short EPUB for marketing: the given chapters (or the book's first few), an optional
+// cover page and a closing page noting that it is a sample
+#[tauri::command]
+fn export_sample_epub(
+    handle: AppHandle,
+    project_path: String,
+    export_dir: String,
+    chapter_ids: Vec<u32>,
+    include_cover: bool,
+) -> Result<String, String> {
+    write_sample_epub_export(&project_path, &export_dir, chapter_ids, include_cover, &mut |progress| {
+        emit_export_progress(&handle, progress)
+    })
+}
+
+fn write_sample_epub_export(
+    project_path: &str,
+    export_dir: &str,
+    chapter_ids: Vec<u32>,
+    include_cover: bool,
+    on_progress: &mut dyn FnMut(ExportProgress),
+) -> Result<String, String> {
+    let chapter_ids = if chapter_ids.is_empty() {
+        let project_value = read_project_value(&Path::new(project_path).join("project.json"))?;
+        full_book_chapter_ids(&project_value).into_iter().take(SAMPLE_CHAPTERS).collect()
+    } else {
+        chapter_ids
+    };
+    let options = EpubExportOptions {
+        filename_template: Some("{title}_sample_{date}"),
+        cover: include_cover,
+        sample: true,
+        ..Default::default()
+    };
+    write_epub_export(project_path, export_dir, chapter_ids, &options, on_progress)
+}
+
+fn write_epub_export(
+    project_path: &str,
+    export_dir: &str,
+    chapter_ids: Vec<u32>,
+    options: &EpubExportOptions,
+    on_progress: &mut dyn FnMut(ExportProgress),
+) -> Result<String, String> {
+    let renumber = options.renumber;
+    let filename_template = options.filename_template;
+    use zip::write::SimpleFileOptions;
+    use zip::CompressionMethod;
+
+    let project_path_buf = PathBuf::from(&project_path);
+    let chapters_dir = project_path_buf.join("chapters");
+
+    // Load project metadata
+    let project_file = project_path_buf.join("project.json");
+    let project_content = fs::read_to_string(&project_file)
+        .map_err(|e| format!("Failed to read project.json: {}", e))?;
+    let project_value: serde_json::Value = serde_json::from_str(&project_content)
+        .map_err(|e| format!("Failed to parse project.json: {}", e))?;
+    let mut project: Project = serde_json::from_value(project_value.clone())
+        .map_err(|e| format!("Failed to parse project: {}", e))?;
+
+    // Style overrides (e.g. from an export preset) replace the project's keys for this export only
+    if let Some(overrides) = options.style_overrides.and_then(|o| o.as_object()) {
+        let mut styles = project.styles.take()
+            .filter(|s| s.is_object())
+            .unwrap_or_else(|| serde_json::json!({}));
+        for (key, value) in overrides {
+            styles[key] = value.clone();
+        }
+        project.styles = Some(styles);
+    }
+
+    // Load chapter titles map (stored separately from Project struct)
+    let chapter_titles_map = project_value
+        .get("chapterTitles")
+        .and_then(|v| v.as_object())
+        .cloned()
+        .unwrap_or_default();
+
+    // Determine chapters to export, maintaining project order; a full-book export skips excluded chapters
+    let ids_to_export: Vec<u32> = if chapter_ids.is_empty() {
+        full_book_chapter_ids(&project_value)
+    } else {
+        project.chapter_order.iter()
+            .filter(|id| chapter_ids.contains(id))
+            .copied()
+            .collect()
+    };
+
+    // Load chapter content and titles. Untitled chapters fall back to "Chapter <id>",
+    // or "Chapter <n>" numbered over the exported chapters when renumbering; stored titles
+    // that lead with their chapter id ("Chapter 7: ...", "7. ...") get <n> in its place.
+    let mut chapters: Vec<(String, Option<serde_json::Value>)> = Vec::new();
+    for (i, &id) in ids_to_export.iter().enumerate() {
+        let chapter_file = chapters_dir.join(format!("{}.json", id));
+        let content = if chapter_file.exists() {
+            let s = fs::read_to_string(&chapter_file)
+                .map_err(|e| format!("Failed to read chapter {}: {}", id, e))?;
+            serde_json::from_str(&s).ok()
+        } else {
+            None
+        };
+        let chapter_number = if renumber { i as u32 + 1 } else { id };
+        let title = chapter_titles_map.get(&id.to_string()).and_then(|v| v.as_str()).map(|title| {
+            if renumber { renumber_title(title, id, chapter_number) } else { title.to_string() }
+        });
+        let fallback = format!("Chapter {}", chapter_number);
+        let title = prefix_chapter_title(options.title_prefix, i as u32 + 1, id, title.as_deref(), fallback)?;
+        chapters.push((title, content));
+    }
+
+    if options.strict_assets {
+        let missing = missing_image_assets(&project_path_buf, chapters.iter().map(|(_, c)| c));
+        if !missing.is_empty() {
+            return Err(format!("Missing image assets: {}", missing.join(", ")));
+        }
+    }
+
+    let uuid = generate_epub_uuid(&project.title);
+    let modified = Local::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let date = Local::now().format("%Y-%m-%d").to_string();
+
+    let filename = sanitize_filename(&match filename_template {
+        Some(template) => render_export_filename(template, &project, &date, chapters.len(), "epub")?,
+        None => format!("{}_{}.epub", sanitize_filename_part(&project.title), date),
+    });
+    let export_path = PathBuf::from(&export_dir).join(&filename);
+
+    let file = fs::File::create(&export_path)
+        .map_err(|e| format!("Failed to create EPUB file: {}", e))?;
+    let mut zip = zip::ZipWriter::new(file);
+
+    let stored   = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // mimetype — must be first entry, uncompressed
+    zip.start_file("mimetype", stored).map_err(|e| e.to_string())?;
+    zip.write_all(b"application/epub+zip").map_err(|e| e.to_string())?;
+
+    // META-INF/container.xml
+    zip.start_file("META-INF/container.xml", deflated).map_err(|e| e.to_string())?;
+    zip.write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n\
+          <rootfiles>\n\
+            <rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/>\n\
+          </rootfiles>\n\
+        </container>").map_err(|e| e.to_string())?;
+
+    // Images pasted as data: URLs have no asset file; decode them and name them here
+    let mut taken: HashSet<String> = chapters.iter()
+        .flat_map(|(_, content)| collect_image_names(content))
+        .collect();
+    let mut names_by_src: HashMap<String, String> = HashMap::new();
+    let mut inline_images: Vec<(String, Vec<u8>)> = Vec::new();
+    for (_, content) in chapters.iter_mut() {
+        if let Some(doc) = content.as_mut() {
+            embed_data_url_images(doc, &mut taken, &mut names_by_src, &mut inline_images);
+        }
+    }
+
+    // Collect all image filenames referenced by imageBleed nodes
+    let mut all_image_names: Vec<String> = Vec::new();
+    for (_, content) in &chapters {
+        for name in collect_image_names(content) {
+            if !all_image_names.contains(&name) {
+                all_image_names.push(name);
+            }
+        }
+    }
+
+    // The cover is only used when its asset file exists
+    let cover = project_value.get("coverImage")
+        .and_then(|v| v.as_str())
+        .filter(|name| options.cover && !name.is_empty())
+        .filter(|name| project_path_buf.join("assets").join(name).is_file())
+        .map(|name| name.to_string());
+    if let Some(cover) = cover.as_ref().filter(|c| !all_image_names.contains(c)) {
+        all_image_names.push(cover.clone());
+    }
+
+    let layout = EpubLayout {
+        heading_offset: options.heading_offset,
+        ..EpubLayout::for_project(&project_value, &project.styles)
+    };
+
+    // OEBPS/style.css
+    zip.start_file("OEBPS/style.css", deflated).map_err(|e| e.to_string())?;
+    let mut css = build_epub_css(&project.styles);
+    if layout.rtl {
+        css.push_str(EPUB_RTL_CSS);
+    }
+    if options.single_file {
+        css.push_str(EPUB_SINGLE_FILE_CSS);
+        css.push_str(&chapter_class_css(&project.styles, "section"));
+    }
+    if let Some(custom) = options.custom_css.as_deref() {
+        css.push_str("\n/* Custom CSS */\n");
+        css.push_str(custom);
+    }
+    zip.write_all(css.as_bytes()).map_err(|e| e.to_string())?;
+
+    // OEBPS/images/* — embed any referenced images
+    for img_name in &all_image_names {
+        let img_path = project_path_buf.join("assets").join(img_name);
+        if let Some((_, bytes)) = inline_images.iter().find(|(name, _)| name == img_name) {
+            zip.start_file(format!("OEBPS/images/{}", img_name), deflated)
+                .map_err(|e| e.to_string())?;
+            zip.write_all(bytes).map_err(|e| e.to_string())?;
+        } else if img_path.exists() {
+            let img_bytes = fs::read(&img_path)
+                .map_err(|e| format!("Failed to read image {}: {}", img_name, e))?;
+            zip.start_file(&format!("OEBPS/images/{}", img_name), deflated)
+                .map_err(|e| e.to_string())?;
+            zip.write_all(&img_bytes).map_err(|e| e.to_string())?;
+        }
+    }
+
+    let chapter_titles: Vec<String> = chapters.iter().map(|(t, _)| t.clone()).collect();
+    let mut toc = epub_toc(&project_value, &ids_to_export, &chapter_titles);
+
+    // OEBPS/content.xhtml — the cover, contents page, every part divider and chapter, and the
+    // sample page, each as an anchored section
+    if options.single_file {
+        anchor_toc_in_single_file(&mut toc);
+        let mut sections = String::new();
+        if let Some(cover) = cover.as_deref() {
+            sections.push_str(&format!(
+                "<section class=\"cover\" id=\"cover\">\n{}</section>\n",
+                cover_page_body(&project.title, cover)
+            ));
+        }
+        if options.include_toc_page {
+            sections.push_str(&format!(
+                "<section class=\"toc-page\" id=\"toc-page\">\n{}</section>\n",
+                toc_page_body(&toc)
+            ));
+        }
+        let mut i = 0;
+        for entry in flatten_toc(&toc) {
+            if !entry.children.is_empty() {
+                sections.push_str(&format!(
+                    "<section class=\"part\" id=\"{}\">\n<h1 class=\"part-title\">{}</h1>\n</section>\n",
+                    entry.id, escape_xml(&entry.title)
+                ));
+                continue;
+            }
+            let (title, content) = &chapters[i];
+            let epigraph = chapter_epigraph(&project_value, ids_to_export[i]);
+            sections.push_str(&single_file_chapter_section(&entry.id, title, content, epigraph.as_ref(), &layout));
+            i += 1;
+            on_progress(chapter_progress(i, chapters.len()));
+        }
+        if options.sample {
+            sections.push_str(&format!(
+                "<section class=\"sample\" id=\"sample\">\n{}</section>\n",
+                sample_page_body(&project.title, &project.author, chapters.len())
+            ));
+        }
+        zip.start_file("OEBPS/content.xhtml", deflated).map_err(|e| e.to_string())?;
+        zip.write_all(build_single_file_content(&project.title, &sections, &layout).as_bytes())
+            .map_err(|e| e.to_string())?;
+    } else {
+        // OEBPS/chapters/chNNN.xhtml — one file per chapter
+        for (i, (title, content)) in chapters.iter().enumerate() {
+            let fname = format!("OEBPS/chapters/ch{:03}.xhtml", i + 1);
+            zip.start_file(&fname, deflated).map_err(|e| e.to_string())?;
+            let epigraph = chapter_epigraph(&project_value, ids_to_export[i]);
+            zip.write_all(chapter_to_xhtml(title, content, epigraph.as_ref(), &layout).as_bytes()).map_err(|e| e.to_string())?;
+            on_progress(chapter_progress(i + 1, chapters.len()));
+        }
+
+        // OEBPS/parts/partNN.xhtml — a divider page for each part
+        for part in toc.iter().filter(|e| !e.children.is_empty()) {
+            zip.start_file(format!("OEBPS/{}", part.href), deflated).map_err(|e| e.to_string())?;
+            zip.write_all(build_part_page(&part.title, &layout).as_bytes()).map_err(|e| e.to_string())?;
+        }
+    }
+
+    // OEBPS/cover.xhtml and OEBPS/sample.xhtml (sections of content.xhtml in a single-file EPUB)
+    if let Some(cover) = cover.as_deref().filter(|_| !options.single_file) {
+        zip.start_file("OEBPS/cover.xhtml", deflated).map_err(|e| e.to_string())?;
+        zip.write_all(build_cover_page(&project.title, cover, &layout).as_bytes()).map_err(|e| e.to_string())?;
+    }
+    if options.sample && !options.single_file {
+        zip.start_file("OEBPS/sample.xhtml", deflated).map_err(|e| e.to_string())?;
+        zip.write_all(build_sample_page(&project.title, &project.author, chapters.len(), &layout).as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+
+    // OEBPS/toc.xhtml (visible contents page)
+    if options.include_toc_page && !options.single_file {
+        zip.start_file("OEBPS/toc.xhtml", deflated).map_err(|e| e.to_string())?;
+        zip.write_all(build_toc_page(&toc, &layout).as_bytes()).map_err(|e| e.to_string())?;
+    }
+
+    // OEBPS/nav.xhtml (EPUB 3 navigation document)
+    if options.version == EpubVersion::Epub3 {
+        zip.start_file("OEBPS/nav.xhtml", deflated).map_err(|e| e.to_string())?;
+        zip.write_all(build_nav(&project.title, &toc, &layout).as_bytes()).map_err(|e| e.to_string())?;
+    }
+
+    // OEBPS/toc.ncx (EPUB 2 table of contents, kept in EPUB 3 for compatibility)
+    zip.start_file("OEBPS/toc.ncx", deflated).map_err(|e| e.to_string())?;
+    zip.write_all(build_ncx(&project.title, &uuid, &toc).as_bytes()).map_err(|e| e.to_string())?;
+
+    // OEBPS/content.opf (package document)
+    zip.start_file("OEBPS/content.opf", deflated).map_err(|e| e.to_string())?;
+    let meta = OpfMetadata {
+        title: &project.title,
+        author: &project.author,
+        uuid: &uuid,
+        modified: &modified,
+        cover: cover.as_deref(),
+    };
+    zip.write_all(
+        build_opf(&meta, &toc, &all_image_names, options, &layout).as_bytes()
+    ).map_err(|e| e.to_string())?;
+
+    zip.finish().map_err(|e| format!("Failed to finalize EPUB: {}", e))?;
+
+    let output_path = export_path.to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| "Failed to convert path to string".to_string())?;
+    on_progress(complete_progress(chapters.len(), &output_path));
+    Ok(output_path)
+}
+
+/// A chapter rendered the way EPUB export would render it, for the editor's preview pane:
+/// a <style> block with the generated stylesheet followed by the body HTML. Images are
+/// inlined as data URLs, so nothing is written to disk.
+#[tauri::command]
+fn render_chapter_html(project_path: String, chapter_id: u32) -> Result<String, String> {
+    let path = PathBuf::from(&project_path);
+    let project_value = read_project_value(&path.join("project.json"))?;
+    if !chapter_order_of(&project_value).contains(&chapter_id) {
+        return Err(format!("Chapter {} not found", chapter_id));
+    }
+    let styles = project_value.get("styles").cloned();
+    let layout = EpubLayout::for_project(&project_value, &styles);
+    let body = render_preview_body(&path, &project_value, chapter_id, &layout);
+    Ok(format!("<style>\n{}\n</style>\n{}", build_epub_css(&styles), body))
+}
+
+/// The whole book (or the given chapters) as one HTML string for the in-app reader: the
+/// stylesheet once, then each chapter in chapterOrder as a <section id="chapter-N"> rendered
+/// as in render_chapter_html, always with its title heading and its footnote ids prefixed
+/// with the section id. With no chapter ids, chapters excluded from the book are left out.
+#[tauri::command]
+fn render_book_html(project_path: String, chapter_ids: Vec<u32>) -> Result<String, String> {
+    let path = PathBuf::from(&project_path);
+    let project_value = read_project_value(&path.join("project.json"))?;
+    let ids: Vec<u32> = if chapter_ids.is_empty() {
+        full_book_chapter_ids(&project_value)
+    } else {
+        chapter_order_of(&project_value).into_iter().filter(|id| chapter_ids.contains(id)).collect()
+    };
+    let styles = project_value.get("styles").cloned();
+    // Headings mark where each chapter starts in the scrolling reader, whatever the EPUB shows
+    let layout = EpubLayout {
+        show_chapter_titles: true,
+        ..EpubLayout::for_project(&project_value, &styles)
+    };
+
+    let mut html = format!("<style>\n{}\n</style>\n", build_epub_css(&styles));
+    for id in ids {
+        let anchor = format!("chapter-{}", id);
+        let body = prefix_footnote_ids(&render_preview_body(&path, &project_value, id, &layout), &anchor);
+        html.push_str(&format!("<section class=\"chapter\" id=\"{}\">\n{}</section>\n", anchor, body));
+    }
+    Ok(html)
+}
+
+// Title, epigraph, body and footnotes of one chapter, with images inlined as data URLs
+fn render_preview_body(project_path: &Path, project_value: &serde_json::Value, chapter_id: u32, layout: &EpubLayout) -> String {
+    let mut content = read_chapter_content(project_path, chapter_id);
+    let mut taken: HashSet<String> = collect_image_names(&content).into_iter().collect();
+    let mut inline_images: Vec<(String, Vec<u8>)> = Vec::new();
+    if let Some(doc) = content.as_mut() {
+        embed_data_url_images(doc, &mut taken, &mut HashMap::new(), &mut inline_images);
+    }
+
+    let title = project_value.get("chapterTitles")
+        .and_then(|t| t.get(chapter_id.to_string()))
+        .and_then(|v| v.as_str())
+        .map(|t| t.to_string())
+        .unwrap_or_else(|| format!("Chapter {}", chapter_id));
+    let epigraph = chapter_epigraph(project_value, chapter_id);
+    let (mut body, _) = chapter_body_xhtml(&title, &content, epigraph.as_ref(), layout);
+
+    // chapter_body_xhtml points images at the EPUB's images/ folder; swap in the bytes themselves
+    for name in collect_image_names(&content) {
+        let bytes = match inline_images.iter().find(|(n, _)| *n == name) {
+            Some((_, bytes)) => bytes.clone(),
+            None => match fs::read(project_path.join("assets").join(&name)) {
+                Ok(bytes) => bytes,
+                Err(_) => continue,
+            },
+        };
+        let ext = Path::new(&name).extension().and_then(|e| e.to_str()).unwrap_or("");
+        body = body.replace(
+            &format!("src=\"../images/{}\"", escape_xml(&name)),
+            &format!("src=\"data:{};base64,{}\"", image_mime_for_ext(ext), base64_encode(&bytes)),
+        );
+    }
+    body
+}
+
+// ============================================================
+// Markdown export
+// ============================================================
+
+fn escape_markdown(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Render TipTap inline content to Markdown.
+fn render_markdown_inline(items: &[serde_json::Value]) -> String {
+    let mut out = String::new();
+    for item in items {
+        match item.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+            "hardBreak" => out.push_str("  \n"),
+            "footnoteReference" => out.push_str(&format!("[^{}]", footnote_label(item))),
+            "text" => {
+                let text = item.get("text").and_then(|v| v.as_str()).unwrap_or("");
+                let empty = vec![];
+                let marks = item.get("marks").and_then(|m| m.as_array()).unwrap_or(&empty);
+                let has = |name: &str| marks.iter()
+                    .any(|m| m.get("type").and_then(|v| v.as_str()) == Some(name));
+
+                if has("code") {
+                    out.push_str(&format!("`{}`", text));
+                    continue;
+                }
+                let mut s = escape_markdown(text);
+                if has("strike") { s = format!("~~{}~~", s); }
+                if has("italic") { s = format!("*{}*", s); }
+                if has("bold")   { s = format!("**{}**", s); }
+                let href = marks.iter()
+                    .find(|m| m.get("type").and_then(|v| v.as_str()) == Some("link"))
+                    .and_then(|m| m.get("attrs")).and_then(|a| a.get("href")).and_then(|v| v.as_str());
+                if let Some(href) = href {
+                    s = format!("[{}]({})", s, href);
+                }
+                out.push_str(&s);
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Render TipTap block nodes to Markdown, blocks separated by blank lines.
+fn render_markdown_blocks(nodes: &[serde_json::Value]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        let t = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        let inline = || node.get("content").and_then(|c| c.as_array())
+            .map(|items| render_markdown_inline(items)).unwrap_or_default();
+        let children = || node.get("content").and_then(|c| c.as_array())
+            .map(|items| render_markdown_blocks(items)).unwrap_or_default();
+
+        match t {
+            "paragraph" => {
+                let inner = inline();
+                if !inner.is_empty() {
+                    out.push_str(&inner);
+                    out.push_str("\n\n");
+                }
+            }
+            "heading" => {
+                let level = node.get("attrs").and_then(|a| a.get("level"))
+                    .and_then(|v| v.as_u64()).unwrap_or(2).clamp(2, 6) as usize;
+                out.push_str(&format!("{} {}\n\n", "#".repeat(level), inline()));
+            }
+            "blockquote" => {
+                let inner = children();
+                for line in inner.trim_end().lines() {
+                    if line.is_empty() { out.push_str(">\n"); } else { out.push_str(&format!("> {}\n", line)); }
+                }
+                out.push('\n');
+            }
+            "bulletList" | "orderedList" => {
+                if let Some(items) = node.get("content").and_then(|c| c.as_array()) {
+                    for (i, item) in items.iter().enumerate() {
+                        let start = node.get("attrs").and_then(|a| a.get("start")).and_then(|v| v.as_u64()).unwrap_or(1);
+                        let marker = if t == "bulletList" { "-".to_string() } else { format!("{}.", start as usize + i) };
+                        let body = item.get("content").and_then(|c| c.as_array())
+                            .map(|c| render_markdown_blocks(c)).unwrap_or_default();
+                        let indent = " ".repeat(marker.len() + 1);
+                        for (j, line) in body.trim_end().lines().enumerate() {
+                            if j == 0 {
+                                out.push_str(&format!("{} {}\n", marker, line));
+                            } else if line.is_empty() {
+                                out.push('\n');
+                            } else {
+                                out.push_str(&format!("{}{}\n", indent, line));
+                            }
+                        }
+                    }
+                    out.push('\n');
+                }
+            }
+            "codeBlock" => {
+                let lang = node.get("attrs").and_then(|a| a.get("language"))
+                    .and_then(|v| v.as_str()).unwrap_or("");
+                let code: String = node.get("content").and_then(|c| c.as_array())
+                    .map(|items| items.iter().filter_map(|i| i.get("text").and_then(|v| v.as_str())).collect())
+                    .unwrap_or_default();
+                out.push_str(&format!("```{}\n{}\n```\n\n", lang, code));
+            }
+            "horizontalRule" => out.push_str("---\n\n"),
+            "footnoteDefinition" => {
+                // Continuation lines are indented so they stay part of the note
+                let body = children();
+                for (i, line) in body.trim_end().lines().enumerate() {
+                    if i == 0 {
+                        out.push_str(&format!("[^{}]: {}\n", footnote_label(node), line));
+                    } else if line.is_empty() {
+                        out.push('\n');
+                    } else {
+                        out.push_str(&format!("    {}\n", line));
+                    }
+                }
+                out.push('\n');
+            }
+            "colorBleed" => out.push_str(&children()),
+            "imageBleed" => {
+                let name = node.get("attrs").and_then(|a| a.get("name"))
+                    .and_then(|v| v.as_str()).unwrap_or("");
+                let alt = node.get("attrs").and_then(|a| a.get("alt"))
+                    .and_then(|v| v.as_str()).unwrap_or("");
+                if !name.is_empty() {
+                    out.push_str(&format!("![{}](assets/{})\n\n", escape_markdown(alt), name));
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// GitHub-style heading slug: lowercase alphanumerics, spaces and hyphens become '-'.
+fn markdown_slug(title: &str) -> String {
+    let slug: String = title.trim().to_lowercase().chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '_' { Some(c) }
+            else if c == ' ' || c == '-' { Some('-') }
+            else { None }
+        })
+        .collect();
+    if slug.is_empty() { "chapter".to_string() } else { slug }
+}
+
+/// Slug that isn't already in `used`, suffixing -1, -2, … as needed.
+fn unique_slug(title: &str, used: &mut HashSet<String>) -> String {
+    let base = markdown_slug(title);
+    let mut slug = base.clone();
+    let mut n = 1;
+    while used.contains(&slug) {
+        slug = format!("{}-{}", base, n);
+        n += 1;
+    }
+    used.insert(slug.clone());
+    slug
+}
+
+/// Export one chapter (e.g. for a beta reader) as "rtf", "epub", "markdown" or "docx", named after the
+/// chapter title and date rather than the book.
+#[tauri::command]
+fn export_chapter(
+    project_path: String,
+    export_dir: String,
+    chapter_id: u32,
+    format: String,
+) -> Result<String, String> {
+    let project_path_buf = PathBuf::from(&project_path);
+    let project_value = read_project_value(&project_path_buf.join("project.json"))?;
+    if read_chapter_content(&project_path_buf, chapter_id).is_none() {
+        return Err(format!("Chapter {} not found", chapter_id));
+    }
+
+    let title = project_value
+        .get("chapterTitles")
+        .and_then(|t| t.get(chapter_id.to_string()))
+        .and_then(|v| v.as_str())
+        .filter(|t| !t.trim().is_empty())
+        .map(|t| t.to_string())
+        .unwrap_or_else(|| format!("Chapter {}", chapter_id));
+    // The sanitized title can't contain braces, so it's safe as literal template text
+    let template = format!("{}_{{date}}", sanitize_filename_part(&title));
+
+    match format.as_str() {
+        "rtf" => write_rtf_export(&project_path, &export_dir, vec![chapter_id], false, Some(&template), &RtfHeadingStyle::default(), RtfLineSpacing::default(), &mut |_| {}),
+        "epub" => {
+            let options = EpubExportOptions { filename_template: Some(&template), ..Default::default() };
+            write_epub_export(&project_path, &export_dir, vec![chapter_id], &options, &mut |_| {})
         }
+        "markdown" | "md" => export_markdown(project_path, export_dir, vec![chapter_id], Some(false), Some(template), None, None),
+        "docx" => write_docx_export(&project_path, &export_dir, vec![chapter_id], Some(&template), &mut |_| {}),
+        other => Err(format!("Unsupported export format: {}", other)),
+    }
+}
+
+fn is_zero(n: &i8) -> bool {
+    *n == 0
+}
+
+/// Saved export settings, stored by name under `exportPresets` in project.json.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ExportPreset {
+    // "rtf", "epub", "markdown" or "docx"
+    format: String,
+    // Empty means every chapter
+    #[serde(rename = "chapterIds", default)]
+    chapter_ids: Vec<u32>,
+    #[serde(rename = "filenameTemplate", default, skip_serializing_if = "Option::is_none")]
+    filename_template: Option<String>,
+    #[serde(default)]
+    renumber: bool,
+    #[serde(rename = "includeToc", default)]
+    include_toc: bool,
+    // Falls back to the project's default export directory
+    #[serde(rename = "exportDir", default, skip_serializing_if = "Option::is_none")]
+    export_dir: Option<String>,
+    // EPUB style keys (indentParagraphs, dropCaps, sceneBreak, ...) overriding the project's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    styles: Option<serde_json::Value>,
+    #[serde(rename = "customCssPath", default, skip_serializing_if = "Option::is_none")]
+    custom_css_path: Option<String>,
+    // 2 or 3; EPUB 3 when unset
+    #[serde(rename = "epubVersion", default, skip_serializing_if = "Option::is_none")]
+    epub_version: Option<u8>,
+    // RTF/EPUB: shift every in-chapter heading level by this much
+    #[serde(rename = "headingOffset", default, skip_serializing_if = "is_zero")]
+    heading_offset: i8,
+    // EPUB/Markdown: title template prepended to chapter titles, e.g. "Chapter {n}: "
+    #[serde(rename = "titlePrefix", default, skip_serializing_if = "Option::is_none")]
+    title_prefix: Option<String>,
+}
+
+const EXPORT_FORMATS: &[&str] = &["rtf", "epub", "markdown", "docx"];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NamedExportPreset {
+    name: String,
+    #[serde(flatten)]
+    preset: ExportPreset,
+}
+
+#[tauri::command]
+fn save_export_preset(project_path: String, name: String, preset_json: serde_json::Value) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Preset name cannot be empty".to_string());
+    }
+    let preset: ExportPreset = serde_json::from_value(preset_json)
+        .map_err(|e| format!("Invalid export preset: {}", e))?;
+    if !EXPORT_FORMATS.contains(&preset.format.as_str()) {
+        return Err(format!("Unsupported export format: {}", preset.format));
+    }
+
+    let project_file = PathBuf::from(&project_path).join("project.json");
+    let mut project = read_project_value(&project_file)?;
+    if !project.get("exportPresets").is_some_and(|p| p.is_object()) {
+        project["exportPresets"] = serde_json::json!({});
+    }
+    project["exportPresets"][name] = serde_json::to_value(&preset)
+        .map_err(|e| format!("Failed to serialize preset: {}", e))?;
+    write_project_value(&project_file, &project)
+}
+
+// Saved presets sorted by name; entries that no longer parse are skipped
+#[tauri::command]
+fn list_export_presets(project_path: String) -> Result<Vec<NamedExportPreset>, String> {
+    let project = read_project_value(&PathBuf::from(&project_path).join("project.json"))?;
+    let mut presets: Vec<NamedExportPreset> = project.get("exportPresets")
+        .and_then(|p| p.as_object())
+        .map(|map| map.iter()
+            .filter_map(|(name, v)| serde_json::from_value(v.clone()).ok()
+                .map(|preset| NamedExportPreset { name: name.clone(), preset }))
+            .collect())
+        .unwrap_or_default();
+    presets.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(presets)
+}
+
+// Run a saved preset through the matching exporter; returns the exported file's path
+#[tauri::command]
+fn export_with_preset(handle: AppHandle, project_path: String, name: String) -> Result<String, String> {
+    run_export_preset(&project_path, &name, &mut |progress| emit_export_progress(&handle, progress))
+}
+
+fn run_export_preset(
+    project_path: &str,
+    name: &str,
+    on_progress: &mut dyn FnMut(ExportProgress),
+) -> Result<String, String> {
+    let project = read_project_value(&PathBuf::from(project_path).join("project.json"))?;
+    let preset: ExportPreset = project.get("exportPresets")
+        .and_then(|p| p.get(name))
+        .ok_or_else(|| format!("Export preset '{}' not found", name))
+        .and_then(|v| serde_json::from_value(v.clone()).map_err(|e| format!("Invalid export preset: {}", e)))?;
+
+    let export_dir = match preset.export_dir.clone().filter(|d| !d.is_empty()) {
+        Some(dir) => resolve_portable_path(&dir, Path::new(project_path)).to_string_lossy().to_string(),
+        None => get_default_export_dir(project_path.to_string())?,
     };
+    let template = preset.filename_template.as_deref().filter(|t| !t.trim().is_empty());
+    let headings = RtfHeadingStyle { offset: preset.heading_offset, ..Default::default() };
+
+    match preset.format.as_str() {
+        "rtf" => write_rtf_export(project_path, &export_dir, preset.chapter_ids, preset.renumber, template, &headings, RtfLineSpacing::default(), on_progress),
+        "epub" => {
+            let options = EpubExportOptions {
+                renumber: preset.renumber,
+                filename_template: template,
+                style_overrides: preset.styles.as_ref(),
+                custom_css: read_custom_css(preset.custom_css_path.as_deref())?,
+                version: EpubVersion::parse(preset.epub_version)?,
+                include_toc_page: preset.include_toc,
+                heading_offset: preset.heading_offset,
+                strict_assets: false,
+                title_prefix: preset.title_prefix.as_deref().filter(|p| !p.trim().is_empty()),
+                cover: false,
+                sample: false,
+                single_file: false,
+            };
+            write_epub_export(project_path, &export_dir, preset.chapter_ids, &options, on_progress)
+        }
+        "markdown" => export_markdown(
+            project_path.to_string(), export_dir, preset.chapter_ids, Some(preset.include_toc), preset.filename_template, None,
+            preset.title_prefix,
+        ),
+        "docx" => write_docx_export(project_path, &export_dir, preset.chapter_ids, template, on_progress),
+        other => Err(format!("Unsupported export format: {}", other)),
+    }
+}
 
-    let bytes = fs::read(&src)
-        .map_err(|e| format!("Failed to read image: {}", e))?;
+// Export with each format's default settings to `export_dir`. The saved exportDir is only
+// changed when `remember` is set, so a one-off location leaves the project's default alone.
+#[tauri::command]
+fn export_book(
+    handle: AppHandle,
+    project_path: String,
+    export_dir: String,
+    format: String,
+    chapter_ids: Vec<u32>,
+    remember: bool,
+) -> Result<String, String> {
+    write_book_export(&project_path, &export_dir, &format, chapter_ids, remember, &mut |progress| {
+        emit_export_progress(&handle, progress)
+    })
+}
 
-    fs::write(&dest_path, &bytes)
-        .map_err(|e| format!("Failed to copy image: {}", e))?;
+fn write_book_export(
+    project_path: &str,
+    export_dir: &str,
+    format: &str,
+    chapter_ids: Vec<u32>,
+    remember: bool,
+    on_progress: &mut dyn FnMut(ExportProgress),
+) -> Result<String, String> {
+    let output_path = match format {
+        "rtf" => write_rtf_export(project_path, export_dir, chapter_ids, false, None, &RtfHeadingStyle::default(), RtfLineSpacing::default(), on_progress),
+        "epub" => write_epub_export(project_path, export_dir, chapter_ids, &EpubExportOptions::default(), on_progress),
+        "markdown" | "md" => export_markdown(project_path.to_string(), export_dir.to_string(), chapter_ids, Some(false), None, None, None),
+        "docx" => write_docx_export(project_path, export_dir, chapter_ids, None, on_progress),
+        "manuscript" => write_manuscript_export(project_path, export_dir, chapter_ids, on_progress),
+        other => Err(format!("Unsupported export format: {}", other)),
+    }?;
+    if remember {
+        update_export_dir(project_path.to_string(), export_dir.to_string(), None)?;
+    }
+    Ok(output_path)
+}
 
-    let final_name = dest_path.file_name()
-        .unwrap()
-        .to_string_lossy()
-        .to_string();
+/// Replace the chapter id a stored title leads with ("7. Storm", "Chapter 7: Storm") by `n`.
+/// Titles that start with any other number ("1984") are left alone.
+fn renumber_title(title: &str, id: u32, n: u32) -> String {
+    let (lead, rest) = match title.get(..8) {
+        Some(word) if word.eq_ignore_ascii_case("chapter ") => title.split_at(8),
+        _ => ("", title),
+    };
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 && rest[..digits].parse::<u32>() == Ok(id) {
+        format!("{}{}{}", lead, n, &rest[digits..])
+    } else {
+        title.to_string()
+    }
+}
 
-    let ext = dest_path.extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("");
-    let mime = image_mime_for_ext(ext);
-    let data_url = format!("data:{};base64,{}", mime, base64_encode(&bytes));
+/// `title` with `prefix` (a title template, see render_title_template) expanded in front of it,
+/// for the n-th exported chapter. Only changes the exported text; chapterTitles is untouched.
+/// An untitled chapter is named by the prefix alone ("Chapter {n}: " gives "Chapter 2") rather
+/// than "Chapter 2: Chapter 7"; `fallback` is used when there is neither.
+fn prefix_chapter_title(prefix: Option<&str>, n: u32, id: u32, title: Option<&str>, fallback: String) -> Result<String, String> {
+    match (prefix, title) {
+        (Some(prefix), Some(title)) => Ok(format!("{}{}", render_title_template(prefix, n, id)?, title)),
+        (Some(prefix), None) => {
+            let label = render_title_template(prefix, n, id)?;
+            let label = label.trim_end_matches(|c: char| c.is_whitespace() || ":.-|\u{2013}\u{2014}".contains(c));
+            Ok(if label.is_empty() { fallback } else { label.to_string() })
+        }
+        (None, title) => Ok(title.map(str::to_string).unwrap_or(fallback)),
+    }
+}
 
-    Ok(serde_json::json!({
-        "name": final_name,
-        "dataUrl": data_url,
-    }))
+/// Export chapters as one Markdown file. With `include_toc`, a linked table of contents is
+/// prepended and each chapter heading is preceded by a matching `<a id="slug">` anchor.
+/// `title_prefix` is prepended to each chapter title; see prefix_chapter_title.
+#[tauri::command]
+fn export_markdown(
+    project_path: String,
+    export_dir: String,
+    chapter_ids: Vec<u32>,
+    include_toc: Option<bool>,
+    filename_template: Option<String>,
+    modified_since: Option<String>,
+    title_prefix: Option<String>,
+) -> Result<String, String> {
+    let chapter_ids = chapters_modified_since(&project_path, chapter_ids, modified_since.as_deref())?;
+    let title_prefix = title_prefix.as_deref().filter(|p| !p.trim().is_empty());
+    let project_path_buf = PathBuf::from(&project_path);
+    let project_value = read_project_value(&project_path_buf.join("project.json"))?;
+    let project: Project = serde_json::from_value(project_value.clone())
+        .map_err(|e| format!("Failed to parse project: {}", e))?;
+
+    let chapter_titles_map = project_value
+        .get("chapterTitles")
+        .and_then(|v| v.as_object())
+        .cloned()
+        .unwrap_or_default();
+
+    // Determine chapters to export, maintaining project order; a full-book export skips excluded chapters
+    let ids_to_export: Vec<u32> = if chapter_ids.is_empty() {
+        full_book_chapter_ids(&project_value)
+    } else {
+        project.chapter_order.iter()
+            .filter(|id| chapter_ids.contains(id))
+            .copied()
+            .collect()
+    };
+
+    let mut used_slugs: HashSet<String> = HashSet::new();
+    let mut toc = String::new();
+    let mut body = String::new();
+
+    for (i, &id) in ids_to_export.iter().enumerate() {
+        let title = chapter_titles_map.get(&id.to_string()).and_then(|v| v.as_str());
+        let title = prefix_chapter_title(title_prefix, i as u32 + 1, id, title, format!("Chapter {}", id))?;
+        let content = read_chapter_content(&project_path_buf, id);
+
+        let slug = unique_slug(&title, &mut used_slugs);
+        toc.push_str(&format!("- [{}](#{})\n", escape_markdown(&title), slug));
+
+        body.push_str(&format!("<a id=\"{}\"></a>\n\n# {}\n\n", slug, escape_markdown(&title)));
+        if let Some(nodes) = content.as_ref().and_then(|d| d.get("content")).and_then(|c| c.as_array()) {
+            body.push_str(&render_markdown_blocks(nodes));
+        }
+    }
+
+    let mut markdown = String::new();
+    if include_toc.unwrap_or(false) {
+        markdown.push_str("## Table of Contents\n\n");
+        markdown.push_str(&toc);
+        markdown.push('\n');
+    }
+    markdown.push_str(body.trim_end());
+    markdown.push('\n');
+
+    let date = Local::now().format("%Y-%m-%d").to_string();
+    let filename = sanitize_filename(&match filename_template.as_deref().filter(|t| !t.trim().is_empty()) {
+        Some(template) => render_export_filename(template, &project, &date, ids_to_export.len(), "md")?,
+        None => format!("{}_{}.md", sanitize_filename_part(&project.title), date),
+    });
+    let export_path = PathBuf::from(&export_dir).join(filename);
+
+    fs::write(&export_path, markdown)
+        .map_err(|e| format!("Failed to write Markdown file: {}", e))?;
+
+    export_path.to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| "Failed to convert path to string".to_string())
 }
 
 // ============================================================
-// EPUB export
+// DOCX export
 // ============================================================
 
-fn escape_xml(s: &str) -> String {
-    s.replace('&', "&amp;")
-     .replace('<', "&lt;")
-     .replace('>', "&gt;")
-     .replace('"', "&quot;")
+const DOCX_W_NS: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+
+/// Page size in twentieths of a point (twips) for pageSettings.paperSize; Letter when unknown.
+fn docx_page_size(paper_size: &str) -> (u32, u32) {
+    match paper_size {
+        "a4" => (11906, 16838),
+        "trade" => (8640, 12960),
+        "digest" => (7920, 12240),
+        "pocket" => (7200, 11520),
+        _ => (12240, 15840),
+    }
 }
 
-/// Pseudo-UUID v4 from FNV hash of seed + current timestamp.
-fn generate_epub_uuid(seed: &str) -> String {
-    let ts = Local::now().timestamp_millis() as u64;
-    let mut h: u64 = 0xcbf29ce484222325;
-    for b in seed.bytes() {
-        h ^= b as u64;
-        h = h.wrapping_mul(0x100000001b3);
+/// Inches (as stored in pageSettings) to twips.
+fn inches_to_twips(inches: f64) -> u32 {
+    (inches.max(0.0) * 1440.0).round() as u32
+}
+
+/// `<w:rPr>` for a text node's marks, children in the order the schema requires.
+fn docx_run_properties(marks: &[serde_json::Value], superscript: bool) -> String {
+    let mark = |t: &str| marks.iter().find(|m| m.get("type").and_then(|v| v.as_str()) == Some(t));
+    let text_style = mark("textStyle").and_then(|m| m.get("attrs"));
+    let font = if mark("code").is_some() {
+        Some("Courier New")
+    } else {
+        text_style.and_then(|a| a.get("fontFamily")).and_then(|v| v.as_str()).filter(|f| !f.is_empty())
+    };
+
+    let mut props = String::new();
+    if let Some(font) = font {
+        props.push_str(&format!("<w:rFonts w:ascii=\"{0}\" w:hAnsi=\"{0}\" w:cs=\"{0}\"/>", escape_xml(font)));
     }
-    h ^= ts;
-    h = h.wrapping_mul(0x100000001b3);
-    let a = (h >> 32) as u32;
-    let b = ((h >> 16) & 0xffff) as u16;
-    let c = 0x4000u16 | ((h >> 4) & 0x0fff) as u16;
-    let d = 0x8000u16 | ((h >> 2) & 0x3fff) as u16;
-    let e = h.wrapping_mul(0x9e3779b97f4a7c15) & 0xffffffffffff;
-    format!("{:08x}-{:04x}-{:04x}-{:04x}-{:012x}", a, b, c, d, e)
+    if mark("bold").is_some() {
+        props.push_str("<w:b/>");
+    }
+    if mark("italic").is_some() {
+        props.push_str("<w:i/>");
+    }
+    if mark("strike").is_some() {
+        props.push_str("<w:strike/>");
+    }
+    if let Some(size) = text_style.and_then(|a| a.get("fontSize")).and_then(|v| v.as_f64()) {
+        props.push_str(&format!("<w:sz w:val=\"{}\"/>", (size * 2.0).round() as u32));
+    }
+    if mark("underline").is_some() || mark("link").is_some() {
+        props.push_str("<w:u w:val=\"single\"/>");
+    }
+    if superscript {
+        props.push_str("<w:vertAlign w:val=\"superscript\"/>");
+    }
+    if props.is_empty() {
+        return props;
+    }
+    format!("<w:rPr>{}</w:rPr>", props)
 }
 
-/// Render TipTap inline content (text nodes + hardBreak) to XHTML.
-fn render_inline(items: &[serde_json::Value]) -> String {
+fn docx_text_run(text: &str, props: &str) -> String {
+    format!("<w:r>{}<w:t xml:space=\"preserve\">{}</w:t></w:r>", props, escape_xml(text))
+}
+
+/// Runs for TipTap inline content (text, hardBreak, footnoteReference).
+fn docx_runs(items: &[serde_json::Value]) -> String {
     let mut out = String::new();
     for item in items {
         match item.get("type").and_then(|v| v.as_str()).unwrap_or("") {
-            "hardBreak" => out.push_str("<br/>"),
+            "hardBreak" => out.push_str("<w:r><w:br/></w:r>"),
+            "footnoteReference" => out.push_str(&docx_text_run(footnote_label(item), &docx_run_properties(&[], true))),
             "text" => {
                 let text = item.get("text").and_then(|v| v.as_str()).unwrap_or("");
-                let empty = vec![];
-                let marks = item.get("marks").and_then(|m| m.as_array()).unwrap_or(&empty);
-                // Open marks
-                for mark in marks.iter() {
-                    match mark.get("type").and_then(|v| v.as_str()).unwrap_or("") {
-                        "bold"   => out.push_str("<strong>"),
-                        "italic" => out.push_str("<em>"),
-                        "strike" => out.push_str("<s>"),
-                        "code"   => out.push_str("<code>"),
-                        "textStyle" => {
-                            let a = mark.get("attrs");
-                            let fs = a.and_then(|x| x.get("fontSize")).and_then(|v| v.as_f64());
-                            let ff = a.and_then(|x| x.get("fontFamily")).and_then(|v| v.as_str()).filter(|s| !s.is_empty());
-                            if fs.is_some() || ff.is_some() {
-                                let mut style = String::new();
-                                if let Some(sz) = fs  { style.push_str(&format!("font-size:{}pt;", sz)); }
-                                if let Some(fm) = ff  { style.push_str(&format!("font-family:{};", escape_xml(fm))); }
-                                out.push_str(&format!("<span style=\"{}\">", style));
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                out.push_str(&escape_xml(text));
-                // Close marks in reverse
-                for mark in marks.iter().rev() {
-                    match mark.get("type").and_then(|v| v.as_str()).unwrap_or("") {
-                        "bold"   => out.push_str("</strong>"),
-                        "italic" => out.push_str("</em>"),
-                        "strike" => out.push_str("</s>"),
-                        "code"   => out.push_str("</code>"),
-                        "textStyle" => {
-                            let a = mark.get("attrs");
-                            let fs = a.and_then(|x| x.get("fontSize")).and_then(|v| v.as_f64());
-                            let ff = a.and_then(|x| x.get("fontFamily")).and_then(|v| v.as_str()).filter(|s| !s.is_empty());
-                            if fs.is_some() || ff.is_some() { out.push_str("</span>"); }
-                        }
-                        _ => {}
-                    }
-                }
+                let marks = item.get("marks").and_then(|m| m.as_array()).map(|m| m.as_slice()).unwrap_or(&[]);
+                out.push_str(&docx_text_run(text, &docx_run_properties(marks, false)));
             }
             _ => {}
         }
@@ -1505,383 +8600,394 @@ fn render_inline(items: &[serde_json::Value]) -> String {
     out
 }
 
-/// Render TipTap block nodes to XHTML.
-fn render_blocks(nodes: &[serde_json::Value]) -> String {
-    let mut out = String::new();
-    for node in nodes {
-        let t = node.get("type").and_then(|v| v.as_str()).unwrap_or("");
-        let align = node.get("attrs")
-            .and_then(|a| a.get("textAlign"))
-            .and_then(|v| v.as_str())
-            .filter(|&a| a != "left");
-        let style = align.map(|a| format!(" style=\"text-align:{}\"", a)).unwrap_or_default();
+/// Paragraph-level settings for one `<w:p>`.
+#[derive(Default, Clone, Copy)]
+struct DocxParagraph<'a> {
+    style: Option<&'a str>,
+    page_break_before: bool,
+    // (numId, ilvl) for a numbered or bulleted list item
+    numbering: Option<(usize, usize)>,
+    align: Option<&'a str>,
+}
 
-        match t {
-            "paragraph" => {
-                let inner = node.get("content").and_then(|c| c.as_array())
-                    .map(|items| render_inline(items)).unwrap_or_default();
-                if inner.is_empty() {
-                    out.push_str(&format!("<p{}>&#160;</p>\n", style));
-                } else {
-                    out.push_str(&format!("<p{}>{}</p>\n", style, inner));
-                }
-            }
-            "heading" => {
-                let level = node.get("attrs").and_then(|a| a.get("level"))
-                    .and_then(|v| v.as_u64()).unwrap_or(2).clamp(2, 6);
-                let inner = node.get("content").and_then(|c| c.as_array())
-                    .map(|items| render_inline(items)).unwrap_or_default();
-                out.push_str(&format!("<h{}{}>{}</h{}>\n", level, style, inner, level));
+fn docx_paragraph(para: DocxParagraph, runs: &str) -> String {
+    let mut props = String::new();
+    if let Some(style) = para.style {
+        props.push_str(&format!("<w:pStyle w:val=\"{}\"/>", style));
+    }
+    if para.page_break_before {
+        props.push_str("<w:pageBreakBefore/>");
+    }
+    if let Some((num_id, level)) = para.numbering {
+        props.push_str(&format!("<w:numPr><w:ilvl w:val=\"{}\"/><w:numId w:val=\"{}\"/></w:numPr>", level, num_id));
+    }
+    if let Some(align) = para.align {
+        props.push_str(&format!("<w:jc w:val=\"{}\"/>", align));
+    }
+    if props.is_empty() {
+        format!("<w:p>{}</w:p>\n", runs)
+    } else {
+        format!("<w:p><w:pPr>{}</w:pPr>{}</w:p>\n", props, runs)
+    }
+}
+
+/// TipTap `textAlign` as a `w:jc` value; None for the default left alignment.
+fn docx_alignment(node: &serde_json::Value) -> Option<&'static str> {
+    match node.get("attrs").and_then(|a| a.get("textAlign")).and_then(|v| v.as_str()) {
+        Some("center") => Some("center"),
+        Some("right") => Some("right"),
+        Some("justify") => Some("both"),
+        _ => None,
+    }
+}
+
+/// Builds word/document.xml body content. Every ordered list gets its own numbering instance
+/// so its start number holds; they're written to word/numbering.xml afterwards.
+struct DocxWriter {
+    body: String,
+    // (start, level) for each ordered list, numbered from DOCX_FIRST_ORDERED_NUM_ID
+    ordered_lists: Vec<(u64, usize)>,
+    scene_break: String,
+}
+
+// numId 1 is the shared bullet numbering; ordered lists follow
+const DOCX_BULLET_NUM_ID: usize = 1;
+const DOCX_FIRST_ORDERED_NUM_ID: usize = 2;
+
+impl DocxWriter {
+    fn new(scene_break: Option<String>) -> Self {
+        DocxWriter {
+            body: String::new(),
+            ordered_lists: Vec::new(),
+            scene_break: scene_break.unwrap_or_else(|| "* * *".to_string()),
+        }
+    }
+
+    fn chapter(&mut self, title: &str, content: &Option<serde_json::Value>, epigraph: Option<&Epigraph>, first: bool) {
+        let heading = DocxParagraph { style: Some("Heading1"), page_break_before: !first, ..Default::default() };
+        self.body.push_str(&docx_paragraph(heading, &docx_text_run(title, "")));
+        if let Some(epigraph) = epigraph {
+            let runs: Vec<String> = epigraph.text.lines().map(|line| docx_text_run(line, "")).collect();
+            let quote = DocxParagraph { style: Some("Quote"), ..Default::default() };
+            self.body.push_str(&docx_paragraph(quote, &runs.join("<w:r><w:br/></w:r>")));
+            if let Some(attribution) = epigraph.attribution.as_deref() {
+                let para = DocxParagraph { style: Some("Quote"), align: Some("right"), ..Default::default() };
+                self.body.push_str(&docx_paragraph(para, &docx_text_run(&format!("\u{2014} {}", attribution), "<w:rPr><w:i w:val=\"0\"/></w:rPr>")));
             }
-            "blockquote" => {
-                out.push_str("<blockquote>\n");
-                if let Some(inner) = node.get("content").and_then(|c| c.as_array()) {
-                    out.push_str(&render_blocks(inner));
+        }
+
+        let nodes = content.as_ref()
+            .and_then(|doc| doc.get("content").and_then(|c| c.as_array()))
+            .map(|n| n.as_slice())
+            .unwrap_or(&[]);
+        self.blocks(nodes, None);
+        self.footnotes(nodes);
+    }
+
+    /// Block nodes; `style` is applied to plain paragraphs (e.g. "Quote" inside a blockquote).
+    fn blocks(&mut self, nodes: &[serde_json::Value], style: Option<&str>) {
+        for node in nodes {
+            let content = node.get("content").and_then(|c| c.as_array()).map(|c| c.as_slice()).unwrap_or(&[]);
+            match node.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+                "paragraph" => {
+                    let para = DocxParagraph { style, align: docx_alignment(node), ..Default::default() };
+                    self.body.push_str(&docx_paragraph(para, &docx_runs(content)));
                 }
-                out.push_str("</blockquote>\n");
-            }
-            "bulletList" | "orderedList" => {
-                let tag = if t == "bulletList" { "ul" } else { "ol" };
-                out.push_str(&format!("<{}>\n", tag));
-                if let Some(items) = node.get("content").and_then(|c| c.as_array()) {
-                    for item in items {
-                        out.push_str("<li>");
-                        if let Some(item_content) = item.get("content").and_then(|c| c.as_array()) {
-                            for para in item_content {
-                                if let Some(inline) = para.get("content").and_then(|c| c.as_array()) {
-                                    out.push_str(&render_inline(inline));
-                                }
-                            }
-                        }
-                        out.push_str("</li>\n");
-                    }
+                "heading" => {
+                    let level = node.get("attrs").and_then(|a| a.get("level"))
+                        .and_then(|v| v.as_u64()).unwrap_or(2).clamp(2, 6);
+                    let style = format!("Heading{}", level);
+                    let para = DocxParagraph { style: Some(&style), align: docx_alignment(node), ..Default::default() };
+                    self.body.push_str(&docx_paragraph(para, &docx_runs(content)));
                 }
-                out.push_str(&format!("</{}>\n", tag));
-            }
-            "horizontalRule" => out.push_str("<hr/>\n"),
-            "colorBleed" => {
-                let bg = node.get("attrs").and_then(|a| a.get("backgroundColor"))
-                    .and_then(|v| v.as_str()).unwrap_or("#000000");
-                let text = node.get("attrs").and_then(|a| a.get("textColor"))
-                    .and_then(|v| v.as_str()).unwrap_or("#ffffff");
-                out.push_str(&format!(
-                    "<div style=\"background-color:{};color:{};margin:0 -2em;padding:2em;\">\n",
-                    escape_xml(bg), escape_xml(text)
-                ));
-                if let Some(inner) = node.get("content").and_then(|c| c.as_array()) {
-                    out.push_str(&render_blocks(inner));
+                "blockquote" => self.blocks(content, Some("Quote")),
+                "bulletList" | "orderedList" => self.list(node, 0),
+                "codeBlock" => {
+                    let text = tiptap_to_plain_text(node);
+                    let props = docx_run_properties(&[serde_json::json!({ "type": "code" })], false);
+                    let runs: Vec<String> = text.trim_end_matches('\n').split('\n')
+                        .map(|line| docx_text_run(line, &props))
+                        .collect();
+                    let para = DocxParagraph { style: Some("Code"), ..Default::default() };
+                    self.body.push_str(&docx_paragraph(para, &runs.join("<w:r><w:br/></w:r>")));
                 }
-                out.push_str("</div>\n");
-            }
-            "imageBleed" => {
-                let name = node.get("attrs").and_then(|a| a.get("name"))
-                    .and_then(|v| v.as_str()).unwrap_or("");
-                let alt = node.get("attrs").and_then(|a| a.get("alt"))
-                    .and_then(|v| v.as_str()).unwrap_or("");
-                if !name.is_empty() {
-                    out.push_str(&format!(
-                        "<div class=\"image-bleed\"><img src=\"../images/{}\" alt=\"{}\"/></div>\n",
-                        escape_xml(name), escape_xml(alt)
-                    ));
+                "horizontalRule" => {
+                    let para = DocxParagraph { align: Some("center"), ..Default::default() };
+                    let glyph = self.scene_break.clone();
+                    self.body.push_str(&docx_paragraph(para, &docx_text_run(&glyph, "")));
                 }
+                "colorBleed" => self.blocks(content, style),
+                // Footnote bodies go at the end of the chapter; images aren't exported to DOCX
+                _ => {}
             }
-            _ => {}
         }
     }
-    out
-}
 
-/// Collect all imageBleed asset names from a chapter's TipTap JSON.
-fn collect_image_names(content: &Option<serde_json::Value>) -> Vec<String> {
-    let mut names = Vec::new();
-    if let Some(doc) = content {
-        if let Some(nodes) = doc.get("content").and_then(|c| c.as_array()) {
-            for node in nodes {
-                collect_image_names_from_node(node, &mut names);
+    fn list(&mut self, list: &serde_json::Value, level: usize) {
+        let num_id = if list.get("type").and_then(|v| v.as_str()) == Some("orderedList") {
+            let start = list.get("attrs").and_then(|a| a.get("start")).and_then(|v| v.as_u64()).unwrap_or(1);
+            self.ordered_lists.push((start, level));
+            DOCX_FIRST_ORDERED_NUM_ID + self.ordered_lists.len() - 1
+        } else {
+            DOCX_BULLET_NUM_ID
+        };
+        let items = list.get("content").and_then(|c| c.as_array()).map(|c| c.as_slice()).unwrap_or(&[]);
+        for item in items {
+            let children = item.get("content").and_then(|c| c.as_array()).map(|c| c.as_slice()).unwrap_or(&[]);
+            let mut numbered = false;
+            for child in children {
+                match child.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+                    "bulletList" | "orderedList" => self.list(child, (level + 1).min(8)),
+                    _ => {
+                        // Only an item's first paragraph carries the number; the rest are indented
+                        let inline = child.get("content").and_then(|c| c.as_array()).map(|c| c.as_slice()).unwrap_or(&[]);
+                        let para = if numbered {
+                            DocxParagraph { style: Some("ListParagraph"), ..Default::default() }
+                        } else {
+                            DocxParagraph { style: Some("ListParagraph"), numbering: Some((num_id, level)), ..Default::default() }
+                        };
+                        numbered = true;
+                        self.body.push_str(&docx_paragraph(para, &docx_runs(inline)));
+                    }
+                }
             }
         }
     }
-    names
-}
 
-fn collect_image_names_from_node(node: &serde_json::Value, names: &mut Vec<String>) {
-    if node.get("type").and_then(|v| v.as_str()) == Some("imageBleed") {
-        if let Some(name) = node.get("attrs")
-            .and_then(|a| a.get("name"))
-            .and_then(|v| v.as_str())
-        {
-            let s = name.to_string();
-            if !s.is_empty() && !names.contains(&s) {
-                names.push(s);
+    /// footnoteDefinition blocks as "label text" paragraphs after the chapter.
+    fn footnotes(&mut self, nodes: &[serde_json::Value]) {
+        for note in nodes.iter().filter(|n| n.get("type").and_then(|v| v.as_str()) == Some("footnoteDefinition")) {
+            let label_run = docx_text_run(footnote_label(note), &docx_run_properties(&[], true));
+            let blocks = note.get("content").and_then(|c| c.as_array()).map(|c| c.as_slice()).unwrap_or(&[]);
+            let first_inline = blocks.first()
+                .and_then(|b| b.get("content")).and_then(|c| c.as_array())
+                .map(|c| docx_runs(c))
+                .unwrap_or_default();
+            let para = DocxParagraph { style: Some("FootnoteText"), ..Default::default() };
+            self.body.push_str(&docx_paragraph(para, &format!("{}{}{}", label_run, docx_text_run(" ", ""), first_inline)));
+            if blocks.len() > 1 {
+                self.blocks(&blocks[1..], Some("FootnoteText"));
             }
         }
     }
-    if let Some(children) = node.get("content").and_then(|c| c.as_array()) {
-        for child in children {
-            collect_image_names_from_node(child, names);
+
+    fn numbering_xml(&self) -> String {
+        let levels = |format: &str| -> String {
+            (0..9).map(|level| {
+                let text = if format == "bullet" { "\u{2022}".to_string() } else { format!("%{}.", level + 1) };
+                format!(
+                    "<w:lvl w:ilvl=\"{lvl}\"><w:start w:val=\"1\"/><w:numFmt w:val=\"{fmt}\"/><w:lvlText w:val=\"{text}\"/>\
+                     <w:lvlJc w:val=\"left\"/><w:pPr><w:ind w:left=\"{left}\" w:hanging=\"360\"/></w:pPr></w:lvl>",
+                    lvl = level, fmt = format, text = text, left = 720 * (level + 1)
+                )
+            }).collect()
+        };
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:numbering xmlns:w=\"{}\">\n\
+             <w:abstractNum w:abstractNumId=\"0\">{}</w:abstractNum>\n\
+             <w:abstractNum w:abstractNumId=\"1\">{}</w:abstractNum>\n\
+             <w:num w:numId=\"{}\"><w:abstractNumId w:val=\"0\"/></w:num>\n",
+            DOCX_W_NS, levels("bullet"), levels("decimal"), DOCX_BULLET_NUM_ID
+        );
+        for (i, (start, level)) in self.ordered_lists.iter().enumerate() {
+            xml.push_str(&format!(
+                "<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"1\"/>\
+                 <w:lvlOverride w:ilvl=\"{}\"><w:startOverride w:val=\"{}\"/></w:lvlOverride></w:num>\n",
+                DOCX_FIRST_ORDERED_NUM_ID + i, level, start
+            ));
         }
+        xml.push_str("</w:numbering>\n");
+        xml
     }
 }
 
-fn chapter_to_xhtml(title: &str, content: &Option<serde_json::Value>) -> String {
-    let body = content.as_ref()
-        .and_then(|doc| doc.get("content").and_then(|c| c.as_array()))
-        .map(|nodes| render_blocks(nodes))
-        .unwrap_or_default();
-    format!(
-        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
-         <!DOCTYPE html>\n\
-         <html xmlns=\"http://www.w3.org/1999/xhtml\">\n\
-         <head>\n<title>{title}</title>\n\
-         <link rel=\"stylesheet\" type=\"text/css\" href=\"../style.css\"/>\n\
-         </head>\n<body>\n{body}</body>\n</html>\n",
-        title = escape_xml(title), body = body
-    )
-}
-
-fn build_opf(title: &str, author: &str, uuid: &str, modified: &str, n: usize, images: &[String]) -> String {
-    let author_el = if !author.is_empty() {
-        format!("    <dc:creator>{}</dc:creator>\n", escape_xml(author))
-    } else { String::new() };
-    let manifest: String = (0..n).map(|i| format!(
-        "    <item id=\"ch{i:03}\" href=\"chapters/ch{i:03}.xhtml\" media-type=\"application/xhtml+xml\"/>\n",
-        i = i + 1
-    )).collect();
-    let image_manifest: String = images.iter().map(|img| {
-        let ext = std::path::Path::new(img.as_str())
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("");
-        let mime = image_mime_for_ext(ext);
-        // Use a safe ID (replace non-alphanumeric with _)
-        let id: String = img.chars()
-            .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
-            .collect();
-        format!("    <item id=\"img-{id}\" href=\"images/{img}\" media-type=\"{mime}\"/>\n",
-            id = id, img = escape_xml(img), mime = mime)
-    }).collect();
-    let spine: String = (0..n).map(|i| format!(
-        "    <itemref idref=\"ch{:03}\"/>\n", i + 1
+/// word/styles.xml: manuscript defaults from pageSettings plus the heading, quote, code,
+/// list and footnote styles document.xml refers to.
+fn docx_styles_xml(project: &serde_json::Value) -> String {
+    let settings = project.get("pageSettings");
+    let setting = |key: &str| settings.and_then(|s| s.get(key)).and_then(|v| v.as_f64()).unwrap_or(0.0);
+    let font = project.get("fontFamily").and_then(|v| v.as_str())
+        .map(|f| f.split(',').next().unwrap_or("").trim().trim_matches(|c| c == '"' || c == '\''))
+        .filter(|f| !f.is_empty())
+        .unwrap_or("Times New Roman");
+    let justify = settings.and_then(|s| s.get("alignment")).and_then(|v| v.as_str()) == Some("justify");
+    let normal_ppr = format!(
+        "<w:spacing w:after=\"{}\"/><w:ind w:firstLine=\"{}\"/>{}",
+        (setting("paragraphSpacing") * 20.0).round().max(0.0) as u32,
+        inches_to_twips(setting("textIndent")),
+        if justify { "<w:jc w:val=\"both\"/>" } else { "" }
+    );
+
+    let heading_sizes = [(1, 32), (2, 28), (3, 26), (4, 24), (5, 24), (6, 22)];
+    let headings: String = heading_sizes.iter().map(|(level, size)| format!(
+        "<w:style w:type=\"paragraph\" w:styleId=\"Heading{l}\"><w:name w:val=\"heading {l}\"/><w:basedOn w:val=\"Normal\"/>\
+         <w:next w:val=\"Normal\"/><w:pPr><w:keepNext/><w:spacing w:before=\"240\" w:after=\"240\"/><w:ind w:firstLine=\"0\"/>{jc}\
+         <w:outlineLvl w:val=\"{o}\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"{s}\"/></w:rPr></w:style>\n",
+        l = level, o = level - 1, s = size, jc = if *level == 1 { "<w:jc w:val=\"center\"/>" } else { "" }
     )).collect();
+
     format!(
-        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
-         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\">\n\
-           <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
-             <dc:identifier id=\"book-id\">urn:uuid:{uuid}</dc:identifier>\n\
-             <dc:title>{title}</dc:title>\n\
-         {author_el}    <dc:language>en</dc:language>\n\
-             <meta property=\"dcterms:modified\">{modified}</meta>\n\
-           </metadata>\n\
-           <manifest>\n\
-             <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
-             <item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\"/>\n\
-             <item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n\
-         {manifest}{image_manifest}  </manifest>\n\
-           <spine toc=\"ncx\">\n\
-         {spine}  </spine>\n\
-         </package>",
-        uuid = uuid, title = escape_xml(title),
-        author_el = author_el, modified = modified,
-        manifest = manifest, image_manifest = image_manifest, spine = spine
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:styles xmlns:w=\"{ns}\">\n\
+         <w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii=\"{font}\" w:hAnsi=\"{font}\" w:cs=\"{font}\"/>\
+         <w:sz w:val=\"24\"/></w:rPr></w:rPrDefault></w:docDefaults>\n\
+         <w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\"><w:name w:val=\"Normal\"/><w:pPr>{normal}</w:pPr></w:style>\n\
+         {headings}\
+         <w:style w:type=\"paragraph\" w:styleId=\"Quote\"><w:name w:val=\"Quote\"/><w:basedOn w:val=\"Normal\"/>\
+         <w:pPr><w:ind w:left=\"720\" w:right=\"720\" w:firstLine=\"0\"/></w:pPr><w:rPr><w:i/></w:rPr></w:style>\n\
+         <w:style w:type=\"paragraph\" w:styleId=\"Code\"><w:name w:val=\"Code\"/><w:basedOn w:val=\"Normal\"/>\
+         <w:pPr><w:ind w:firstLine=\"0\"/></w:pPr><w:rPr><w:rFonts w:ascii=\"Courier New\" w:hAnsi=\"Courier New\" w:cs=\"Courier New\"/>\
+         <w:sz w:val=\"20\"/></w:rPr></w:style>\n\
+         <w:style w:type=\"paragraph\" w:styleId=\"ListParagraph\"><w:name w:val=\"List Paragraph\"/><w:basedOn w:val=\"Normal\"/>\
+         <w:pPr><w:ind w:left=\"720\" w:firstLine=\"0\"/></w:pPr></w:style>\n\
+         <w:style w:type=\"paragraph\" w:styleId=\"FootnoteText\"><w:name w:val=\"footnote text\"/><w:basedOn w:val=\"Normal\"/>\
+         <w:pPr><w:ind w:firstLine=\"0\"/></w:pPr><w:rPr><w:sz w:val=\"20\"/></w:rPr></w:style>\n\
+         </w:styles>\n",
+        ns = DOCX_W_NS, font = escape_xml(font), normal = normal_ppr, headings = headings
     )
 }
 
-fn build_nav(title: &str, chapter_titles: &[String]) -> String {
-    let items: String = chapter_titles.iter().enumerate().map(|(i, t)| format!(
-        "      <li><a href=\"chapters/ch{:03}.xhtml\">{}</a></li>\n", i + 1, escape_xml(t)
-    )).collect();
+/// word/document.xml around the rendered body, with page size and margins from pageSettings.
+fn docx_document_xml(body: &str, project: &serde_json::Value) -> String {
+    let settings = project.get("pageSettings");
+    let (width, height) = docx_page_size(settings.and_then(|s| s.get("paperSize")).and_then(|v| v.as_str()).unwrap_or("letter"));
+    let margin = |side: &str, default: f64| inches_to_twips(
+        settings.and_then(|s| s.get("margins")).and_then(|m| m.get(side)).and_then(|v| v.as_f64()).unwrap_or(default)
+    );
     format!(
-        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
-         <!DOCTYPE html>\n\
-         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n\
-         <head><title>{title}</title></head>\n\
-         <body>\n  <nav epub:type=\"toc\">\n    <h1>{title}</h1>\n    <ol>\n\
-         {items}    </ol>\n  </nav>\n</body>\n</html>",
-        title = escape_xml(title), items = items
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:document xmlns:w=\"{}\">\n<w:body>\n{}\
+         <w:sectPr><w:pgSz w:w=\"{}\" w:h=\"{}\"/><w:pgMar w:top=\"{}\" w:right=\"{}\" w:bottom=\"{}\" w:left=\"{}\" \
+         w:header=\"720\" w:footer=\"720\" w:gutter=\"0\"/></w:sectPr>\n</w:body>\n</w:document>\n",
+        DOCX_W_NS, body, width, height,
+        margin("top", 1.0), margin("right", 1.25), margin("bottom", 1.0), margin("left", 1.25)
     )
 }
 
-fn build_ncx(title: &str, uuid: &str, chapter_titles: &[String]) -> String {
-    let nav_points: String = chapter_titles.iter().enumerate().map(|(i, t)| format!(
-        "    <navPoint id=\"ch{i:03}\" playOrder=\"{ord}\">\n\
-           <navLabel><text>{title}</text></navLabel>\n\
-           <content src=\"chapters/ch{i:03}.xhtml\"/>\n\
-         </navPoint>\n",
-        i = i + 1, ord = i + 1, title = escape_xml(t)
-    )).collect();
+const DOCX_CONTENT_TYPES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\n\
+<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\n\
+<Default Extension=\"xml\" ContentType=\"application/xml\"/>\n\
+<Override PartName=\"/word/document.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>\n\
+<Override PartName=\"/word/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml\"/>\n\
+<Override PartName=\"/word/numbering.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml\"/>\n\
+<Override PartName=\"/docProps/core.xml\" ContentType=\"application/vnd.openxmlformats-package.core-properties+xml\"/>\n\
+</Types>\n";
+
+const DOCX_PACKAGE_RELS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\n\
+<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"word/document.xml\"/>\n\
+<Relationship Id=\"rId2\" Type=\"http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties\" Target=\"docProps/core.xml\"/>\n\
+</Relationships>\n";
+
+const DOCX_DOCUMENT_RELS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\n\
+<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles\" Target=\"styles.xml\"/>\n\
+<Relationship Id=\"rId2\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/numbering\" Target=\"numbering.xml\"/>\n\
+</Relationships>\n";
+
+fn docx_core_xml(title: &str, author: &str, modified: &str) -> String {
     format!(
-        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
-         <ncx xmlns=\"http://www.daisy.org/z3986/2005/ncx/\" version=\"2005-1\">\n\
-           <head>\n\
-             <meta name=\"dtb:uid\" content=\"urn:uuid:{uuid}\"/>\n\
-             <meta name=\"dtb:depth\" content=\"1\"/>\n\
-             <meta name=\"dtb:totalPageCount\" content=\"0\"/>\n\
-             <meta name=\"dtb:maxPageNumber\" content=\"0\"/>\n\
-           </head>\n\
-           <docTitle><text>{title}</text></docTitle>\n\
-           <navMap>\n{nav_points}  </navMap>\n\
-         </ncx>",
-        uuid = uuid, title = escape_xml(title), nav_points = nav_points
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <cp:coreProperties xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:dcterms=\"http://purl.org/dc/terms/\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n\
+         <dc:title>{}</dc:title>\n<dc:creator>{}</dc:creator>\n\
+         <dcterms:modified xsi:type=\"dcterms:W3CDTF\">{}</dcterms:modified>\n\
+         </cp:coreProperties>\n",
+        escape_xml(title), escape_xml(author), modified
     )
 }
 
-const EPUB_CSS: &str = "\
-body { font-family: serif; font-size: 1em; line-height: 1.6; margin: 0; padding: 0; }\n\
-p { margin: 0 0 1em; orphans: 2; widows: 2; }\n\
-h2 { font-size: 1.5em; font-weight: bold; margin: 1.5em 0 0.5em; page-break-after: avoid; }\n\
-h3 { font-size: 1.3em; font-weight: bold; margin: 1.5em 0 0.5em; page-break-after: avoid; }\n\
-h4 { font-size: 1.1em; font-weight: bold; margin: 1.5em 0 0.5em; page-break-after: avoid; }\n\
-h5 { font-size: 1em;   font-weight: bold; margin: 1.5em 0 0.5em; page-break-after: avoid; }\n\
-h6 { font-size: 0.9em; font-weight: bold; margin: 1.5em 0 0.5em; page-break-after: avoid; }\n\
-blockquote { margin: 1em 2em; font-style: italic; }\n\
-ul, ol { margin: 0 0 1em; padding-left: 2em; }\n\
-li { margin: 0.25em 0; }\n\
-hr { border: none; border-top: 1px solid #ccc; margin: 2em 0; }\n\
-strong { font-weight: bold; }\n\
-em { font-style: italic; }\n\
-s { text-decoration: line-through; }\n\
-code { font-family: monospace; font-size: 0.9em; }";
-
+/// Export chapters as a Word document: one Heading 1 per chapter title, each chapter starting
+/// on a new page, with page size, margins, indent and spacing from pageSettings. Images are
+/// left out.
 #[tauri::command]
-fn export_epub(
+fn export_docx(
+    handle: AppHandle,
     project_path: String,
     export_dir: String,
     chapter_ids: Vec<u32>,
+    filename_template: Option<String>,
+    modified_since: Option<String>,
+) -> Result<String, String> {
+    let chapter_ids = chapters_modified_since(&project_path, chapter_ids, modified_since.as_deref())?;
+    let template = filename_template.as_deref().filter(|t| !t.trim().is_empty());
+    write_docx_export(&project_path, &export_dir, chapter_ids, template, &mut |progress| {
+        emit_export_progress(&handle, progress)
+    })
+}
+
+fn write_docx_export(
+    project_path: &str,
+    export_dir: &str,
+    chapter_ids: Vec<u32>,
+    filename_template: Option<&str>,
+    on_progress: &mut dyn FnMut(ExportProgress),
 ) -> Result<String, String> {
     use zip::write::SimpleFileOptions;
     use zip::CompressionMethod;
 
-    let project_path_buf = PathBuf::from(&project_path);
-    let chapters_dir = project_path_buf.join("chapters");
-
-    // Load project metadata
-    let project_file = project_path_buf.join("project.json");
-    let project_content = fs::read_to_string(&project_file)
-        .map_err(|e| format!("Failed to read project.json: {}", e))?;
-    let project_value: serde_json::Value = serde_json::from_str(&project_content)
-        .map_err(|e| format!("Failed to parse project.json: {}", e))?;
+    let project_path_buf = PathBuf::from(project_path);
+    let project_value = read_project_value(&project_path_buf.join("project.json"))?;
     let project: Project = serde_json::from_value(project_value.clone())
         .map_err(|e| format!("Failed to parse project: {}", e))?;
 
-    // Load chapter titles map (stored separately from Project struct)
-    let chapter_titles_map = project_value
-        .get("chapterTitles")
-        .and_then(|v| v.as_object())
-        .cloned()
-        .unwrap_or_default();
-
-    // Determine chapters to export, maintaining project order
+    // Determine chapters to export, maintaining project order; a full-book export skips excluded chapters
     let ids_to_export: Vec<u32> = if chapter_ids.is_empty() {
-        project.chapter_order.clone()
+        full_book_chapter_ids(&project_value)
     } else {
         project.chapter_order.iter()
             .filter(|id| chapter_ids.contains(id))
             .copied()
-            .collect()
-    };
-
-    // Load chapter content and titles
-    let mut chapters: Vec<(String, Option<serde_json::Value>)> = Vec::new();
-    for &id in &ids_to_export {
-        let chapter_file = chapters_dir.join(format!("{}.json", id));
-        let content = if chapter_file.exists() {
-            let s = fs::read_to_string(&chapter_file)
-                .map_err(|e| format!("Failed to read chapter {}: {}", id, e))?;
-            serde_json::from_str(&s).ok()
-        } else {
-            None
-        };
-        let title = chapter_titles_map
-            .get(&id.to_string())
-            .and_then(|v| v.as_str())
-            .unwrap_or(&format!("Chapter {}", id))
-            .to_string();
-        chapters.push((title, content));
-    }
-
-    let uuid = generate_epub_uuid(&project.title);
-    let modified = Local::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
-    let date = Local::now().format("%Y-%m-%d").to_string();
-
-    let safe_title: String = project.title.chars()
-        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
-        .collect();
-    let filename = format!("{}_{}.epub", safe_title, date);
-    let export_path = PathBuf::from(&export_dir).join(&filename);
-
-    let file = fs::File::create(&export_path)
-        .map_err(|e| format!("Failed to create EPUB file: {}", e))?;
-    let mut zip = zip::ZipWriter::new(file);
-
-    let stored   = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
-    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
-
-    // mimetype — must be first entry, uncompressed
-    zip.start_file("mimetype", stored).map_err(|e| e.to_string())?;
-    zip.write_all(b"application/epub+zip").map_err(|e| e.to_string())?;
-
-    // META-INF/container.xml
-    zip.start_file("META-INF/container.xml", deflated).map_err(|e| e.to_string())?;
-    zip.write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
-        <container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n\
-          <rootfiles>\n\
-            <rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/>\n\
-          </rootfiles>\n\
-        </container>").map_err(|e| e.to_string())?;
-
-    // Collect all image filenames referenced by imageBleed nodes
-    let mut all_image_names: Vec<String> = Vec::new();
-    for (_, content) in &chapters {
-        for name in collect_image_names(content) {
-            if !all_image_names.contains(&name) {
-                all_image_names.push(name);
-            }
-        }
-    }
-
-    // OEBPS/style.css
-    zip.start_file("OEBPS/style.css", deflated).map_err(|e| e.to_string())?;
-    zip.write_all(EPUB_CSS.as_bytes()).map_err(|e| e.to_string())?;
-
-    // OEBPS/images/* — embed any referenced images
-    for img_name in &all_image_names {
-        let img_path = project_path_buf.join("assets").join(img_name);
-        if img_path.exists() {
-            let img_bytes = fs::read(&img_path)
-                .map_err(|e| format!("Failed to read image {}: {}", img_name, e))?;
-            zip.start_file(&format!("OEBPS/images/{}", img_name), deflated)
-                .map_err(|e| e.to_string())?;
-            zip.write_all(&img_bytes).map_err(|e| e.to_string())?;
-        }
-    }
+            .collect()
+    };
 
-    // OEBPS/chapters/chNNN.xhtml — one file per chapter
-    let chapter_titles: Vec<String> = chapters.iter().map(|(t, _)| t.clone()).collect();
-    for (i, (title, content)) in chapters.iter().enumerate() {
-        let fname = format!("OEBPS/chapters/ch{:03}.xhtml", i + 1);
-        zip.start_file(&fname, deflated).map_err(|e| e.to_string())?;
-        zip.write_all(chapter_to_xhtml(title, content).as_bytes()).map_err(|e| e.to_string())?;
+    let mut writer = DocxWriter::new(EpubLayout::from_styles(&project.styles).scene_break);
+    for (i, &id) in ids_to_export.iter().enumerate() {
+        let title = project_value
+            .get("chapterTitles")
+            .and_then(|t| t.get(id.to_string()))
+            .and_then(|v| v.as_str())
+            .filter(|t| !t.trim().is_empty())
+            .map(|t| t.to_string())
+            .unwrap_or_else(|| format!("Chapter {}", id));
+        let epigraph = chapter_epigraph(&project_value, id);
+        writer.chapter(&title, &read_chapter_content(&project_path_buf, id), epigraph.as_ref(), i == 0);
+        on_progress(chapter_progress(i + 1, ids_to_export.len()));
     }
 
-    // OEBPS/nav.xhtml (EPUB 3 navigation document)
-    zip.start_file("OEBPS/nav.xhtml", deflated).map_err(|e| e.to_string())?;
-    zip.write_all(build_nav(&project.title, &chapter_titles).as_bytes()).map_err(|e| e.to_string())?;
-
-    // OEBPS/toc.ncx (EPUB 2 compatibility)
-    zip.start_file("OEBPS/toc.ncx", deflated).map_err(|e| e.to_string())?;
-    zip.write_all(build_ncx(&project.title, &uuid, &chapter_titles).as_bytes()).map_err(|e| e.to_string())?;
+    let date = Local::now().format("%Y-%m-%d").to_string();
+    let filename = sanitize_filename(&match filename_template {
+        Some(template) => render_export_filename(template, &project, &date, ids_to_export.len(), "docx")?,
+        None => format!("{}_{}.docx", sanitize_filename_part(&project.title), date),
+    });
+    let export_path = PathBuf::from(export_dir).join(&filename);
 
-    // OEBPS/content.opf (package document)
-    zip.start_file("OEBPS/content.opf", deflated).map_err(|e| e.to_string())?;
-    zip.write_all(
-        build_opf(&project.title, &project.author, &uuid, &modified, chapters.len(), &all_image_names).as_bytes()
-    ).map_err(|e| e.to_string())?;
+    let file = fs::File::create(&export_path)
+        .map_err(|e| format!("Failed to create DOCX file: {}", e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
 
-    zip.finish().map_err(|e| format!("Failed to finalize EPUB: {}", e))?;
+    let modified = Local::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let parts = [
+        ("[Content_Types].xml", DOCX_CONTENT_TYPES.to_string()),
+        ("_rels/.rels", DOCX_PACKAGE_RELS.to_string()),
+        ("docProps/core.xml", docx_core_xml(&project.title, &project.author, &modified)),
+        ("word/_rels/document.xml.rels", DOCX_DOCUMENT_RELS.to_string()),
+        ("word/styles.xml", docx_styles_xml(&project_value)),
+        ("word/numbering.xml", writer.numbering_xml()),
+        ("word/document.xml", docx_document_xml(&writer.body, &project_value)),
+    ];
+    for (name, xml) in parts {
+        zip.start_file(name, deflated).map_err(|e| e.to_string())?;
+        zip.write_all(xml.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| format!("Failed to finalize DOCX: {}", e))?;
 
-    export_path.to_str()
+    let output_path = export_path.to_str()
         .map(|s| s.to_string())
-        .ok_or_else(|| "Failed to convert path to string".to_string())
+        .ok_or_else(|| "Failed to convert path to string".to_string())?;
+    on_progress(complete_progress(ids_to_export.len(), &output_path));
+    Ok(output_path)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -1890,6 +8996,13 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(JobRegistry::default())
+        .setup(|app| {
+            if let Ok(dirs) = AppDirs::of(app.handle()) {
+                load_json_indent(&dirs);
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             read_config,
             write_config,
@@ -1898,6 +9011,7 @@ pub fn run() {
             save_chapter,
             save_project,
             export_project,
+            export_manuscript,
             get_default_export_dir,
             update_export_dir,
             import_chapters,
@@ -1906,10 +9020,2319 @@ pub fn run() {
             rename_chapter,
             add_to_dictionary,
             get_dictionary_words,
+            export_dictionary,
+            import_dictionary,
+            spelling_suggestions,
             delete_chapter,
             export_epub,
+            export_sample_epub,
             copy_asset_and_encode,
+            set_word_goal,
+            word_goal_progress,
+            set_chapter_target,
+            chapter_targets_progress,
+            mark_word_baseline,
+            words_since_baseline,
+            page_estimate,
+            find_duplicate_chapters,
+            diff_chapters,
+            find_markers,
+            export_markdown,
+            export_docx,
+            cancel_job,
+            style_report,
+            project_stats,
+            readability,
+            typography_report,
+            normalize_typography,
+            flatten_chapter,
+            retarget_font,
+            outline,
+            render_chapter_html,
+            render_book_html,
+            extract_links,
+            used_fonts,
+            check_missing_assets,
+            verify_assets,
+            generate_text_cover,
+            estimate_export_size,
+            clone_project,
+            export_bundle,
+            import_bundle,
+            verify_project,
+            check_chapter_files,
+            rebuild_chapter_order,
+            scan_projects,
+            export_chapter,
+            get_theme,
+            set_theme,
+            get_default_author,
+            set_default_author,
+            get_json_indent,
+            set_json_indent,
+            save_window_state,
+            read_window_state,
+            get_autosave_interval,
+            set_autosave_interval,
+            prune_recent_projects,
+            save_export_preset,
+            list_export_presets,
+            export_with_preset,
+            export_book,
+            set_chapter_excluded,
+            set_last_chapter,
+            get_chapter_modified,
+            import_text,
+            validate_import_file,
+            bulk_rename_chapters,
+            get_chapter_epigraph,
+            set_chapter_epigraph,
+            trash_chapter,
+            restore_chapter,
+            empty_trash,
+            renumber_chapters,
+            get_chapter_json,
+            set_chapter_json,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn chapter_class_on_body() {
+        let doc = Some(json!({"type":"doc","attrs":{"className":"prologue"},"content":[{"type":"paragraph","content":[{"type":"text","text":"Hi"}]}]}));
+        let x = chapter_to_xhtml("P", &doc, None, &EpubLayout::default());
+        assert!(x.contains("<body class=\"prologue\">"), "{}", x);
+        let css = build_epub_css(&Some(json!({"chapterClasses":{"prologue":{"italic":true,"fontSize":11}}})));
+        assert!(css.contains("body.prologue { font-size: 11pt; font-style: italic; }"), "{}", css);
+        assert!(chapter_to_xhtml("P", &None, None, &EpubLayout::default()).contains("<body>\n"));
+    }
+
+    // Empty project in a fresh temp dir
+    fn tmp_project(name: &str) -> PathBuf {
+        let p = std::env::temp_dir().join(format!("scout-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&p);
+        fs::create_dir_all(p.join("chapters")).unwrap();
+        write_project_value(&p.join("project.json"), &json!({"title": "Test Book", "author": "", "chapterOrder": []})).unwrap();
+        p
+    }
+
+    fn tmp_app_dirs(name: &str) -> AppDirs {
+        let base = std::env::temp_dir().join(format!("scout-test-app-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        AppDirs { config: base.join("config"), data: base.join("data") }
+    }
+
+    fn write_chapter(p: &Path, id: u32, text: &str) {
+        let doc = text_to_tiptap_json(text, &ImportOptions::default());
+        save_chapter(p.to_string_lossy().to_string(), id, doc.to_string()).unwrap();
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        let order = v["chapterOrder"].as_array_mut().unwrap();
+        if !order.iter().any(|x| x.as_u64() == Some(id as u64)) { order.push(json!(id)); }
+        write_project_value(&pf, &v).unwrap();
+    }
+
+    fn words(n: usize) -> String { vec!["word"; n].join(" ") }
+
+    fn ps(p: &Path) -> String { p.to_string_lossy().to_string() }
+
+    #[test]
+    fn word_goal_progress_percent() {
+        let p = tmp_project("337");
+        write_chapter(&p, 1, &words(1000));
+        write_chapter(&p, 2, &format!("{}\n\n{}", words(200), words(300)));
+        assert!(set_word_goal(ps(&p), 0).is_err());
+        assert!(set_word_goal(ps(&p), -5).is_err());
+        assert!(word_goal_progress(ps(&p)).is_err());
+        set_word_goal(ps(&p), 50000).unwrap();
+        let g = word_goal_progress(ps(&p)).unwrap();
+        assert_eq!(g.current_words, 1500);
+        assert_eq!(g.percent, 3.0);
+        assert_eq!(g.remaining, 48500);
+    }
+
+    #[test]
+    fn baseline_word_delta() {
+        let p = tmp_project("338");
+        write_chapter(&p, 1, &words(100));
+        assert!(words_since_baseline(ps(&p)).is_err());
+        assert_eq!(mark_word_baseline(ps(&p)).unwrap(), 100);
+        write_chapter(&p, 1, &words(160));
+        assert_eq!(words_since_baseline(ps(&p)).unwrap().delta, 60);
+        write_chapter(&p, 1, &words(40));
+        assert_eq!(words_since_baseline(ps(&p)).unwrap().delta, -60);
+        mark_word_baseline(ps(&p)).unwrap();
+        let v = read_project_value(&p.join("project.json")).unwrap();
+        assert_eq!(v["wordHistory"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn page_estimate_per_chapter() {
+        let p = tmp_project("339");
+        write_chapter(&p, 2, &words(500));
+        write_chapter(&p, 1, &words(251));
+        let e = page_estimate(ps(&p), None).unwrap();
+        assert_eq!(e.chapters[0].id, 2);
+        assert_eq!(e.chapters[0].pages, 2);
+        assert_eq!(e.chapters[1].pages, 2);
+        assert_eq!(e.total_pages, 4);
+        assert!(page_estimate(ps(&p), Some(0)).is_err());
+    }
+
+    #[test]
+    fn duplicate_chapters() {
+        let p = tmp_project("340");
+        write_chapter(&p, 1, "The quick brown fox jumps over the lazy dog.");
+        write_chapter(&p, 2, "The quick brown fox jumps over the lazy dog!");
+        write_chapter(&p, 3, "Completely different prose about ships and storms.");
+        let d = find_duplicate_chapters(ps(&p), 0.5).unwrap();
+        assert_eq!(d.len(), 1);
+        assert!((d[0].similarity - 1.0).abs() < 1e-9);
+        assert_eq!((d[0].id_a, d[0].id_b), (1, 2));
+        assert!(find_duplicate_chapters(ps(&p), 1.5).is_err());
+    }
+
+    fn zip_entry(path: &str, name: &str) -> String {
+        use std::io::Read;
+        let mut z = zip::ZipArchive::new(fs::File::open(path).unwrap()).unwrap();
+        let mut e = z.by_name(name).unwrap();
+        let mut s = String::new();
+        e.read_to_string(&mut s).unwrap();
+        s
+    }
+
+    #[test]
+    fn renumbered_chapter_titles() {
+        let p = tmp_project("341");
+        for id in [5, 7, 9] { write_chapter(&p, id, "text"); }
+        let out = out_dir("341");
+        let f = write_rtf_export(&ps(&p), &ps(&out), vec![7, 9], true, None, &RtfHeadingStyle::default(), RtfLineSpacing::default(), &mut |_| {}).unwrap();
+        let rtf = fs::read_to_string(&f).unwrap();
+        assert!(rtf.contains("Chapter 1\\b0") && rtf.contains("Chapter 2\\b0"), "{}", rtf);
+        assert!(!rtf.contains("Chapter 7"));
+        let f = write_rtf_export(&ps(&p), &ps(&out), vec![7, 9], false, None, &RtfHeadingStyle::default(), RtfLineSpacing::default(), &mut |_| {}).unwrap();
+        assert!(fs::read_to_string(&f).unwrap().contains("Chapter 7"));
+        write_epub_export(&ps(&p), &ps(&out), vec![7, 9], &EpubExportOptions { renumber: true, ..Default::default() }, &mut |_| {}).unwrap();
+
+        // Stored titles that lead with their chapter id are renumbered too
+        assert_eq!(renumber_title("Chapter 7: Storm", 7, 1), "Chapter 1: Storm");
+        assert_eq!(renumber_title("7. Storm", 7, 1), "1. Storm");
+        assert_eq!(renumber_title("1984", 7, 1), "1984");
+        assert_eq!(renumber_title("Chapter 75", 7, 1), "Chapter 75");
+    }
+
+    #[test]
+    fn epub_nav_document() {
+        let p = tmp_project("341b");
+        for id in [5, 7, 9] { write_chapter(&p, id, "text"); }
+        let out = out_dir("341b");
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![7, 9], &EpubExportOptions { renumber: true, ..Default::default() }, &mut |_| {}).unwrap();
+        let nav = zip_entry(&f, "OEBPS/nav.xhtml");
+        assert!(nav.contains(">Chapter 1<") && nav.contains(">Chapter 2<"), "{}", nav);
+        rename_chapter(ps(&p), 9, "Chapter 9: The End".into()).unwrap();
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![7, 9], &EpubExportOptions { renumber: true, ..Default::default() }, &mut |_| {}).unwrap();
+        let nav = zip_entry(&f, "OEBPS/nav.xhtml");
+        assert!(nav.contains(">Chapter 2: The End<") && !nav.contains("Chapter 9"), "{}", nav);
+    }
+
+    #[test]
+    fn markdown_toc() {
+        let p = tmp_project("342");
+        for id in [1, 2, 3] { write_chapter(&p, id, "Some *text* here\n\nSecond para"); }
+        for id in [1, 2] { rename_chapter(ps(&p), id, "The Start".into()).unwrap(); }
+        rename_chapter(ps(&p), 3, "The End!".into()).unwrap();
+        let out = out_dir("342");
+        let f = export_markdown(ps(&p), ps(&out), vec![], Some(true), None, None, None).unwrap();
+        let md = fs::read_to_string(&f).unwrap();
+        for slug in ["the-start", "the-start-1", "the-end"] {
+            assert!(md.contains(&format!("](#{})", slug)));
+            assert!(md.contains(&format!("<a id=\"{}\"></a>", slug)));
+        }
+        assert!(md.contains("Some \\*text\\* here"));
+        // round trip list/blockquote rendering
+        let doc = markdown_to_tiptap_json("> quoted **bold**\n\n1. one\n2. two\n\n- a\n- b\n", false);
+        let r = render_markdown_blocks(doc["content"].as_array().unwrap());
+        assert!(r.contains("> quoted **bold**") && r.contains("1. one\n2. two") && r.contains("- a\n- b"), "{}", r);
+    }
+
+    #[test]
+    fn rtf_blockquote() {
+        let doc = markdown_to_tiptap_json("> A **bold** word\n>\n> second para\n", false);
+        let rtf = json_to_rtf_content(&Some(doc), &RtfHeadingStyle::default(), &RtfBodyFormat::default());
+        assert!(rtf.contains("{\\pard \\li720 A \\b0\\i0 \\b bold\\b0\\i0  word\\b0\\i0 \\par}"));
+        assert_eq!(rtf.matches("\\li720").count(), 2);
+    }
+
+    #[test]
+    fn markdown_nested_blockquote() {
+        let doc = markdown_to_tiptap_json("> outer\n>\n> > inner\n>\n> after\n\nplain\n", false);
+        let outer = &doc["content"][0];
+        assert_eq!(outer["type"], "blockquote");
+        assert_eq!(outer["content"][0]["type"], "paragraph");
+        assert_eq!(outer["content"][1]["type"], "blockquote");
+        assert_eq!(outer["content"][1]["content"][0]["content"][0]["text"], "inner");
+        assert_eq!(outer["content"][2]["content"][0]["text"], "after");
+        assert_eq!(doc["content"][1]["type"], "paragraph");
+        let x = render_blocks(doc["content"].as_array().unwrap(), &EpubLayout::default());
+        assert!(x.contains("<blockquote>\n<p>outer</p>\n<blockquote>"));
+
+        // Lists inside a quote stay inside it, with each item's text in the item
+        let doc = markdown_to_tiptap_json("> intro\n>\n> - one\n> - two\n>\n>   more two\n>\n> outro\n\nafter\n", false);
+        let quote = &doc["content"][0];
+        assert_eq!(doc["content"].as_array().unwrap().len(), 2);
+        assert_eq!(quote["content"][0]["content"][0]["text"], "intro");
+        let list = &quote["content"][1];
+        assert_eq!(list["type"], "bulletList");
+        assert_eq!(list["content"][0]["content"][0]["content"][0]["text"], "one");
+        assert_eq!(list["content"][1]["content"][0]["content"][0]["text"], "two");
+        assert_eq!(list["content"][1]["content"][1]["content"][0]["text"], "more two");
+        assert_eq!(quote["content"][2]["content"][0]["text"], "outro");
+        assert_eq!(doc["content"][1]["content"][0]["text"], "after");
+
+        // A quote under a tight list item keeps the item's own text out of the quote
+        let doc = markdown_to_tiptap_json("- item\n  > quote\n- next\n", false);
+        let item = &doc["content"][0]["content"][0];
+        assert_eq!(item["content"][0]["type"], "paragraph");
+        assert_eq!(item["content"][0]["content"][0]["text"], "item");
+        assert_eq!(item["content"][1]["type"], "blockquote");
+        assert_eq!(item["content"][1]["content"][0]["content"][0]["text"], "quote");
+        assert_eq!(item["content"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn markdown_list_item_paragraphs() {
+        let doc = markdown_to_tiptap_json("- first para\n\n  second para\n- next item\n", false);
+        let item = &doc["content"][0]["content"][0];
+        assert_eq!(item["content"].as_array().unwrap().len(), 2);
+        assert_eq!(item["content"][1]["content"][0]["text"], "second para");
+        let doc = markdown_to_tiptap_json("- a\n- b\n", false);
+        assert_eq!(doc["content"][0]["content"][1]["content"][0]["content"][0]["text"], "b");
+        let doc = markdown_to_tiptap_json("- a\n  - b\n- c\n", false);
+        assert_eq!(doc["content"].as_array().unwrap().len(), 1);
+        let items = doc["content"][0]["content"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["content"][0]["content"][0]["text"], "a");
+        assert_eq!(items[0]["content"][1]["type"], "bulletList");
+        assert_eq!(items[0]["content"][1]["content"][0]["content"][0]["content"][0]["text"], "b");
+        assert_eq!(items[1]["content"][0]["content"][0]["text"], "c");
+        let doc = markdown_to_tiptap_json("- a\n\n  > q\n", false);
+        assert_eq!(doc["content"].as_array().unwrap().len(), 1);
+        assert_eq!(doc["content"][0]["content"][0]["content"][1]["type"], "blockquote");
+    }
+
+    fn out_dir(tag: &str) -> PathBuf {
+        let out = std::env::temp_dir().join(format!("scout-test-{}-out-{}", tag, std::process::id()));
+        let _ = fs::remove_dir_all(&out);
+        fs::create_dir_all(&out).unwrap();
+        out
+    }
+
+    #[test]
+    fn export_progress_events() {
+        let p = tmp_project("346");
+        for id in [1, 2, 3, 4] { write_chapter(&p, id, "text"); }
+        let out = out_dir("346");
+        let mut events = Vec::new();
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &EpubExportOptions::default(), &mut |e| events.push(e)).unwrap();
+        assert_eq!(events.iter().filter(|e| e.stage == "chapters").count(), 4);
+        assert_eq!(events.last().unwrap().output_path.as_deref(), Some(f.as_str()));
+        let mut events = Vec::new();
+        write_rtf_export(&ps(&p), &ps(&out), vec![2, 3], false, None, &RtfHeadingStyle::default(), RtfLineSpacing::default(), &mut |e| events.push(e)).unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[1].done, 2);
+    }
+
+    #[test]
+    fn cancel_import_job() {
+        let p = tmp_project("347");
+        let src = out_dir("347src");
+        let files: Vec<String> = (1..=3).map(|i| { let f = src.join(format!("f{}.txt", i)); fs::write(&f, "hello").unwrap(); ps(&f) }).collect();
+        let options = ImportOptions { use_filename_as_title: true, extract_title_from_delimiter: true, ..Default::default() };
+        let flag = AtomicBool::new(true);
+        assert!(import_chapter_files(&ps(&p), files.clone(), &options, Some(&flag)).unwrap().is_empty());
+
+        // Cancelled through the registry while the second of five sources is being read
+        let reg = JobRegistry::default();
+        let cancel = reg.register("job");
+        let sources = (1..=5).map(|i| {
+            if i == 2 {
+                assert!(reg.cancel("job"));
+            }
+            Ok(ImportSource { title: Some(format!("S{}", i)), content: "hello".into(), markdown: false })
+        });
+        let r = import_sources(&ps(&p), sources, &options, Some(&cancel)).unwrap();
+        reg.finish("job");
+        assert!(!reg.cancel("job"));
+        assert_eq!(r.len(), 1);
+
+        // project.json lists exactly the chapters that were written
+        let v = read_project_value(&p.join("project.json")).unwrap();
+        let mut written: Vec<u32> = fs::read_dir(p.join("chapters")).unwrap()
+            .filter_map(|e| e.unwrap().file_name().to_str()?.strip_suffix(".json")?.parse().ok())
+            .collect();
+        written.sort();
+        assert_eq!(chapter_order_of(&v), written);
+        assert_eq!(written.len(), 1);
+    }
+
+    fn link_of(node: &serde_json::Value) -> Option<String> {
+        node["marks"].as_array()?.iter().find(|m| m["type"] == "link").map(|m| m["attrs"]["href"].as_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn markdown_links_and_autolinks() {
+        let doc = markdown_to_tiptap_json("See https://example.com/a_(b). Or www.x.org, and <https://auto.link> and [t](http://e.com \"x\") \"q\" -- done", false);
+        let para = doc["content"][0]["content"].as_array().unwrap();
+        assert_eq!(para[1]["text"], "https://example.com/a_(b)");
+        assert_eq!(link_of(&para[1]).as_deref(), Some("https://example.com/a_(b)"));
+        assert_eq!(link_of(&para[3]).as_deref(), Some("http://www.x.org"));
+        assert!(para.iter().any(|n| link_of(n).as_deref() == Some("https://auto.link")));
+        assert!(para.iter().any(|n| n["text"] == "t" && link_of(n).as_deref() == Some("http://e.com")));
+        assert!(para.last().unwrap()["text"].as_str().unwrap().contains("\"q\" -- done"));
+        let doc = markdown_to_tiptap_json("\"q\" -- done", true);
+        assert!(doc["content"][0]["content"][0]["text"].as_str().unwrap().contains('\u{201c}'));
+        let x = render_inline(para);
+        assert!(x.contains("<a href=\"https://auto.link\">https://auto.link</a>"));
+        assert!(split_bare_urls("nohttp://x and http:// alone").iter().all(|(_, h)| h.is_none()));
+
+        // Link text that wraps onto a second line stays a single linked run
+        let doc = markdown_to_tiptap_json("See [foo\nbar](http://e.com) and *one\ntwo* end", false);
+        let para = doc["content"][0]["content"].as_array().unwrap();
+        assert_eq!(para[1]["text"], "foo bar");
+        assert_eq!(link_of(&para[1]).as_deref(), Some("http://e.com"));
+        assert_eq!(para[2]["text"], " and ");
+        assert_eq!(para[3]["text"], "one two");
+        assert_eq!(para.len(), 5);
+    }
+
+    #[test]
+    fn style_report_counts() {
+        let p = tmp_project("349");
+        write_chapter(&p, 1, "The raven flew. The raven quickly turned; the raven sang softly. Only a raven.");
+        let doc = json!({"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"tree tree"}]},{"type":"codeBlock","content":[{"type":"text","text":"foo foo foo foo foo"}]}]});
+        save_chapter(ps(&p), 2, doc.to_string()).unwrap();
+        let pf = p.join("project.json"); let mut v = read_project_value(&pf).unwrap(); v["chapterOrder"].as_array_mut().unwrap().push(json!(2)); write_project_value(&pf, &v).unwrap();
+        let r = style_report(ps(&p), vec![], Some(3), None).unwrap();
+        assert_eq!(r.top_words[0].word, "raven");
+        assert_eq!(r.top_words[0].count, 4);
+        assert_eq!(r.chapters[0].adverbs, 2);
+        assert!(r.top_words.iter().all(|w| w.word != "foo"));
+        assert_eq!(r.chapters[1].words, 2);
+        let r = style_report(ps(&p), vec![2], None, None).unwrap();
+        assert_eq!(r.chapters.len(), 1);
+    }
+
+    #[test]
+    fn project_stats_totals() {
+        let p = tmp_project("350");
+        write_chapter(&p, 1, "Mr. Smith arrived. Was it late?\n\nIt was \"very\" late!");
+        let st = project_stats(ps(&p)).unwrap();
+        assert_eq!(st.chapters[0].stats.sentences, 3);
+        assert_eq!(st.chapters[0].stats.paragraphs, 2);
+        assert_eq!(st.total.words, 10);
+        assert_eq!(count_sentences("Wait... what? J. R. R. Tolkien wrote it... Then left. No end"), 4);
+    }
+
+    #[test]
+    fn readability_report() {
+        let p = tmp_project("351");
+        // 8 words, 2 sentences, syllables: the(1) cat(1) sat(1) on(1) the(1) mat(1) it(1) purred(1) = 8
+        write_chapter(&p, 1, "The cat sat on the mat. It purred.");
+        let r = readability(ps(&p), vec![]).unwrap();
+        let s = &r.chapters[0].score;
+        assert_eq!((s.words, s.sentences, s.syllables), (8, 2, 8));
+        // 206.835 - 1.015*4 - 84.6*1 = 118.175
+        assert!((s.reading_ease.unwrap() - 118.2).abs() < 0.11);
+        assert!((s.grade_level.unwrap() - (-2.23)).abs() < 0.11);
+        assert_eq!(count_syllables("table"), 2);
+        assert_eq!(count_syllables("make"), 1);
+        assert_eq!(count_syllables("readability"), 5);
+        assert_eq!(count_syllables("free"), 1);
+        assert_eq!(count_syllables("wanted"), 2);
+        assert_eq!(count_syllables("jumped"), 1);
+    }
+
+    #[test]
+    fn clone_project_copies_chapters() {
+        let p = tmp_project("352");
+        write_chapter(&p, 1, "one"); write_chapter(&p, 2, "two");
+        fs::create_dir_all(p.join("assets")).unwrap(); fs::write(p.join("assets/pic.png"), b"x").unwrap();
+        fs::write(p.join("custom_dictionary.json"), "{\"words\":[\"zyx\"]}").unwrap();
+        fs::create_dir_all(p.join(".backups")).unwrap(); fs::write(p.join(".backups/old.json"), "{}").unwrap();
+        let dest = out_dir("352dest").join("clone");
+        let r = clone_project(ps(&p), ps(&dest), "Second Draft".into()).unwrap();
+        assert_eq!(r.project.title, "Second Draft");
+        assert!(dest.join("chapters/1.json").exists() && dest.join("chapters/2.json").exists());
+        assert!(dest.join("assets/pic.png").exists() && dest.join("custom_dictionary.json").exists());
+        assert!(!dest.join(".backups").exists());
+        assert!(clone_project(ps(&p), ps(&dest), "Again".into()).is_err());
+
+        // Destinations inside the source are refused however the path is spelled
+        let sneaky = p.join("nope").join("..").join("chapters").join("copy");
+        assert!(clone_project(ps(&p), ps(&sneaky), "Inside".into()).unwrap_err().contains("inside the source"));
+        #[cfg(unix)]
+        {
+            let link = out_dir("352link").join("src");
+            std::os::unix::fs::symlink(&p, &link).unwrap();
+            assert!(clone_project(ps(&p), ps(&link.join("copy")), "Inside".into()).unwrap_err().contains("inside the source"));
+        }
+        assert!(!p.join("chapters/copy").exists());
+    }
+
+    #[test]
+    fn project_bundle_round_trip() {
+        let p = tmp_project("353");
+        write_chapter(&p, 1, "one"); write_chapter(&p, 2, "two *x*");
+        fs::create_dir_all(p.join("assets")).unwrap(); fs::write(p.join("assets/pic.png"), b"x").unwrap();
+        let out = out_dir("353");
+        let z = export_bundle(ps(&p), ps(&out.join("b.scout"))).unwrap();
+        let dest = out.join("restored");
+        let r = import_bundle(z.clone(), ps(&dest)).unwrap();
+        assert_eq!(r.chapters.len(), 2);
+        for id in [1, 2] {
+            assert_eq!(fs::read_to_string(p.join(format!("chapters/{}.json", id))).unwrap(), fs::read_to_string(dest.join(format!("chapters/{}.json", id))).unwrap());
+        }
+        assert!(dest.join("assets/pic.png").exists());
+        assert!(import_bundle(z, ps(&dest)).is_err());
+        // zip-slip
+        let evil = out.join("evil.scout");
+        {
+            let mut zw = zip::ZipWriter::new(fs::File::create(&evil).unwrap());
+            let o = zip::write::SimpleFileOptions::default();
+            zw.start_file("project.json", o).unwrap(); zw.write_all(b"{\"title\":\"x\",\"author\":\"\",\"chapterOrder\":[]}").unwrap();
+            zw.start_file("../escape.txt", o).unwrap(); zw.write_all(b"bad").unwrap();
+            zw.finish().unwrap();
+        }
+        assert!(import_bundle(ps(&evil), ps(&out.join("evil_dest"))).is_err());
+        assert!(!out.join("escape.txt").exists());
+        assert!(!out.join("evil_dest").exists());
+    }
+
+    #[test]
+    fn prune_missing_chapters_on_delete() {
+        let p = tmp_project("354");
+        write_chapter(&p, 1, "one"); write_chapter(&p, 2, "two");
+        let pf = p.join("project.json"); let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"].as_array_mut().unwrap().push(json!(9));
+        v["chapterTitles"] = json!({"9": "Ghost", "1": "One"});
+        write_project_value(&pf, &v).unwrap();
+        let issues = verify_project(ps(&p)).unwrap();
+        assert_eq!(issues.missing_chapter_ids, vec![9]);
+        fs::write(p.join("chapters/5.json"), "{}").unwrap();
+        assert_eq!(verify_project(ps(&p)).unwrap().unlisted_chapter_ids, vec![5]);
+        let stale = delete_chapter(ps(&p), 2).unwrap();
+        assert_eq!(stale, vec![9]);
+        let v = read_project_value(&pf).unwrap();
+        assert_eq!(chapter_order_of(&v), vec![1]);
+        assert!(v["chapterTitles"].get("9").is_none());
+    }
+
+    #[test]
+    fn chapter_title_template() {
+        let p = tmp_project("355");
+        let f = p.join("in.txt");
+        fs::write(&f, "##\nfirst\n##\nsecond\n##\nthird\n").unwrap();
+        let opts = ImportOptions { chapter_delimiter: Some("##".into()), extract_title_from_delimiter: true, title_template: Some("Scene {n}".into()), ..Default::default() };
+        let r = import_chapter_files(&ps(&p), vec![ps(&f)], &opts, None).unwrap();
+        let titles: Vec<_> = r.iter().map(|c| c.title.clone()).collect();
+        assert_eq!(titles, vec!["Scene 1", "Scene 2", "Scene 3"]);
+        assert_eq!(render_title_template("Part {pad3} ({id})", 7, 12).unwrap(), "Part 007 (12)");
+        let err = render_title_template("Scene", 1, 1).unwrap_err();
+        assert!(["{n}", "{id}", "{padN}", "{word}", "{roman}"].iter().all(|p| err.contains(p)));
+        assert!(render_title_template("Scene {x}", 1, 1).is_err());
+    }
+
+    #[test]
+    fn export_filename_template() {
+        let p = tmp_project("356");
+        write_chapter(&p, 1, "one"); write_chapter(&p, 2, "two");
+        let pf = p.join("project.json"); let mut v = read_project_value(&pf).unwrap();
+        v["title"] = json!("My Book"); v["author"] = json!("A. Writer");
+        write_project_value(&pf, &v).unwrap();
+        let out = out_dir("356");
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &EpubExportOptions { filename_template: Some("{title} - {author} ({count})"), ..Default::default() }, &mut |_| {}).unwrap();
+        assert!(f.ends_with("My_Book - A__Writer (2).epub"), "{}", f);
+        let f = write_rtf_export(&ps(&p), &ps(&out), vec![], false, Some("draft.{ext}"), &RtfHeadingStyle::default(), RtfLineSpacing::default(), &mut |_| {}).unwrap();
+        assert!(f.ends_with("/draft.rtf"), "{}", f);
+        assert!(write_rtf_export(&ps(&p), &ps(&out), vec![], false, Some("../x"), &RtfHeadingStyle::default(), RtfLineSpacing::default(), &mut |_| {}).is_err());
+        assert!(write_rtf_export(&ps(&p), &ps(&out), vec![], false, Some("{nope}"), &RtfHeadingStyle::default(), RtfLineSpacing::default(), &mut |_| {}).is_err());
+        let f = write_rtf_export(&ps(&p), &ps(&out), vec![], false, Some("{title}... draft"), &RtfHeadingStyle::default(), RtfLineSpacing::default(), &mut |_| {}).unwrap();
+        assert!(f.ends_with("/My_Book... draft.rtf"), "{}", f);
+        let project: Project = serde_json::from_value(v).unwrap();
+        assert_eq!(render_export_filename("..", &project, "d", 1, "rtf").unwrap(), "...rtf");
+        assert!(render_export_filename("..{ext}", &project, "d", 1, "").is_err());
+    }
+
+    #[test]
+    fn import_line_breaks() {
+        let p = tmp_project("357");
+        let f = p.join("poem.txt");
+        fs::write(&f, "Roses are red\nViolets are blue\nSugar is sweet\n\nSecond stanza").unwrap();
+        let opts = ImportOptions { use_filename_as_title: true, preserve_line_breaks: true, ..Default::default() };
+        let r = import_chapter_files(&ps(&p), vec![ps(&f)], &opts, None).unwrap();
+        let doc = r[0].content.as_ref().unwrap();
+        let first = doc["content"][0]["content"].as_array().unwrap();
+        assert_eq!(first.iter().filter(|n| n["type"] == "hardBreak").count(), 2);
+        assert_eq!(first[0]["text"], "Roses are red");
+        assert_eq!(doc["content"].as_array().unwrap().len(), 2);
+        let plain = text_to_tiptap_json("a\nb", &ImportOptions::default());
+        assert_eq!(plain["content"][0]["content"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn scan_for_projects() {
+        let root = out_dir("358");
+        let a = root.join("books/alpha"); let b = root.join("beta");
+        for (d, t) in [(&a, "Alpha"), (&b, "Beta")] {
+            fs::create_dir_all(d.join("chapters")).unwrap();
+            fs::write(d.join("project.json"), json!({"title": t, "author": "X", "chapterOrder": [1, 2]}).to_string()).unwrap();
+        }
+        fs::create_dir_all(b.join(".backups/old")).unwrap();
+        fs::write(b.join(".backups/old/project.json"), json!({"title": "Old"}).to_string()).unwrap();
+        let found = scan_projects(ps(&root), 3).unwrap();
+        let titles: Vec<_> = found.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, vec!["Beta", "Alpha"]);
+        assert_eq!(found[0].chapter_count, 2);
+        assert!(found[0].modified.is_some());
+        assert_eq!(scan_projects(ps(&root), 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn portable_export_dir() {
+        let p = tmp_project("359");
+        let parent = p.parent().unwrap().to_path_buf();
+        // stale absolute exportDir falls back to the project parent
+        let pf = p.join("project.json"); let mut v = read_project_value(&pf).unwrap();
+        v["exportDir"] = json!("/definitely/not/here/exports");
+        write_project_value(&pf, &v).unwrap();
+        assert_eq!(get_default_export_dir(ps(&p)).unwrap(), ps(&parent));
+        // relative storage round-trips
+        let exports = parent.join("exports-359"); fs::create_dir_all(&exports).unwrap();
+        update_export_dir(ps(&p), ps(&exports), Some(true)).unwrap();
+        assert_eq!(read_project_value(&pf).unwrap()["exportDir"], "../exports-359");
+        assert_eq!(get_default_export_dir(ps(&p)).unwrap(), ps(&exports));
+        assert_eq!(to_portable_path(&p.join("out"), &p), "out");
+        assert_eq!(to_portable_path(Path::new("/elsewhere/x"), &p), "/elsewhere/x");
+    }
+
+    #[test]
+    fn indented_paragraph_css() {
+        let css = build_epub_css(&Some(json!({"indentParagraphs": true})));
+        assert!(css.contains("p { text-indent: 1.5em; margin: 0; }"));
+        assert!(css.contains("h2 + p, h3 + p"));
+        assert!(!build_epub_css(&None).contains("text-indent: 1.5em"));
+    }
+
+    #[test]
+    fn drop_caps() {
+        let layout = EpubLayout { drop_caps: true, ..Default::default() };
+        let doc = Some(json!({"type":"doc","content":[
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"One"}]},
+            {"type":"paragraph","content":[{"type":"text","text":"Once upon a time"}]},
+            {"type":"paragraph","content":[{"type":"text","text":"Later"}]}]}));
+        let x = chapter_to_xhtml("T", &doc, None, &layout);
+        assert!(x.contains("<p><span class=\"dropcap\">O</span>nce upon a time</p>"), "{}", x);
+        assert!(x.contains("<p>Later</p>"));
+        let quoted = Some(json!({"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"\u{201c}Hi"}]}]}));
+        assert!(!chapter_to_xhtml("T", &quoted, None, &layout).contains("dropcap"));
+        assert!(build_epub_css(&Some(json!({"dropCaps": true}))).contains("span.dropcap { float: left;"));
+    }
+
+    #[test]
+    fn scene_break_rendering() {
+        let styles = Some(json!({"sceneBreak": "* * *"}));
+        let layout = EpubLayout::from_styles(&styles);
+        let nodes = vec![json!({"type":"paragraph","content":[{"type":"text","text":"a"}]}), json!({"type":"horizontalRule"})];
+        let x = render_blocks(&nodes, &layout);
+        assert!(x.contains("<p class=\"scene-break\">* * *</p>"));
+        assert!(!x.contains("<hr/>"));
+        assert!(build_epub_css(&styles).contains("p.scene-break { text-align: center;"));
+        let rule = EpubLayout::from_styles(&Some(json!({"sceneBreak": "rule"})));
+        assert!(render_blocks(&nodes, &rule).contains("<hr/>"));
+    }
+
+    #[test]
+    fn export_single_chapter() {
+        let p = tmp_project("363");
+        write_chapter(&p, 1, "one"); write_chapter(&p, 2, "two words");
+        let pf = p.join("project.json"); let mut v = read_project_value(&pf).unwrap();
+        v["chapterTitles"] = json!({"2": "The Storm"});
+        write_project_value(&pf, &v).unwrap();
+        let out = out_dir("363");
+        let f = export_chapter(ps(&p), ps(&out), 2, "rtf".into()).unwrap();
+        let name = Path::new(&f).file_name().unwrap().to_str().unwrap().to_string();
+        assert!(name.starts_with("The_Storm_") && name.ends_with(".rtf"), "{}", name);
+        assert!(fs::read_to_string(&f).unwrap().contains("two words"));
+        assert!(export_chapter(ps(&p), ps(&out), 9, "rtf".into()).is_err());
+        assert!(export_chapter(ps(&p), ps(&out), 2, "pdf".into()).is_err());
+    }
+
+    #[test]
+    fn data_url_images_in_epub() {
+        let p = tmp_project("364");
+        let bytes: Vec<u8> = (0u8..=200).collect();
+        let url = format!("data:image/png;base64,{}", base64_encode(&bytes));
+        let doc = json!({"type":"doc","content":[
+            {"type":"imageBleed","attrs":{"src": url, "name": "", "alt": "x"}},
+            {"type":"imageBleed","attrs":{"src": url, "name": "", "alt": "y"}}]});
+        fs::write(p.join("chapters/1.json"), doc.to_string()).unwrap();
+        let pf = p.join("project.json"); let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"] = json!([1]); write_project_value(&pf, &v).unwrap();
+        let out = out_dir("364");
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &EpubExportOptions::default(), &mut |_| {}).unwrap();
+        let mut zip = zip::ZipArchive::new(fs::File::open(&f).unwrap()).unwrap();
+        let mut got = Vec::new();
+        std::io::Read::read_to_end(&mut zip.by_name("OEBPS/images/inline-1.png").unwrap(), &mut got).unwrap();
+        assert_eq!(got, bytes);
+        assert!(zip.by_name("OEBPS/images/inline-2.png").is_err());
+        let opf = zip_entry(&f, "OEBPS/content.opf");
+        assert!(opf.contains("href=\"images/inline-1.png\" media-type=\"image/png\""));
+        assert!(zip_entry(&f, "OEBPS/chapters/ch001.xhtml").contains("src=\"../images/inline-1.png\""));
+        assert_eq!(base64_decode("aGk=").unwrap(), b"hi");
+    }
+
+    #[test]
+    fn xml_control_chars_stripped() {
+        let nav = build_nav("Book", &[TocEntry::chapter(0, "Bad\u{0}Ti\u{1b}tle's")], &EpubLayout::default());
+        assert!(nav.contains(">BadTitle&apos;s</a>"), "{}", nav);
+        assert!(!build_ncx("B", "u", &[TocEntry::chapter(0, "A\u{7}B")]).contains('\u{7}'));
+        assert_eq!(sanitize_xml_text("a\tb\nc\u{FFFF}"), "a\tb\nc");
+    }
+
+    #[test]
+    fn theme_setting() {
+        let dirs = tmp_app_dirs("366");
+        assert_eq!(get_theme_in(&dirs).unwrap(), "system");
+        update_font_in(&dirs, "Georgia".into()).unwrap();
+        set_theme_in(&dirs, "sepia".into()).unwrap();
+        assert_eq!(get_theme_in(&dirs).unwrap(), "sepia");
+        assert!(set_theme_in(&dirs, "neon".into()).is_err());
+        write_config_in(&dirs, "/tmp/x".into(), None).unwrap();
+        let c = read_config_in(&dirs).unwrap();
+        assert_eq!(c.theme, "sepia");
+        assert_eq!(c.font_family.as_deref(), Some("Georgia"));
+    }
+
+    #[test]
+    fn window_state() {
+        let dirs = tmp_app_dirs("367");
+        assert_eq!(read_window_state_in(&dirs).unwrap(), None);
+        let st = WindowState { width: 1200, height: 800, x: Some(40), y: Some(30), maximized: false };
+        save_window_state_in(&dirs, st.clone()).unwrap();
+        assert_eq!(read_window_state_in(&dirs).unwrap(), Some(st));
+        save_window_state_in(&dirs, WindowState { width: 10, height: 10, x: Some(-5000), y: Some(0), maximized: true }).unwrap();
+        let r = read_window_state_in(&dirs).unwrap().unwrap();
+        assert_eq!((r.width, r.height, r.x, r.y, r.maximized), (MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT, None, None, true));
+    }
+
+    #[test]
+    fn autosave_interval() {
+        let dirs = tmp_app_dirs("368");
+        assert_eq!(get_autosave_interval_in(&dirs).unwrap(), 30);
+        assert!(set_autosave_interval_in(&dirs, 4).is_err());
+        assert!(set_autosave_interval_in(&dirs, 601).is_err());
+        set_autosave_interval_in(&dirs, 120).unwrap();
+        assert_eq!(get_autosave_interval_in(&dirs).unwrap(), 120);
+        assert_eq!(read_config_in(&dirs).unwrap().autosave_interval_seconds, Some(120));
+    }
+
+    #[test]
+    fn prune_recent_projects_drops_missing() {
+        let dirs = tmp_app_dirs("369");
+        let live = tmp_project("369live");
+        let dead = tmp_project("369dead");
+        write_config_in(&dirs, ps(&dead), None).unwrap();
+        write_config_in(&dirs, ps(&live), None).unwrap();
+        assert_eq!(read_config_in(&dirs).unwrap().recent_projects, vec![ps(&live), ps(&dead)]);
+        fs::remove_dir_all(&dead).unwrap();
+        assert_eq!(prune_recent_projects_in(&dirs).unwrap(), vec![ps(&live)]);
+        assert_eq!(read_config_in(&dirs).unwrap().recent_projects, vec![ps(&live)]);
+    }
+
+    #[test]
+    fn export_presets() {
+        let p = tmp_project("370");
+        write_chapter(&p, 1, "one"); write_chapter(&p, 2, "two");
+        let out = out_dir("370");
+        save_export_preset(ps(&p), "Beta".into(), json!({"format": "epub", "chapterIds": [2], "filenameTemplate": "beta-{count}", "exportDir": ps(&out), "styles": {"dropCaps": true}})).unwrap();
+        save_export_preset(ps(&p), "All RTF".into(), json!({"format": "rtf"})).unwrap();
+        assert!(save_export_preset(ps(&p), "Bad".into(), json!({"format": "pdf"})).is_err());
+        let names: Vec<_> = list_export_presets(ps(&p)).unwrap().into_iter().map(|n| n.name).collect();
+        assert_eq!(names, vec!["All RTF", "Beta"]);
+        let f = run_export_preset(&ps(&p), "Beta", &mut |_| {}).unwrap();
+        assert!(f.ends_with("/beta-1.epub"), "{}", f);
+        assert!(zip_entry(&f, "OEBPS/style.css").contains("span.dropcap"));
+        assert!(!read_project_value(&p.join("project.json")).unwrap()["styles"].is_object());
+        assert!(run_export_preset(&ps(&p), "Nope", &mut |_| {}).is_err());
+    }
+
+    #[test]
+    fn custom_epub_css() {
+        let p = tmp_project("371");
+        write_chapter(&p, 1, "one");
+        let out = out_dir("371");
+        let css_file = out.join("extra.css");
+        fs::write(&css_file, "p { color: rebeccapurple; }").unwrap();
+        let opts = EpubExportOptions { custom_css: read_custom_css(Some(&ps(&css_file))).unwrap(), ..Default::default() };
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &opts, &mut |_| {}).unwrap();
+        let css = zip_entry(&f, "OEBPS/style.css");
+        let custom = css.find("rebeccapurple").unwrap();
+        assert!(css.find("body { font-family").unwrap() < custom);
+        assert_eq!(read_custom_css(Some("/no/such/file.css")).unwrap(), None);
+        fs::write(&css_file, "a".repeat(300 * 1024)).unwrap();
+        assert!(read_custom_css(Some(&ps(&css_file))).is_err());
+    }
+
+    #[test]
+    fn excluded_chapters() {
+        let p = tmp_project("372");
+        write_chapter(&p, 1, "kept text"); write_chapter(&p, 2, "scratch notes");
+        assert_eq!(set_chapter_excluded(ps(&p), 2, true).unwrap(), vec![2]);
+        let out = out_dir("372");
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &EpubExportOptions::default(), &mut |_| {}).unwrap();
+        let opf = zip_entry(&f, "OEBPS/content.opf");
+        assert!(opf.contains("ch001.xhtml") && !opf.contains("ch002.xhtml"));
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![1, 2], &EpubExportOptions::default(), &mut |_| {}).unwrap();
+        assert!(zip_entry(&f, "OEBPS/chapters/ch002.xhtml").contains("scratch notes"));
+        let f = write_rtf_export(&ps(&p), &ps(&out), vec![], false, None, &RtfHeadingStyle::default(), RtfLineSpacing::default(), &mut |_| {}).unwrap();
+        assert!(!fs::read_to_string(&f).unwrap().contains("scratch"));
+        assert!(!fs::read_to_string(export_markdown(ps(&p), ps(&out), vec![], None, None, None, None).unwrap()).unwrap().contains("scratch"));
+        assert_eq!(set_chapter_excluded(ps(&p), 2, false).unwrap(), Vec::<u32>::new());
+
+        // Deleting a chapter, or finding its file gone, also forgets that it was excluded
+        write_chapter(&p, 3, "outline");
+        set_chapter_excluded(ps(&p), 2, true).unwrap();
+        set_chapter_excluded(ps(&p), 3, true).unwrap();
+        fs::remove_file(p.join("chapters/3.json")).unwrap();
+        assert_eq!(delete_chapter(ps(&p), 2).unwrap(), vec![3]);
+        let project = read_project_value(&p.join("project.json")).unwrap();
+        assert_eq!(excluded_chapter_ids(&project), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn asset_dedupe() {
+        let p = tmp_project("373");
+        let src = out_dir("373");
+        fs::write(src.join("a.png"), b"same-bytes").unwrap();
+        fs::write(src.join("b.png"), b"same-bytes").unwrap();
+        fs::write(src.join("c.png"), b"other").unwrap();
+        let a = copy_asset_and_encode(ps(&p), ps(&src.join("a.png"))).unwrap();
+        let b = copy_asset_and_encode(ps(&p), ps(&src.join("b.png"))).unwrap();
+        assert_eq!(a["name"], "a.png");
+        assert_eq!(b["name"], "a.png");
+        assert_eq!(b["reused"], true);
+        copy_asset_and_encode(ps(&p), ps(&src.join("c.png"))).unwrap();
+        assert_eq!(fs::read_dir(p.join("assets")).unwrap().count(), 2);
+        let v = read_project_value(&p.join("project.json")).unwrap();
+        assert_eq!(v["assetHashes"][asset_content_hash(b"same-bytes")], "a.png");
+    }
+
+    #[test]
+    fn epub2_package() {
+        let p = tmp_project("374");
+        write_chapter(&p, 1, "one");
+        let out = out_dir("374");
+        let opts = EpubExportOptions { version: EpubVersion::Epub2, ..Default::default() };
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &opts, &mut |_| {}).unwrap();
+        let opf = zip_entry(&f, "OEBPS/content.opf");
+        assert!(opf.contains("version=\"2.0\""));
+        assert!(!opf.contains("properties=\"nav\""));
+        assert!(opf.contains("<guide>"));
+        let z = zip::ZipArchive::new(fs::File::open(&f).unwrap()).unwrap();
+        assert!(z.file_names().all(|n| n != "OEBPS/nav.xhtml"));
+        let f3 = write_epub_export(&ps(&p), &ps(&out), vec![], &EpubExportOptions::default(), &mut |_| {}).unwrap();
+        assert!(zip_entry(&f3, "OEBPS/content.opf").contains("version=\"3.0\""));
+        assert!(EpubVersion::parse(Some(4)).is_err());
+    }
+
+    #[test]
+    fn chapter_modified_stamps() {
+        let p = tmp_project("375");
+        write_chapter(&p, 1, "one");
+        let before = get_chapter_modified(ps(&p)).unwrap();
+        assert!(before.contains_key("1"), "{:?}", before); // mtime fallback
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        save_chapter(ps(&p), 1, "{\"type\":\"doc\",\"content\":[]}".into()).unwrap();
+        let v = read_project_value(&p.join("project.json")).unwrap();
+        let stamped = v["chapterModified"]["1"].as_str().unwrap().to_string();
+        let after = get_chapter_modified(ps(&p)).unwrap();
+        assert_eq!(after["1"], stamped);
+        assert!(chrono::DateTime::parse_from_rfc3339(&stamped).unwrap() > chrono::DateTime::parse_from_rfc3339(&before["1"]).unwrap());
+        assert_eq!(load_project(ps(&p)).unwrap().chapter_modified["1"], stamped);
+    }
+
+    #[test]
+    fn export_modified_since() {
+        let p = tmp_project("376");
+        write_chapter(&p, 1, "old chapter"); write_chapter(&p, 2, "fresh chapter");
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterModified"]["1"] = json!("2020-01-01T00:00:00+00:00");
+        write_project_value(&pf, &v).unwrap();
+        let out = out_dir("376");
+        let f = export_markdown(ps(&p), ps(&out), vec![], None, None, Some("2024-01-01T00:00:00Z".into()), None).unwrap();
+        let md = fs::read_to_string(f).unwrap();
+        assert!(md.contains("fresh chapter") && !md.contains("old chapter"), "{}", md);
+        assert!(export_markdown(ps(&p), ps(&out), vec![], None, None, Some("yesterday".into()), None).is_err());
+        assert!(export_markdown(ps(&p), ps(&out), vec![1], None, None, Some("2024-01-01T00:00:00Z".into()), None).is_err());
+        // Re-saving identical content keeps the old stamp
+        let doc = fs::read_to_string(p.join("chapters/1.json")).unwrap();
+        save_chapter(ps(&p), 1, doc).unwrap();
+        assert_eq!(read_project_value(&pf).unwrap()["chapterModified"]["1"], "2020-01-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn save_project_revision() {
+        let p = tmp_project("377");
+        let rev = load_project(ps(&p)).unwrap().project.rev;
+        assert_eq!(rev, 0);
+        let r1 = save_project(ps(&p), json!({"title":"A"}), Some(0)).unwrap();
+        assert_eq!(r1, 1);
+        let err = save_project(ps(&p), json!({"title":"B"}), Some(0)).unwrap_err();
+        assert!(err.starts_with("Conflict"), "{}", err);
+        assert_eq!(read_project_value(&p.join("project.json")).unwrap()["title"], "A");
+        assert_eq!(save_project(ps(&p), json!({"rev": 99}), None).unwrap(), 2);
+        assert_eq!(read_project_value(&p.join("project.json")).unwrap()["rev"], 2);
+    }
+
+    #[test]
+    fn import_pasted_text() {
+        let p = tmp_project("378");
+        let md = "# Opening\nIt was **dark**.\n\n# Second\nMorning came.\n";
+        let chs = import_text(ps(&p), md.into(), "markdown".into(), ImportOptions { chapter_delimiter: Some("# ".into()), ..Default::default() }).unwrap();
+        assert_eq!(chs.len(), 2);
+        assert_eq!(chs[0].title, "Opening");
+        assert_eq!(chs[1].title, "Second");
+        assert!(chs[0].content.as_ref().unwrap().to_string().contains("\"bold\""));
+        assert_eq!(read_project_value(&p.join("project.json")).unwrap()["chapterOrder"].as_array().unwrap().len(), 2);
+        assert!(import_text(ps(&p), "x".into(), "docx".into(), ImportOptions::default()).is_err());
+    }
+
+    #[test]
+    fn rtf_mark_nesting() {
+        let items = vec![json!({"type":"text","text":"x","marks":[
+            {"type":"code"},{"type":"italic"},{"type":"link","attrs":{"href":"http://a"}},{"type":"bold"},{"type":"strike"}]})];
+        let html = render_inline(&items);
+        assert_eq!(html, "<a href=\"http://a\"><strong><em><s><code>x</code></s></em></strong></a>");
+        // Well-formed: every close tag matches the innermost open tag
+        let mut stack: Vec<String> = vec![];
+        for tag in html.split('<').skip(1) {
+            let name: String = tag.trim_start_matches('/').chars().take_while(|c| c.is_alphanumeric()).collect();
+            if tag.starts_with('/') { assert_eq!(stack.pop().unwrap(), name); } else { stack.push(name); }
+        }
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn sanitize_export_filename() {
+        assert_eq!(sanitize_filename("CON.epub"), "CON_.epub");
+        assert_eq!(sanitize_filename("nul"), "nul_");
+        assert_eq!(sanitize_filename("Book: One?. ."), "Book_ One_");
+        let long = format!("{}.epub", "é".repeat(300));
+        let s = sanitize_filename(&long);
+        assert!(s.len() <= 200 && s.ends_with(".epub"), "{}", s.len());
+        let p = tmp_project("380");
+        write_chapter(&p, 1, "one");
+        save_project(ps(&p), json!({"title": "CON"}), None).unwrap();
+        let out = out_dir("380");
+        let opts = EpubExportOptions { filename_template: Some("{title}"), ..Default::default() };
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &opts, &mut |_| {}).unwrap();
+        assert!(f.ends_with("CON_.epub"), "{}", f);
+        save_project(ps(&p), json!({"title": "x".repeat(300)}), None).unwrap();
+        let f = write_rtf_export(&ps(&p), &ps(&out), vec![], false, None, &RtfHeadingStyle::default(), RtfLineSpacing::default(), &mut |_| {}).unwrap();
+        assert!(Path::new(&f).file_name().unwrap().len() <= 200);
+        assert!(f.ends_with(".rtf"));
+    }
+
+    #[test]
+    fn validate_import_file_report() {
+        let dir = out_dir("381");
+        let f = dir.join("book.md");
+        fs::write(&f, "# One\nText\n# Two\nMore\n# Three\nEnd\n").unwrap();
+        let r = validate_import_file(ps(&f), Some("# ".into()), None, None, None).unwrap();
+        assert_eq!(r.chapter_count, 3);
+        assert_eq!(r.encoding, "UTF-8");
+        assert!(r.warnings.is_empty());
+        assert_eq!(validate_import_file(ps(&f), None, None, None, None).unwrap().chapter_count, 1);
+        let e = dir.join("empty.txt");
+        fs::write(&e, "").unwrap();
+        let r = validate_import_file(ps(&e), None, None, None, None).unwrap();
+        assert_eq!(r.chapter_count, 0);
+        assert!(!r.warnings.is_empty());
+        let b = dir.join("bin.txt");
+        fs::write(&b, [0xffu8, 0x00, 0x9c]).unwrap();
+        assert!(validate_import_file(ps(&b), None, None, None, None).unwrap().warnings.len() >= 2);
+        assert!(validate_import_file(ps(&dir.join("x.docx")), None, None, None, None).is_err());
+
+        // A delimiter that never starts a line is reported, even though the text still imports
+        let r = validate_import_file(ps(&f), Some("## ".into()), None, None, None).unwrap();
+        assert_eq!(r.chapter_count, split_by_delimiter(&fs::read_to_string(&f).unwrap(), "## ", true).len());
+        assert!(r.warnings.iter().any(|w| w.contains("No lines start with")), "{:?}", r.warnings);
+
+        // A BOM before the first delimiter is stripped both when checking and when importing
+        let bom = dir.join("bom.md");
+        fs::write(&bom, "\u{feff}# One\nText\n# Two\nMore\n").unwrap();
+        let r = validate_import_file(ps(&bom), Some("# ".into()), None, None, None).unwrap();
+        assert_eq!((r.encoding.as_str(), r.chapter_count), ("UTF-8 (BOM)", 2));
+        let p = tmp_project("381");
+        let opts = ImportOptions { chapter_delimiter: Some("# ".into()), extract_title_from_delimiter: true, ..Default::default() };
+        let titles: Vec<_> = import_chapter_files(&ps(&p), vec![ps(&bom)], &opts, None).unwrap().into_iter().map(|c| c.title).collect();
+        assert_eq!(titles, vec!["One", "Two"]);
+    }
+
+    #[test]
+    fn epub_toc_page() {
+        let p = tmp_project("382");
+        write_chapter(&p, 1, "one"); write_chapter(&p, 2, "two");
+        let out = out_dir("382");
+        let opts = EpubExportOptions { include_toc_page: true, ..Default::default() };
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &opts, &mut |_| {}).unwrap();
+        let opf = zip_entry(&f, "OEBPS/content.opf");
+        let toc = opf.find("<itemref idref=\"toc-page\"/>").unwrap();
+        assert!(toc < opf.find("<itemref idref=\"ch001\"/>").unwrap());
+        let page = zip_entry(&f, "OEBPS/toc.xhtml");
+        assert!(page.contains("href=\"chapters/ch002.xhtml\""));
+        let opts2 = EpubExportOptions { include_toc_page: true, version: EpubVersion::Epub2, ..Default::default() };
+        let f2 = write_epub_export(&ps(&p), &ps(&out), vec![], &opts2, &mut |_| {}).unwrap();
+        assert!(zip_entry(&f2, "OEBPS/content.opf").contains("type=\"toc\""));
+        let f3 = write_epub_export(&ps(&p), &ps(&out), vec![], &EpubExportOptions::default(), &mut |_| {}).unwrap();
+        assert!(!zip_entry(&f3, "OEBPS/content.opf").contains("toc-page"));
+    }
+
+    #[test]
+    fn epub_right_to_left() {
+        let p = tmp_project("383");
+        write_chapter(&p, 1, "one");
+        save_project(ps(&p), json!({"direction": "rtl"}), None).unwrap();
+        let out = out_dir("383");
+        let opts = EpubExportOptions { include_toc_page: true, ..Default::default() };
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &opts, &mut |_| {}).unwrap();
+        assert!(zip_entry(&f, "OEBPS/content.opf").contains("<spine toc=\"ncx\" page-progression-direction=\"rtl\">"));
+        assert!(zip_entry(&f, "OEBPS/chapters/ch001.xhtml").contains("xml:lang=\"en\" dir=\"rtl\">"));
+        assert!(zip_entry(&f, "OEBPS/nav.xhtml").contains("dir=\"rtl\""));
+        assert!(zip_entry(&f, "OEBPS/style.css").contains("direction: rtl"));
+        let p2 = tmp_project("383b");
+        write_chapter(&p2, 1, "one");
+        let f2 = write_epub_export(&ps(&p2), &ps(&out), vec![], &EpubExportOptions::default(), &mut |_| {}).unwrap();
+        assert!(!zip_entry(&f2, "OEBPS/content.opf").contains("page-progression"));
+    }
+
+    #[test]
+    fn epub_language() {
+        let p = tmp_project("384");
+        write_chapter(&p, 1, "un");
+        save_project(ps(&p), json!({"language": "fr"}), None).unwrap();
+        let out = out_dir("384");
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &EpubExportOptions::default(), &mut |_| {}).unwrap();
+        assert!(zip_entry(&f, "OEBPS/chapters/ch001.xhtml").contains("<html xmlns=\"http://www.w3.org/1999/xhtml\" lang=\"fr\" xml:lang=\"fr\">"));
+        assert!(zip_entry(&f, "OEBPS/content.opf").contains("<dc:language>fr</dc:language>"));
+        assert!(chapter_to_xhtml("x", &None, None, &EpubLayout::default()).contains("lang=\"en\" xml:lang=\"en\""));
+        let bad = EpubLayout { language: Some("fr\"><x".into()), ..Default::default() };
+        assert_eq!(bad.lang(), "en");
+    }
+
+    #[test]
+    fn bulk_rename() {
+        let p = tmp_project("385");
+        for i in 1..=4 { write_chapter(&p, i, "x"); }
+        rename_chapter(ps(&p), 4, "Epilogue".into()).unwrap();
+        let mut m = HashMap::new();
+        m.insert(1, "Arrival".to_string());
+        m.insert(2, "Departure".to_string());
+        m.insert(3, "epilogue".to_string());
+        assert_eq!(bulk_rename_chapters(ps(&p), m).unwrap(), 3);
+        let v = read_project_value(&p.join("project.json")).unwrap();
+        assert_eq!(v["chapterTitles"]["1"], "Arrival");
+        assert_eq!(v["chapterTitles"]["2"], "Departure");
+        assert_eq!(v["chapterTitles"]["3"], "epilogue (1)");
+        let mut bad = HashMap::new();
+        bad.insert(1, "Changed".to_string());
+        bad.insert(99, "Nope".to_string());
+        assert!(bulk_rename_chapters(ps(&p), bad).is_err());
+        assert_eq!(read_project_value(&p.join("project.json")).unwrap()["chapterTitles"]["1"], "Arrival");
+    }
+
+    #[test]
+    fn trash_and_restore_chapter() {
+        let p = tmp_project("386");
+        for i in 1..=3 { write_chapter(&p, i, "x"); }
+        rename_chapter(ps(&p), 2, "Middle".into()).unwrap();
+        trash_chapter(ps(&p), 2).unwrap();
+        let v = read_project_value(&p.join("project.json")).unwrap();
+        assert_eq!(v["chapterOrder"], json!([1, 3]));
+        assert!(p.join("chapters/.trash/2.json").exists());
+        assert!(!p.join("chapters/2.json").exists());
+        assert_eq!(load_project(ps(&p)).unwrap().chapters.len(), 2);
+        let ch = restore_chapter(ps(&p), 2).unwrap();
+        assert_eq!(ch.title, "Middle");
+        let v = read_project_value(&p.join("project.json")).unwrap();
+        assert_eq!(v["chapterOrder"], json!([1, 2, 3]));
+        assert!(v["trash"].as_object().unwrap().is_empty());
+        assert!(restore_chapter(ps(&p), 2).is_err());
+        trash_chapter(ps(&p), 3).unwrap();
+        assert_eq!(empty_trash(ps(&p)).unwrap(), 1);
+        assert!(!p.join("chapters/.trash").exists());
+        assert!(read_project_value(&p.join("project.json")).unwrap().get("trash").is_none());
+
+        // Imports allocate ids above trashed chapters, whether recorded in project.json or only on disk
+        trash_chapter(ps(&p), 2).unwrap();
+        fs::create_dir_all(p.join("chapters/.trash")).unwrap();
+        fs::write(p.join("chapters/.trash/7.json"), "{}").unwrap();
+        let opts = ImportOptions::default();
+        let src = ImportSource { title: Some("New".into()), content: "text".into(), markdown: false };
+        let imported = import_sources(&ps(&p), std::iter::once(Ok(src)), &opts, None).unwrap();
+        assert_eq!(imported[0].id, 8);
+        assert!(restore_chapter(ps(&p), 2).is_ok());
+    }
+
+    #[test]
+    fn chapter_json_round_trip() {
+        let p = tmp_project("387");
+        write_chapter(&p, 1, "x");
+        let doc = json!({"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"Hi","marks":[{"type":"bold"}]}]}]});
+        set_chapter_json(ps(&p), 1, doc.clone()).unwrap();
+        assert_eq!(get_chapter_json(ps(&p), 1).unwrap(), doc);
+        assert!(get_chapter_json(ps(&p), 9).unwrap_err().contains("not found"));
+        assert!(set_chapter_json(ps(&p), 9, doc.clone()).is_err());
+        assert!(set_chapter_json(ps(&p), 1, json!({"type":"paragraph"})).is_err());
+        assert!(set_chapter_json(ps(&p), 1, json!({"type":"doc","content":[{"type":"text"}]})).is_err());
+        assert!(set_chapter_json(ps(&p), 1, json!({"type":"doc","content":{}})).is_err());
+        assert_eq!(get_chapter_json(ps(&p), 1).unwrap(), doc);
+    }
+
+    #[test]
+    fn ordered_list_style() {
+        let list = |attrs: serde_json::Value| vec![json!({"type":"orderedList","attrs":attrs,"content":[
+            {"type":"listItem","content":[{"type":"paragraph","content":[{"type":"text","text":"one"}]}]}]})];
+        let html = render_blocks(&list(json!({"type":"lower-alpha"})), &EpubLayout::default());
+        assert!(html.contains("<ol style=\"list-style-type: lower-alpha\">"), "{}", html);
+        let html = render_blocks(&list(json!({"type":"I","start":3})), &EpubLayout::default());
+        assert!(html.contains("<ol start=\"3\" style=\"list-style-type: upper-roman\">"), "{}", html);
+        assert!(render_blocks(&list(json!({"type":"1"})), &EpubLayout::default()).contains("<ol>\n"));
+        let doc = markdown_to_tiptap_json("3. three\n4. four\n", false);
+        assert_eq!(doc["content"][0]["attrs"]["start"], 3);
+        assert!(markdown_to_tiptap_json("1. a\n", false)["content"][0].get("attrs").is_none());
+    }
+
+    #[test]
+    fn rtf_heading_sizes() {
+        let h = |level: u64| json!({"type":"doc","content":[{"type":"heading","attrs":{"level":level},"content":[{"type":"text","text":"H"}]}]});
+        let sizes = RtfHeadingStyle { sizes: [(2, 20.0), (3, 13.5)].into_iter().collect(), offset: 0 };
+        assert!(json_to_rtf_content(&Some(h(2)), &sizes, &RtfBodyFormat::default()).contains("\\fs40 "));
+        assert!(json_to_rtf_content(&Some(h(3)), &sizes, &RtfBodyFormat::default()).contains("\\fs27 "));
+        assert!(json_to_rtf_content(&Some(h(4)), &sizes, &RtfBodyFormat::default()).contains("\\fs24 "));
+        assert!(json_to_rtf_content(&Some(h(2)), &RtfHeadingStyle::default(), &RtfBodyFormat::default()).contains("\\fs32 "));
+    }
+
+    #[test]
+    fn typography_normalization() {
+        let p = tmp_project("391");
+        let doc = json!({"type":"doc","content":[
+            {"type":"paragraph","content":[{"type":"text","text":"He said \"it's fine.\"  Then - left \u{2014} gone"}]},
+            {"type":"paragraph","content":[{"type":"text","text":"'Quote' "},{"type":"text","text":" x","marks":[{"type":"bold"}]},{"type":"text","text":" \"c\"","marks":[{"type":"code"}]}]},
+            {"type":"codeBlock","content":[{"type":"text","text":"let s = \"a\";"}]}]});
+        write_chapter(&p, 1, "x");
+        fs::write(p.join("chapters/1.json"), doc.to_string()).unwrap();
+        let r = typography_report(ps(&p), vec![]).unwrap();
+        let c = &r.chapters[0].counts;
+        assert_eq!(c.straight_double_quotes, 2); // inline code and the code block are skipped
+        assert_eq!(c.straight_single_quotes, 3);
+        assert_eq!((c.hyphens, c.em_dashes, c.double_spaces), (1, 1, 1));
+        assert_eq!(normalize_typography(ps(&p), vec![], None).unwrap(), 1);
+        let d = read_chapter_content(&p, 1).unwrap();
+        assert_eq!(d["content"][0]["content"][0]["text"], "He said \u{201c}it\u{2019}s fine.\u{201d} Then - left \u{2014} gone");
+        assert_eq!(d["content"][1]["content"][0]["text"], "\u{2018}Quote\u{2019} ");
+        assert_eq!(d["content"][1]["content"][1]["text"], "x");
+        assert_eq!(d["content"][1]["content"][2]["text"], " \"c\"");
+        assert_eq!(d["content"][2]["content"][0]["text"], "let s = \"a\";");
+        let r = typography_report(ps(&p), vec![1]).unwrap();
+        assert_eq!(r.total.straight_double_quotes, 0);
+        assert_eq!(normalize_typography(ps(&p), vec![], None).unwrap(), 0);
+    }
+
+    #[test]
+    fn footnotes() {
+        let doc = markdown_to_tiptap_json("Some text[^1] here.\n\n[^1]: The *note* body.\n", false);
+        assert_eq!(doc["content"][0]["content"][1]["type"], "footnoteReference");
+        assert_eq!(doc["content"][0]["content"][1]["attrs"]["label"], "1");
+        assert_eq!(doc["content"][1]["type"], "footnoteDefinition");
+        let x = chapter_to_xhtml("C", &Some(doc.clone()), None, &EpubLayout::default());
+        assert!(x.contains("xmlns:epub=\"http://www.idpf.org/2007/ops\""));
+        assert!(x.contains("<sup><a epub:type=\"noteref\" href=\"#fn-1\">1</a></sup>"), "{}", x);
+        assert!(x.contains("<aside epub:type=\"footnote\" id=\"fn-1\">\n<p>The <em>note</em> body.</p>\n</aside>"), "{}", x);
+        assert!(x.find("<aside").unwrap() > x.find("here.</p>").unwrap());
+        let md = render_markdown_blocks(doc["content"].as_array().unwrap());
+        assert!(md.contains("text[^1] here.") && md.contains("[^1]: The *note* body."), "{}", md);
+        assert!(!chapter_to_xhtml("C", &Some(markdown_to_tiptap_json("plain", false)), None, &EpubLayout::default()).contains("xmlns:epub"));
+
+        // Labels that differ only in characters outside [A-Za-z0-9-] still get distinct ids
+        let ids: HashSet<String> = ["一", "二", "*", "†", "a b", "ab", "a_20_b"].iter().map(|l| footnote_id(l)).collect();
+        assert_eq!(ids.len(), 7);
+        assert_eq!(footnote_id("note-1"), "fn-note-1");
+
+        // Headings and code blocks in a definition stay inside it
+        let doc = markdown_to_tiptap_json("Text[^n].\n\n[^n]: First.\n\n    # Inner\n\n    ```\n    code\n    ```\n\nAfter.\n", false);
+        let blocks = doc["content"].as_array().unwrap();
+        assert_eq!(blocks.len(), 3, "{:#}", doc);
+        let note = &blocks[1]["content"];
+        assert_eq!(note[0]["content"][0]["text"], "First.");
+        assert_eq!(note[1]["type"], "heading");
+        assert_eq!(note[2]["type"], "codeBlock");
+        assert_eq!(blocks[2]["content"][0]["text"], "After.");
+    }
+
+    #[test]
+    fn heading_offset() {
+        let h = |level: u64| vec![json!({"type":"heading","attrs":{"level":level},"content":[{"type":"text","text":"H"}]})];
+        let layout = EpubLayout { heading_offset: 1, ..Default::default() };
+        assert_eq!(render_blocks(&h(2), &layout), "<h3>H</h3>\n");
+        assert_eq!(render_blocks(&h(6), &layout), "<h6>H</h6>\n");
+        let layout = EpubLayout { heading_offset: -3, ..Default::default() };
+        assert_eq!(render_blocks(&h(2), &layout), "<h1>H</h1>\n");
+        let doc = |level| Some(json!({"type":"doc","content":h(level)}));
+        let shifted = RtfHeadingStyle { offset: 1, ..Default::default() };
+        assert!(json_to_rtf_content(&doc(2), &shifted, &RtfBodyFormat::default()).contains("\\fs28 "));
+        assert!(json_to_rtf_content(&doc(2), &RtfHeadingStyle::default(), &RtfBodyFormat::default()).contains("\\fs32 "));
+
+        // Presets only store an offset when there is one
+        let preset: ExportPreset = serde_json::from_value(json!({"format": "rtf"})).unwrap();
+        assert!(serde_json::to_value(&preset).unwrap().get("headingOffset").is_none());
+        let preset: ExportPreset = serde_json::from_value(json!({"format": "rtf", "headingOffset": -1})).unwrap();
+        assert_eq!(serde_json::to_value(&preset).unwrap()["headingOffset"], -1);
+    }
+
+    #[test]
+    fn chapter_outline() {
+        let p = tmp_project("394");
+        write_chapter(&p, 1, "x");
+        write_chapter(&p, 2, "y");
+        rename_chapter(ps(&p), 1, "Opening".into()).unwrap();
+        let doc = json!({"type":"doc","content":[
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"First "},{"type":"text","text":"part","marks":[{"type":"bold"}]}]},
+            {"type":"paragraph","content":[{"type":"text","text":"body"}]},
+            {"type":"colorBleed","content":[{"type":"heading","attrs":{"level":3},"content":[{"type":"text","text":"Sub"}]}]}]});
+        fs::write(p.join("chapters/1.json"), doc.to_string()).unwrap();
+        let o = outline(ps(&p)).unwrap();
+        assert_eq!(o.len(), 2);
+        assert_eq!(o[0].title, "Opening");
+        let h: Vec<(u64, &str)> = o[0].headings.iter().map(|h| (h.level, h.text.as_str())).collect();
+        assert_eq!(h, vec![(2, "First part"), (3, "Sub")]);
+        assert_eq!(o[1].title, "Chapter 2");
+        assert!(o[1].headings.is_empty());
+    }
+
+    #[test]
+    fn epub_parts() {
+        let p = tmp_project("395");
+        for id in [1, 2, 3, 4, 5] { write_chapter(&p, id, "text"); }
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["parts"] = json!([{"title":"Part One","chapterIds":[2,3]},{"title":"Part Two","chapterIds":[4,5]}]);
+        write_project_value(&pf, &v).unwrap();
+        let out = out_dir("395");
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &EpubExportOptions { include_toc_page: true, ..Default::default() }, &mut |_| {}).unwrap();
+        let nav = zip_entry(&f, "OEBPS/nav.xhtml");
+        assert!(nav.contains("      <li><a href=\"chapters/ch001.xhtml\">Chapter 1</a></li>\n      <li><a href=\"parts/part01.xhtml\">Part One</a>\n        <ol>\n          <li><a href=\"chapters/ch002.xhtml\">Chapter 2</a></li>\n          <li><a href=\"chapters/ch003.xhtml\">Chapter 3</a></li>\n        </ol>\n      </li>\n      <li><a href=\"parts/part02.xhtml\">Part Two</a>\n        <ol>"), "{}", nav);
+        assert!(zip_entry(&f, "OEBPS/parts/part02.xhtml").contains("<h1 class=\"part-title\">Part Two</h1>"));
+        let opf = zip_entry(&f, "OEBPS/content.opf");
+        let spine: Vec<&str> = opf.lines().filter(|l| l.contains("itemref")).collect();
+        assert_eq!(spine.len(), 8);
+        assert!(spine[2].contains("part01") && spine[3].contains("ch002") && spine[5].contains("part02"), "{:?}", spine);
+        let ncx = zip_entry(&f, "OEBPS/toc.ncx");
+        assert!(ncx.contains("dtb:depth\" content=\"2\"") && ncx.contains("playOrder=\"7\""), "{}", ncx);
+        assert!(zip_entry(&f, "OEBPS/toc.xhtml").contains("Part One</a>\n    <ol>"));
+        // chapters outside any part stay flat
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![1], &EpubExportOptions::default(), &mut |_| {}).unwrap();
+        assert!(!zip_entry(&f, "OEBPS/nav.xhtml").contains("parts/"));
+    }
+
+    #[test]
+    fn chapter_preview_html() {
+        let p = tmp_project("396");
+        write_chapter(&p, 1, "x");
+        let doc = json!({"type":"doc","content":[
+            {"type":"paragraph","content":[{"type":"text","text":"Hi","marks":[{"type":"bold"}]}]},
+            {"type":"imageBleed","attrs":{"name":"pic.png"}},
+            {"type":"imageBleed","attrs":{"src":"data:image/gif;base64,R0lG"}}]});
+        fs::write(p.join("chapters/1.json"), doc.to_string()).unwrap();
+        fs::create_dir_all(p.join("assets")).unwrap();
+        fs::write(p.join("assets/pic.png"), b"hi").unwrap();
+        let html = render_chapter_html(ps(&p), 1).unwrap();
+        assert!(html.starts_with("<style>\n"));
+        assert!(html.contains("<p><strong>Hi</strong></p>"));
+        assert!(html.contains("src=\"data:image/png;base64,aGk=\""));
+        assert!(html.contains("src=\"data:image/gif;base64,R0lG\""));
+        assert!(!html.contains("../images/"));
+        assert!(render_chapter_html(ps(&p), 9).is_err());
+    }
+
+    #[test]
+    fn chapter_title_normalization() {
+        let p = tmp_project("397");
+        write_chapter(&p, 1, "x");
+        assert!(rename_chapter(ps(&p), 1, "   ".into()).unwrap_err().contains("empty"));
+        assert!(rename_chapter(ps(&p), 1, "".into()).is_err());
+        assert_eq!(rename_chapter(ps(&p), 1, "  The \t Long\n Road ".into()).unwrap(), "The Long Road");
+        let v = read_project_value(&p.join("project.json")).unwrap();
+        assert_eq!(v["chapterTitles"]["1"], "The Long Road");
+        let m: HashMap<u32, String> = [(1, " \n ".to_string())].into_iter().collect();
+        assert!(bulk_rename_chapters(ps(&p), m).is_err());
+        let m: HashMap<u32, String> = [(1, " A   B ".to_string())].into_iter().collect();
+        bulk_rename_chapters(ps(&p), m).unwrap();
+        assert_eq!(read_project_value(&p.join("project.json")).unwrap()["chapterTitles"]["1"], "A B");
+    }
+
+    #[test]
+    fn thematic_break_import_split() {
+        let text = "# Arrival\n\nThey came at dawn.\n\n***\n\n# Departure\n\nThey left at dusk.\n\n* * *\n\nNo heading here.\n\nSetext\n---\n\nStill third.\n";
+        let s = split_on_thematic_breaks(text, true);
+        assert_eq!(s.len(), 3);
+        assert_eq!(s[0], (Some("Arrival".to_string()), "They came at dawn.".to_string()));
+        assert_eq!(s[1], (Some("Departure".to_string()), "They left at dusk.".to_string()));
+        assert_eq!(s[2].0, None);
+        assert!(s[2].1.contains("Setext\n---") && s[2].1.ends_with("Still third."));
+        assert_eq!(split_on_thematic_breaks("***\n\none\n\n---\n\ntwo", false).len(), 2);
+        let p = tmp_project("398");
+        let chs = import_text(ps(&p), text.into(), "markdown".into(), ImportOptions { split_on_thematic_break: true, ..Default::default() }).unwrap();
+        assert_eq!(chs.len(), 3);
+        assert_eq!((chs[0].title.as_str(), chs[1].title.as_str(), chs[2].title.as_str()), ("Arrival", "Departure", "Chapter 3"));
+        let dir = std::env::temp_dir().join(format!("scout-test-398f-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.md"), text).unwrap();
+        assert_eq!(validate_import_file(ps(&dir.join("a.md")), None, None, Some(true), None).unwrap().chapter_count, 3);
+    }
+
+    #[test]
+    fn project_field_validation() {
+        let p = tmp_project("399");
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        assert!(project_field_errors(&v).is_empty());
+        v["chapterOrder"] = json!([1, 2, "3", -4]);
+        write_project_value(&pf, &v).unwrap();
+        let err = load_project(ps(&p)).err().unwrap();
+        assert_eq!(err, "Invalid project.json: chapterOrder[2] is not an integer; chapterOrder[3] is not an integer");
+        v["chapterOrder"] = json!([]);
+        v["title"] = json!(42);
+        write_project_value(&pf, &v).unwrap();
+        assert_eq!(load_project(ps(&p)).err().unwrap(), "Invalid project.json: title is not a string");
+        let e = project_field_errors(&json!({"author":"a","chapterOrder":{},"rev":-1,"language":null}));
+        let fields: Vec<String> = e.iter().map(|e| e.to_string()).collect();
+        assert_eq!(fields, vec!["title is missing", "chapterOrder is not an array", "rev is not a non-negative integer"]);
+        assert_eq!(project_field_errors(&json!([])).len(), 1);
+    }
+
+    #[test]
+    fn verify_project_field_errors() {
+        let p = tmp_project("399b");
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"] = json!(["x"]);
+        write_project_value(&pf, &v).unwrap();
+        let issues = verify_project(ps(&p)).unwrap();
+        assert_eq!(issues.field_errors.len(), 1);
+        assert_eq!(issues.field_errors[0].field, "chapterOrder[0]");
+    }
+
+    #[test]
+    fn rebuild_chapter_order_from_files() {
+        let p = tmp_project("400");
+        for id in [3, 1, 2] { write_chapter(&p, id, "x"); }
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"] = json!([]);
+        v["chapterTitles"] = json!({"2": "Two"});
+        write_project_value(&pf, &v).unwrap();
+        assert_eq!(rebuild_chapter_order(ps(&p)).unwrap(), vec![1, 2, 3]);
+        let v = read_project_value(&pf).unwrap();
+        assert_eq!(v["chapterOrder"], json!([1, 2, 3]));
+        assert_eq!(v["chapterTitles"]["2"], "Two");
+        assert!(verify_project(ps(&p)).unwrap().unlisted_chapter_ids.is_empty());
+    }
+
+    #[test]
+    fn spelling_suggestions_ranked() {
+        let custom = ["Eldoria", "Eldrin", "Kael", "Kaelen", "Vrenn"];
+        let s = suggest_words("eldorai", custom, 3);
+        assert_eq!(s.first().map(String::as_str), Some("Eldoria"), "{:?}", s);
+        let s = suggest_words("Kaeln", custom, 5);
+        assert_eq!(s, ["Kael", "Kaelen"]);
+        assert!(suggest_words("zzzzqqqq", custom, 5).is_empty());
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert!(suggest_words("Vrenn", custom, 2).iter().all(|w| w != "Vrenn"));
+    }
+
+    #[test]
+    fn plain_text_lists() {
+        let doc = text_to_tiptap_json("Intro line.\n\n3. first\n4. second\n   wrapped\n\n5. third\n\n- a\n* b", &ImportOptions { detect_lists: true, ..Default::default() });
+        let c = doc["content"].as_array().unwrap();
+        assert_eq!(c.len(), 3, "{:#}", doc);
+        assert_eq!(c[0]["type"], "paragraph");
+        assert_eq!(c[1]["type"], "orderedList");
+        assert_eq!(c[1]["attrs"]["start"], 3);
+        assert_eq!(c[1]["content"].as_array().unwrap().len(), 3);
+        assert_eq!(c[1]["content"][1]["content"][0]["content"][0]["text"], "second\nwrapped");
+        assert_eq!(c[2]["type"], "bulletList");
+        assert_eq!(c[2]["content"].as_array().unwrap().len(), 2);
+        let off = text_to_tiptap_json("3. first\n4. second", &ImportOptions::default());
+        assert_eq!(off["content"][0]["type"], "paragraph");
+        let plain = text_to_tiptap_json("1990. A year.\nMore", &ImportOptions { detect_lists: true, ..Default::default() });
+        assert_eq!(plain["content"][0]["type"], "paragraph");
+        let p = tmp_project("402");
+        let chs = import_text(ps(&p), "3. x\n4. y".into(), "text".into(), ImportOptions { detect_lists: true, ..Default::default() }).unwrap();
+        let d = chs[0].content.as_ref().unwrap();
+        assert_eq!(d["content"][0]["type"], "orderedList");
+        assert_eq!(d["content"][0]["attrs"]["start"], 3);
+        assert_eq!(text_list_marker("-x"), None);
+        assert_eq!(text_list_marker("12) twelve"), Some((Some(12), "twelve")));
+
+        let lists = ImportOptions { detect_lists: true, ..Default::default() };
+        // Scene breaks and a lone dialogue line stay out of lists
+        assert_eq!(text_list_marker("* * *"), None);
+        assert_eq!(text_list_marker("- - -"), None);
+        let doc = text_to_tiptap_json("- Who's there? she asked.\n\n* * *\n\nMorning.", &lists);
+        let types: Vec<_> = doc["content"].as_array().unwrap().iter().map(|n| n["type"].as_str().unwrap()).collect();
+        assert_eq!(types, vec!["paragraph", "paragraph", "paragraph"]);
+        // An unindented line ends the list and becomes a paragraph of its own
+        let doc = text_to_tiptap_json("- a\n- b\nAfter the list.\nStill after.", &lists);
+        assert_eq!(doc["content"][0]["content"].as_array().unwrap().len(), 2);
+        assert_eq!(doc["content"][1]["type"], "paragraph");
+        assert_eq!(doc["content"][1]["content"][0]["text"], "After the list.\nStill after.");
+        // A lone numbered line only joins a list it continues
+        let doc = text_to_tiptap_json("Intro.\n\n7. Not a list.", &lists);
+        assert_eq!(doc["content"][1]["type"], "paragraph");
+    }
+
+    #[test]
+    fn extract_links_report() {
+        let p = tmp_project("403");
+        let link = |h: &str| json!([{"type": "link", "attrs": {"href": h}}]);
+        let bold_link = json!([{"type": "bold"}, {"type": "link", "attrs": {"href": "https://a.example"}}]);
+        let c1 = json!({"type": "doc", "content": [{"type": "paragraph", "content": [
+            {"type": "text", "text": "See "},
+            {"type": "text", "text": "the ", "marks": link("https://a.example")},
+            {"type": "text", "text": "source", "marks": bold_link},
+            {"type": "text", "text": " now."}]}]});
+        let c2 = json!({"type": "doc", "content": [{"type": "bulletList", "content": [{"type": "listItem", "content": [{"type": "paragraph", "content": [
+            {"type": "text", "text": "B", "marks": link("https://b.example")},
+            {"type": "text", "text": " and "},
+            {"type": "text", "text": "A again", "marks": link("https://a.example")}]}]}]}]});
+        write_chapter(&p, 1, "x");
+        write_chapter(&p, 2, "x");
+        save_chapter(ps(&p), 1, c1.to_string()).unwrap();
+        save_chapter(ps(&p), 2, c2.to_string()).unwrap();
+        let links = extract_links(ps(&p)).unwrap();
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].href, "https://a.example");
+        assert_eq!(links[0].text, "the source");
+        assert_eq!(links[0].count, 2);
+        assert_eq!(links[0].chapter_ids, vec![1, 2]);
+        assert_eq!((links[1].href.as_str(), links[1].chapter_id, links[1].count), ("https://b.example", 2, 1));
+    }
+
+    #[test]
+    fn missing_assets() {
+        let p = tmp_project("404");
+        write_chapter(&p, 1, "x");
+        fs::create_dir_all(p.join("assets")).unwrap();
+        fs::write(p.join("assets/here.png"), b"png").unwrap();
+        let doc = json!({"type": "doc", "content": [
+            {"type": "imageBleed", "attrs": {"name": "here.png"}},
+            {"type": "imageBleed", "attrs": {"name": "gone.png"}},
+            {"type": "paragraph", "content": [{"type": "text", "text": "hi"}]}]});
+        save_chapter(ps(&p), 1, doc.to_string()).unwrap();
+        assert_eq!(check_missing_assets(ps(&p), vec![]).unwrap(), vec!["gone.png"]);
+        assert_eq!(check_missing_assets(ps(&p), vec![1]).unwrap(), vec!["gone.png"]);
+        let out = out_dir("404");
+        let strict = EpubExportOptions { strict_assets: true, ..Default::default() };
+        let err = write_epub_export(&ps(&p), &ps(&out), vec![], &strict, &mut |_| {}).unwrap_err();
+        assert_eq!(err, "Missing image assets: gone.png");
+        assert_eq!(fs::read_dir(&out).unwrap().count(), 0);
+        assert!(write_epub_export(&ps(&p), &ps(&out), vec![], &EpubExportOptions::default(), &mut |_| {}).is_ok());
+    }
+
+    #[test]
+    fn chapter_titles_list() {
+        let p = tmp_project("405");
+        write_chapter(&p, 1, "Body text");
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterTitles"] = json!({"1": "The <Start>"});
+        write_project_value(&pf, &v).unwrap();
+        let out = out_dir("405");
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &EpubExportOptions::default(), &mut |_| {}).unwrap();
+        assert!(!zip_entry(&f, "OEBPS/chapters/ch001.xhtml").contains("chapter-title"));
+        fs::remove_file(&f).unwrap();
+        v["styles"] = json!({"showChapterTitles": true});
+        write_project_value(&pf, &v).unwrap();
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &EpubExportOptions::default(), &mut |_| {}).unwrap();
+        let ch = zip_entry(&f, "OEBPS/chapters/ch001.xhtml");
+        assert!(ch.contains("<body>\n<h1 class=\"chapter-title\">The &lt;Start&gt;</h1>\n<p>"), "{}", ch);
+        assert!(zip_entry(&f, "OEBPS/style.css").contains("h1.chapter-title"));
+        assert!(render_chapter_html(ps(&p), 1).unwrap().contains("<h1 class=\"chapter-title\">"));
+    }
+
+    #[test]
+    fn chapter_title_prefix() {
+        let p = tmp_project("406");
+        write_chapter(&p, 4, "a");
+        write_chapter(&p, 9, "b");
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterTitles"] = json!({"4": "Arrival", "9": "Departure"});
+        write_project_value(&pf, &v).unwrap();
+        let out = out_dir("406");
+        let opts = EpubExportOptions { title_prefix: Some("Chapter {n}: "), ..Default::default() };
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &opts, &mut |_| {}).unwrap();
+        let nav = zip_entry(&f, "OEBPS/nav.xhtml");
+        assert!(nav.contains(">Chapter 1: Arrival<") && nav.contains(">Chapter 2: Departure<"), "{}", nav);
+        assert!(zip_entry(&f, "OEBPS/toc.ncx").contains("<text>Chapter 2: Departure</text>"));
+        assert_eq!(read_project_value(&pf).unwrap()["chapterTitles"]["4"], "Arrival");
+        let md = export_markdown(ps(&p), ps(&out), vec![], None, None, None, Some("{word}. ".into())).unwrap();
+        assert!(fs::read_to_string(md).unwrap().contains("# Two. Departure"));
+        assert_eq!(number_to_words(21), "Twenty-One");
+        assert_eq!(number_to_words(105), "One Hundred Five");
+        assert_eq!(number_to_words(2040), "Two Thousand Forty");
+        assert_eq!(to_roman(1994), "MCMXCIV");
+        assert_eq!(render_title_template("Part {roman}", 4, 1).unwrap(), "Part IV");
+
+        // Untitled chapters take the prefix as their whole title
+        write_chapter(&p, 12, "c");
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &opts, &mut |_| {}).unwrap();
+        let nav = zip_entry(&f, "OEBPS/nav.xhtml");
+        assert!(nav.contains(">Chapter 3<") && !nav.contains("Chapter 12"), "{}", nav);
+        assert_eq!(prefix_chapter_title(Some("{n}"), 3, 12, None, "Chapter 12".into()).unwrap(), "3");
+        assert_eq!(prefix_chapter_title(None, 3, 12, None, "Chapter 12".into()).unwrap(), "Chapter 12");
+    }
+
+    #[test]
+    fn chapter_diff() {
+        let p = tmp_project("407");
+        write_chapter(&p, 1, "The quick brown fox.\n\nIt jumped over the dog.");
+        write_chapter(&p, 2, "The quick red fox.\n\nIt jumped over the dog.");
+        let d = diff_chapters(ps(&p), 1, 2).unwrap();
+        assert_eq!(d.changes.len(), 1, "{:?}", d.changes);
+        let c = &d.changes[0];
+        assert_eq!((c.kind.as_str(), c.old_text.as_str(), c.new_text.as_str()), ("replace", "brown", "red"));
+        let a: Vec<char> = d.text_a.chars().collect();
+        assert_eq!(a[c.start_a..c.end_a].iter().collect::<String>(), "brown");
+        let b: Vec<char> = d.text_b.chars().collect();
+        assert_eq!(b[c.start_b..c.end_b].iter().collect::<String>(), "red");
+        assert!(diff_chapters(ps(&p), 1, 1).unwrap().changes.is_empty());
+        assert!(diff_chapters(ps(&p), 1, 99).is_err());
+        let ch = diff_texts("a b\nc\n", "a b\nnew\nc\n");
+        assert_eq!(ch.len(), 1);
+        assert_eq!((ch[0].kind.as_str(), ch[0].new_text.as_str(), ch[0].start_b), ("insert", "new\n", 4));
+        let ch = diff_texts("één twee drie", "één drie");
+        assert_eq!((ch[0].kind.as_str(), ch[0].old_text.as_str(), ch[0].start_a, ch[0].end_a), ("delete", "twee ", 4, 9));
+    }
+
+    #[test]
+    fn last_opened_chapter() {
+        let p = tmp_project("408");
+        write_chapter(&p, 1, "a");
+        write_chapter(&p, 2, "b");
+        write_chapter(&p, 3, "c");
+        assert_eq!(load_project(ps(&p)).unwrap().last_opened_chapter, Some(1));
+        set_last_chapter(ps(&p), 3).unwrap();
+        assert_eq!(load_project(ps(&p)).unwrap().last_opened_chapter, Some(3));
+        assert!(set_last_chapter(ps(&p), 9).is_err());
+        fs::remove_file(p.join("chapters/3.json")).unwrap();
+        assert_eq!(load_project(ps(&p)).unwrap().last_opened_chapter, Some(1));
+    }
+
+    #[test]
+    fn collapse_blank_lines() {
+        let empty = |d: &serde_json::Value| d["content"].as_array().unwrap().iter()
+            .filter(|n| n["type"] == "paragraph" && n["content"].as_array().is_none_or(|c| c.is_empty())).count();
+        let text = "\n\nOne.\n\n\n\nTwo.\n \t\nThree.\n\n\n";
+        let doc = text_to_tiptap_json(text, &ImportOptions::default());
+        assert_eq!(doc["content"].as_array().unwrap().len(), 3, "{:#}", doc);
+        assert_eq!(empty(&doc), 0);
+        assert_eq!(doc["content"][2]["content"][0]["text"], "Three.");
+        let keep = text_to_tiptap_json(text, &ImportOptions { keep_blank_lines: true, ..Default::default() });
+        let types: Vec<usize> = keep["content"].as_array().unwrap().iter().map(|n| n["content"].as_array().map_or(0, |c| c.len())).collect();
+        assert_eq!(types, vec![1, 0, 1, 1]);
+        let p = tmp_project("409");
+        let chs = import_text(ps(&p), "a\n\n\n\nb".into(), "text".into(), ImportOptions::default()).unwrap();
+        assert_eq!(empty(chs[0].content.as_ref().unwrap()), 0);
+    }
+
+    #[test]
+    fn find_markers_in_chapters() {
+        let p = tmp_project("410");
+        write_chapter(&p, 1, "The ship was TK meters long.\n\nA TKO is not a marker. [TODO] check dates ???");
+        write_chapter(&p, 2, "Clean chapter.");
+        let m = find_markers(ps(&p), None).unwrap();
+        let found: Vec<&str> = m.iter().map(|m| m.marker.as_str()).collect();
+        assert_eq!(found, vec!["TK", "[TODO]", "???"]);
+        assert_eq!(m[0].chapter_id, 1);
+        assert_eq!(m[0].context, "The ship was TK meters long.");
+        assert_eq!(m[0].offset, 13);
+        let long = format!("{} NOTE {}", "x".repeat(60), "y".repeat(60));
+        write_chapter(&p, 3, &long);
+        let m = find_markers(ps(&p), Some(vec!["NOTE".into(), " ".into()])).unwrap();
+        assert_eq!(m.len(), 1);
+        assert_eq!(m[0].context, format!("…{} NOTE {}…", "x".repeat(39), "y".repeat(39)));
+    }
+
+    #[test]
+    fn docx_export() {
+        let p = tmp_project("411");
+        write_chapter(&p, 1, "First & best paragraph.");
+        let doc = json!({"type":"doc","content":[
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"Sub"}]},
+            {"type":"paragraph","content":[{"type":"text","text":"bold","marks":[{"type":"bold"},{"type":"italic"}]}]},
+            {"type":"orderedList","attrs":{"start":3},"content":[{"type":"listItem","content":[{"type":"paragraph","content":[{"type":"text","text":"item"}]}]}]},
+            {"type":"blockquote","content":[{"type":"paragraph","content":[{"type":"text","text":"quoted"}]}]}
+        ]});
+        save_chapter(ps(&p), 2, doc.to_string()).unwrap();
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"].as_array_mut().unwrap().push(json!(2));
+        write_project_value(&pf, &v).unwrap();
+
+        let out = write_docx_export(&ps(&p), &ps(&p), vec![], None, &mut |_| {}).unwrap();
+        assert!(out.ends_with(".docx"));
+        let d = zip_entry(&out, "word/document.xml");
+        assert!(d.contains("First &amp; best paragraph."), "{}", d);
+        assert!(d.contains("<w:pStyle w:val=\"Heading2\"/>"));
+        assert!(d.contains("<w:rPr><w:b/><w:i/></w:rPr><w:t xml:space=\"preserve\">bold</w:t>"));
+        assert!(d.contains("<w:pStyle w:val=\"Quote\"/>"));
+        assert_eq!(d.matches("<w:pageBreakBefore/>").count(), 1);
+        assert!(d.contains("<w:numId w:val=\"2\"/>"));
+        assert!(zip_entry(&out, "word/numbering.xml").contains("<w:startOverride w:val=\"3\"/>"));
+        assert!(zip_entry(&out, "[Content_Types].xml").contains("/word/document.xml"));
+        assert!(zip_entry(&out, "_rels/.rels").contains("word/document.xml"));
+        let _ = fs::copy(&out, "/tmp/t411.docx");
+    }
+
+    #[test]
+    fn default_author() {
+        let dirs = tmp_app_dirs("412");
+        assert_eq!(get_default_author_in(&dirs).unwrap(), None);
+        set_default_author_in(&dirs, "  Ann Writer ".into()).unwrap();
+        assert_eq!(get_default_author_in(&dirs).unwrap().as_deref(), Some("Ann Writer"));
+        let p = std::env::temp_dir().join(format!("scout-test-412-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&p);
+        let r = create_project_in(&dirs, ps(&p), "Book".into()).unwrap();
+        assert_eq!(r.project.author, "Ann Writer");
+        assert_eq!(read_project_value(&p.join("project.json")).unwrap()["author"], "Ann Writer");
+        set_default_author_in(&dirs, " ".into()).unwrap();
+        assert_eq!(get_default_author_in(&dirs).unwrap(), None);
+        assert_eq!(read_config_in(&dirs).unwrap().default_author, None);
+    }
+
+    #[test]
+    fn chapter_epigraphs() {
+        let p = tmp_project("413");
+        write_chapter(&p, 1, "Body text here.");
+        write_chapter(&p, 2, "Second.");
+        assert!(set_chapter_epigraph(ps(&p), 9, Some(Epigraph { text: "x".into(), attribution: None })).is_err());
+        set_chapter_epigraph(ps(&p), 1, Some(Epigraph { text: " All happy families… ".into(), attribution: Some(" Tolstoy ".into()) })).unwrap();
+        let e = get_chapter_epigraph(ps(&p), 1).unwrap().unwrap();
+        assert_eq!((e.text.as_str(), e.attribution.as_deref()), ("All happy families…", Some("Tolstoy")));
+
+        let out = write_epub_export(&ps(&p), &ps(&p), vec![1], &EpubExportOptions::default(), &mut |_| {}).unwrap();
+        let x = zip_entry(&out, "OEBPS/chapters/ch001.xhtml");
+        let ep = x.find("<blockquote class=\"epigraph\">").expect(&x);
+        assert!(ep < x.find("Body text here.").unwrap());
+        assert!(x.contains("<p class=\"epigraph-attribution\">\u{2014} Tolstoy</p>"));
+        assert!(zip_entry(&out, "OEBPS/style.css").contains("blockquote.epigraph"));
+
+        let rtf = write_rtf_export(&ps(&p), &ps(&p), vec![1], false, None, &RtfHeadingStyle::default(), RtfLineSpacing::default(), &mut |_| {}).unwrap();
+        let r = fs::read_to_string(rtf).unwrap();
+        assert!(r.contains("\\i All happy families\\u8230?\\i0\\par"), "{}", r);
+        assert!(r.find("\\u8230?").unwrap() < r.find("Body text here.").unwrap());
+        assert!(render_chapter_html(ps(&p), 1).unwrap().contains("class=\"epigraph\""));
+        assert!(!render_chapter_html(ps(&p), 2).unwrap().contains("class=\"epigraph\""));
+
+        delete_chapter(ps(&p), 1).unwrap();
+        assert!(read_project_value(&p.join("project.json")).unwrap()["chapterEpigraphs"].get("1").is_none());
+        assert_eq!(get_chapter_epigraph(ps(&p), 1).unwrap(), None);
+    }
+
+    #[test]
+    fn rtf_line_spacing() {
+        let doc = Some(json!({"type":"doc","content":[
+            {"type":"paragraph","content":[{"type":"text","text":"Hi"}]},
+            {"type":"blockquote","content":[{"type":"paragraph","content":[{"type":"text","text":"Q"}]}]}
+        ]}));
+        let single = json_to_rtf_content(&doc, &RtfHeadingStyle::default(), &RtfBodyFormat::default());
+        assert!(!single.contains("\\sl"));
+        let double = json_to_rtf_content(&doc, &RtfHeadingStyle::default(), &RtfBodyFormat { line_spacing: RtfLineSpacing::parse(Some("double")).unwrap(), ..Default::default() });
+        assert!(double.contains("{\\pard \\sl480\\slmult1 Hi"), "{}", double);
+        assert!(double.contains("{\\pard \\li720 \\sl480\\slmult1 "));
+        assert_eq!(RtfLineSpacing::parse(Some("1.5")).unwrap().control_words(), "\\sl360\\slmult1 ");
+        assert!(RtfLineSpacing::parse(Some("triple")).is_err());
+
+        let p = tmp_project("414");
+        write_chapter(&p, 1, "Body.");
+        let f = write_rtf_export(&ps(&p), &ps(&p), vec![], false, None, &RtfHeadingStyle::default(), RtfLineSpacing::Double, &mut |_| {}).unwrap();
+        assert!(fs::read_to_string(f).unwrap().contains("\\sl480\\slmult1 Body."));
+    }
+
+    #[test]
+    fn manuscript_export() {
+        assert_eq!(manuscript_word_count(42), "about 42 words");
+        assert_eq!(manuscript_word_count(1249), "about 1,200 words");
+        assert_eq!(manuscript_word_count(81_950), "about 82,000 words");
+        let p = tmp_project("415");
+        write_chapter(&p, 1, &words(700));
+        write_chapter(&p, 2, &words(580));
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["author"] = json!("Jane Q. Public");
+        v["contactInfo"] = json!("Jane Public\n123 Main St\njane@example.com");
+        write_project_value(&pf, &v).unwrap();
+
+        let f = write_manuscript_export(&ps(&p), &ps(&p), vec![], &mut |_| {}).unwrap();
+        let r = fs::read_to_string(&f).unwrap();
+        assert!(r.contains("{\\fonttbl{\\f0\\fmodern\\fcharset0 Courier New;}}"));
+        assert!(r.contains("{\\pard\\tqr\\tx9360 Jane Public\\tab about 1,300 words\\par}"), "{}", &r[..600]);
+        assert!(r.contains("{\\pard 123 Main St\\par}"));
+        assert!(r.contains("by Jane Q. Public"));
+        assert!(r.contains("Public / TEST BOOK / \\chpgn"));
+        assert!(r.contains("{\\pard \\sl480\\slmult1 \\fi720 word"));
+        assert!(r.contains("\\margl1440\\margr1440\\margt1440\\margb1440"));
+        assert_eq!(r.matches("\\page").count(), 2);
+        assert!(r.contains("Chapter Two"));
+
+        // Chapter text and headings are escaped, with non-ASCII as \uN? escapes
+        let doc = json!({"type": "doc", "content": [
+            {"type": "heading", "attrs": {"level": 2}, "content": [{"type": "text", "text": "Caf\u{e9} {1}"}]},
+            {"type": "paragraph", "content": [{"type": "text", "text": "a\\b \u{2014} \u{1F600}", "marks": [{"type": "italic"}]}]}
+        ]});
+        let body = json_to_rtf_content(&Some(doc), &RtfHeadingStyle::default(), &RtfBodyFormat::default());
+        assert!(body.contains("Caf\\u233? \\{1\\}"), "{}", body);
+        assert!(body.contains("\\i a\\\\b \\u8212? \\u-10179?\\u-8704?"), "{}", body);
+        assert!(body.is_ascii());
+    }
+
+    #[test]
+    fn export_size_estimate() {
+        assert_eq!(format_byte_size(812), "812 B");
+        assert_eq!(format_byte_size(1536), "1.5 KB");
+        assert_eq!(format_byte_size(3 * 1024 * 1024), "3.0 MB");
+        let p = tmp_project("416");
+        write_chapter(&p, 1, &words(500));
+        let rtf = estimate_export_size(ps(&p), vec![], "rtf".into()).unwrap();
+        assert_eq!(rtf.bytes, (2500.0_f64 * RTF_MARKUP_FACTOR).round() as u64);
+        let before = estimate_export_size(ps(&p), vec![], "epub".into()).unwrap();
+        fs::create_dir_all(p.join("assets")).unwrap();
+        fs::write(p.join("assets").join("map.png"), vec![0u8; 50_000]).unwrap();
+        let doc = json!({"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"x"}]},{"type":"imageBleed","attrs":{"name":"map.png"}}]});
+        save_chapter(ps(&p), 2, doc.to_string()).unwrap();
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"].as_array_mut().unwrap().push(json!(2));
+        write_project_value(&pf, &v).unwrap();
+        let after = estimate_export_size(ps(&p), vec![], "epub".into()).unwrap();
+        assert!(after.bytes >= before.bytes + 50_000, "{:?} {:?}", before, after);
+        assert!(after.human.ends_with("KB"));
+        assert!(estimate_export_size(ps(&p), vec![], "pdf".into()).is_err());
+    }
+
+    #[test]
+    fn one_off_export_dir() {
+        let p = tmp_project("417");
+        write_chapter(&p, 1, "Hello.");
+        let saved = p.join("saved");
+        let once = p.join("once");
+        fs::create_dir_all(&saved).unwrap();
+        fs::create_dir_all(&once).unwrap();
+        update_export_dir(ps(&p), ps(&saved), None).unwrap();
+        for format in ["rtf", "epub", "markdown", "docx", "manuscript"] {
+            let f = write_book_export(&ps(&p), &ps(&once), format, vec![], false, &mut |_| {}).unwrap();
+            assert!(f.starts_with(&ps(&once)));
+        }
+        assert_eq!(read_project_value(&p.join("project.json")).unwrap()["exportDir"], json!(ps(&saved)));
+        assert_eq!(get_default_export_dir(ps(&p)).unwrap(), ps(&saved));
+        write_book_export(&ps(&p), &ps(&once), "rtf", vec![], true, &mut |_| {}).unwrap();
+        assert_eq!(get_default_export_dir(ps(&p)).unwrap(), ps(&once));
+        assert!(write_book_export(&ps(&p), &ps(&saved), "pdf", vec![], true, &mut |_| {}).is_err());
+        assert_eq!(get_default_export_dir(ps(&p)).unwrap(), ps(&once));
+    }
+
+    #[test]
+    fn used_fonts_report() {
+        let dirs = tmp_app_dirs("418");
+        update_font_in(&dirs, "Georgia".into()).unwrap();
+        let p = tmp_project("418");
+        let styled = |family: &str, text: &str| json!({"type":"text","text":text,"marks":[{"type":"bold"},{"type":"textStyle","attrs":{"fontFamily":family}}]});
+        let doc1 = json!({"type":"doc","content":[{"type":"paragraph","content":[styled("Georgia", "a"), {"type":"text","text":" plain "}, styled("Georgia", "b")]}]});
+        let doc2 = json!({"type":"doc","content":[{"type":"blockquote","content":[{"type":"paragraph","content":[styled("Courier New", "c")]}]}]});
+        save_chapter(ps(&p), 1, doc1.to_string()).unwrap();
+        save_chapter(ps(&p), 2, doc2.to_string()).unwrap();
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"] = json!([1, 2]);
+        v["fontFamily"] = json!("Courier New");
+        write_project_value(&pf, &v).unwrap();
+
+        // The app-wide font counts once, without a chapter
+        let fonts = used_fonts_in(&dirs, ps(&p)).unwrap();
+        let get = |f: &str| fonts.iter().find(|u| u.family == f).unwrap();
+        assert_eq!((get("Georgia").count, get("Georgia").chapter_ids.clone()), (3, vec![1]));
+        assert_eq!((get("Courier New").count, get("Courier New").chapter_ids.clone()), (2, vec![2]));
+    }
+
+    #[test]
+    fn paragraph_classes() {
+        let para = |attrs: serde_json::Value| json!({"type":"paragraph","attrs":attrs,"content":[{"type":"text","text":"x"}]});
+        let out = render_blocks(&[para(json!({"class":"noindent"}))], &EpubLayout::default());
+        assert_eq!(out, "<p class=\"noindent\">x</p>\n");
+        let out = render_blocks(&[para(json!({"class":"noindent  big\"<x","textAlign":"center"}))], &EpubLayout::default());
+        assert_eq!(out, "<p class=\"noindent bigx\" style=\"text-align:center\">x</p>\n");
+        let out = render_blocks(&[para(json!({"class":"   "}))], &EpubLayout::default());
+        assert_eq!(out, "<p>x</p>\n");
+        assert!(build_epub_css(&Some(json!({"indentParagraphs":true}))).contains("p.noindent { text-indent: 0; }"));
+    }
+
+    #[test]
+    fn retarget_font_family() {
+        let p = tmp_project("420");
+        let run = |text: &str, attrs: serde_json::Value| json!({"type":"text","text":text,"marks":[{"type":"textStyle","attrs":attrs}]});
+        let doc1 = json!({"type":"doc","content":[{"type":"paragraph","content":[
+            run("a", json!({"fontFamily":"Georgia","fontSize":14})),
+            run("b", json!({"fontFamily":"Courier New"})),
+            run("c", json!({"fontSize":12})),
+        ]}]});
+        let doc2 = json!({"type":"doc","content":[{"type":"blockquote","content":[{"type":"paragraph","content":[run("d", json!({"fontFamily":"georgia"}))]}]}]});
+        let doc3 = json!({"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"plain"}]}]});
+        for (id, d) in [(1, &doc1), (2, &doc2), (3, &doc3)] { save_chapter(ps(&p), id, d.to_string()).unwrap(); }
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"] = json!([1, 2, 3]);
+        write_project_value(&pf, &v).unwrap();
+        let before3 = fs::read_to_string(p.join("chapters/3.json")).unwrap();
+
+        assert_eq!(retarget_font(ps(&p), "Georgia".into(), "Garamond".into()).unwrap(), 2);
+        let c1 = read_chapter_content(&p, 1).unwrap();
+        let marks = |i: usize| c1["content"][0]["content"][i]["marks"][0]["attrs"].clone();
+        assert_eq!(marks(0), json!({"fontFamily":"Garamond","fontSize":14}));
+        assert_eq!(marks(1), json!({"fontFamily":"Courier New"}));
+        assert_eq!(marks(2), json!({"fontSize":12}));
+        assert_eq!(read_chapter_content(&p, 2).unwrap()["content"][0]["content"][0]["content"][0]["marks"][0]["attrs"]["fontFamily"], "Garamond");
+        assert_eq!(fs::read_to_string(p.join("chapters/3.json")).unwrap(), before3);
+        assert!(!p.join("chapters/1.json.tmp").exists());
+        assert!(read_project_value(&pf).unwrap()["chapterModified"].get("1").is_some());
+
+        assert_eq!(retarget_font(ps(&p), "".into(), "Palatino".into()).unwrap(), 1);
+        assert_eq!(read_chapter_content(&p, 1).unwrap()["content"][0]["content"][2]["marks"][0]["attrs"], json!({"fontFamily":"Palatino","fontSize":12}));
+        assert_eq!(retarget_font(ps(&p), "Nope".into(), "X".into()).unwrap(), 0);
+    }
+
+    #[test]
+    fn sample_epub() {
+        let p = tmp_project("421");
+        write_chapter(&p, 1, "One.");
+        write_chapter(&p, 2, "Two.");
+        fs::create_dir_all(p.join("assets")).unwrap();
+        fs::write(p.join("assets").join("cover.png"), b"\x89PNG fake").unwrap();
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["coverImage"] = json!("cover.png");
+        write_project_value(&pf, &v).unwrap();
+        let out = out_dir("421");
+        let f = write_sample_epub_export(&ps(&p), &ps(&out), vec![1], true, &mut |_| {}).unwrap();
+        assert!(f.contains("_sample_"));
+        let opf = zip_entry(&f, "OEBPS/content.opf");
+        let spine: Vec<&str> = opf.lines().filter(|l| l.contains("<itemref")).collect();
+        assert_eq!(spine.len(), 3, "{}", opf);
+        assert!(spine[0].contains("idref=\"cover\""), "{}", opf);
+        assert!(spine[1].contains("idref=\"ch001\""), "{}", opf);
+        assert!(spine[2].contains("idref=\"sample\""), "{}", opf);
+        assert!(opf.contains("properties=\"cover-image\""));
+        assert!(!opf.contains("ch002"));
+        assert!(zip_entry(&f, "OEBPS/sample.xhtml").contains("the first chapter"));
+        assert!(zip_entry(&f, "OEBPS/cover.xhtml").contains("images/cover.png"));
+        // Without a cover: chapter plus sample page only
+        let f = write_sample_epub_export(&ps(&p), &ps(&out), vec![1], false, &mut |_| {}).unwrap();
+        let opf = zip_entry(&f, "OEBPS/content.opf");
+        assert_eq!(opf.matches("<itemref").count(), 2);
+        assert!(!opf.contains("cover-image"));
+    }
+
+    #[test]
+    fn renumber_chapter_ids() {
+        let p = tmp_project("422");
+        for id in [2, 5, 9, 7] { write_chapter(&p, id, &format!("Text {}.", id)); }
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"] = json!([5, 2, 9, 7]);
+        v["chapterTitles"] = json!({"2":"Two","5":"Five","9":"Nine","7":"Seven","40":"Stale"});
+        v["excludedChapters"] = json!([9]);
+        v["lastOpenedChapter"] = json!(9);
+        v["parts"] = json!([{"title":"Part One","chapterIds":[5, 2]}]);
+        write_project_value(&pf, &v).unwrap();
+        trash_chapter(ps(&p), 7).unwrap();
+
+        let changes = renumber_chapters(ps(&p)).unwrap();
+        assert_eq!(changes.get(&5), Some(&1));
+        assert_eq!(changes.get(&9), Some(&3));
+        assert_eq!(changes.get(&7), Some(&4));
+        assert!(!changes.contains_key(&2));
+        let v = read_project_value(&pf).unwrap();
+        assert_eq!(v["chapterOrder"], json!([1, 2, 3]));
+        assert_eq!(v["chapterTitles"], json!({"1":"Five","2":"Two","3":"Nine"}));
+        assert_eq!(v["excludedChapters"], json!([3]));
+        assert_eq!(v["lastOpenedChapter"], json!(3));
+        assert_eq!(v["parts"][0]["chapterIds"], json!([1, 2]));
+        assert!(v["trash"].get("4").is_some());
+        assert_eq!(tiptap_to_plain_text(&read_chapter_content(&p, 1).unwrap()).trim(), "Text 5.");
+        assert_eq!(tiptap_to_plain_text(&read_chapter_content(&p, 3).unwrap()).trim(), "Text 9.");
+        assert!(!p.join("chapters").join("5.json").exists());
+        assert!(!p.join("chapters").join("9.json").exists());
+        assert_eq!(fs::read_dir(p.join("chapters")).unwrap().filter(|e| e.as_ref().unwrap().path().extension().is_some_and(|x| x == "json")).count(), 3);
+        assert_eq!(restore_chapter(ps(&p), 4).unwrap().title, "Seven");
+        // Already contiguous: nothing to do
+        assert!(renumber_chapters(ps(&p)).unwrap().is_empty());
+
+        // An orphan file at a target name blocks the whole renumber
+        let q = tmp_project("422b");
+        write_chapter(&q, 3, "Three.");
+        fs::write(q.join("chapters").join("1.json"), "{}").unwrap();
+        assert!(renumber_chapters(ps(&q)).is_err());
+        assert!(q.join("chapters").join("3.json").exists());
+        assert_eq!(read_project_value(&q.join("project.json")).unwrap()["chapterOrder"], json!([3]));
+    }
+
+    #[test]
+    fn form_feed_import() {
+        let p = tmp_project("423");
+        let f = std::env::temp_dir().join(format!("scout-test-423-{}.txt", std::process::id()));
+        fs::write(&f, "The Arrival\n\nShe came by train.\x0C\n\nThe Storm\nRain all night.\n\x0C\x0C  \n\x0CEpilogue\nQuiet.").unwrap();
+        let opts = ImportOptions { extract_title_from_delimiter: true, split_on_form_feed: true, ..Default::default() };
+        let chs = import_chapter_files(&ps(&p), vec![ps(&f)], &opts, None).unwrap();
+        let titles: Vec<&str> = chs.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["The Arrival", "The Storm", "Epilogue"]);
+        assert_eq!(tiptap_to_plain_text(chs[0].content.as_ref().unwrap()).trim(), "She came by train.");
+        assert_eq!(validate_import_file(ps(&f), None, Some(false), None, Some(true)).unwrap().chapter_count, 3);
+        let sections = split_on_form_feeds("One\nbody\x0CTwo", false);
+        assert_eq!(sections, vec![(None, "One\nbody".to_string()), (None, "Two".to_string())]);
+    }
+
+    #[test]
+    fn dictionary_export_import() {
+        let dirs = tmp_app_dirs("424");
+        let a = tmp_project("424a");
+        let b = tmp_project("424b");
+        for w in ["Eldoria", "Kael", "Vrenn"] {
+            add_to_dictionary_in(&dirs, w.into(), "project".into(), Some(ps(&a))).unwrap();
+        }
+        add_to_dictionary_in(&dirs, "kael".into(), "project".into(), Some(ps(&b))).unwrap();
+        add_to_dictionary_in(&dirs, "Zzyx".into(), "project".into(), Some(ps(&b))).unwrap();
+        let dest = std::env::temp_dir().join(format!("scout-test-424-{}.json", std::process::id()));
+        assert_eq!(export_dictionary_in(&dirs, Some(ps(&a)), ps(&dest)).unwrap(), 3);
+        assert_eq!(import_dictionary_in(&dirs, ps(&dest), "project".into(), Some(ps(&b))).unwrap(), 2);
+        assert_eq!(load_dictionary(&b.join("custom_dictionary.json")).unwrap(), vec!["Eldoria", "Vrenn", "Zzyx", "kael"]);
+        assert_eq!(import_dictionary_in(&dirs, ps(&dest), "project".into(), Some(ps(&b))).unwrap(), 0);
+        assert!(import_dictionary_in(&dirs, ps(&dest), "project".into(), None).is_err());
+        assert!(import_dictionary_in(&dirs, "/nonexistent/d.json".into(), "project".into(), Some(ps(&b))).is_err());
+
+        // Without a project, the global dictionary in the config folder is used
+        assert_eq!(import_dictionary_in(&dirs, ps(&dest), "global".into(), None).unwrap(), 3);
+        assert_eq!(load_dictionary(&dirs.config.join("custom_dictionary.json")).unwrap().len(), 3);
+        assert_eq!(export_dictionary_in(&dirs, None, ps(&dest)).unwrap(), 3);
+    }
+
+    #[test]
+    fn chapter_file_check() {
+        let p = tmp_project("426");
+        write_chapter(&p, 1, "Fine.");
+        write_chapter(&p, 2, "Bom.");
+        let f2 = p.join("chapters").join("2.json");
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend(fs::read(&f2).unwrap());
+        fs::write(&f2, &bytes).unwrap();
+        fs::write(p.join("chapters").join("3.json"), b"\xFF\xFE{\x00}\x00").unwrap();
+        fs::write(p.join("chapters").join("4.json"), "{\"type\": \"doc\",").unwrap();
+        let issues = check_chapter_files(ps(&p)).unwrap();
+        let ids: Vec<u32> = issues.iter().map(|i| i.chapter_id).collect();
+        assert_eq!(ids, vec![2, 3, 4]);
+        assert_eq!(issues[0].encoding, "UTF-8 (BOM)");
+        assert_eq!(issues[0].problems.len(), 1, "{:?}", issues[0].problems);
+        assert!(issues[0].problems[0].contains("byte order mark"));
+        assert_eq!(issues[1].encoding, "UTF-16LE");
+        assert!(issues[2].problems[0].starts_with("Invalid JSON"));
+        // Nothing was modified
+        assert_eq!(fs::read(&f2).unwrap(), bytes);
+    }
+
+    #[test]
+    fn json_indent_setting() {
+        let doc = text_to_tiptap_json(&"A paragraph of prose.\n\n".repeat(50), &ImportOptions::default());
+        let pretty = format_json(&doc, JsonIndent::Spaces(2)).unwrap();
+        assert_eq!(pretty, serde_json::to_string_pretty(&doc).unwrap());
+        let compact = format_json(&doc, JsonIndent::Compact).unwrap();
+        assert!(compact.len() < pretty.len(), "{} vs {}", compact.len(), pretty.len());
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&compact).unwrap(), doc);
+        let tabs = format_json(&doc, JsonIndent::Tabs).unwrap();
+        assert!(tabs.contains("\n\t\"content\""));
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&tabs).unwrap(), doc);
+        assert_eq!(JsonIndent::parse("4").unwrap(), JsonIndent::Spaces(4));
+        assert!(JsonIndent::parse("0").is_err() && JsonIndent::parse("wide").is_err());
+
+        assert_eq!(layout_json(&doc, None, JsonIndent::Compact).unwrap(), compact);
+        assert_eq!(layout_json(&doc, Some(JsonIndent::Tabs), JsonIndent::Compact).unwrap(), tabs);
+
+        // The setting lives in config.json; applying it is left to the command
+        let dirs = tmp_app_dirs("427");
+        assert_eq!(get_json_indent_in(&dirs).unwrap(), None);
+        assert_eq!(set_json_indent_in(&dirs, "tabs".into()).unwrap(), Some(JsonIndent::Tabs));
+        assert_eq!(get_json_indent_in(&dirs).unwrap().as_deref(), Some("tabs"));
+        assert_eq!(set_json_indent_in(&dirs, "".into()).unwrap(), None);
+        assert_eq!(get_json_indent_in(&dirs).unwrap(), None);
+        assert!(set_json_indent_in(&dirs, "wide".into()).is_err());
+
+        // Chapters are saved in the given format, or as sent without one
+        let p = tmp_project("427");
+        save_chapter_as(ps(&p), 1, compact.clone(), Some(JsonIndent::Tabs)).unwrap();
+        assert_eq!(fs::read_to_string(p.join("chapters").join("1.json")).unwrap(), tabs);
+        save_chapter_as(ps(&p), 1, compact.clone(), None).unwrap();
+        assert_eq!(fs::read_to_string(p.join("chapters").join("1.json")).unwrap(), compact);
+
+        // Imported chapters are written via a temporary file
+        let src = ImportSource { title: Some("Imported".into()), content: "Imported text.".into(), markdown: false };
+        let imported = import_sources(&ps(&p), std::iter::once(Ok(src)), &ImportOptions::default(), None).unwrap();
+        let imported_file = p.join("chapters").join(format!("{}.json", imported[0].id));
+        assert!(serde_json::from_str::<serde_json::Value>(&fs::read_to_string(&imported_file).unwrap()).is_ok());
+        assert!(!p.join("chapters").join(format!("{}.json.tmp", imported[0].id)).exists());
+    }
+
+    #[test]
+    fn single_line_paragraph_import() {
+        let p = tmp_project("429");
+        let text = "First paragraph.\nSecond paragraph.\n  Third, indented.\n\nAfter the break.\n";
+        let chs = import_text(ps(&p), text.into(), "text".into(), ImportOptions { paragraph_mode: ParagraphMode::SingleLine, ..Default::default() }).unwrap();
+        let nodes = chs[0].content.as_ref().unwrap()["content"].as_array().unwrap().clone();
+        let kinds: Vec<&str> = nodes.iter().map(|n| n["type"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["paragraph", "paragraph", "paragraph", "horizontalRule", "paragraph"]);
+        assert_eq!(nodes[2]["content"][0]["text"], "Third, indented.");
+        // Default mode keeps the old behaviour: lines joined into one paragraph
+        let chs = import_text(ps(&p), text.into(), "text".into(), ImportOptions::default()).unwrap();
+        assert_eq!(chs[0].content.as_ref().unwrap()["content"].as_array().unwrap().len(), 2);
+        // The frontend sends the options as one object; unknown modes are rejected
+        let options: ImportOptions = serde_json::from_value(json!({"paragraphMode": "single-line", "titleTemplate": " "})).unwrap();
+        assert_eq!(options.paragraph_mode, ParagraphMode::SingleLine);
+        assert!(options.extract_title_from_delimiter);
+        assert!(options.checked().unwrap().title_template.is_none());
+        assert!(serde_json::from_value::<ImportOptions>(json!({"paragraphMode": "lines"})).is_err());
+        assert!(ImportOptions { title_template: Some("{bad}".into()), ..Default::default() }.checked().is_err());
+    }
+
+    #[test]
+    fn code_skipped_in_prose_tools() {
+        let p = tmp_project("430");
+        let doc = json!({"type":"doc","content":[
+            {"type":"paragraph","content":[{"type":"text","text":"Call \"it\" quickly: "},{"type":"text","text":"f(\"x\")","marks":[{"type":"code"}]}]},
+            {"type":"codeBlock","content":[{"type":"text","text":"quickly  say \"hi\""}]}]});
+        write_chapter(&p, 1, "x");
+        fs::write(p.join("chapters/1.json"), doc.to_string()).unwrap();
+        assert!(is_in_code_context(&doc["content"][1]));
+        assert!(is_in_code_context(&doc["content"][0]["content"][1]));
+        assert!(!is_in_code_context(&doc["content"][0]["content"][0]));
+
+        let r = style_report(ps(&p), vec![], None, None).unwrap();
+        assert_eq!(r.chapters[0].words, 3);
+        assert_eq!(style_report(ps(&p), vec![], None, Some(true)).unwrap().chapters[0].words, 8);
+
+        assert_eq!(normalize_typography(ps(&p), vec![], None).unwrap(), 1);
+        let d = read_chapter_content(&p, 1).unwrap();
+        assert_eq!(d["content"][0]["content"][0]["text"], "Call \u{201c}it\u{201d} quickly: ");
+        assert_eq!(d["content"][0]["content"][1]["text"], "f(\"x\")");
+        assert_eq!(d["content"][1], doc["content"][1]);
+        // Opted in: code is normalized too
+        assert_eq!(normalize_typography(ps(&p), vec![], Some(true)).unwrap(), 1);
+        let d = read_chapter_content(&p, 1).unwrap();
+        assert_eq!(d["content"][1]["content"][0]["text"], "quickly say \u{201c}hi\u{201d}");
+    }
+
+    #[test]
+    fn book_preview_html() {
+        let p = tmp_project("431");
+        write_chapter(&p, 1, "Opening lines.");
+        write_chapter(&p, 2, "Closing lines.");
+        write_chapter(&p, 3, "Left out.");
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"] = json!([2, 1, 3]);
+        v["chapterTitles"] = json!({"1":"Beta","2":"Alpha","3":"Gamma"});
+        v["excludedChapters"] = json!([3]);
+        write_project_value(&pf, &v).unwrap();
+        let html = render_book_html(ps(&p), vec![]).unwrap();
+        assert!(html.starts_with("<style>"));
+        assert_eq!(html.matches("<style>").count(), 1);
+        let pos = |s: &str| html.find(s).unwrap_or_else(|| panic!("{} missing", s));
+        assert!(pos("Alpha") < pos("Closing lines.") && pos("Closing lines.") < pos("Beta") && pos("Beta") < pos("Opening lines."));
+        assert!(pos("id=\"chapter-2\"") < pos("id=\"chapter-1\""));
+        assert!(!html.contains("Left out."));
+        let one = render_book_html(ps(&p), vec![3, 1]).unwrap();
+        assert!(one.find("Gamma").unwrap() > one.find("Beta").unwrap());
+        assert!(!one.contains("Alpha"));
+
+        // Each chapter body matches the EPUB's, apart from inlined images
+        let content = read_chapter_content(&p, 1);
+        let (epub_body, _) = chapter_body_xhtml("Beta", &content, None, &EpubLayout { show_chapter_titles: true, ..EpubLayout::for_project(&v, &None) });
+        assert!(html.contains(&epub_body));
+    }
+
+    #[test]
+    fn book_preview_html_footnotes() {
+        let p = tmp_project("431b");
+        let note = json!({"type":"doc","content":[
+            {"type":"paragraph","content":[{"type":"text","text":"Noted"},{"type":"footnoteReference","attrs":{"label":"1"}}]},
+            {"type":"footnoteDefinition","attrs":{"label":"1"},"content":[{"type":"paragraph","content":[{"type":"text","text":"A note."}]}]}]});
+        save_chapter(ps(&p), 1, note.to_string()).unwrap();
+        save_chapter(ps(&p), 2, note.to_string()).unwrap();
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"] = json!([1, 2]);
+        write_project_value(&pf, &v).unwrap();
+        let html = render_book_html(ps(&p), vec![]).unwrap();
+        for id in [1, 2] {
+            assert_eq!(html.matches(&format!("id=\"chapter-{}-fn-1\"", id)).count(), 1, "{}", html);
+            assert_eq!(html.matches(&format!("href=\"#chapter-{}-fn-1\"", id)).count(), 1, "{}", html);
+        }
+        assert!(!html.contains("\"fn-1\"") && !html.contains("\"#fn-1\""));
+    }
+
+    #[test]
+    fn blockquote_cite_attribution() {
+        let layout = EpubLayout::default();
+        let quote = |attrs: serde_json::Value| json!({"type":"blockquote","attrs":attrs,"content":[
+            {"type":"paragraph","content":[{"type":"text","text":"Hope is the thing with feathers"}]}]});
+        let html = render_blocks(&[quote(json!({"cite":"Emily Dickinson & co"}))], &layout);
+        let body = html.find("feathers").unwrap();
+        let cite = html.find("<p class=\"quote-attribution\"><cite>\u{2014} Emily Dickinson &amp; co</cite></p>").unwrap();
+        assert!(body < cite && cite < html.find("</blockquote>").unwrap());
+        assert!(!render_blocks(&[quote(json!({}))], &layout).contains("<cite>"));
+        assert!(!render_blocks(&[quote(json!({"cite":"  "}))], &layout).contains("<cite>"));
+        assert!(build_epub_css(&None).contains("p.quote-attribution"));
+    }
+
+    #[test]
+    fn verify_assets_mislabeled_image() {
+        let p = tmp_project("433");
+        let assets = p.join("assets");
+        fs::create_dir_all(&assets).unwrap();
+        fs::write(assets.join("map.jpg"), b"\x89PNG\r\n\x1a\nrest").unwrap();
+        fs::write(assets.join("map.png"), b"\x89PNG\r\n\x1a\nother").unwrap();
+        fs::write(assets.join("ok.jpeg"), [0xFF, 0xD8, 0xFF, 0xE0]).unwrap();
+        fs::write(assets.join("notes.txt"), b"hello").unwrap();
+        let doc = json!({"type":"doc","content":[{"type":"imageBleed","attrs":{"name":"map.jpg","src":"data:image/jpeg;base64,AAAA"}}]});
+        save_chapter(ps(&p), 1, doc.to_string()).unwrap();
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"] = json!([1]);
+        v["coverImage"] = json!("map.jpg");
+        write_project_value(&pf, &v).unwrap();
+
+        let found = verify_assets(ps(&p), None).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "map.jpg");
+        assert_eq!(found[0].actual_mime, "image/png");
+        assert_eq!(found[0].suggested_name, "map-1.png");
+        assert!(!found[0].repaired && assets.join("map.jpg").exists());
+
+        // project.json can't be replaced while a directory sits at its temp path: nothing changes
+        let original = fs::read_to_string(p.join("chapters/1.json")).unwrap();
+        fs::create_dir(p.join("project.json.tmp")).unwrap();
+        assert!(verify_assets(ps(&p), Some(true)).is_err());
+        assert!(assets.join("map.jpg").exists() && !assets.join("map-1.png").exists());
+        assert_eq!(fs::read_to_string(p.join("chapters/1.json")).unwrap(), original);
+        assert_eq!(read_project_value(&pf).unwrap()["coverImage"], "map.jpg");
+        fs::remove_dir(p.join("project.json.tmp")).unwrap();
+
+        let fixed = verify_assets(ps(&p), Some(true)).unwrap();
+        assert!(fixed[0].repaired);
+        assert!(!assets.join("map.jpg").exists() && assets.join("map-1.png").exists());
+        let d: serde_json::Value = serde_json::from_str(&fs::read_to_string(p.join("chapters/1.json")).unwrap()).unwrap();
+        assert_eq!(d["content"][0]["attrs"]["name"], "map-1.png");
+        assert_eq!(d["content"][0]["attrs"]["src"], "data:image/png;base64,AAAA");
+        assert_eq!(read_project_value(&pf).unwrap()["coverImage"], "map-1.png");
+        assert!(verify_assets(ps(&p), None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn chapter_target_progress() {
+        let p = tmp_project("434");
+        write_chapter(&p, 1, &words(250));
+        write_chapter(&p, 2, &words(10));
+        set_chapter_target(ps(&p), 1, Some(1000)).unwrap();
+        assert!(set_chapter_target(ps(&p), 2, Some(0)).is_err());
+        assert!(set_chapter_target(ps(&p), 9, Some(100)).is_err());
+        let progress = chapter_targets_progress(ps(&p)).unwrap();
+        assert_eq!(progress.len(), 1);
+        assert_eq!((progress[0].chapter_id, progress[0].target, progress[0].current), (1, 1000, 250));
+        assert_eq!(progress[0].percent, 25.0);
+        set_chapter_target(ps(&p), 2, Some(5)).unwrap();
+        assert_eq!(chapter_targets_progress(ps(&p)).unwrap()[1].percent, 200.0);
+        delete_chapter(ps(&p), 1).unwrap();
+        let v = read_project_value(&p.join("project.json")).unwrap();
+        assert!(v["chapterTargets"].get("1").is_none());
+        set_chapter_target(ps(&p), 2, None).unwrap();
+        assert!(chapter_targets_progress(ps(&p)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn single_file_epub() {
+        let p = tmp_project("435");
+        let note = json!({"type":"doc","content":[
+            {"type":"paragraph","content":[{"type":"text","text":"Noted"},{"type":"footnoteReference","attrs":{"label":"1"}}]},
+            {"type":"footnoteDefinition","attrs":{"label":"1"},"content":[{"type":"paragraph","content":[{"type":"text","text":"A note."}]}]}]});
+        save_chapter(ps(&p), 1, note.to_string()).unwrap();
+        save_chapter(ps(&p), 2, note.to_string()).unwrap();
+        write_chapter(&p, 3, "Third.");
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"] = json!([1, 2, 3]);
+        v["parts"] = json!([{"title":"Part One","chapterIds":[2, 3]}]);
+        write_project_value(&pf, &v).unwrap();
+        let out = p.join("out");
+        fs::create_dir_all(&out).unwrap();
+        let opts = EpubExportOptions { single_file: true, ..Default::default() };
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &opts, &mut |_| {}).unwrap();
+        let opf = zip_entry(&f, "OEBPS/content.opf");
+        assert_eq!(opf.matches("<itemref ").count(), 1, "{}", opf);
+        assert!(opf.contains("<itemref idref=\"content\"/>") && !opf.contains("chapters/ch001"));
+        let content = zip_entry(&f, "OEBPS/content.xhtml");
+        for id in ["ch001", "ch002", "ch003", "part01"] {
+            assert!(content.contains(&format!("id=\"{}\"", id)), "{} missing", id);
+        }
+        assert!(content.find("id=\"part01\"").unwrap() < content.find("id=\"ch002\"").unwrap());
+        assert!(content.contains("id=\"ch001-fn-1\"") && content.contains("href=\"#ch002-fn-1\""));
+        let nav = zip_entry(&f, "OEBPS/nav.xhtml");
+        assert!(nav.contains("href=\"content.xhtml#ch003\"") && nav.contains("href=\"content.xhtml#part01\""));
+        assert!(zip_entry(&f, "OEBPS/toc.ncx").contains("src=\"content.xhtml#ch001\""));
+        let mut z = zip::ZipArchive::new(fs::File::open(&f).unwrap()).unwrap();
+        assert!(z.by_name("OEBPS/chapters/ch001.xhtml").is_err() && z.by_name("OEBPS/parts/part01.xhtml").is_err());
+
+        // The cover, contents and sample pages become sections of content.xhtml too
+        fs::create_dir_all(p.join("assets")).unwrap();
+        fs::write(p.join("assets").join("cover.png"), b"\x89PNG fake").unwrap();
+        let mut v = read_project_value(&pf).unwrap();
+        v["coverImage"] = json!("cover.png");
+        write_project_value(&pf, &v).unwrap();
+        let opts = EpubExportOptions { single_file: true, include_toc_page: true, cover: true, sample: true, ..Default::default() };
+        let f = write_epub_export(&ps(&p), &ps(&out), vec![], &opts, &mut |_| {}).unwrap();
+        let opf = zip_entry(&f, "OEBPS/content.opf");
+        assert_eq!(opf.matches("<itemref ").count(), 1, "{}", opf);
+        assert!(opf.contains("properties=\"cover-image\"") && !opf.contains("cover.xhtml"));
+        let content = zip_entry(&f, "OEBPS/content.xhtml");
+        let pos = |s: &str| content.find(s).unwrap_or_else(|| panic!("{} missing", s));
+        assert!(pos("id=\"cover\"") < pos("id=\"toc-page\"") && pos("id=\"toc-page\"") < pos("id=\"ch001\""));
+        assert!(pos("id=\"ch003\"") < pos("id=\"sample\""));
+        assert!(content.contains("src=\"images/cover.png\"") && content.contains("href=\"content.xhtml#ch002\""));
+        let mut z = zip::ZipArchive::new(fs::File::open(&f).unwrap()).unwrap();
+        for page in ["cover", "toc", "sample"] {
+            assert!(z.by_name(&format!("OEBPS/{}.xhtml", page)).is_err(), "{} written", page);
+        }
+        let opts = EpubExportOptions { version: EpubVersion::Epub2, ..opts };
+        let opf = zip_entry(&write_epub_export(&ps(&p), &ps(&out), vec![], &opts, &mut |_| {}).unwrap(), "OEBPS/content.opf");
+        assert!(opf.contains("href=\"content.xhtml#cover\"") && opf.contains("href=\"content.xhtml#toc-page\""), "{}", opf);
+    }
+
+    #[test]
+    fn text_cover_svg() {
+        let p = tmp_project("436");
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["title"] = json!("The Long & Winding Road to Somewhere Else");
+        v["author"] = json!("Jo <Writer>");
+        write_project_value(&pf, &v).unwrap();
+        let out = p.join("out").join("cover.svg");
+        let written = generate_text_cover(ps(&p), ps(&out), Some("#ABC".into()), None, None).unwrap();
+        let svg = fs::read_to_string(&written).unwrap();
+        assert!(svg.contains("<svg ") && svg.contains("fill=\"#abc\"") && svg.contains("fill=\"#ffffff\""));
+        let text: String = svg.split('>').filter_map(|s| s.split('<').next()).collect::<Vec<_>>().join(" ");
+        assert!(text.split_whitespace().collect::<Vec<_>>().join(" ").contains("The Long &amp; Winding Road to Somewhere Else"), "{}", svg);
+        assert!(svg.contains("Jo &lt;Writer&gt;"));
+        assert!(svg.matches("<tspan").count() > 1);
+        assert!(read_project_value(&pf).unwrap().get("coverImage").is_none());
+        assert!(generate_text_cover(ps(&p), ps(&out), Some("red\"/><script".into()), None, None).is_err());
+        generate_text_cover(ps(&p), ps(&out), None, Some("#000000".into()), Some(true)).unwrap();
+        assert_eq!(read_project_value(&pf).unwrap()["coverImage"], "cover.svg");
+        assert!(fs::read_to_string(p.join("assets/cover.svg")).unwrap().contains("fill=\"#000000\""));
+        // An asset already called cover.svg is left alone
+        generate_text_cover(ps(&p), ps(&out), Some("#123456".into()), None, Some(true)).unwrap();
+        assert_eq!(read_project_value(&pf).unwrap()["coverImage"], "cover_1.svg");
+        assert!(fs::read_to_string(p.join("assets/cover.svg")).unwrap().contains("fill=\"#000000\""));
+        assert!(fs::read_to_string(p.join("assets/cover_1.svg")).unwrap().contains("fill=\"#123456\""));
+        // Generating straight into assets/ reuses the file instead of numbering a copy
+        generate_text_cover(ps(&p), ps(&p.join("assets/cover.svg")), None, None, Some(true)).unwrap();
+        assert_eq!(read_project_value(&pf).unwrap()["coverImage"], "cover.svg");
+        assert!(!p.join("assets/cover_2.svg").exists());
+
+        // A very long title shrinks to fit instead of running off the top of the cover
+        let title = "word ".repeat(120);
+        let svg = build_text_cover_svg(&title, "A", "#000", "#fff");
+        let ys: Vec<i64> = svg.split("<tspan").skip(1)
+            .map(|t| t.split("y=\"").nth(1).unwrap().split('"').next().unwrap().parse().unwrap())
+            .collect();
+        assert!(ys.len() > 20);
+        assert!(ys[0] > TEXT_COVER_TITLE_TOP as i64, "{:?}", ys);
+        assert!(*ys.last().unwrap() <= TEXT_COVER_TITLE_BOTTOM as i64, "{:?}", ys);
+        // Past what fits at the smallest font, the title is cut short instead
+        let svg = build_text_cover_svg(&"word ".repeat(400), "A", "#000", "#fff");
+        let ys: Vec<i64> = svg.split("<tspan").skip(1)
+            .map(|t| t.split("y=\"").nth(1).unwrap().split('"').next().unwrap().parse().unwrap())
+            .collect();
+        assert!(svg.contains("font-size=\"24\"") && svg.contains("word\u{2026}</tspan>"), "{}", svg);
+        assert!(ys[0] > TEXT_COVER_TITLE_TOP as i64, "{:?}", ys);
+        assert!(*ys.last().unwrap() <= TEXT_COVER_TITLE_BOTTOM as i64, "{:?}", ys);
+    }
+
+    #[test]
+    fn flatten_chapter_to_paragraphs() {
+        let p = tmp_project("437");
+        let b = |t: &str| json!({"type":"text","text":t,"marks":[{"type":"bold"}]});
+        let doc = json!({"type":"doc","content":[
+            {"type":"heading","attrs":{"level":2},"content":[b("Bold Heading")]},
+            {"type":"paragraph","attrs":{"textAlign":"center"},"content":[{"type":"text","text":"Plain "},b("and bold"),{"type":"hardBreak"},{"type":"text","text":"next","marks":[{"type":"italic"}]}]},
+            {"type":"bulletList","content":[
+                {"type":"listItem","content":[{"type":"paragraph","content":[{"type":"text","text":"One"}]}]},
+                {"type":"listItem","content":[{"type":"paragraph","content":[b("Two")]}]}]},
+            {"type":"horizontalRule"}]});
+        save_chapter(ps(&p), 1, doc.to_string()).unwrap();
+        let pf = p.join("project.json");
+        let mut v = read_project_value(&pf).unwrap();
+        v["chapterOrder"] = json!([1]);
+        write_project_value(&pf, &v).unwrap();
+
+        assert!(flatten_chapter(ps(&p), 1).unwrap());
+        let d = read_chapter_content(&p, 1).unwrap();
+        let blocks = d["content"].as_array().unwrap();
+        assert_eq!(blocks.len(), 4);
+        assert!(blocks.iter().all(|b| b["type"] == "paragraph" && b.get("attrs").is_none()));
+        assert!(!d.to_string().contains("marks"));
+        assert_eq!(blocks[0]["content"], json!([{"type":"text","text":"Bold Heading"}]));
+        assert_eq!(blocks[1]["content"], json!([{"type":"text","text":"Plain and bold"},{"type":"hardBreak"},{"type":"text","text":"next"}]));
+        assert_eq!(blocks[3]["content"][0]["text"], "Two");
+        assert!(!flatten_chapter(ps(&p), 1).unwrap());
+        assert!(flatten_chapter(ps(&p), 5).is_err());
+
+        // Footnotes move to the end behind their "[label]" markers; images stay where they were
+        let image = json!({"type":"imageBleed","attrs":{"name":"map.png"}});
+        let doc = json!({"type":"doc","content":[
+            {"type":"paragraph","content":[{"type":"text","text":"Noted"},{"type":"footnoteReference","attrs":{"label":"a"}},{"type":"text","text":" here."}]},
+            {"type":"footnoteDefinition","attrs":{"label":"a"},"content":[
+                {"type":"paragraph","content":[b("The note.")]},
+                {"type":"paragraph","content":[{"type":"text","text":"More."}]}]},
+            image.clone(),
+            {"type":"paragraph","content":[{"type":"text","text":"After."}]}]});
+        save_chapter(ps(&p), 1, doc.to_string()).unwrap();
+        assert!(flatten_chapter(ps(&p), 1).unwrap());
+        let d = read_chapter_content(&p, 1).unwrap();
+        let text = |t: &str| json!({"type":"paragraph","content":[{"type":"text","text":t}]});
+        assert_eq!(d["content"], json!([text("Noted[a] here."), image, text("After."), text("[a] The note."), text("More.")]));
+        assert!(!flatten_chapter(ps(&p), 1).unwrap());
+    }
+}
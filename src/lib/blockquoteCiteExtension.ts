@@ -0,0 +1,27 @@
+import { Extension } from '@tiptap/core'
+
+/**
+ * Keeps a `cite` attribute on blockquotes (the quote's attribution, e.g. "Emily Dickinson")
+ * so it survives editing and EPUB export can print it after the quote.
+ */
+export const BlockquoteCite = Extension.create({
+	name: 'blockquoteCite',
+
+	addGlobalAttributes() {
+		return [
+			{
+				types: ['blockquote'],
+				attributes: {
+					cite: {
+						default: null,
+						parseHTML: (element: HTMLElement) => element.getAttribute('cite') || null,
+						renderHTML: (attributes: Record<string, any>) => {
+							if (!attributes.cite) return {}
+							return { cite: attributes.cite }
+						},
+					},
+				},
+			},
+		]
+	},
+})
@@ -2,45 +2,51 @@ import { Extension } from '@tiptap/core'
 import { Plugin, PluginKey } from 'prosemirror-state'
 import { Decoration, DecorationSet } from 'prosemirror-view'
 import type { Node } from 'prosemirror-model'
+import { dictionaryRanges, phrasePattern } from './dictionaryMatch'
 
 export const DictionaryPluginKey = new PluginKey<DecorationSet>('customDictionary')
 
 // Module-level word set — updated externally via setDictionaryWords / addDictionaryWord
 const customWords = new Set<string>()
 
+// Multi-word entries ("McKinley Park"), matched as phrases (see phrasePattern)
+const customPhrases = new Map<string, RegExp>()
+
+function addEntry(entry: string): void {
+	const words = entry.trim().toLowerCase().split(/\s+/).filter(Boolean)
+	if (words.length === 0) return
+	if (words.length === 1) {
+		customWords.add(words[0])
+		return
+	}
+	const key = words.join(' ')
+	if (!customPhrases.has(key)) {
+		customPhrases.set(key, phrasePattern(words))
+	}
+}
+
 export function setDictionaryWords(words: string[]): void {
 	customWords.clear()
+	customPhrases.clear()
 	for (const word of words) {
-		customWords.add(word.toLowerCase())
+		addEntry(word)
 	}
 }
 
 export function addDictionaryWord(word: string): void {
-	customWords.add(word.toLowerCase())
+	addEntry(word)
 }
 
 function buildDecorations(doc: Node): DecorationSet {
-	if (customWords.size === 0) return DecorationSet.empty
+	if (customWords.size === 0 && customPhrases.size === 0) return DecorationSet.empty
 
 	const decorations: Decoration[] = []
 
 	doc.descendants((node, pos) => {
 		if (!node.isText || !node.text) return
 
-		const text = node.text
-		const wordRegex = /\b\w+\b/g
-		let match: RegExpExecArray | null
-
-		while ((match = wordRegex.exec(text)) !== null) {
-			if (customWords.has(match[0].toLowerCase())) {
-				decorations.push(
-					Decoration.inline(
-						pos + match.index,
-						pos + match.index + match[0].length,
-						{ spellcheck: 'false' }
-					)
-				)
-			}
+		for (const [from, to] of dictionaryRanges(node.text, customWords, customPhrases.values())) {
+			decorations.push(Decoration.inline(pos + from, pos + to, { spellcheck: 'false' }))
 		}
 	})
 
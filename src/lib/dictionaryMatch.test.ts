@@ -0,0 +1,29 @@
+import { test } from 'node:test'
+import assert from 'node:assert/strict'
+import { dictionaryRanges, phrasePattern } from './dictionaryMatch.ts'
+
+function skipped(text: string, words: string[], phrases: string[][]): string[] {
+	return dictionaryRanges(text, new Set(words), phrases.map(phrasePattern)).map(([from, to]) =>
+		text.slice(from, to)
+	)
+}
+
+test('a two-word entry covers both words only when they are adjacent', () => {
+	const park = [['mckinley', 'park']]
+	assert.deepEqual(skipped('We met at McKinley Park today.', [], park), ['McKinley Park'])
+	assert.deepEqual(skipped('We met at McKinley\n  Park today.', [], park), ['McKinley\n  Park'])
+	assert.deepEqual(skipped('McKinley walked to the park.', [], park), [])
+	assert.deepEqual(skipped('Park near McKinley', [], park), [])
+})
+
+test('phrase boundaries count letters from any script', () => {
+	const phrase = [['san', 'jose']]
+	assert.deepEqual(skipped('In San Jose.', [], phrase), ['San Jose'])
+	assert.deepEqual(skipped('In San Joseé.', [], phrase), [])
+	assert.deepEqual(skipped('In éSan Jose.', [], phrase), [])
+	assert.deepEqual(skipped('In San Jose_2', [], phrase), [])
+})
+
+test('single-word entries are still skipped outside phrases', () => {
+	assert.deepEqual(skipped('Kael and Kael Vrenn', ['kael'], [['kael', 'vrenn']]), ['Kael Vrenn', 'Kael'])
+})
@@ -0,0 +1,49 @@
+// Matching of custom dictionary entries against editor text, kept free of editor imports so it
+// can be tested on its own
+
+function escapeRegExp(text: string): string {
+	return text.replace(/[.*+?^${}()|[\]\\]/g, '\\$&')
+}
+
+// A multi-word entry ("McKinley Park") as a phrase: any whitespace between the words, and no
+// letter, digit or underscore (in any script) directly before or after
+export function phrasePattern(words: string[]): RegExp {
+	return new RegExp(
+		`(?<![\\p{L}\\p{N}_])${words.map(escapeRegExp).join('\\s+')}(?![\\p{L}\\p{N}_])`,
+		'giu'
+	)
+}
+
+// [from, to) ranges of `text` the spellchecker should skip. Phrases are matched first and their
+// words are not looked up again; a word of a phrase on its own (not next to the rest) is only
+// skipped if it is also a single-word entry.
+export function dictionaryRanges(
+	text: string,
+	words: ReadonlySet<string>,
+	phrases: Iterable<RegExp>
+): [number, number][] {
+	const ranges: [number, number][] = []
+
+	const phraseRanges: [number, number][] = []
+	for (const phraseRegex of phrases) {
+		phraseRegex.lastIndex = 0
+		let phrase: RegExpExecArray | null
+		while ((phrase = phraseRegex.exec(text)) !== null) {
+			const start = phrase.index
+			phraseRanges.push([start, start + phrase[0].length])
+		}
+	}
+	ranges.push(...phraseRanges)
+
+	const wordRegex = /\b\w+\b/g
+	let match: RegExpExecArray | null
+	while ((match = wordRegex.exec(text)) !== null) {
+		const start = match.index
+		if (phraseRanges.some(([from, to]) => start >= from && start < to)) continue
+		if (words.has(match[0].toLowerCase())) {
+			ranges.push([start, start + match[0].length])
+		}
+	}
+
+	return ranges
+}
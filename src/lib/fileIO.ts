@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { open } from '@tauri-apps/plugin-dialog';
 import {
 	type Chapter,
@@ -9,6 +10,35 @@ import {
 	type LoadProjectResponse,
 	type CreateProjectResponse,
 	type TipTapJSON,
+	type WordGoalProgress,
+	type ChapterTargetProgress,
+	type WordsSinceBaseline,
+	type PageEstimate,
+	type DuplicateChapterPair,
+	type ExportProgress,
+	type StyleReport,
+	type ProjectStats,
+	type ReadabilityReport,
+	type TypographyReport,
+	type ChapterOutline,
+	type ChapterDiff,
+	type MarkerMatch,
+	type LinkEntry,
+	type ProjectIssues,
+	type ChapterFileIssue,
+	type AssetMismatch,
+	type ImportFileReport,
+	type ProjectSummary,
+	type Theme,
+	type JsonIndent,
+	type ParagraphMode,
+	type WindowState,
+	type ExportPreset,
+	type NamedExportPreset,
+	type Epigraph,
+	type RtfLineSpacing,
+	type ExportSizeEstimate,
+	type FontUsage,
 } from './types';
 
 /**
@@ -24,17 +54,137 @@ export async function readConfig(): Promise<Config> {
 }
 
 /**
- * Write the lastProjectPath to the config file
+ * Write the lastProjectPath to the config file (stored relative to the app data dir when possible)
  */
-export async function writeConfig(lastProjectPath: string): Promise<void> {
+export async function writeConfig(lastProjectPath: string, portable: boolean = true): Promise<void> {
 	try {
-		await invoke('write_config', { lastProjectPath });
+		await invoke('write_config', { lastProjectPath, portable });
 	} catch (error) {
 		console.error('Failed to write config:', error);
 		throw error;
 	}
 }
 
+/**
+ * Get the saved app theme ('system' if none has been chosen)
+ */
+export async function getTheme(): Promise<Theme> {
+	try {
+		return await invoke<Theme>('get_theme');
+	} catch (error) {
+		console.error('Failed to read theme:', error);
+		return 'system';
+	}
+}
+
+/**
+ * Save the app theme to the config file
+ */
+export async function setTheme(theme: Theme): Promise<void> {
+	try {
+		await invoke('set_theme', { theme });
+	} catch (error) {
+		console.error('Failed to save theme:', error);
+		throw error;
+	}
+}
+
+/**
+ * Save the window size and position so it can be restored next launch
+ */
+export async function saveWindowState(state: WindowState): Promise<void> {
+	try {
+		await invoke('save_window_state', { state });
+	} catch (error) {
+		console.error('Failed to save window state:', error);
+	}
+}
+
+/**
+ * Read the saved window geometry (null if none saved)
+ */
+export async function readWindowState(): Promise<WindowState | null> {
+	try {
+		return await invoke<WindowState | null>('read_window_state');
+	} catch (error) {
+		console.error('Failed to read window state:', error);
+		return null;
+	}
+}
+
+/**
+ * Get the autosave interval in seconds (defaults to 30)
+ */
+export async function getAutosaveInterval(): Promise<number> {
+	try {
+		return await invoke<number>('get_autosave_interval');
+	} catch (error) {
+		console.error('Failed to read autosave interval:', error);
+		return 30;
+	}
+}
+
+/**
+ * Save the autosave interval in seconds (must be 5–600)
+ */
+export async function setAutosaveInterval(seconds: number): Promise<void> {
+	try {
+		await invoke('set_autosave_interval', { seconds });
+	} catch (error) {
+		console.error('Failed to save autosave interval:', error);
+		throw error;
+	}
+}
+
+/**
+ * Get the author name new projects start with (null when unset)
+ */
+export async function getDefaultAuthor(): Promise<string | null> {
+	try {
+		return await invoke<string | null>('get_default_author');
+	} catch (error) {
+		console.error('Failed to read default author:', error);
+		return null;
+	}
+}
+
+/**
+ * Save the author name new projects start with; an empty name clears it
+ */
+export async function setDefaultAuthor(author: string): Promise<void> {
+	try {
+		await invoke('set_default_author', { author });
+	} catch (error) {
+		console.error('Failed to save default author:', error);
+		throw error;
+	}
+}
+
+/**
+ * Get the configured layout for chapter and project JSON; null when unset (the default)
+ */
+export async function getJsonIndent(): Promise<JsonIndent | null> {
+	try {
+		return await invoke<JsonIndent | null>('get_json_indent');
+	} catch (error) {
+		console.error('Failed to read JSON format:', error);
+		return null;
+	}
+}
+
+/**
+ * Set the layout for chapter and project JSON; applies from the next save of each file.
+ * An empty string restores the default.
+ */
+export async function setJsonIndent(indent: JsonIndent | ''): Promise<void> {
+	try {
+		await invoke('set_json_indent', { indent });
+	} catch (error) {
+		console.error('Failed to save JSON format:', error);
+		throw error;
+	}
+}
+
 /**
  * Create a new project with the given title in the selected directory
  */
@@ -98,6 +248,18 @@ export async function openProject(): Promise<LoadProjectResponse> {
 	}
 }
 
+/**
+ * Remove recent projects whose folders no longer exist; returns the remaining paths
+ */
+export async function pruneRecentProjects(): Promise<string[]> {
+	try {
+		return await invoke<string[]>('prune_recent_projects');
+	} catch (error) {
+		console.error('Failed to prune recent projects:', error);
+		return [];
+	}
+}
+
 /**
  * Open the last project without showing the folder picker
  */
@@ -114,6 +276,17 @@ export async function openRecentProject(projectPath: string): Promise<LoadProjec
 	}
 }
 
+/**
+ * Remember the chapter that is open, so reopening the project returns to it
+ */
+export async function setLastChapter(projectPath: string, chapterId: number): Promise<void> {
+	try {
+		await invoke('set_last_chapter', { projectPath, chapterId });
+	} catch (error) {
+		console.error('Failed to record last chapter:', error);
+	}
+}
+
 /**
  * Save a single chapter's content
  */
@@ -136,12 +309,13 @@ export async function saveChapter(
 }
 
 /**
- * Save project metadata (title, author, chapter order)
+ * Save project metadata (title, author, chapter order); returns the new revision.
+ * Rejects with a "Conflict" error if project.rev is stale (another window saved first).
  */
 export async function saveProjectMetadata(
 	projectPath: string,
 	project: Project
-): Promise<void> {
+): Promise<number> {
 	try {
 		const projectData = {
 			title: project.title,
@@ -151,9 +325,10 @@ export async function saveProjectMetadata(
 			...(project.fontFamily && { fontFamily: project.fontFamily }),
 		};
 
-		await invoke('save_project', {
+		return await invoke<number>('save_project', {
 			projectPath,
 			projectData,
+			expectedRev: project.rev ?? null,
 		});
 	} catch (error) {
 		console.error('Failed to save project:', error);
@@ -176,16 +351,18 @@ export async function getDefaultExportDir(projectPath: string): Promise<string>
 }
 
 /**
- * Update the saved export directory for a project
+ * Update the saved export directory for a project (stored relative to the project when possible)
  */
 export async function saveExportDir(
 	projectPath: string,
-	exportDir: string
+	exportDir: string,
+	portable: boolean = true
 ): Promise<void> {
 	try {
 		await invoke('update_export_dir', {
 			projectPath,
 			newExportDir: exportDir,
+			portable,
 		});
 	} catch (error) {
 		console.error('Failed to save export dir:', error);
@@ -199,13 +376,25 @@ export async function saveExportDir(
 export async function exportProjectToRTF(
 	projectPath: string,
 	exportDir: string,
-	chapterIds: number[]
+	chapterIds: number[],
+	renumber: boolean = false,
+	filenameTemplate?: string,
+	modifiedSince?: string,
+	headingSizes?: Record<number, number>, // heading level -> pt
+	headingOffset: number = 0,             // added to every heading level, clamped to 1–6
+	lineSpacing: RtfLineSpacing = 'single'
 ): Promise<string> {
 	try {
 		return await invoke<string>('export_project', {
 			projectPath,
 			exportDir,
 			chapterIds,
+			renumber,
+			filenameTemplate: filenameTemplate || null,
+			modifiedSince: modifiedSince || null,
+			headingSizes: headingSizes ?? null,
+			headingOffset,
+			lineSpacing,
 		});
 	} catch (error) {
 		console.error('Failed to export project:', error);
@@ -213,26 +402,104 @@ export async function exportProjectToRTF(
 	}
 }
 
+/**
+ * Export chapters as an RTF manuscript in standard submission format (Courier, double-spaced,
+ * title page with word count and contact info)
+ */
+export async function exportManuscript(
+	projectPath: string,
+	exportDir: string,
+	chapterIds: number[]
+): Promise<string> {
+	try {
+		return await invoke<string>('export_manuscript', { projectPath, exportDir, chapterIds });
+	} catch (error) {
+		console.error('Failed to export manuscript:', error);
+		throw error;
+	}
+}
+
+/**
+ * Settings for importChaptersFromFiles and importText; anything left out uses the default
+ */
+export interface ImportOptions {
+	useFilenameAsTitle?: boolean; // files only; pasted text has no filename
+	chapterDelimiter?: string; // blank means no delimiter
+	extractTitleFromDelimiter?: boolean; // default true
+	smartPunctuation?: boolean;
+	titleTemplate?: string;
+	preserveLineBreaks?: boolean;
+	splitOnThematicBreak?: boolean; // new chapter at every ***/--- break instead of the delimiter
+	detectLists?: boolean; // build lists from "1." / "-" lines in .txt files
+	keepBlankLines?: boolean; // one empty paragraph where .txt files have 2+ blank lines
+	splitOnFormFeed?: boolean; // new chapter at every form feed (page break); overrides the others
+	paragraphMode?: ParagraphMode; // how .txt files separate paragraphs
+}
+
 /**
  * Import chapters from text or markdown files
  */
 export async function importChaptersFromFiles(
 	projectPath: string,
 	filePaths: string[],
-	useFilenameAsTitle: boolean = true,
-	chapterDelimiter?: string,
-	extractTitleFromDelimiter: boolean = true
+	options: ImportOptions = {},
+	jobId?: string
 ): Promise<Chapter[]> {
 	try {
 		return await invoke<Chapter[]>('import_chapters', {
 			projectPath,
 			filePaths,
-			useFilenameAsTitle,
+			options: { useFilenameAsTitle: true, ...options },
+			jobId: jobId ?? null,
+		});
+	} catch (error) {
+		console.error('Failed to import chapters:', error);
+		throw error;
+	}
+}
+
+/**
+ * Check a file before importing it (encoding, size, chapter count, warnings); nothing is written
+ */
+export async function validateImportFile(
+	filePath: string,
+	chapterDelimiter?: string,
+	extractTitleFromDelimiter: boolean = true,
+	splitOnThematicBreak: boolean = false,
+	splitOnFormFeed: boolean = false
+): Promise<ImportFileReport> {
+	try {
+		return await invoke<ImportFileReport>('validate_import_file', {
+			filePath,
 			chapterDelimiter: chapterDelimiter || null,
 			extractTitleFromDelimiter,
+			splitOnThematicBreak,
+			splitOnFormFeed,
 		});
 	} catch (error) {
-		console.error('Failed to import chapters:', error);
+		console.error('Failed to validate import file:', error);
+		throw error;
+	}
+}
+
+/**
+ * Import pasted text or markdown as chapters, split by the delimiter if one is given
+ */
+export async function importText(
+	projectPath: string,
+	text: string,
+	format: 'text' | 'markdown',
+	options: ImportOptions = {}
+): Promise<Chapter[]> {
+	try {
+		return await invoke<Chapter[]>('import_text', {
+			projectPath,
+			text,
+			format,
+			options,
+		});
+	} catch (error) {
+		console.error('Failed to import text:', error);
 		throw error;
 	}
 }
@@ -265,11 +532,11 @@ export async function updateProjectFont(projectPath: string, fontFamily: string)
 }
 
 /**
- * Rename a chapter
+ * Rename a chapter; returns the title as stored (trimmed, inner whitespace collapsed)
  */
-export async function renameChapter(projectPath: string, chapterId: number, newTitle: string): Promise<void> {
+export async function renameChapter(projectPath: string, chapterId: number, newTitle: string): Promise<string> {
 	try {
-		await invoke('rename_chapter', {
+		return await invoke<string>('rename_chapter', {
 			projectPath,
 			chapterId,
 			newTitle,
@@ -280,6 +547,50 @@ export async function renameChapter(projectPath: string, chapterId: number, newT
 	}
 }
 
+/**
+ * Rename several chapters in one write (id -> title); returns how many were renamed.
+ * Titles that clash with another chapter get a " (n)" suffix.
+ */
+export async function bulkRenameChapters(
+	projectPath: string,
+	mapping: Record<number, string>
+): Promise<number> {
+	try {
+		return await invoke<number>('bulk_rename_chapters', { projectPath, mapping });
+	} catch (error) {
+		console.error('Failed to rename chapters:', error);
+		throw error;
+	}
+}
+
+/**
+ * Get a chapter's epigraph (null when it has none)
+ */
+export async function getChapterEpigraph(projectPath: string, chapterId: number): Promise<Epigraph | null> {
+	try {
+		return await invoke<Epigraph | null>('get_chapter_epigraph', { projectPath, chapterId });
+	} catch (error) {
+		console.error('Failed to read chapter epigraph:', error);
+		throw error;
+	}
+}
+
+/**
+ * Set a chapter's epigraph, shown before the body in exports; null or blank text removes it
+ */
+export async function setChapterEpigraph(
+	projectPath: string,
+	chapterId: number,
+	epigraph: Epigraph | null
+): Promise<void> {
+	try {
+		await invoke('set_chapter_epigraph', { projectPath, chapterId, epigraph });
+	} catch (error) {
+		console.error('Failed to save chapter epigraph:', error);
+		throw error;
+	}
+}
+
 /**
  * Add a word to a custom dictionary
  */
@@ -300,18 +611,74 @@ export async function addToDictionary(
 	}
 }
 
+/**
+ * Write the project's custom dictionary (the global one if projectPath is null) to a file;
+ * returns the number of words written
+ */
+export async function exportDictionary(projectPath: string | null, destPath: string): Promise<number> {
+	try {
+		return await invoke<number>('export_dictionary', { projectPath, destPath });
+	} catch (error) {
+		console.error('Failed to export dictionary:', error);
+		throw error;
+	}
+}
+
+/**
+ * Merge an exported dictionary file into the global or project dictionary;
+ * returns the number of new words added
+ */
+export async function importDictionary(
+	srcPath: string,
+	scope: 'global' | 'project',
+	projectPath: string | null
+): Promise<number> {
+	try {
+		return await invoke<number>('import_dictionary', { srcPath, scope, projectPath });
+	} catch (error) {
+		console.error('Failed to import dictionary:', error);
+		throw error;
+	}
+}
+
 /**
  * Save project styles to project.json (merged in, other fields preserved)
  */
-export async function saveStyles(projectPath: string, styles: ProjectStyles): Promise<void> {
-	await invoke('save_project', { projectPath, projectData: { styles } });
+export async function saveStyles(projectPath: string, styles: ProjectStyles): Promise<number> {
+	return await invoke<number>('save_project', { projectPath, projectData: { styles } });
+}
+
+/**
+ * Save book-level EPUB settings (reading direction, language) to project.json; returns the new revision
+ */
+export async function saveBookSettings(
+	projectPath: string,
+	settings: Pick<Project, 'direction' | 'language'>
+): Promise<number> {
+	return await invoke<number>('save_project', { projectPath, projectData: settings });
 }
 
 /**
  * Save page settings to project.json (merged in, other fields preserved)
  */
-export async function savePageSettings(projectPath: string, settings: PageSettings): Promise<void> {
-	await invoke('save_project', { projectPath, projectData: { pageSettings: settings } });
+export async function savePageSettings(projectPath: string, settings: PageSettings): Promise<number> {
+	return await invoke<number>('save_project', { projectPath, projectData: { pageSettings: settings } });
+}
+
+/**
+ * Settings for exportProjectToEPUB; anything left out uses the default
+ */
+export interface EpubExportOptions {
+	renumber?: boolean;
+	filenameTemplate?: string;
+	customCssPath?: string;
+	epubVersion?: 2 | 3;
+	modifiedSince?: string;
+	includeTocPage?: boolean;
+	headingOffset?: number;
+	strict?: boolean; // fail if a referenced image asset is missing
+	titlePrefix?: string; // e.g. 'Chapter {n}: ', '{word}. ', '{roman} – '
+	singleFileContent?: boolean; // every page in one content.xhtml, for minimal e-readers
 }
 
 /**
@@ -320,13 +687,24 @@ export async function savePageSettings(projectPath: string, settings: PageSettin
 export async function exportProjectToEPUB(
 	projectPath: string,
 	exportDir: string,
-	chapterIds: number[]
+	chapterIds: number[],
+	options: EpubExportOptions = {}
 ): Promise<string> {
 	try {
 		return await invoke<string>('export_epub', {
 			projectPath,
 			exportDir,
 			chapterIds,
+			renumber: options.renumber ?? false,
+			filenameTemplate: options.filenameTemplate || null,
+			customCssPath: options.customCssPath || null,
+			epubVersion: options.epubVersion ?? 3,
+			modifiedSince: options.modifiedSince || null,
+			includeTocPage: options.includeTocPage ?? false,
+			headingOffset: options.headingOffset ?? 0,
+			strict: options.strict ?? false,
+			titlePrefix: options.titlePrefix || null,
+			singleFileContent: options.singleFileContent ?? false,
 		});
 	} catch (error) {
 		console.error('Failed to export EPUB:', error);
@@ -335,40 +713,766 @@ export async function exportProjectToEPUB(
 }
 
 /**
- * Delete a chapter: removes the chapter file and its entries in project.json
+ * Export a sample EPUB of the chosen chapters (the first three if none), with an optional cover page
+ */
+export async function exportSampleEpub(
+	projectPath: string,
+	exportDir: string,
+	chapterIds: number[] = [],
+	includeCover: boolean = true
+): Promise<string> {
+	try {
+		return await invoke<string>('export_sample_epub', { projectPath, exportDir, chapterIds, includeCover });
+	} catch (error) {
+		console.error('Failed to export sample EPUB:', error);
+		throw error;
+	}
+}
+
+/**
+ * List image assets the chapters (all exported chapters if empty) reference but that are missing
  */
-export async function deleteChapter(projectPath: string, chapterId: number): Promise<void> {
-	await invoke('delete_chapter', { projectPath, chapterId });
+export async function checkMissingAssets(projectPath: string, chapterIds: number[] = []): Promise<string[]> {
+	try {
+		return await invoke<string[]>('check_missing_assets', { projectPath, chapterIds });
+	} catch (error) {
+		console.error('Failed to check assets:', error);
+		throw error;
+	}
 }
 
 /**
- * Copy an image file into the project's assets/ folder and return a data URL for display
+ * Find assets whose contents don't match their extension; with `repair`, rename them and
+ * update the chapters that reference them
  */
-export async function copyAssetAndEncode(
+export async function verifyAssets(projectPath: string, repair = false): Promise<AssetMismatch[]> {
+	try {
+		return await invoke<AssetMismatch[]>('verify_assets', { projectPath, repair });
+	} catch (error) {
+		console.error('Failed to verify assets:', error);
+		throw error;
+	}
+}
+
+/**
+ * Write an SVG text cover (title and author on a solid background) to outPath. Colors are
+ * hex, e.g. '#1d2a44'; with setAsCover it is also copied into assets/ as the project cover.
+ * Returns the path written.
+ */
+export async function generateTextCover(
 	projectPath: string,
-	srcPath: string
-): Promise<{ name: string; dataUrl: string }> {
+	outPath: string,
+	background?: string,
+	textColor?: string,
+	setAsCover: boolean = false
+): Promise<string> {
 	try {
-		return await invoke<{ name: string; dataUrl: string }>('copy_asset_and_encode', {
+		return await invoke<string>('generate_text_cover', {
 			projectPath,
-			srcPath,
+			outPath,
+			background: background || null,
+			textColor: textColor || null,
+			setAsCover,
 		});
 	} catch (error) {
-		console.error('Failed to copy asset:', error);
+		console.error('Failed to generate cover:', error);
 		throw error;
 	}
 }
 
 /**
- * Get all custom dictionary words (global + project)
+ * Estimate the output size of exporting the chapters (all exported chapters if empty) as `format`
  */
-export async function getDictionaryWords(projectPath: string | null): Promise<string[]> {
+export async function estimateExportSize(
+	projectPath: string,
+	chapterIds: number[],
+	format: 'rtf' | 'epub' | 'markdown' | 'docx'
+): Promise<ExportSizeEstimate> {
 	try {
-		return await invoke<string[]>('get_dictionary_words', {
-			projectPath,
-		});
+		return await invoke<ExportSizeEstimate>('estimate_export_size', { projectPath, chapterIds, format });
 	} catch (error) {
-		console.error('Failed to get dictionary words:', error);
-		return [];
+		console.error('Failed to estimate export size:', error);
+		throw error;
+	}
+}
+
+/**
+ * Render a chapter to HTML (with an inline stylesheet) as EPUB export would, for preview
+ */
+export async function renderChapterHtml(projectPath: string, chapterId: number): Promise<string> {
+	try {
+		return await invoke<string>('render_chapter_html', { projectPath, chapterId });
+	} catch (error) {
+		console.error('Failed to render chapter preview:', error);
+		throw error;
+	}
+}
+
+/**
+ * Render the book (all included chapters if chapterIds is empty) to one HTML string for the
+ * reader view; each chapter is a <section id="chapter-N"> in chapter order
+ */
+export async function renderBookHtml(projectPath: string, chapterIds: number[] = []): Promise<string> {
+	try {
+		return await invoke<string>('render_book_html', { projectPath, chapterIds });
+	} catch (error) {
+		console.error('Failed to render book preview:', error);
+		throw error;
+	}
+}
+
+/**
+ * Delete a chapter: removes the chapter file and its entries in project.json.
+ * Returns any other chapterOrder ids that were dropped because their files were missing.
+ */
+export async function deleteChapter(projectPath: string, chapterId: number): Promise<number[]> {
+	return await invoke<number[]>('delete_chapter', { projectPath, chapterId });
+}
+
+/**
+ * Get a chapter's raw TipTap document
+ */
+export async function getChapterJson(projectPath: string, chapterId: number): Promise<TipTapJSON> {
+	try {
+		return await invoke<TipTapJSON>('get_chapter_json', { projectPath, chapterId });
+	} catch (error) {
+		console.error('Failed to read chapter JSON:', error);
+		throw error;
+	}
+}
+
+/**
+ * Replace a chapter's TipTap document; rejected if it isn't a well-formed doc
+ */
+export async function setChapterJson(projectPath: string, chapterId: number, doc: TipTapJSON): Promise<void> {
+	try {
+		await invoke('set_chapter_json', { projectPath, chapterId, doc });
+	} catch (error) {
+		console.error('Failed to write chapter JSON:', error);
+		throw error;
+	}
+}
+
+/**
+ * Move a chapter to the project's trash (chapters/.trash/) so it can be restored later
+ */
+export async function trashChapter(projectPath: string, chapterId: number): Promise<void> {
+	try {
+		await invoke('trash_chapter', { projectPath, chapterId });
+	} catch (error) {
+		console.error('Failed to trash chapter:', error);
+		throw error;
+	}
+}
+
+/**
+ * Restore a trashed chapter to its original position; returns the restored chapter
+ */
+export async function restoreChapter(projectPath: string, chapterId: number): Promise<Chapter> {
+	try {
+		return await invoke<Chapter>('restore_chapter', { projectPath, chapterId });
+	} catch (error) {
+		console.error('Failed to restore chapter:', error);
+		throw error;
+	}
+}
+
+/**
+ * Permanently delete all trashed chapters; returns how many were removed
+ */
+export async function emptyTrash(projectPath: string): Promise<number> {
+	try {
+		return await invoke<number>('empty_trash', { projectPath });
+	} catch (error) {
+		console.error('Failed to empty trash:', error);
+		throw error;
+	}
+}
+
+/**
+ * Renumber chapter ids to 1..N in chapter order (trashed chapters after them).
+ * Returns old id -> new id for the chapters whose id changed; reload the project afterwards.
+ */
+export async function renumberChapters(projectPath: string): Promise<Record<number, number>> {
+	try {
+		return await invoke<Record<number, number>>('renumber_chapters', { projectPath });
+	} catch (error) {
+		console.error('Failed to renumber chapters:', error);
+		throw error;
+	}
+}
+
+/**
+ * Copy an image file into the project's assets/ folder and return a data URL for display
+ */
+export async function copyAssetAndEncode(
+	projectPath: string,
+	srcPath: string
+): Promise<{ name: string; dataUrl: string; reused: boolean }> {
+	try {
+		return await invoke<{ name: string; dataUrl: string; reused: boolean }>('copy_asset_and_encode', {
+			projectPath,
+			srcPath,
+		});
+	} catch (error) {
+		console.error('Failed to copy asset:', error);
+		throw error;
+	}
+}
+
+/**
+ * Get all custom dictionary words (global + project)
+ */
+export async function getDictionaryWords(projectPath: string | null): Promise<string[]> {
+	try {
+		return await invoke<string[]>('get_dictionary_words', {
+			projectPath,
+		});
+	} catch (error) {
+		console.error('Failed to get dictionary words:', error);
+		return [];
+	}
+}
+
+/**
+ * Suggest corrections for a misspelled word from the custom dictionaries, closest first
+ */
+export async function getSpellingSuggestions(
+	projectPath: string | null,
+	word: string,
+	max: number = 5
+): Promise<string[]> {
+	try {
+		return await invoke<string[]>('spelling_suggestions', {
+			projectPath,
+			word,
+			max,
+		});
+	} catch (error) {
+		console.error('Failed to get spelling suggestions:', error);
+		return [];
+	}
+}
+
+/**
+ * Set the project's total word goal (must be greater than zero)
+ */
+export async function setWordGoal(projectPath: string, totalGoal: number): Promise<void> {
+	await invoke('set_word_goal', { projectPath, totalGoal });
+}
+
+/**
+ * Get progress toward the project's word goal
+ */
+export async function getWordGoalProgress(projectPath: string): Promise<WordGoalProgress> {
+	try {
+		return await invoke<WordGoalProgress>('word_goal_progress', { projectPath });
+	} catch (error) {
+		console.error('Failed to get word goal progress:', error);
+		throw error;
+	}
+}
+
+/**
+ * Set a chapter's target word count, or clear it with null
+ */
+export async function setChapterTarget(projectPath: string, chapterId: number, target: number | null): Promise<void> {
+	await invoke('set_chapter_target', { projectPath, chapterId, target });
+}
+
+/**
+ * Get progress toward each chapter's target word count, in chapter order
+ */
+export async function getChapterTargetsProgress(projectPath: string): Promise<ChapterTargetProgress[]> {
+	try {
+		return await invoke<ChapterTargetProgress[]>('chapter_targets_progress', { projectPath });
+	} catch (error) {
+		console.error('Failed to get chapter target progress:', error);
+		throw error;
+	}
+}
+
+/**
+ * Record the current project word count as the session baseline; returns that count
+ */
+export async function markWordBaseline(projectPath: string): Promise<number> {
+	return await invoke<number>('mark_word_baseline', { projectPath });
+}
+
+/**
+ * Get the word-count change since the last baseline (negative if text was cut)
+ */
+export async function getWordsSinceBaseline(projectPath: string): Promise<WordsSinceBaseline> {
+	try {
+		return await invoke<WordsSinceBaseline>('words_since_baseline', { projectPath });
+	} catch (error) {
+		console.error('Failed to get words since baseline:', error);
+		throw error;
+	}
+}
+
+/**
+ * Estimate printed page counts per chapter and in total (defaults to 250 words per page)
+ */
+export async function getPageEstimate(projectPath: string, wordsPerPage?: number): Promise<PageEstimate> {
+	try {
+		return await invoke<PageEstimate>('page_estimate', {
+			projectPath,
+			wordsPerPage: wordsPerPage ?? null,
+		});
+	} catch (error) {
+		console.error('Failed to estimate pages:', error);
+		throw error;
+	}
+}
+
+/**
+ * Find pairs of chapters with near-identical prose (similarity above threshold, 0–1)
+ */
+export async function findDuplicateChapters(
+	projectPath: string,
+	threshold: number
+): Promise<DuplicateChapterPair[]> {
+	try {
+		return await invoke<DuplicateChapterPair[]>('find_duplicate_chapters', {
+			projectPath,
+			threshold,
+		});
+	} catch (error) {
+		console.error('Failed to find duplicate chapters:', error);
+		throw error;
+	}
+}
+
+/**
+ * Find drafting markers (TK, TODO, ???, ...) in every chapter; custom patterns replace the defaults
+ */
+export async function findMarkers(projectPath: string, patterns?: string[]): Promise<MarkerMatch[]> {
+	try {
+		return await invoke<MarkerMatch[]>('find_markers', { projectPath, patterns: patterns ?? null });
+	} catch (error) {
+		console.error('Failed to find markers:', error);
+		throw error;
+	}
+}
+
+/**
+ * Compare two chapters as plain text; offsets count characters (use Array.from to slice)
+ */
+export async function diffChapters(projectPath: string, idA: number, idB: number): Promise<ChapterDiff> {
+	try {
+		return await invoke<ChapterDiff>('diff_chapters', { projectPath, idA, idB });
+	} catch (error) {
+		console.error('Failed to compare chapters:', error);
+		throw error;
+	}
+}
+
+/**
+ * Export selected chapters to a single Markdown file, optionally with a linked table of contents
+ */
+export async function exportProjectToMarkdown(
+	projectPath: string,
+	exportDir: string,
+	chapterIds: number[],
+	includeToc: boolean = false,
+	filenameTemplate?: string,
+	modifiedSince?: string,
+	titlePrefix?: string
+): Promise<string> {
+	try {
+		return await invoke<string>('export_markdown', {
+			projectPath,
+			exportDir,
+			chapterIds,
+			includeToc,
+			filenameTemplate: filenameTemplate || null,
+			modifiedSince: modifiedSince || null,
+			titlePrefix: titlePrefix || null,
+		});
+	} catch (error) {
+		console.error('Failed to export Markdown:', error);
+		throw error;
+	}
+}
+
+/**
+ * Export selected chapters to a Word document, one page-broken section per chapter
+ */
+export async function exportProjectToDOCX(
+	projectPath: string,
+	exportDir: string,
+	chapterIds: number[],
+	filenameTemplate?: string,
+	modifiedSince?: string
+): Promise<string> {
+	try {
+		return await invoke<string>('export_docx', {
+			projectPath,
+			exportDir,
+			chapterIds,
+			filenameTemplate: filenameTemplate || null,
+			modifiedSince: modifiedSince || null,
+		});
+	} catch (error) {
+		console.error('Failed to export DOCX:', error);
+		throw error;
+	}
+}
+
+/**
+ * Export a single chapter (for sharing with beta readers), named after the chapter title
+ */
+export async function exportChapter(
+	projectPath: string,
+	exportDir: string,
+	chapterId: number,
+	format: 'rtf' | 'epub' | 'markdown' | 'docx'
+): Promise<string> {
+	try {
+		return await invoke<string>('export_chapter', {
+			projectPath,
+			exportDir,
+			chapterId,
+			format,
+		});
+	} catch (error) {
+		console.error('Failed to export chapter:', error);
+		throw error;
+	}
+}
+
+/**
+ * Save (or overwrite) a named export preset in project.json
+ */
+export async function saveExportPreset(projectPath: string, name: string, preset: ExportPreset): Promise<void> {
+	try {
+		await invoke('save_export_preset', { projectPath, name, presetJson: preset });
+	} catch (error) {
+		console.error('Failed to save export preset:', error);
+		throw error;
+	}
+}
+
+/**
+ * List the project's saved export presets, sorted by name
+ */
+export async function listExportPresets(projectPath: string): Promise<NamedExportPreset[]> {
+	try {
+		return await invoke<NamedExportPreset[]>('list_export_presets', { projectPath });
+	} catch (error) {
+		console.error('Failed to list export presets:', error);
+		throw error;
+	}
+}
+
+/**
+ * Export using a saved preset; returns the exported file path
+ */
+export async function exportWithPreset(projectPath: string, name: string): Promise<string> {
+	try {
+		return await invoke<string>('export_with_preset', { projectPath, name });
+	} catch (error) {
+		console.error('Failed to export with preset:', error);
+		throw error;
+	}
+}
+
+/**
+ * Export with the format's default settings; the export location is saved as the project's
+ * default only when `remember` is true
+ */
+export async function exportBook(
+	projectPath: string,
+	exportDir: string,
+	format: 'rtf' | 'epub' | 'markdown' | 'docx' | 'manuscript',
+	chapterIds: number[] = [],
+	remember: boolean = false
+): Promise<string> {
+	try {
+		return await invoke<string>('export_book', { projectPath, exportDir, format, chapterIds, remember });
+	} catch (error) {
+		console.error('Failed to export:', error);
+		throw error;
+	}
+}
+
+/**
+ * Include or exclude a chapter from full-book exports; returns the updated excluded ids
+ */
+export async function setChapterExcluded(
+	projectPath: string,
+	chapterId: number,
+	excluded: boolean
+): Promise<number[]> {
+	try {
+		return await invoke<number[]>('set_chapter_excluded', { projectPath, chapterId, excluded });
+	} catch (error) {
+		console.error('Failed to update chapter exclusion:', error);
+		throw error;
+	}
+}
+
+/**
+ * Get each chapter's last edit time (RFC 3339), keyed by chapter id
+ */
+export async function getChapterModified(projectPath: string): Promise<Record<string, string>> {
+	try {
+		return await invoke<Record<string, string>>('get_chapter_modified', { projectPath });
+	} catch (error) {
+		console.error('Failed to read chapter modified times:', error);
+		return {};
+	}
+}
+
+/**
+ * Subscribe to export progress events; call the returned function to unsubscribe
+ */
+export async function onExportProgress(
+	callback: (progress: ExportProgress) => void
+): Promise<UnlistenFn> {
+	return await listen<ExportProgress>('export://progress', (event) => callback(event.payload));
+}
+
+/**
+ * Request cancellation of a running job (e.g. an import started with a jobId)
+ */
+export async function cancelJob(jobId: string): Promise<boolean> {
+	return await invoke<boolean>('cancel_job', { jobId });
+}
+
+/**
+ * Word frequency and adverb report for the given chapters (all chapters if empty).
+ * Code blocks and inline code are skipped unless includeCode is set.
+ */
+export async function getStyleReport(
+	projectPath: string,
+	chapterIds: number[] = [],
+	topN?: number,
+	includeCode: boolean = false
+): Promise<StyleReport> {
+	try {
+		return await invoke<StyleReport>('style_report', {
+			projectPath,
+			chapterIds,
+			topN: topN ?? null,
+			includeCode,
+		});
+	} catch (error) {
+		console.error('Failed to build style report:', error);
+		throw error;
+	}
+}
+
+/**
+ * Word, character, sentence and paragraph counts per chapter and for the whole project
+ */
+export async function getProjectStats(projectPath: string): Promise<ProjectStats> {
+	try {
+		return await invoke<ProjectStats>('project_stats', { projectPath });
+	} catch (error) {
+		console.error('Failed to get project stats:', error);
+		throw error;
+	}
+}
+
+/**
+ * Flesch readability scores for the given chapters (all chapters if empty)
+ */
+export async function getReadability(
+	projectPath: string,
+	chapterIds: number[] = []
+): Promise<ReadabilityReport> {
+	try {
+		return await invoke<ReadabilityReport>('readability', { projectPath, chapterIds });
+	} catch (error) {
+		console.error('Failed to compute readability:', error);
+		throw error;
+	}
+}
+
+/**
+ * Quote, dash and double-space counts for the given chapters (all chapters if empty)
+ */
+export async function getTypographyReport(
+	projectPath: string,
+	chapterIds: number[] = []
+): Promise<TypographyReport> {
+	try {
+		return await invoke<TypographyReport>('typography_report', { projectPath, chapterIds });
+	} catch (error) {
+		console.error('Failed to build typography report:', error);
+		throw error;
+	}
+}
+
+/**
+ * Curl straight quotes and collapse double spaces (code is left alone unless includeCode).
+ * Returns the number of chapters changed.
+ */
+export async function normalizeTypography(
+	projectPath: string,
+	chapterIds: number[] = [],
+	includeCode: boolean = false
+): Promise<number> {
+	try {
+		return await invoke<number>('normalize_typography', { projectPath, chapterIds, includeCode });
+	} catch (error) {
+		console.error('Failed to normalize typography:', error);
+		throw error;
+	}
+}
+
+/**
+ * Strip a chapter to plain paragraphs: marks, headings, lists and other blocks are removed.
+ * Images stay and footnotes move to the end. Returns whether the chapter changed.
+ */
+export async function flattenChapter(projectPath: string, chapterId: number): Promise<boolean> {
+	try {
+		return await invoke<boolean>('flatten_chapter', { projectPath, chapterId });
+	} catch (error) {
+		console.error('Failed to flatten chapter:', error);
+		throw error;
+	}
+}
+
+/**
+ * Switch every text mark set in `fromFamily` (empty = no family set) to `toFamily` across all
+ * chapters; returns how many marks changed
+ */
+export async function retargetFont(projectPath: string, fromFamily: string, toFamily: string): Promise<number> {
+	try {
+		return await invoke<number>('retarget_font', { projectPath, fromFamily, toFamily });
+	} catch (error) {
+		console.error('Failed to change font:', error);
+		throw error;
+	}
+}
+
+/**
+ * Chapter titles in order, each with its headings, for an outline view
+ */
+export async function getOutline(projectPath: string): Promise<ChapterOutline[]> {
+	try {
+		return await invoke<ChapterOutline[]>('outline', { projectPath });
+	} catch (error) {
+		console.error('Failed to build outline:', error);
+		throw error;
+	}
+}
+
+/**
+ * List every external link in the manuscript, one entry per URL with its occurrence count
+ */
+export async function extractLinks(projectPath: string): Promise<LinkEntry[]> {
+	try {
+		return await invoke<LinkEntry[]>('extract_links', { projectPath });
+	} catch (error) {
+		console.error('Failed to extract links:', error);
+		throw error;
+	}
+}
+
+/**
+ * List the font families the project uses (text marks plus default fonts), most used first
+ */
+export async function getUsedFonts(projectPath: string): Promise<FontUsage[]> {
+	try {
+		return await invoke<FontUsage[]>('used_fonts', { projectPath });
+	} catch (error) {
+		console.error('Failed to list used fonts:', error);
+		throw error;
+	}
+}
+
+/**
+ * Copy an entire project to a new folder with a new title
+ */
+export async function cloneProject(
+	sourcePath: string,
+	destPath: string,
+	newTitle: string
+): Promise<CreateProjectResponse> {
+	try {
+		return await invoke<CreateProjectResponse>('clone_project', {
+			sourcePath,
+			destPath,
+			newTitle,
+		});
+	} catch (error) {
+		console.error('Failed to clone project:', error);
+		throw error;
+	}
+}
+
+/**
+ * Pack a project into a single .scout bundle file
+ */
+export async function exportBundle(projectPath: string, destZip: string): Promise<string> {
+	try {
+		return await invoke<string>('export_bundle', { projectPath, destZip });
+	} catch (error) {
+		console.error('Failed to export bundle:', error);
+		throw error;
+	}
+}
+
+/**
+ * Unpack a .scout bundle into an empty folder and load the project
+ */
+export async function importBundle(archivePath: string, destDir: string): Promise<LoadProjectResponse> {
+	try {
+		return await invoke<LoadProjectResponse>('import_bundle', { archivePath, destDir });
+	} catch (error) {
+		console.error('Failed to import bundle:', error);
+		throw error;
+	}
+}
+
+/**
+ * Check that chapterOrder and the chapter files on disk agree
+ */
+export async function verifyProject(projectPath: string): Promise<ProjectIssues> {
+	try {
+		return await invoke<ProjectIssues>('verify_project', { projectPath });
+	} catch (error) {
+		console.error('Failed to verify project:', error);
+		throw error;
+	}
+}
+
+/**
+ * Report chapter files with encoding (BOM, UTF-16) or JSON problems; nothing is modified
+ */
+export async function checkChapterFiles(projectPath: string): Promise<ChapterFileIssue[]> {
+	try {
+		return await invoke<ChapterFileIssue[]>('check_chapter_files', { projectPath });
+	} catch (error) {
+		console.error('Failed to check chapter files:', error);
+		throw error;
+	}
+}
+
+/**
+ * Rebuild chapterOrder from the chapter files on disk, in ascending id order
+ */
+export async function rebuildChapterOrder(projectPath: string): Promise<number[]> {
+	try {
+		return await invoke<number[]>('rebuild_chapter_order', { projectPath });
+	} catch (error) {
+		console.error('Failed to rebuild chapter order:', error);
+		throw error;
+	}
+}
+
+/**
+ * Find projects under a folder without loading their chapters
+ */
+export async function scanProjects(rootDir: string, maxDepth: number = 3): Promise<ProjectSummary[]> {
+	try {
+		return await invoke<ProjectSummary[]>('scan_projects', { rootDir, maxDepth });
+	} catch (error) {
+		console.error('Failed to scan for projects:', error);
+		throw error;
 	}
 }
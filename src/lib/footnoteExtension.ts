@@ -0,0 +1,55 @@
+import { Node, mergeAttributes } from '@tiptap/core';
+
+/** Inline footnote marker, e.g. the `[^1]` of imported markdown. */
+export const FootnoteReference = Node.create({
+	name: 'footnoteReference',
+	group: 'inline',
+	inline: true,
+	atom: true,
+	selectable: true,
+
+	addAttributes() {
+		return {
+			label: { default: '' },
+		};
+	},
+
+	parseHTML() {
+		return [{ tag: 'sup[data-footnote-ref]', getAttrs: (el) => ({ label: (el as HTMLElement).textContent ?? '' }) }];
+	},
+
+	renderHTML({ node, HTMLAttributes }) {
+		return [
+			'sup',
+			mergeAttributes(HTMLAttributes, { 'data-footnote-ref': '', class: 'footnote-ref' }),
+			node.attrs.label,
+		];
+	},
+});
+
+/** Footnote body; exporters collect these at the end of the chapter. */
+export const FootnoteDefinition = Node.create({
+	name: 'footnoteDefinition',
+	group: 'block',
+	content: 'block+',
+	defining: true,
+	isolating: true,
+
+	addAttributes() {
+		return {
+			label: { default: '' },
+		};
+	},
+
+	parseHTML() {
+		return [{ tag: 'div[data-footnote]', getAttrs: (el) => ({ label: (el as HTMLElement).dataset.footnote ?? '' }) }];
+	},
+
+	renderHTML({ node, HTMLAttributes }) {
+		return [
+			'div',
+			mergeAttributes(HTMLAttributes, { 'data-footnote': node.attrs.label, class: 'footnote' }),
+			0,
+		];
+	},
+});
@@ -0,0 +1,27 @@
+import { Extension } from '@tiptap/core'
+
+/**
+ * Keeps a `class` attribute on paragraphs (e.g. "noindent") so it survives editing and
+ * reaches EPUB export, where project CSS can target it.
+ */
+export const ParagraphClass = Extension.create({
+	name: 'paragraphClass',
+
+	addGlobalAttributes() {
+		return [
+			{
+				types: ['paragraph'],
+				attributes: {
+					class: {
+						default: null,
+						parseHTML: (element: HTMLElement) => element.getAttribute('class') || null,
+						renderHTML: (attributes: Record<string, any>) => {
+							if (!attributes.class) return {}
+							return { class: attributes.class }
+						},
+					},
+				},
+			},
+		]
+	},
+})
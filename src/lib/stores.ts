@@ -17,6 +17,18 @@ export function mergeWithDefaults(overrides?: ProjectStyles): ProjectStyles {
 	for (const key of Object.keys(DEFAULT_STYLES) as StyleKey[]) {
 		result[key] = { ...DEFAULT_STYLES[key], ...(overrides?.[key] ?? {}) };
 	}
+	if (overrides?.chapterClasses) {
+		result.chapterClasses = overrides.chapterClasses;
+	}
+	if (overrides?.indentParagraphs !== undefined) {
+		result.indentParagraphs = overrides.indentParagraphs;
+	}
+	if (overrides?.dropCaps !== undefined) {
+		result.dropCaps = overrides.dropCaps;
+	}
+	if (overrides?.sceneBreak !== undefined) {
+		result.sceneBreak = overrides.sceneBreak;
+	}
 	return result;
 }
 
@@ -60,6 +72,11 @@ export const error = writable<string | null>(null);
  */
 export const hasStarted = writable(false);
 
+/**
+ * Chapter to open when a project loads; the editor page switches to it once and clears it
+ */
+export const openingChapterId = writable<number | null>(null);
+
 /**
  * Set of chapter IDs that have unsaved changes (empty set = all saved)
  */
@@ -101,8 +118,10 @@ export function clearUnsaved() {
  */
 export function setProject(
 	newProject: Project & { path: string },
-	newChapters: Chapter[]
+	newChapters: Chapter[],
+	lastOpenedChapter: number | null = null
 ) {
+	openingChapterId.set(lastOpenedChapter);
 	project.set(newProject);
 	chapters.set(newChapters);
 	hasStarted.set(true);
@@ -111,10 +130,18 @@ export function setProject(
 	pageSettings.set({ ...DEFAULT_PAGE_SETTINGS, ...newProject.pageSettings });
 }
 
+/**
+ * Record the project.json revision returned by a save
+ */
+export function setProjectRev(rev: number) {
+	project.update((p) => (p ? { ...p, rev } : p));
+}
+
 /**
  * Clear the current project (on close)
  */
 export function clearProject() {
+	openingChapterId.set(null);
 	project.set(null);
 	chapters.set([]);
 	hasStarted.set(false);
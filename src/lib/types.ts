@@ -27,6 +27,16 @@ export interface ProjectStyles {
 	h5?: StyleDefinition;
 	h6?: StyleDefinition;
 	blockquote?: StyleDefinition;
+	/** Chapter-level styles keyed by class name; a chapter opts in via its doc's `attrs.className` */
+	chapterClasses?: Record<string, StyleDefinition>;
+	/** EPUB: first-line paragraph indents with no spacing between paragraphs */
+	indentParagraphs?: boolean;
+	/** EPUB: drop cap on the first letter of each chapter */
+	dropCaps?: boolean;
+	/** EPUB: show the chapter title as a heading at the top of each chapter */
+	showChapterTitles?: boolean;
+	/** EPUB: glyph shown for scene breaks (e.g. "⁂", "* * *"), or "rule" for a plain line */
+	sceneBreak?: string;
 }
 
 export interface PageSettings {
@@ -48,11 +58,70 @@ export interface Project {
 	fontFamily?: string;
 	styles?: ProjectStyles;
 	pageSettings?: PageSettings;
+	wordGoal?: number;
+	wordBaseline?: { words: number; markedAt: string };
+	wordHistory?: WordHistoryEntry[];
+	exportPresets?: Record<string, ExportPreset>;
+	excludedChapters?: number[]; // left out of full-book exports
+	rev?: number; // bumped on every save; a stale rev means another window saved first
+	direction?: 'ltr' | 'rtl'; // book reading direction for EPUB export (default ltr)
+	language?: string;         // BCP 47 tag for EPUB export, e.g. 'fr' (default 'en')
+	trash?: Record<string, TrashEntry>; // trashed chapters by id
+	parts?: BookPart[];        // EPUB: chapters grouped under part dividers
+	chapterEpigraphs?: Record<string, Epigraph>; // shown before the chapter body in exports
+	contactInfo?: string;      // manuscript title page: name, address, email, one per line
+	coverImage?: string;       // EPUB cover: asset filename under assets/
 }
 
+export interface Epigraph {
+	text: string;
+	attribution?: string;
+}
+
+export interface BookPart {
+	title: string;        // e.g. "Part One"
+	chapterIds: number[]; // chapters in this part; those in no part stay at the top level
+}
+
+export interface TrashEntry {
+	index: number;        // position in chapterOrder when trashed
+	title: string | null;
+	trashedAt: string;    // RFC 3339
+}
+
+export interface WordHistoryEntry {
+	date: string;   // YYYY-MM-DD
+	words: number;  // total project words when the baseline was marked that day
+}
+
+export type Theme = 'light' | 'dark' | 'sepia' | 'system';
+
+export type RtfLineSpacing = 'single' | '1.5' | 'double';
+
+// Plain-text import: paragraphs separated by blank lines, or one paragraph per line
+// (blank lines then mark scene breaks)
+export type ParagraphMode = 'blank-line' | 'single-line';
+
 export interface Config {
 	lastProjectPath: string | null;
 	fontFamily?: string;
+	theme?: Theme;
+	window?: WindowState;
+	autosaveIntervalSeconds?: number; // 5–600, default 30
+	recentProjects?: string[];        // newest first
+	defaultAuthor?: string;           // filled into new projects
+	jsonIndent?: JsonIndent;          // chapter/project file layout; omitted = as before
+}
+
+// 'compact' (one line), 'tabs', or spaces per level as a string ('1'–'8')
+export type JsonIndent = 'compact' | 'tabs' | `${number}`;
+
+export interface WindowState {
+	width: number;
+	height: number;
+	x?: number; // omitted when the saved position was off-screen
+	y?: number;
+	maximized: boolean;
 }
 
 export interface ProjectData extends Project {
@@ -63,9 +132,221 @@ export interface LoadProjectResponse {
 	project: Project;
 	chapters: Chapter[];
 	path: string;
+	chapterModified: Record<string, string>; // chapter id -> last edit time (RFC 3339)
+	lastOpenedChapter: number | null;        // chapter to open first (falls back to the first chapter)
 }
 
 export interface CreateProjectResponse {
 	project: Project;
 	path: string;
 }
+
+export interface WordGoalProgress {
+	goal: number;
+	currentWords: number;
+	percent: number;
+	remaining: number;
+}
+
+export interface ChapterTargetProgress {
+	chapterId: number;
+	target: number;
+	current: number;
+	percent: number;
+}
+
+export interface WordsSinceBaseline {
+	baseline: number;
+	currentWords: number;
+	delta: number;
+	markedAt: string;
+}
+
+export interface PageEstimate {
+	wordsPerPage: number;
+	chapters: { id: number; words: number; pages: number }[];
+	totalWords: number;
+	totalPages: number;
+}
+
+export interface DuplicateChapterPair {
+	idA: number;
+	idB: number;
+	similarity: number; // 0–1, token-set Jaccard
+}
+
+export interface DiffChange {
+	kind: 'insert' | 'delete' | 'replace';
+	startA: number; // character offsets into ChapterDiff.textA (empty range for an insert)
+	endA: number;
+	startB: number; // character offsets into ChapterDiff.textB (empty range for a delete)
+	endB: number;
+	oldText: string;
+	newText: string;
+}
+
+export interface MarkerMatch {
+	chapterId: number;
+	marker: string;  // the matched text, e.g. 'TK'
+	context: string; // the marker's line, shortened with '…'
+	offset: number;  // character offset in the chapter's plain text
+}
+
+export interface ChapterDiff {
+	textA: string;
+	textB: string;
+	changes: DiffChange[];
+}
+
+/** Payload of the `export://progress` event emitted by RTF and EPUB export */
+export interface ExportProgress {
+	done: number;
+	total: number;
+	stage: 'chapters' | 'complete';
+	outputPath?: string;
+}
+
+export interface WordFrequency {
+	word: string;
+	count: number;
+}
+
+export interface ChapterStyleReport {
+	id: number;
+	words: number;
+	topWords: WordFrequency[];
+	adverbs: number;
+	adverbDensity: number; // -ly adverbs per 100 words
+}
+
+export interface StyleReport {
+	chapters: ChapterStyleReport[];
+	topWords: WordFrequency[];
+	words: number;
+	adverbs: number;
+	adverbDensity: number;
+}
+
+export interface TextStats {
+	words: number;
+	characters: number;
+	sentences: number;
+	paragraphs: number;
+}
+
+export interface ProjectStats {
+	chapters: (TextStats & { id: number })[];
+	total: TextStats;
+}
+
+export interface ReadabilityScore {
+	words: number;
+	sentences: number;
+	syllables: number;
+	readingEase: number | null; // Flesch Reading Ease
+	gradeLevel: number | null;  // Flesch-Kincaid Grade Level
+}
+
+export interface ReadabilityReport {
+	chapters: (ReadabilityScore & { id: number })[];
+	overall: ReadabilityScore;
+}
+
+export interface TypographyCounts {
+	straightDoubleQuotes: number;
+	straightSingleQuotes: number;
+	curlyDoubleQuotes: number;
+	curlySingleQuotes: number;
+	hyphens: number;
+	enDashes: number;
+	emDashes: number;
+	doubleSpaces: number; // two or more spaces after a period
+}
+
+export interface TypographyReport {
+	chapters: (TypographyCounts & { id: number })[];
+	total: TypographyCounts;
+}
+
+export interface ChapterOutline {
+	id: number;
+	title: string;
+	headings: { level: number; text: string }[]; // in document order
+}
+
+export interface LinkEntry {
+	href: string;
+	text: string;         // link text at the first occurrence
+	chapterId: number;    // chapter of the first occurrence
+	count: number;
+	chapterIds: number[]; // every chapter the link appears in
+}
+
+export interface FontUsage {
+	family: string;
+	count: number;        // text runs in the family, plus one per default (project/app) it is
+	chapterIds: number[]; // chapters with text set in the family
+}
+
+export interface ImportFileReport {
+	encoding: string;     // 'UTF-8', 'UTF-8 (BOM)', 'UTF-16LE', 'UTF-16BE' or 'unknown'
+	byteSize: number;
+	chapterCount: number; // chapters the import would create with the given delimiter
+	warnings: string[];
+}
+
+export interface ProjectIssues {
+	missingChapterIds: number[];  // in chapterOrder but no chapter file
+	unlistedChapterIds: number[]; // chapter file on disk but not in chapterOrder
+	fieldErrors: ProjectFieldError[]; // project.json fields with the wrong type
+}
+
+export interface ChapterFileIssue {
+	chapterId: number;
+	encoding: 'UTF-8' | 'UTF-8 (BOM)' | 'UTF-16LE' | 'UTF-16BE' | 'unknown';
+	problems: string[]; // e.g. 'Invalid JSON: EOF while parsing an object at line 3 column 0'
+}
+
+export interface AssetMismatch {
+	name: string;          // file under assets/, e.g. 'map.jpg'
+	extensionMime: string; // what the extension implies, e.g. 'image/jpeg'
+	actualMime: string;    // what the file contents are, e.g. 'image/png'
+	suggestedName: string; // e.g. 'map.png'
+	repaired: boolean;
+}
+
+export interface ProjectFieldError {
+	field: string;   // e.g. 'chapterOrder[2]'
+	problem: string; // e.g. 'is not an integer'
+}
+
+export interface ExportPreset {
+	format: 'rtf' | 'epub' | 'markdown' | 'docx';
+	chapterIds?: number[];       // empty/omitted = all chapters
+	filenameTemplate?: string;
+	renumber?: boolean;
+	includeToc?: boolean;        // Markdown: linked TOC; EPUB: visible contents page
+	exportDir?: string;          // defaults to the project's export directory
+	styles?: ProjectStyles;      // EPUB style overrides for this preset
+	customCssPath?: string;      // EPUB: stylesheet appended after the generated CSS
+	epubVersion?: 2 | 3;         // EPUB: package version, 3 when omitted
+	headingOffset?: number;      // RTF/EPUB: shift every heading level, e.g. 1 turns h2 into h3
+	titlePrefix?: string;        // EPUB/Markdown: prepended to chapter titles, e.g. 'Chapter {n}: '
+}
+
+export interface ExportSizeEstimate {
+	bytes: number;
+	human: string; // e.g. '1.4 MB'
+}
+
+export interface NamedExportPreset extends ExportPreset {
+	name: string;
+}
+
+export interface ProjectSummary {
+	path: string;
+	title: string;
+	author: string;
+	chapterCount: number;
+	modified: string | null; // RFC 3339 timestamp of project.json
+}